@@ -0,0 +1,129 @@
+//! Storage benchmarks on a synthetic history.
+//!
+//! Run with `cargo bench`; set `CB_BENCH_ROWS` to change the database size
+//! (default 100k rows).
+
+use std::hint::black_box;
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+
+use cb::hash::hash_content;
+use cb::storage::ClipStorage;
+use cb::storage::models::{ClipFilter, ContentType, NewClip};
+use cb::storage::sqlite::SqliteStorage;
+
+const WORDS: &[&str] = &[
+    "docker", "login", "kubectl", "select", "from", "where", "invoice", "meeting", "https://",
+    "error", "token", "deploy", "branch", "release", "notes", "lorem", "ipsum",
+];
+
+fn rows() -> usize {
+    std::env::var("CB_BENCH_ROWS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(100_000)
+}
+
+fn synthetic_text(i: usize) -> String {
+    let mut text = format!("clip {} ", i);
+    for n in 0..8 {
+        text.push_str(WORDS[(i * 7 + n * 3) % WORDS.len()]);
+        text.push(' ');
+    }
+    text
+}
+
+fn new_clip(i: usize) -> NewClip {
+    let text = synthetic_text(i);
+    NewClip {
+        content_type: if i.is_multiple_of(10) { ContentType::Image } else { ContentType::Text },
+        hash: hash_content(text.as_bytes()),
+        size_bytes: text.len() as i64,
+        text_content: Some(text),
+        ..Default::default()
+    }
+}
+
+fn populated_storage(n: usize) -> SqliteStorage {
+    let storage = SqliteStorage::in_memory().unwrap();
+    for i in 0..n {
+        let clip = storage.insert(new_clip(i)).unwrap();
+        if i.is_multiple_of(5) {
+            storage.add_tag(clip.id, "work").unwrap();
+        }
+        if i.is_multiple_of(50) {
+            storage.set_pinned(clip.id, true).unwrap();
+        }
+    }
+    storage
+}
+
+fn bench_insert(c: &mut Criterion) {
+    c.bench_function("insert", |b| {
+        b.iter_batched(
+            || (SqliteStorage::in_memory().unwrap(), new_clip(1)),
+            |(storage, clip)| black_box(storage.insert(clip).unwrap()),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_queries(c: &mut Criterion) {
+    let n = rows();
+    let storage = populated_storage(n);
+    let mut group = c.benchmark_group(format!("queries_{}_rows", n));
+
+    group.bench_function("list_recent", |b| {
+        b.iter(|| storage.list(ClipFilter { limit: 50, ..Default::default() }).unwrap())
+    });
+    group.bench_function("list_deep_offset", |b| {
+        b.iter(|| {
+            storage
+                .list(ClipFilter { limit: 50, offset: (n / 2) as i64, ..Default::default() })
+                .unwrap()
+        })
+    });
+    group.bench_function("list_type_filter", |b| {
+        b.iter(|| {
+            storage
+                .list(ClipFilter {
+                    content_type: Some(ContentType::Image),
+                    limit: 50,
+                    ..Default::default()
+                })
+                .unwrap()
+        })
+    });
+    group.bench_function("list_tag_filter", |b| {
+        b.iter(|| {
+            storage
+                .list(ClipFilter { tag: Some("work".into()), limit: 50, ..Default::default() })
+                .unwrap()
+        })
+    });
+    group.bench_function("list_pinned_filter", |b| {
+        b.iter(|| {
+            storage
+                .list(ClipFilter { pinned: Some(true), limit: 50, ..Default::default() })
+                .unwrap()
+        })
+    });
+    group.bench_function("search_like_common", |b| {
+        b.iter(|| storage.search(black_box("docker"), 50).unwrap())
+    });
+    group.bench_function("search_like_miss", |b| {
+        b.iter(|| storage.search(black_box("zzz-no-match"), 50).unwrap())
+    });
+    group.bench_function("dedup_find_by_hash", |b| {
+        let hash = new_clip(n / 3).hash;
+        b.iter(|| storage.find_by_hash(black_box(&hash)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = bench_insert, bench_queries
+}
+criterion_main!(benches);
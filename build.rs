@@ -0,0 +1,23 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    grpc();
+}
+
+/// Generate the `grpc` module's messages and service from proto/cb.proto,
+/// with a vendored `protoc` so no system install is needed.
+#[cfg(feature = "grpc")]
+fn grpc() {
+    let protoc =
+        protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc for this platform");
+    let include = protoc_bin_vendored::include_path().expect("no vendored protobuf includes");
+    let mut config = prost_build::Config::new();
+    config.protoc_executable(protoc);
+    tonic_prost_build::configure()
+        .compile_with_config(
+            config,
+            &["proto/cb.proto".into()],
+            &["proto".into(), include],
+        )
+        .expect("can't compile proto/cb.proto");
+}
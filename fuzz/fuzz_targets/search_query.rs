@@ -0,0 +1,55 @@
+//! Search queries straight from the search box or `cb search`: any text has
+//! to come back as results or an empty list, never a panic or an SQL error.
+
+#![no_main]
+
+use cb::fuzzy;
+use cb::hash::hash_content;
+use cb::storage::ClipStorage;
+use cb::storage::models::{ClipFilter, NewClip};
+use cb::storage::sqlite::SqliteStorage;
+use libfuzzer_sys::fuzz_target;
+
+const TEXTS: &[&str] = &[
+    "cargo build --release",
+    "50% off_sale",
+    "back\\slash 'quoted' \"double\"",
+    "https://example.com/?q=a&utm_source=feed",
+    "Grüße, naïve café",
+];
+
+thread_local! {
+    static STORAGE: SqliteStorage = {
+        let storage = SqliteStorage::in_memory().unwrap();
+        for text in TEXTS {
+            storage
+                .insert(NewClip {
+                    text_content: Some(text.to_string()),
+                    hash: hash_content(text.as_bytes()),
+                    size_bytes: text.len() as i64,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        storage
+    };
+}
+
+fuzz_target!(|query: &str| {
+    STORAGE.with(|storage| {
+        for clip in storage.search(query, 20).unwrap() {
+            let text = clip.text_content.unwrap_or_default();
+            assert!(text.to_ascii_lowercase().contains(&query.to_ascii_lowercase()));
+        }
+        let filter = ClipFilter {
+            query: Some(query.to_string()),
+            ..Default::default()
+        };
+        storage.list(filter.clone()).unwrap();
+        storage.count_matching(&filter).unwrap();
+        storage.search_fuzzy(filter).unwrap();
+        for text in TEXTS {
+            let _ = fuzzy::score(query, text);
+        }
+    });
+});
@@ -0,0 +1,302 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{OpenFlags, params};
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppPaths;
+use crate::encryption;
+use crate::errors::{CbError, Result};
+use crate::repair::{self, unused_path, with_suffix};
+use crate::storage::sqlite::SqliteStorage;
+
+/// Folder every entry of a backup sits in.
+const ROOT: &str = "cb-backup";
+
+/// Version of the backup layout, bumped when it changes incompatibly.
+pub const VERSION: u32 = 1;
+
+/// `manifest.json` at the top of a backup.
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    created_at: DateTime<Utc>,
+    profile: String,
+    /// Where the images lived, so restoring elsewhere can re-point clips.
+    images_dir: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupSummary {
+    pub path: PathBuf,
+    pub images: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreSummary {
+    pub clips: i64,
+    pub images: usize,
+    /// Where the database being replaced was moved, if there was one.
+    pub previous: Option<PathBuf>,
+}
+
+/// `cb-backup-<profile>-<timestamp>.tar` in the current directory.
+pub fn default_path(paths: &AppPaths) -> PathBuf {
+    PathBuf::from(format!(
+        "cb-backup-{}-{}.tar",
+        paths.profile,
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ))
+}
+
+/// Write a tarball at `out` holding a consistent snapshot of the database
+/// (taken with `VACUUM INTO`, so it's safe while the daemon runs) and every
+/// file in the images directory. Thumbnails are left out; they're rebuilt
+/// on demand.
+pub fn create(paths: &AppPaths, out: &Path) -> Result<BackupSummary> {
+    if !paths.db_path.exists() {
+        return Err(CbError::NotFound(format!("no database at {}", paths.db_path.display())));
+    }
+    let io = |e: std::io::Error| CbError::InvalidInput(format!("{}: {}", out.display(), e));
+    let snapshot = unused_path(out, ".db-snapshot");
+    let conn = encryption::connect(&paths.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    conn.execute("VACUUM INTO ?", [snapshot.to_string_lossy()])?;
+    drop(conn);
+
+    let result = (|| -> Result<usize> {
+        let mut tar = tar::Builder::new(File::create(out).map_err(io)?);
+        let manifest = Manifest {
+            version: VERSION,
+            created_at: Utc::now(),
+            profile: paths.profile.clone(),
+            images_dir: paths.images_dir.clone(),
+        };
+        let manifest = serde_json::to_vec_pretty(&manifest).unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(Utc::now().timestamp() as u64);
+        header.set_cksum();
+        tar.append_data(&mut header, format!("{}/manifest.json", ROOT), manifest.as_slice())
+            .map_err(io)?;
+        tar.append_path_with_name(&snapshot, format!("{}/clips.db", ROOT))
+            .map_err(io)?;
+
+        let mut images = 0;
+        for image in image_files(&paths.images_dir) {
+            let name = image.file_name().unwrap().to_string_lossy();
+            tar.append_path_with_name(&image, format!("{}/images/{}", ROOT, name))
+                .map_err(io)?;
+            images += 1;
+        }
+        tar.into_inner().map_err(io)?;
+        Ok(images)
+    })();
+    let _ = fs::remove_file(&snapshot);
+    let images = result?;
+
+    Ok(BackupSummary {
+        path: out.to_path_buf(),
+        images,
+        bytes: fs::metadata(out).map(|m| m.len()).unwrap_or(0),
+    })
+}
+
+/// Replace the profile's database with the one in the backup at `archive`
+/// and copy its images into the images directory, alongside any already
+/// there. The database being replaced is kept as
+/// `<db>.before-restore-<timestamp>`. Clips are re-pointed at the images
+/// directory when the backup came from a different one. The daemon must not
+/// be running.
+pub fn restore(paths: &AppPaths, archive: &Path) -> Result<RestoreSummary> {
+    let invalid = |e: &dyn std::fmt::Display| CbError::InvalidInput(format!("{}: {}", archive.display(), e));
+    let file = File::open(archive).map_err(|e| invalid(&e))?;
+    fs::create_dir_all(&paths.data_dir).map_err(|e| invalid(&e))?;
+    let stamp = Utc::now().format("%Y%m%d%H%M%S");
+    let staging = unused_path(&paths.data_dir.join("restore"), &format!("-{}", stamp));
+    fs::create_dir_all(&staging).map_err(|e| invalid(&e))?;
+
+    let result = (|| -> Result<RestoreSummary> {
+        let mut tar = tar::Archive::new(file);
+        for entry in tar.entries().map_err(|e| invalid(&e))? {
+            // `unpack_in` refuses entries that would land outside `staging`.
+            entry.and_then(|mut e| e.unpack_in(&staging)).map_err(|e| invalid(&e))?;
+        }
+        let root = staging.join(ROOT);
+        let manifest: Manifest = fs::read(root.join("manifest.json"))
+            .map_err(|e| invalid(&e))
+            .and_then(|data| serde_json::from_slice(&data).map_err(|e| invalid(&e)))?;
+        if manifest.version > VERSION {
+            return Err(invalid(&format!(
+                "backup version {} is newer than this cb understands ({})",
+                manifest.version, VERSION
+            )));
+        }
+        let db = root.join("clips.db");
+        let problems = repair::check_file(&db)?;
+        if !problems.is_empty() {
+            return Err(CbError::Corrupt(format!(
+                "the backup's database fails its integrity check: {}",
+                problems[0]
+            )));
+        }
+
+        fs::create_dir_all(&paths.images_dir).map_err(|e| invalid(&e))?;
+        let mut images = 0;
+        for image in image_files(&root.join("images")) {
+            let target = paths.images_dir.join(image.file_name().unwrap());
+            fs::copy(&image, &target)
+                .map_err(|e| CbError::Image(format!("{}: {}", target.display(), e)))?;
+            images += 1;
+        }
+
+        let previous = if paths.db_path.exists() {
+            let previous = unused_path(&paths.db_path, &format!(".before-restore-{}", stamp));
+            for suffix in ["", "-wal", "-shm"] {
+                let from = with_suffix(&paths.db_path, suffix);
+                if from.exists() {
+                    fs::rename(&from, with_suffix(&previous, suffix)).map_err(|e| invalid(&e))?;
+                }
+            }
+            Some(previous)
+        } else {
+            None
+        };
+        fs::rename(&db, &paths.db_path).map_err(|e| invalid(&e))?;
+
+        // Bring an older backup up to date, then re-point its images.
+        let storage = SqliteStorage::new(encryption::connect(&paths.db_path, OpenFlags::default())?)?;
+        drop(storage);
+        let conn = encryption::connect(&paths.db_path, OpenFlags::default())?;
+        let (old, new) = (dir_prefix(&manifest.images_dir), dir_prefix(&paths.images_dir));
+        if old != new {
+            // Protected clips refuse path changes, so lift that for the move.
+            conn.execute_batch(
+                "BEGIN;
+                 CREATE TEMP TABLE restore_protected AS SELECT id FROM clips WHERE protected = 1;
+                 UPDATE clips SET protected = 0 WHERE protected = 1;",
+            )?;
+            conn.execute(
+                "UPDATE clips SET image_path = ?2 || substr(image_path, length(?1) + 1)
+                 WHERE content_type = 'image' AND substr(image_path, 1, length(?1)) = ?1",
+                params![old, new],
+            )?;
+            conn.execute_batch(
+                "UPDATE clips SET protected = 1 WHERE id IN (SELECT id FROM restore_protected);
+                 DROP TABLE restore_protected;
+                 COMMIT;",
+            )?;
+        }
+        let clips = conn.query_row("SELECT COUNT(*) FROM clips", [], |row| row.get(0))?;
+        Ok(RestoreSummary {
+            clips,
+            images,
+            previous,
+        })
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// `dir` as a string ending in a separator, for matching path prefixes.
+fn dir_prefix(dir: &Path) -> String {
+    let mut s = dir.to_string_lossy().into_owned();
+    if !s.ends_with(std::path::MAIN_SEPARATOR) {
+        s.push(std::path::MAIN_SEPARATOR);
+    }
+    s
+}
+
+/// Regular files directly inside `dir`, sorted; none if it doesn't exist.
+fn image_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::ClipStorage;
+    use crate::storage::models::{ContentType, NewClip};
+    use rusqlite::Connection;
+    use tempfile::TempDir;
+
+    fn paths_in(dir: &Path) -> AppPaths {
+        AppPaths::from_base(dir.to_path_buf())
+    }
+
+    #[test]
+    fn test_backup_and_restore_elsewhere() {
+        let tmp = TempDir::new().unwrap();
+        let from = paths_in(&tmp.path().join("old"));
+        fs::create_dir_all(from.images_dir.join("thumbs")).unwrap();
+        let image = from.images_dir.join("abc.png");
+        fs::write(&image, b"png").unwrap();
+        fs::write(from.images_dir.join("thumbs").join("abc.png"), b"thumb").unwrap();
+        {
+            let storage = SqliteStorage::new(Connection::open(&from.db_path).unwrap()).unwrap();
+            let clip = storage
+                .insert(NewClip {
+                    content_type: ContentType::Image,
+                    image_path: Some(image.to_string_lossy().into_owned()),
+                    hash: "abc".into(),
+                    size_bytes: 3,
+                    ..Default::default()
+                })
+                .unwrap();
+            storage.set_protected(clip.id, true).unwrap();
+        }
+
+        let archive = tmp.path().join("backup.tar");
+        let summary = create(&from, &archive).unwrap();
+        assert_eq!(summary.images, 1);
+        assert!(summary.bytes > 0);
+
+        let to = paths_in(&tmp.path().join("new"));
+        fs::create_dir_all(&to.data_dir).unwrap();
+        fs::write(&to.db_path, b"").unwrap();
+        let restored = restore(&to, &archive).unwrap();
+        assert_eq!((restored.clips, restored.images), (1, 1));
+        assert!(restored.previous.unwrap().exists());
+        assert!(!to.images_dir.join("thumbs").exists());
+
+        let storage = SqliteStorage::open_read_only(&to.db_path).unwrap();
+        let clip = storage.get_by_id(1).unwrap();
+        assert!(clip.protected);
+        let moved = to.images_dir.join("abc.png");
+        assert_eq!(clip.image_path.as_deref(), Some(moved.to_string_lossy().as_ref()));
+        assert_eq!(fs::read(moved).unwrap(), b"png");
+        // The staging folder is cleaned up.
+        let leftovers: Vec<_> = fs::read_dir(&to.data_dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with("restore"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
+
+    #[test]
+    fn test_restore_rejects_other_archives() {
+        let tmp = TempDir::new().unwrap();
+        let archive = tmp.path().join("other.tar");
+        let mut tar = tar::Builder::new(File::create(&archive).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(2);
+        header.set_cksum();
+        tar.append_data(&mut header, "notes.txt", &b"hi"[..]).unwrap();
+        tar.into_inner().unwrap();
+
+        let paths = paths_in(tmp.path());
+        assert!(matches!(restore(&paths, &archive), Err(CbError::InvalidInput(_))));
+        assert!(!paths.db_path.exists());
+    }
+}
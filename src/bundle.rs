@@ -0,0 +1,307 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::errors::{CbError, Result};
+use crate::storage::ClipStorage;
+use crate::storage::models::{Clip, ClipFilter, ContentType};
+
+/// Archive format, chosen from the output file's extension.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BundleFormat {
+    Zip,
+    Tar,
+}
+
+impl BundleFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("zip") => Ok(BundleFormat::Zip),
+            Some(ext) if ext.eq_ignore_ascii_case("tar") => Ok(BundleFormat::Tar),
+            _ => Err(CbError::InvalidInput(format!(
+                "{}: bundle must end in .zip or .tar",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// One clip's entry in `manifest.json`.
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry {
+    pub id: i64,
+    /// Path of the clip's file inside the archive.
+    pub file: String,
+    pub content_type: ContentType,
+    pub created_at: DateTime<Utc>,
+    pub pinned: bool,
+    pub lang: Option<String>,
+    pub source_app: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Manifest<'a> {
+    created_at: DateTime<Utc>,
+    clips: &'a [ManifestEntry],
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct BundleSummary {
+    pub clips: i64,
+    /// Image or file clips whose file could no longer be read.
+    pub skipped: i64,
+}
+
+/// Write every clip matching `filter` to a zip or tar archive at `out`, one
+/// file per clip, oldest first, plus a `manifest.json` describing them. All
+/// entries sit in a folder named after the archive so unpacking stays tidy.
+pub fn write_bundle<S: ClipStorage>(
+    storage: &S,
+    filter: &ClipFilter,
+    out: &Path,
+) -> Result<BundleSummary> {
+    let format = BundleFormat::from_path(out)?;
+    let count = storage.count_matching(filter)?;
+    if count == 0 {
+        return Err(CbError::NotFound("no clips match".into()));
+    }
+    let mut clips = storage.list(ClipFilter {
+        limit: count,
+        offset: 0,
+        ..filter.clone()
+    })?;
+    clips.reverse();
+
+    let folder = out
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("bundle")
+        .to_string();
+    let mut archive = Archive::create(out, format)?;
+    let mut summary = BundleSummary::default();
+    let mut entries = Vec::new();
+    for clip in clips {
+        let Some((name, data)) = clip_file(&clip) else {
+            summary.skipped += 1;
+            continue;
+        };
+        let file = format!("{}/{}", folder, name);
+        archive.add(&file, &data)?;
+        summary.clips += 1;
+        entries.push(ManifestEntry {
+            id: clip.id,
+            file,
+            content_type: clip.content_type,
+            created_at: clip.created_at,
+            pinned: clip.pinned,
+            lang: clip.lang,
+            source_app: clip.source_app,
+            tags: clip.tags,
+        });
+    }
+
+    let manifest = Manifest {
+        created_at: Utc::now(),
+        clips: &entries,
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest).unwrap();
+    archive.add(&format!("{}/manifest.json", folder), &manifest)?;
+    archive.finish()?;
+    Ok(summary)
+}
+
+/// File name and contents for `clip`, or `None` when its file is gone.
+fn clip_file(clip: &Clip) -> Option<(String, Vec<u8>)> {
+    match clip.content_type {
+        ContentType::Text => {
+            let text = clip.text_content.clone().unwrap_or_default();
+            let ext = if looks_like_markdown(&text) {
+                "md"
+            } else {
+                "txt"
+            };
+            Some((format!("clip-{}.{}", clip.id, ext), text.into_bytes()))
+        }
+        ContentType::Image | ContentType::FileRef => {
+            let path = Path::new(clip.image_path.as_deref()?);
+            let data = fs::read(path).ok()?;
+            let name = match path.extension().and_then(|e| e.to_str()) {
+                Some(ext) => format!("clip-{}.{}", clip.id, ext),
+                None => format!("clip-{}", clip.id),
+            };
+            Some((name, data))
+        }
+    }
+}
+
+/// Headings, fences, task lists, or inline links.
+fn looks_like_markdown(text: &str) -> bool {
+    text.contains("](http")
+        || text.lines().any(|line| {
+            let line = line.trim_start();
+            line.starts_with("# ")
+                || line.starts_with("## ")
+                || line.starts_with("```")
+                || line.starts_with("- [ ]")
+                || line.starts_with("- [x]")
+        })
+}
+
+enum Archive {
+    Zip(Box<zip::ZipWriter<File>>),
+    Tar(tar::Builder<File>),
+}
+
+impl Archive {
+    fn create(path: &Path, format: BundleFormat) -> Result<Self> {
+        let file = File::create(path)
+            .map_err(|e| CbError::InvalidInput(format!("{}: {}", path.display(), e)))?;
+        Ok(match format {
+            BundleFormat::Zip => Archive::Zip(Box::new(zip::ZipWriter::new(file))),
+            BundleFormat::Tar => Archive::Tar(tar::Builder::new(file)),
+        })
+    }
+
+    fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let io_err = |e: std::io::Error| CbError::InvalidInput(format!("writing bundle: {}", e));
+        match self {
+            Archive::Zip(zip) => {
+                let options = zip::write::SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Deflated);
+                zip.start_file(name, options)
+                    .map_err(|e| CbError::InvalidInput(format!("writing bundle: {}", e)))?;
+                zip.write_all(data).map_err(io_err)
+            }
+            Archive::Tar(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(Utc::now().timestamp() as u64);
+                header.set_cksum();
+                tar.append_data(&mut header, name, data).map_err(io_err)
+            }
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Archive::Zip(zip) => zip
+                .finish()
+                .map(|_| ())
+                .map_err(|e| CbError::InvalidInput(format!("writing bundle: {}", e))),
+            Archive::Tar(tar) => tar
+                .into_inner()
+                .map(|_| ())
+                .map_err(|e| CbError::InvalidInput(format!("writing bundle: {}", e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::hash_content;
+    use crate::storage::models::NewClip;
+    use crate::storage::sqlite::SqliteStorage;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    fn seeded() -> SqliteStorage {
+        let storage = SqliteStorage::in_memory().unwrap();
+        for text in ["plain note", "# Plan\n- [ ] pack", "untagged"] {
+            let clip = storage
+                .insert(NewClip {
+                    text_content: Some(text.into()),
+                    hash: hash_content(text.as_bytes()),
+                    size_bytes: text.len() as i64,
+                    ..Default::default()
+                })
+                .unwrap();
+            if text != "untagged" {
+                storage.add_tag(clip.id, "trip").unwrap();
+            }
+        }
+        storage
+    }
+
+    fn trip() -> ClipFilter {
+        ClipFilter {
+            tag: Some("trip".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            BundleFormat::from_path(Path::new("a.ZIP")).unwrap(),
+            BundleFormat::Zip
+        );
+        assert_eq!(
+            BundleFormat::from_path(Path::new("a.tar")).unwrap(),
+            BundleFormat::Tar
+        );
+        assert!(BundleFormat::from_path(Path::new("a.rar")).is_err());
+    }
+
+    #[test]
+    fn test_zip_bundle_has_files_and_manifest() {
+        let dir = TempDir::new().unwrap();
+        let out = dir.path().join("trip.zip");
+        let summary = write_bundle(&seeded(), &trip(), &out).unwrap();
+        assert_eq!(summary.clips, 2);
+
+        let mut zip = zip::ZipArchive::new(File::open(&out).unwrap()).unwrap();
+        let mut names: Vec<_> = zip.file_names().map(String::from).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            ["trip/clip-1.txt", "trip/clip-2.md", "trip/manifest.json"]
+        );
+
+        let mut manifest = String::new();
+        zip.by_name("trip/manifest.json")
+            .unwrap()
+            .read_to_string(&mut manifest)
+            .unwrap();
+        let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(manifest["clips"][0]["file"], "trip/clip-1.txt");
+        assert_eq!(manifest["clips"][1]["tags"], serde_json::json!(["trip"]));
+    }
+
+    #[test]
+    fn test_tar_bundle() {
+        let dir = TempDir::new().unwrap();
+        let out = dir.path().join("trip.tar");
+        write_bundle(&seeded(), &trip(), &out).unwrap();
+
+        let mut tar = tar::Archive::new(File::open(&out).unwrap());
+        let mut entries = tar.entries().unwrap().map(|e| e.unwrap());
+        let mut first = entries.next().unwrap();
+        assert_eq!(first.path().unwrap().to_str(), Some("trip/clip-1.txt"));
+        let mut text = String::new();
+        first.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "plain note");
+        assert_eq!(entries.count(), 2);
+    }
+
+    #[test]
+    fn test_empty_bundle_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        let filter = ClipFilter {
+            tag: Some("nope".into()),
+            ..Default::default()
+        };
+        let out = dir.path().join("x.zip");
+        assert!(matches!(
+            write_bundle(&seeded(), &filter, &out),
+            Err(CbError::NotFound(_))
+        ));
+        assert!(!out.exists());
+    }
+}
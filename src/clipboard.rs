@@ -1,13 +1,20 @@
 use std::fs;
 use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use arboard::Clipboard;
 use image::ImageEncoder;
+use serde::Serialize;
 
 use crate::errors::{CbError, Result};
 use crate::hash::hash_content;
-use crate::storage::models::{ContentType, NewClip};
+use crate::hook::CopyHook;
+use crate::i18n::tf;
+use crate::storage::models::{Clip, ContentType, NewClip, Workspace};
 
+#[derive(Clone)]
 pub struct ClipboardContent {
     pub content_type: ContentType,
     pub text: Option<String>,
@@ -16,61 +23,692 @@ pub struct ClipboardContent {
     pub height: Option<i32>,
     pub hash: String,
     pub size_bytes: i64,
+    /// Bundle identifier of the app the content was copied from.
+    pub source_app: Option<String>,
+    /// That app's name, e.g. `Safari`.
+    pub app_name: Option<String>,
+    /// Space and display it was copied in, when `record_workspace` is on.
+    pub workspace: Workspace,
+    /// Project the frontmost window was in, per `project_tags`.
+    pub project: Option<String>,
 }
 
-pub fn read_clipboard() -> Result<Option<ClipboardContent>> {
-    let mut cb = Clipboard::new().map_err(|e| CbError::Clipboard(e.to_string()))?;
-
-    if let Ok(text) = cb.get_text()
-        && !text.is_empty()
-    {
-        let hash = hash_content(text.as_bytes());
-        let size = text.len() as i64;
-        return Ok(Some(ClipboardContent {
+impl ClipboardContent {
+    pub fn from_text(text: String) -> Self {
+        ClipboardContent {
             content_type: ContentType::Text,
+            hash: hash_content(text.as_bytes()),
+            size_bytes: text.len() as i64,
             text: Some(text),
             image_data: None,
             width: None,
             height: None,
-            hash,
-            size_bytes: size,
-        }));
+            source_app: None,
+            app_name: None,
+            workspace: Workspace::default(),
+            project: None,
+        }
     }
 
-    if let Ok(img) = cb.get_image() {
-        let hash = hash_content(&img.bytes);
-        let size = img.bytes.len() as i64;
-        return Ok(Some(ClipboardContent {
+    /// An image from RGBA pixels, as read off the clipboard.
+    pub fn from_rgba(bytes: Vec<u8>, width: usize, height: usize) -> Self {
+        ClipboardContent {
             content_type: ContentType::Image,
+            hash: hash_content(&bytes),
+            size_bytes: bytes.len() as i64,
             text: None,
-            image_data: Some(img.bytes.into_owned()),
-            width: Some(img.width as i32),
-            height: Some(img.height as i32),
-            hash,
-            size_bytes: size,
-        }));
+            image_data: Some(bytes),
+            width: Some(width as i32),
+            height: Some(height as i32),
+            source_app: None,
+            app_name: None,
+            workspace: Workspace::default(),
+            project: None,
+        }
+    }
+
+    /// An image clip from PNG file data, hashed by its pixels like a copied
+    /// image so the two deduplicate.
+    pub fn from_png(data: &[u8]) -> Result<Self> {
+        let img = image::load_from_memory_with_format(data, image::ImageFormat::Png)
+            .map_err(|e| CbError::Image(e.to_string()))?
+            .to_rgba8();
+        let (width, height) = img.dimensions();
+        Ok(Self::from_rgba(img.into_raw(), width as usize, height as usize))
+    }
+}
+
+/// Whether `data` starts with the PNG file signature.
+pub fn is_png(data: &[u8]) -> bool {
+    data.starts_with(b"\x89PNG\r\n\x1a\n")
+}
+
+/// A clipboard cb reads from and copies to: the system one, or a
+/// `FakeClipboard` in tests, so capture and copy paths run without touching
+/// the real pasteboard.
+pub trait ClipboardProvider {
+    /// What's on the clipboard, if it's text or an image.
+    fn read(&self) -> Result<Option<ClipboardContent>>;
+    fn set_text(&self, text: &str) -> Result<()>;
+    /// Put an image, as RGBA pixels, on the clipboard.
+    fn set_image(&self, width: usize, height: usize, rgba: &[u8]) -> Result<()>;
+    fn clear(&self) -> Result<()>;
+    /// Every type on the pasteboard; `None` when they can't be listed.
+    fn types(&self) -> Option<Vec<PasteboardType>>;
+    /// A number that changes whenever something is copied, much cheaper to
+    /// get than the content. `None` when the clipboard can't tell, so
+    /// callers have to read the content to notice changes.
+    fn change_count(&self) -> Option<i64>;
+
+    /// The first of `PRIVATE_MARKERS` on the clipboard's content, if any.
+    /// Cheap enough to ask before reading the content.
+    fn marked_private(&self) -> Option<&'static str> {
+        self.types().as_deref().and_then(private_marker)
+    }
+}
+
+/// The system clipboard, through `arboard`.
+pub struct SystemClipboard;
+
+impl SystemClipboard {
+    fn open() -> Result<Clipboard> {
+        Clipboard::new().map_err(|e| CbError::Clipboard(e.to_string()))
+    }
+}
+
+impl ClipboardProvider for SystemClipboard {
+    fn read(&self) -> Result<Option<ClipboardContent>> {
+        let mut cb = Self::open()?;
+
+        if let Ok(text) = cb.get_text()
+            && !text.is_empty()
+        {
+            return Ok(Some(ClipboardContent::from_text(text)));
+        }
+
+        if let Ok(img) = cb.get_image() {
+            return Ok(Some(ClipboardContent::from_rgba(
+                img.bytes.into_owned(),
+                img.width,
+                img.height,
+            )));
+        }
+
+        Ok(None)
+    }
+
+    fn set_text(&self, text: &str) -> Result<()> {
+        Self::open()?
+            .set_text(text)
+            .map_err(|e| CbError::Clipboard(e.to_string()))
+    }
+
+    fn set_image(&self, width: usize, height: usize, rgba: &[u8]) -> Result<()> {
+        Self::open()?
+            .set_image(arboard::ImageData {
+                width,
+                height,
+                bytes: rgba.into(),
+            })
+            .map_err(|e| CbError::Clipboard(e.to_string()))
+    }
+
+    fn clear(&self) -> Result<()> {
+        Self::open()?.clear().map_err(|e| CbError::Clipboard(e.to_string()))
+    }
+
+    fn types(&self) -> Option<Vec<PasteboardType>> {
+        pasteboard_types()
+    }
+
+    fn change_count(&self) -> Option<i64> {
+        pasteboard_change_count()
+    }
+
+    fn marked_private(&self) -> Option<&'static str> {
+        // Only the names: listing sizes would read every type's data.
+        let types: Vec<PasteboardType> = pasteboard_type_names()?
+            .into_iter()
+            .map(|name| PasteboardType { name, size: None })
+            .collect();
+        private_marker(&types)
+    }
+}
+
+/// An in-memory clipboard for tests. Content put on it with `copy_text`
+/// carries the pasteboard types given, as an app's copy would. Like the
+/// system pasteboard it counts changes, and it also counts reads.
+#[cfg(any(test, feature = "fake-clipboard"))]
+#[derive(Default)]
+pub struct FakeClipboard {
+    state: std::sync::Mutex<(Option<ClipboardContent>, Vec<PasteboardType>)>,
+    changes: std::sync::atomic::AtomicI64,
+    reads: std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(any(test, feature = "fake-clipboard"))]
+impl FakeClipboard {
+    /// Copy `text` as an app would, offering it as `types` besides plain
+    /// text, e.g. a password manager's `PRIVATE_MARKERS`.
+    pub fn copy_text(&self, text: &str, types: &[&str]) {
+        let types = std::iter::once("public.utf8-plain-text")
+            .chain(types.iter().copied())
+            .map(|name| PasteboardType {
+                name: name.to_string(),
+                size: None,
+            })
+            .collect();
+        self.put(Some(ClipboardContent::from_text(text.to_string())), types);
+    }
+
+    /// How many times the content was read through `read`.
+    pub fn reads(&self) -> usize {
+        self.reads.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn put(&self, content: Option<ClipboardContent>, types: Vec<PasteboardType>) {
+        *self.state.lock().unwrap() = (content, types);
+        self.changes.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// The text on the clipboard, if it holds text.
+    pub fn text(&self) -> Option<String> {
+        self.state.lock().unwrap().0.as_ref().and_then(|c| c.text.clone())
+    }
+
+    /// What's on the clipboard, without going through `read`.
+    pub fn content(&self) -> Option<ClipboardContent> {
+        self.state.lock().unwrap().0.clone()
+    }
+}
+
+#[cfg(any(test, feature = "fake-clipboard"))]
+impl ClipboardProvider for FakeClipboard {
+    fn read(&self) -> Result<Option<ClipboardContent>> {
+        self.reads.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Ok(self.content())
+    }
+
+    fn set_text(&self, text: &str) -> Result<()> {
+        self.copy_text(text, &[]);
+        Ok(())
+    }
+
+    fn set_image(&self, width: usize, height: usize, rgba: &[u8]) -> Result<()> {
+        let content = ClipboardContent::from_rgba(rgba.to_vec(), width, height);
+        let types = vec![PasteboardType {
+            name: "public.png".to_string(),
+            size: None,
+        }];
+        self.put(Some(content), types);
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.put(None, Vec::new());
+        Ok(())
+    }
+
+    fn types(&self) -> Option<Vec<PasteboardType>> {
+        Some(self.state.lock().unwrap().1.clone())
+    }
+
+    fn change_count(&self) -> Option<i64> {
+        Some(self.changes.load(std::sync::atomic::Ordering::Relaxed))
+    }
+}
+
+/// One representation on the pasteboard, e.g. `public.utf8-plain-text`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PasteboardType {
+    #[serde(rename = "type")]
+    pub name: String,
+    /// Bytes of data for this type, given only for the one cb captures
+    /// (text, or else PNG or TIFF image data).
+    pub size: Option<i64>,
+}
+
+/// Lists each pasteboard type, one per line. The type cb captures is
+/// followed by a tab and the byte length of its data; the others' data
+/// (possibly large images) isn't read.
+const PASTEBOARD_TYPES_SCRIPT: &str = r#"ObjC.import("AppKit");
+const pb = $.NSPasteboard.generalPasteboard;
+const types = ObjC.deepUnwrap(pb.types) || [];
+const captured = ["public.utf8-plain-text", "public.png", "public.tiff"].find((t) => types.includes(t));
+types.map((t) => {
+  if (t !== captured) return t;
+  const data = pb.dataForType(t);
+  return t + "\t" + (data.isNil() ? "" : data.length);
+}).join("\n")"#;
+
+/// Every type on the macOS pasteboard, in the order the source app offered
+/// them. `None` when they can't be listed (e.g. off macOS).
+pub fn pasteboard_types() -> Option<Vec<PasteboardType>> {
+    let output = Command::new("osascript")
+        .args(["-l", "JavaScript", "-e", PASTEBOARD_TYPES_SCRIPT])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_pasteboard_types(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// The Objective-C runtime, with AppKit linked for `NSPasteboard`.
+#[cfg(target_os = "macos")]
+#[link(name = "AppKit", kind = "framework")]
+#[link(name = "objc")]
+unsafe extern "C" {
+    fn objc_getClass(name: *const std::ffi::c_char) -> *mut std::ffi::c_void;
+    fn sel_registerName(name: *const std::ffi::c_char) -> *mut std::ffi::c_void;
+    fn objc_msgSend();
+    fn objc_autoreleasePoolPush() -> *mut std::ffi::c_void;
+    fn objc_autoreleasePoolPop(pool: *mut std::ffi::c_void);
+}
+
+/// `+[NSPasteboard generalPasteboard]`, or `None` if AppKit can't provide it.
+///
+/// # Safety
+///
+/// The shared pasteboard isn't owned by the caller and must not be released.
+#[cfg(target_os = "macos")]
+unsafe fn general_pasteboard() -> Option<*mut std::ffi::c_void> {
+    type Object = *mut std::ffi::c_void;
+    // SAFETY: objc_msgSend is called through the exact signature of
+    // +[NSPasteboard generalPasteboard].
+    unsafe {
+        let class = objc_getClass(c"NSPasteboard".as_ptr());
+        if class.is_null() {
+            return None;
+        }
+        let send = objc_msgSend as unsafe extern "C" fn();
+        let general: unsafe extern "C" fn(Object, Object) -> Object = std::mem::transmute(send);
+        let pasteboard = general(class, sel_registerName(c"generalPasteboard".as_ptr()));
+        (!pasteboard.is_null()).then_some(pasteboard)
+    }
+}
+
+/// `changeCount` of the general pasteboard, which macOS bumps on every copy.
+/// Asking for it is a cheap in-process call, unlike reading the content.
+/// `None` off macOS.
+pub fn pasteboard_change_count() -> Option<i64> {
+    #[cfg(target_os = "macos")]
+    {
+        type Object = *mut std::ffi::c_void;
+        // SAFETY: objc_msgSend is called through the exact signature of
+        // -[NSPasteboard changeCount].
+        unsafe {
+            let pasteboard = general_pasteboard()?;
+            let send = objc_msgSend as unsafe extern "C" fn();
+            let change_count: unsafe extern "C" fn(Object, Object) -> isize = std::mem::transmute(send);
+            Some(change_count(pasteboard, sel_registerName(c"changeCount".as_ptr())) as i64)
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        None
+    }
+}
+
+/// Names of the types on the general pasteboard, asked in-process without
+/// reading any of their data, so it's cheap enough to check on every copy.
+/// `None` off macOS.
+pub fn pasteboard_type_names() -> Option<Vec<String>> {
+    #[cfg(target_os = "macos")]
+    {
+        type Object = *mut std::ffi::c_void;
+        // SAFETY: objc_msgSend is called through the exact signatures of
+        // -[NSPasteboard types], -[NSArray count], -[NSArray objectAtIndex:],
+        // and -[NSString UTF8String]. The autoreleased array and strings are
+        // copied out before their pool is popped.
+        unsafe {
+            let pasteboard = general_pasteboard()?;
+            let send = objc_msgSend as unsafe extern "C" fn();
+            let object: unsafe extern "C" fn(Object, Object) -> Object = std::mem::transmute(send);
+            let count: unsafe extern "C" fn(Object, Object) -> usize = std::mem::transmute(send);
+            let at: unsafe extern "C" fn(Object, Object, usize) -> Object = std::mem::transmute(send);
+            let utf8: unsafe extern "C" fn(Object, Object) -> *const std::ffi::c_char =
+                std::mem::transmute(send);
+
+            let pool = objc_autoreleasePoolPush();
+            let mut names = Vec::new();
+            // `types` is nil when the pasteboard is empty.
+            let types = object(pasteboard, sel_registerName(c"types".as_ptr()));
+            if !types.is_null() {
+                let object_at = sel_registerName(c"objectAtIndex:".as_ptr());
+                let utf8_string = sel_registerName(c"UTF8String".as_ptr());
+                for i in 0..count(types, sel_registerName(c"count".as_ptr())) {
+                    let name = utf8(at(types, object_at, i), utf8_string);
+                    if !name.is_null() {
+                        names.push(std::ffi::CStr::from_ptr(name).to_string_lossy().into_owned());
+                    }
+                }
+            }
+            objc_autoreleasePoolPop(pool);
+            Some(names)
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        None
+    }
+}
+
+/// Types password managers and other apps add to the pasteboard to ask
+/// clipboard managers not to keep what they copied (see nspasteboard.org).
+pub const PRIVATE_MARKERS: &[&str] = &["org.nspasteboard.ConcealedType", "org.nspasteboard.TransientType"];
+
+/// The first of `PRIVATE_MARKERS` among `types`, if any.
+pub fn private_marker(types: &[PasteboardType]) -> Option<&'static str> {
+    PRIVATE_MARKERS
+        .iter()
+        .copied()
+        .find(|marker| types.iter().any(|t| t.name == *marker))
+}
+
+fn parse_pasteboard_types(output: &str) -> Vec<PasteboardType> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, size) = line.split_once('\t').unwrap_or((line, ""));
+            let name = name.trim();
+            (!name.is_empty()).then(|| PasteboardType {
+                name: name.to_string(),
+                size: size.trim().parse().ok(),
+            })
+        })
+        .collect()
+}
+
+/// Whether the system clipboard can be opened from this process.
+pub fn check_access() -> Result<()> {
+    SystemClipboard::open().map(|_| ())
+}
+
+/// The frontmost app, as `frontmost_app` found it.
+pub struct FrontApp {
+    pub bundle_id: String,
+    /// The name shown in the Dock, e.g. `Safari`.
+    pub name: Option<String>,
+}
+
+/// The frontmost app, via `lsappinfo`. `None` when it can't be determined
+/// (e.g. off macOS).
+pub fn frontmost_app() -> Option<FrontApp> {
+    let asn = Command::new("lsappinfo").arg("front").output().ok()?;
+    let asn = String::from_utf8_lossy(&asn.stdout).trim().to_string();
+    if asn.is_empty() {
+        return None;
+    }
+    let info = |key: &str| {
+        let output = Command::new("lsappinfo")
+            .args(["info", "-only", key, &asn])
+            .output()
+            .ok()?;
+        parse_app_info(&String::from_utf8_lossy(&output.stdout))
+    };
+    Some(FrontApp {
+        bundle_id: info("bundleid")?,
+        name: info("name"),
+    })
+}
+
+/// Title of the frontmost app's front window, via System Events. Needs
+/// Accessibility permission; `None` without it or off macOS.
+pub fn frontmost_window_title() -> Option<String> {
+    let output = Command::new("osascript")
+        .args([
+            "-e",
+            "tell application \"System Events\" to get name of front window of \
+             (first application process whose frontmost is true)",
+        ])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let title = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!title.is_empty()).then_some(title)
+}
+
+/// Extract the value from `lsappinfo` output such as
+/// `"CFBundleIdentifier"="com.apple.Terminal"` or `"LSDisplayName"="Terminal"`.
+fn parse_app_info(output: &str) -> Option<String> {
+    let (_, value) = output.trim().split_once('=')?;
+    let value = value.trim().trim_matches('"');
+    if value.is_empty() || value == "[ NULL ]" {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+#[cfg(target_os = "macos")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CGPoint {
+    x: f64,
+    y: f64,
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    // Private but long-stable; window managers rely on them too.
+    fn CGSMainConnectionID() -> i32;
+    fn CGSGetActiveSpace(connection: i32) -> u64;
+    fn CGEventCreate(source: *const std::ffi::c_void) -> *mut std::ffi::c_void;
+    fn CGEventGetLocation(event: *mut std::ffi::c_void) -> CGPoint;
+    fn CGGetDisplaysWithPoint(point: CGPoint, max: u32, displays: *mut u32, count: *mut u32) -> i32;
+}
+
+/// The Space active now and the display the pointer is on. Both are `None`
+/// off macOS.
+pub fn active_workspace() -> Workspace {
+    #[cfg(target_os = "macos")]
+    {
+        // SAFETY: the connection is this process's own; the event is only
+        // read for its location and released before returning.
+        unsafe {
+            let space = CGSGetActiveSpace(CGSMainConnectionID());
+            let event = CGEventCreate(std::ptr::null());
+            let display = if event.is_null() {
+                None
+            } else {
+                let point = CGEventGetLocation(event);
+                CFRelease(event);
+                let (mut display, mut count) = (0u32, 0u32);
+                (CGGetDisplaysWithPoint(point, 1, &mut display, &mut count) == 0 && count > 0)
+                    .then_some(display as i64)
+            };
+            Workspace {
+                space: (space != 0).then_some(space as i64),
+                display,
+            }
+        }
     }
+    #[cfg(not(target_os = "macos"))]
+    {
+        Workspace::default()
+    }
+}
+
+/// System Settings pane listing apps allowed to control the computer.
+const ACCESSIBILITY_SETTINGS_URL: &str =
+    "x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility";
+
+/// What to do when Accessibility permission is missing.
+pub const ACCESSIBILITY_HELP: &str = "Accessibility permission is needed to paste. In System Settings → \
+     Privacy & Security → Accessibility, turn on your terminal app, then restart it";
+
+#[cfg(target_os = "macos")]
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn AXIsProcessTrusted() -> bool;
+}
+
+/// Whether this process may send keystrokes to other apps. macOS grants
+/// that to the terminal cb runs in, not to cb itself. `None` off macOS,
+/// where there's no such permission.
+pub fn accessibility_trusted() -> Option<bool> {
+    #[cfg(target_os = "macos")]
+    {
+        // SAFETY: takes no arguments and only reads the process's trust state.
+        Some(unsafe { AXIsProcessTrusted() })
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        None
+    }
+}
+
+/// Open the Accessibility pane of System Settings.
+pub fn open_accessibility_settings() -> Result<()> {
+    Command::new("open")
+        .arg(ACCESSIBILITY_SETTINGS_URL)
+        .status()
+        .map(|_| ())
+        .map_err(|e| CbError::Clipboard(e.to_string()))
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventCreateKeyboardEvent(
+        source: *const std::ffi::c_void,
+        keycode: u16,
+        key_down: bool,
+    ) -> *mut std::ffi::c_void;
+    fn CGEventSetFlags(event: *mut std::ffi::c_void, flags: u64);
+    fn CGEventPost(tap: u32, event: *mut std::ffi::c_void);
+}
 
-    Ok(None)
+#[cfg(target_os = "macos")]
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    fn CFRelease(cf: *const std::ffi::c_void);
 }
 
-pub fn write_text_to_clipboard(text: &str) -> Result<()> {
-    let mut cb = Clipboard::new().map_err(|e| CbError::Clipboard(e.to_string()))?;
-    cb.set_text(text).map_err(|e| CbError::Clipboard(e.to_string()))
+/// Press Cmd+V in whatever app is frontmost, right away, by posting the key
+/// events directly. Needs Accessibility permission for the terminal (or
+/// launcher) cb runs in; without it macOS drops the events silently, so
+/// callers go through `paste_after`, which checks first.
+fn paste_keystroke() -> Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        /// Virtual key code of V on an ANSI layout.
+        const KEY_V: u16 = 9;
+        const FLAG_COMMAND: u64 = 0x0010_0000;
+        const HID_EVENT_TAP: u32 = 0;
+        for key_down in [true, false] {
+            // SAFETY: a null source is allowed; the event is checked for null,
+            // posted, and released exactly once.
+            unsafe {
+                let event = CGEventCreateKeyboardEvent(std::ptr::null(), KEY_V, key_down);
+                if event.is_null() {
+                    return Err(CbError::Clipboard("could not create the paste keystroke".into()));
+                }
+                CGEventSetFlags(event, FLAG_COMMAND);
+                CGEventPost(HID_EVENT_TAP, event);
+                CFRelease(event);
+            }
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        Err(CbError::Clipboard("pasting is only supported on macOS".into()))
+    }
 }
 
-pub fn write_image_to_clipboard(path: &Path) -> Result<()> {
+/// Press Cmd+V in whatever app is frontmost once `after` has passed, e.g.
+/// for a launcher window to close and hand focus back. Without
+/// Accessibility permission it opens the settings pane and fails instead,
+/// since the keystroke would be dropped.
+pub fn paste_after(after: Duration) -> Result<()> {
+    if accessibility_trusted() == Some(false) {
+        let _ = open_accessibility_settings();
+        return Err(CbError::Clipboard(tf("copied, but not pasted. {0}", &[&ACCESSIBILITY_HELP])));
+    }
+    thread::sleep(after);
+    paste_keystroke()
+}
+
+/// Put the image file at `path` on `clipboard`.
+fn write_image(clipboard: &dyn ClipboardProvider, path: &Path) -> Result<()> {
     let img = image::open(path).map_err(|e| CbError::Image(e.to_string()))?;
     let rgba = img.to_rgba8();
     let (w, h) = rgba.dimensions();
-    let mut cb = Clipboard::new().map_err(|e| CbError::Clipboard(e.to_string()))?;
-    let img_data = arboard::ImageData {
-        width: w as usize,
-        height: h as usize,
-        bytes: rgba.into_raw().into(),
-    };
-    cb.set_image(img_data)
-        .map_err(|e| CbError::Clipboard(e.to_string()))
+    clipboard.set_image(w as usize, h as usize, rgba.as_raw())
+}
+
+/// Put `clip` back on `clipboard`, passing text through the `on_copy` hook
+/// first when one is configured. Shared by the CLI and TUI. Returns `false`
+/// when there was nothing to copy (a file reference, or a clip whose
+/// content is missing).
+pub fn copy_clip(clipboard: &dyn ClipboardProvider, clip: &Clip, on_copy: Option<&CopyHook>) -> Result<bool> {
+    match clip.content_type {
+        ContentType::Text => {
+            let Some(ref text) = clip.text_content else {
+                return Ok(false);
+            };
+            match on_copy {
+                Some(hook) => clipboard.set_text(&hook.apply(text)?)?,
+                None => clipboard.set_text(text)?,
+            }
+            Ok(true)
+        }
+        ContentType::Image => {
+            let Some(ref path) = clip.image_path else {
+                return Ok(false);
+            };
+            write_image(clipboard, Path::new(path))?;
+            Ok(true)
+        }
+        ContentType::FileRef => Ok(false),
+    }
+}
+
+/// Interval at which `restore_after` checks whether the clipboard changed.
+const RESTORE_POLL: Duration = Duration::from_millis(250);
+
+/// Wait `after`, then put `previous` back on the clipboard (or empty it, if
+/// it was empty). Gives up without touching anything if something else is
+/// copied in the meantime, so a newer copy is never clobbered. Returns
+/// whether it restored.
+pub fn restore_after(
+    clipboard: &dyn ClipboardProvider,
+    previous: Option<&ClipboardContent>,
+    after: Duration,
+) -> Result<bool> {
+    // A copy moves the change count, which is far cheaper to watch than
+    // the content (an image especially); compare content only without one.
+    let current_hash = || clipboard.read().map(|c| c.map(|c| c.hash));
+    let count = clipboard.change_count();
+    let ours = if count.is_none() { current_hash()? } else { None };
+    let deadline = Instant::now() + after;
+    while Instant::now() < deadline {
+        thread::sleep(RESTORE_POLL.min(deadline.saturating_duration_since(Instant::now())));
+        let changed = match count {
+            Some(count) => clipboard.change_count() != Some(count),
+            None => current_hash()? != ours,
+        };
+        if changed {
+            return Ok(false);
+        }
+    }
+
+    match previous {
+        Some(ClipboardContent {
+            text: Some(text), ..
+        }) => clipboard.set_text(text)?,
+        Some(ClipboardContent {
+            image_data: Some(bytes),
+            width: Some(w),
+            height: Some(h),
+            ..
+        }) => clipboard.set_image(*w as usize, *h as usize, bytes)?,
+        _ => clipboard.clear()?,
+    }
+    Ok(true)
 }
 
 pub fn save_image_to_file(data: &[u8], width: u32, height: u32, path: &Path) -> Result<()> {
@@ -95,7 +733,10 @@ pub fn clipboard_content_to_new_clip(
         image_width: content.width,
         image_height: content.height,
         hash: content.hash,
+        norm_hash: None,
         size_bytes: content.size_bytes,
+        created_at: None,
+        origin: None,
     }
 }
 
@@ -114,6 +755,10 @@ mod tests {
             height: None,
             hash: "abc123".to_string(),
             size_bytes: 5,
+            source_app: None,
+            app_name: None,
+            workspace: Workspace::default(),
+            project: None,
         };
         let clip = clipboard_content_to_new_clip(content, None);
         assert_eq!(clip.content_type, ContentType::Text);
@@ -132,6 +777,10 @@ mod tests {
             height: Some(10),
             hash: "img_hash".to_string(),
             size_bytes: 100,
+            source_app: None,
+            app_name: None,
+            workspace: Workspace::default(),
+            project: None,
         };
         let clip = clipboard_content_to_new_clip(content, Some("/images/test.png".to_string()));
         assert_eq!(clip.content_type, ContentType::Image);
@@ -139,6 +788,100 @@ mod tests {
         assert_eq!(clip.image_width, Some(10));
     }
 
+    #[test]
+    fn test_from_png_hashes_pixels() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("dot.png");
+        let pixels = vec![255u8, 0, 0, 255, 0, 0, 255, 255];
+        save_image_to_file(&pixels, 2, 1, &path).unwrap();
+        let data = fs::read(&path).unwrap();
+        assert!(is_png(&data));
+        assert!(!is_png(b"plain text"));
+
+        let content = ClipboardContent::from_png(&data).unwrap();
+        assert_eq!(content.content_type, ContentType::Image);
+        assert_eq!((content.width, content.height), (Some(2), Some(1)));
+        assert_eq!(content.hash, hash_content(&pixels));
+        assert!(ClipboardContent::from_png(b"\x89PNG\r\n\x1a\nbroken").is_err());
+    }
+
+    #[test]
+    fn test_copy_clip_and_restore_with_fake_clipboard() {
+        use crate::storage::ClipStorage;
+        use crate::storage::sqlite::SqliteStorage;
+
+        let storage = SqliteStorage::in_memory().unwrap();
+        let new_clip = clipboard_content_to_new_clip(ClipboardContent::from_text("from history".into()), None);
+        let clip = storage.insert(new_clip).unwrap();
+        let fake = FakeClipboard::default();
+        fake.copy_text("was there", &[]);
+        let previous = fake.read().unwrap();
+
+        assert!(copy_clip(&fake, &clip, None).unwrap());
+        assert_eq!(fake.text().as_deref(), Some("from history"));
+        assert!(restore_after(&fake, previous.as_ref(), Duration::ZERO).unwrap());
+        assert_eq!(fake.text().as_deref(), Some("was there"));
+        assert!(restore_after(&fake, None, Duration::ZERO).unwrap());
+        assert!(fake.content().is_none());
+    }
+
+    #[test]
+    fn test_restore_after_leaves_a_newer_copy_alone() {
+        let fake = FakeClipboard::default();
+        fake.copy_text("copied by cb", &[]);
+        let previous = ClipboardContent::from_text("was there".into());
+        let reads = fake.reads();
+
+        let restored = thread::scope(|s| {
+            s.spawn(|| {
+                thread::sleep(Duration::from_millis(100));
+                fake.copy_text("copied since", &[]);
+            });
+            restore_after(&fake, Some(&previous), Duration::from_millis(600)).unwrap()
+        });
+        assert!(!restored);
+        assert_eq!(fake.text().as_deref(), Some("copied since"));
+        // Watched through the change count, without reading the content.
+        assert_eq!(fake.reads(), reads);
+    }
+
+    #[test]
+    fn test_parse_app_info() {
+        assert_eq!(
+            parse_app_info("\"CFBundleIdentifier\"=\"com.apple.Terminal\"\n").as_deref(),
+            Some("com.apple.Terminal")
+        );
+        assert!(parse_app_info("").is_none());
+        assert!(parse_app_info("\"CFBundleIdentifier\"=[ NULL ]").is_none());
+    }
+
+    #[test]
+    fn test_private_marker() {
+        let types = parse_pasteboard_types("public.utf8-plain-text\t12\norg.nspasteboard.ConcealedType\t0\n");
+        assert_eq!(private_marker(&types), Some("org.nspasteboard.ConcealedType"));
+        let types = parse_pasteboard_types("org.nspasteboard.TransientType\t\npublic.utf8-plain-text\t3\n");
+        assert_eq!(private_marker(&types), Some("org.nspasteboard.TransientType"));
+        assert_eq!(private_marker(&parse_pasteboard_types("public.html\t5\n")), None);
+    }
+
+    #[test]
+    fn test_parse_pasteboard_types() {
+        let types = parse_pasteboard_types("public.utf8-plain-text\t42\npublic.html\t\n\n");
+        assert_eq!(
+            types,
+            vec![
+                PasteboardType {
+                    name: "public.utf8-plain-text".into(),
+                    size: Some(42),
+                },
+                PasteboardType {
+                    name: "public.html".into(),
+                    size: None,
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_save_image_to_file() {
         let dir = TempDir::new().unwrap();
@@ -0,0 +1,174 @@
+use std::ops::Deref;
+
+use image::{ImageBuffer, Rgba};
+
+use crate::errors::{CbError, Result};
+use crate::storage::models::Clip;
+
+/// Dominant colors reported per image, most common first.
+pub const MAX_COLORS: usize = 3;
+/// A color must cover at least this share of the image to count as dominant.
+const MIN_SHARE: f64 = 0.05;
+/// Pixels sampled per image at most; large screenshots are strided.
+const MAX_SAMPLES: usize = 65_536;
+/// How far (Euclidean RGB distance, 0-441) a dominant color may be from the
+/// one searched for and still match.
+pub const COLOR_TOLERANCE: f64 = 100.0;
+/// Images at or below this mean brightness count as dark.
+pub const DARK_THRESHOLD: f64 = 0.35;
+/// Images at or above this mean brightness count as light.
+pub const LIGHT_THRESHOLD: f64 = 0.65;
+
+pub type Rgb = [u8; 3];
+
+/// Basic descriptors stored for image clips so they can be found by color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Descriptor {
+    /// Dominant colors as `#rrggbb`, most common first.
+    pub colors: Vec<String>,
+    /// Mean luminance from 0 (black) to 1 (white).
+    pub brightness: f64,
+}
+
+/// Describe an image from its raw RGBA pixels. Fully transparent pixels are
+/// ignored.
+pub fn describe<C: Deref<Target = [u8]>>(img: &ImageBuffer<Rgba<u8>, C>) -> Descriptor {
+    let total = (img.width() as usize) * (img.height() as usize);
+    let stride = total.div_ceil(MAX_SAMPLES).max(1);
+
+    // 8 levels per channel; each bucket remembers its pixels' sum for an
+    // average that looks like the real color rather than the bucket corner.
+    let mut buckets = vec![(0u64, [0u64; 3]); 512];
+    let mut luma = 0.0;
+    let mut sampled = 0usize;
+    for p in img.pixels().step_by(stride).filter(|p| p[3] > 0) {
+        let idx = (p[0] as usize >> 5) << 6 | (p[1] as usize >> 5) << 3 | p[2] as usize >> 5;
+        let bucket = &mut buckets[idx];
+        bucket.0 += 1;
+        for c in 0..3 {
+            bucket.1[c] += p[c] as u64;
+        }
+        luma += 0.2126 * p[0] as f64 + 0.7152 * p[1] as f64 + 0.0722 * p[2] as f64;
+        sampled += 1;
+    }
+    if sampled == 0 {
+        return Descriptor {
+            colors: Vec::new(),
+            brightness: 0.0,
+        };
+    }
+
+    buckets.sort_by_key(|b| std::cmp::Reverse(b.0));
+    let colors = buckets
+        .iter()
+        .take_while(|(count, _)| *count as f64 / sampled as f64 >= MIN_SHARE)
+        .take(MAX_COLORS)
+        .map(|(count, sum)| to_hex([0, 1, 2].map(|c| (sum[c] / count) as u8)))
+        .collect();
+    Descriptor {
+        colors,
+        brightness: luma / sampled as f64 / 255.0,
+    }
+}
+
+pub fn to_hex(rgb: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+/// `#ff0000`, `ff0000`, `#f00`, or a basic color name such as `red`.
+pub fn parse_color(s: &str) -> Result<Rgb> {
+    let named = match s.to_ascii_lowercase().as_str() {
+        "black" => Some([0, 0, 0]),
+        "white" => Some([255, 255, 255]),
+        "gray" | "grey" => Some([128, 128, 128]),
+        "red" => Some([220, 30, 30]),
+        "orange" => Some([255, 140, 0]),
+        "yellow" => Some([240, 220, 40]),
+        "green" => Some([40, 170, 60]),
+        "blue" => Some([30, 90, 220]),
+        "purple" => Some([130, 60, 180]),
+        "pink" => Some([240, 120, 180]),
+        _ => None,
+    };
+    if let Some(rgb) = named {
+        return Ok(rgb);
+    }
+
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    let expanded: String = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_string(),
+        _ => String::new(),
+    };
+    let channel = |i: usize| u8::from_str_radix(expanded.get(i..i + 2)?, 16).ok();
+    match (channel(0), channel(2), channel(4)) {
+        (Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => Err(CbError::InvalidInput(format!(
+            "invalid color \"{}\" (use #rrggbb or a name like red)",
+            s
+        ))),
+    }
+}
+
+fn distance(a: Rgb, b: Rgb) -> f64 {
+    (0..3)
+        .map(|c| (a[c] as f64 - b[c] as f64).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Whether one of `clip`'s dominant colors is close to `target`.
+pub fn has_color(clip: &Clip, target: Rgb) -> bool {
+    clip.colors
+        .iter()
+        .filter_map(|c| parse_color(c).ok())
+        .any(|c| distance(c, target) <= COLOR_TOLERANCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_describe_dominant_colors_and_brightness() {
+        // Three quarters dark navy, one quarter red.
+        let img = RgbaImage::from_fn(40, 40, |x, _| {
+            if x < 30 {
+                Rgba([10, 20, 60, 255])
+            } else {
+                Rgba([230, 20, 20, 255])
+            }
+        });
+        let d = describe(&img);
+        assert_eq!(d.colors, vec!["#0a143c", "#e61414"]);
+        assert!(d.brightness < DARK_THRESHOLD, "{}", d.brightness);
+
+        let white = describe(&RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255])));
+        assert_eq!(white.colors, vec!["#ffffff"]);
+        assert!(white.brightness > 0.99);
+
+        let clear = describe(&RgbaImage::new(4, 4));
+        assert!(clear.colors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#ff0000").unwrap(), [255, 0, 0]);
+        assert_eq!(parse_color("0F0").unwrap(), [0, 255, 0]);
+        assert_eq!(parse_color("Blue").unwrap(), [30, 90, 220]);
+        assert!(parse_color("#ff00").is_err());
+        assert!(parse_color("#gg0000").is_err());
+    }
+
+    #[test]
+    fn test_has_color() {
+        let chart = Clip {
+            colors: vec!["#ffffff".into(), "#d62728".into()],
+            ..Default::default()
+        };
+        assert!(has_color(&chart, parse_color("#ff0000").unwrap()));
+        assert!(has_color(&chart, parse_color("white").unwrap()));
+        assert!(!has_color(&chart, parse_color("blue").unwrap()));
+    }
+}
@@ -1,11 +1,50 @@
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{CbError, Result};
+use crate::hash::DedupMode;
+use crate::hook::{CaptureHook, CopyHook};
+use crate::normalize::Normalize;
+use crate::project::ProjectTags;
+use crate::retention::{ExpireRule, RetentionPolicy};
+use crate::screenshots;
+use crate::secrets::SecretAction;
+use crate::spotlight::SpotlightExport;
+
+/// Default for `capture_debounce_ms`.
+pub const DEFAULT_CAPTURE_DEBOUNCE_MS: u64 = 500;
+
+/// Default for `enrich_workers`.
+pub const DEFAULT_ENRICH_WORKERS: usize = 2;
+/// Upper bound on `enrich_workers`, since OCR runs one process per worker.
+const MAX_ENRICH_WORKERS: usize = 8;
+
+/// Name of the profile whose history lives directly in the base directory.
+pub const DEFAULT_PROFILE: &str = "default";
 
 pub struct AppPaths {
     pub base_dir: PathBuf,
+    /// Profile whose history these paths point at.
+    pub profile: String,
+    /// Directory holding the profile's database and images.
+    pub data_dir: PathBuf,
     pub db_path: PathBuf,
     pub images_dir: PathBuf,
     pub pid_file: PathBuf,
     pub log_file: PathBuf,
+    pub config_file: PathBuf,
+    /// Per-pattern counts of captures dropped by `ignore_patterns`, written
+    /// by the daemon.
+    pub ignore_stats_file: PathBuf,
+    /// The daemon's CPU time and wakeups, written by the daemon.
+    pub resource_stats_file: PathBuf,
+    /// Where the TUI left off: search, order, selection, and pane split.
+    pub tui_state_file: PathBuf,
+    /// Open the database read-only and refuse commands that would write to it.
+    pub read_only: bool,
 }
 
 impl Default for AppPaths {
@@ -16,23 +55,189 @@ impl Default for AppPaths {
 
 impl AppPaths {
     pub fn new() -> Self {
-        let base = dirs::home_dir()
+        Self::from_base(Self::default_base())
+    }
+
+    /// `~/.cb`, the root of all profiles.
+    pub fn default_base() -> PathBuf {
+        dirs::home_dir()
             .expect("Could not determine home directory")
-            .join(".cb");
-        Self::from_base(base)
+            .join(".cb")
     }
 
     pub fn from_base(base: PathBuf) -> Self {
+        Self::for_profile(base, DEFAULT_PROFILE)
+    }
+
+    /// Paths for `profile`. Named profiles keep their own database and images
+    /// under `profiles/<name>/`; the config file and the daemon's pid and log
+    /// are shared, since there is only one clipboard to watch.
+    pub fn for_profile(base: PathBuf, profile: &str) -> Self {
+        let data_dir = if profile == DEFAULT_PROFILE {
+            base.clone()
+        } else {
+            base.join("profiles").join(profile)
+        };
         Self {
-            db_path: base.join("cb.db"),
-            images_dir: base.join("images"),
+            profile: profile.to_string(),
+            db_path: data_dir.join("cb.db"),
+            images_dir: data_dir.join("images"),
+            tui_state_file: data_dir.join("tui.json"),
+            data_dir,
             pid_file: base.join("cb.pid"),
             log_file: base.join("cb.log"),
+            config_file: base.join("config.toml"),
+            ignore_stats_file: base.join("ignored.json"),
+            resource_stats_file: base.join("daemon.json"),
             base_dir: base,
+            read_only: false,
         }
     }
 }
 
+/// User settings from `config.toml`. Every field is optional so a missing or
+/// partial file behaves like the defaults.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// Auto-tagging rules evaluated by the daemon on each capture.
+    pub rules: Vec<TagRule>,
+    /// History retention. When present, the daemon also enforces it
+    /// periodically; otherwise it only applies to explicit `cb clear`.
+    pub retention: Option<RetentionPolicy>,
+    /// Tags whose clips the daemon deletes after a set time, independent of
+    /// `retention`, e.g. a `scratch` tag cleared every 25 minutes.
+    pub expire: Vec<ExpireRule>,
+    /// When true, re-copying content already in history bumps that clip and
+    /// logs the capture instead of ignoring it.
+    pub upsert_duplicates: bool,
+    /// Regexes for text that never enters history, such as one-time codes
+    /// (`^[0-9]{6}$`) or private keys (`^-{5}BEGIN`).
+    pub ignore_patterns: Vec<String>,
+    /// What to do with text that looks like a credential (AWS keys, private
+    /// keys, JWTs, random-looking tokens): `off` (default), `skip`, or
+    /// `redact`.
+    pub secrets: SecretAction,
+    /// Text shorter than this many characters (ignoring surrounding
+    /// whitespace) isn't captured, so stray single-character copies stay out
+    /// of history. 0 (default) captures everything.
+    pub min_text_length: usize,
+    /// Whether text copies differing only in whitespace (or case) count as
+    /// duplicates: `exact` (default), `whitespace`, or `ignore-case`.
+    pub dedup: DedupMode,
+    /// Cleanup applied to copied text before it's hashed and stored, such
+    /// as converting CRLF to LF or dropping `utm_*` parameters from URLs.
+    pub normalize: Normalize,
+    /// Profiles always opened read-only, e.g. ones pointing at a synced or
+    /// backup database.
+    pub read_only_profiles: Vec<String>,
+    /// Inserted between a clip's text and content added with `cb append`
+    /// (default: a newline).
+    pub append_separator: Option<String>,
+    /// External command that can keep, skip, or rewrite each capture before
+    /// the daemon stores it.
+    pub capture_hook: Option<CaptureHook>,
+    /// Command that rewrites text as `cb copy` and the TUI put it back on the
+    /// clipboard.
+    pub on_copy: Option<CopyHook>,
+    /// How long clipboard content must stay unchanged before the daemon
+    /// captures it (default 500). Collapses bursts of rewrites from apps that
+    /// copy progressively; 0 captures every change.
+    pub capture_debounce_ms: Option<u64>,
+    /// Folder screenshots are saved to, for pairing copied screenshots with
+    /// their full-quality files. Defaults to the macOS screenshot location.
+    pub screenshot_dir: Option<PathBuf>,
+    /// Name recorded with each capture, so a database shared by several
+    /// machines shows where content was copied. Defaults to the hostname.
+    pub machine_name: Option<String>,
+    /// Token the `cb serve --http` web viewer requires. When unset, a new
+    /// random token is generated each time the server starts.
+    pub api_token: Option<String>,
+    /// Background workers the daemon runs for OCR, thumbnails, and other
+    /// enrichment (default 2).
+    pub enrich_workers: Option<usize>,
+    /// Fetch the page title of each URL copied, in the background. Off by
+    /// default since it requests every URL you copy.
+    pub fetch_link_titles: bool,
+    /// Record the macOS Space and display active at each capture, so
+    /// history can be listed per Space with `cb list --space`.
+    pub record_workspace: bool,
+    /// Tag captures with the project the frontmost terminal or editor is in.
+    pub project_tags: Option<ProjectTags>,
+    /// Mirror recent text clips into a folder Spotlight indexes.
+    pub spotlight: Option<SpotlightExport>,
+    /// Language for messages, e.g. `de`. Defaults to the system locale
+    /// (`LC_ALL`, `LC_MESSAGES`, or `LANG`).
+    pub locale: Option<String>,
+}
+
+/// `pattern = "jira\\.company\\.com"` / `source_app = "com.apple.dt.Xcode"` → `tag`.
+/// When both conditions are given, both must match.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TagRule {
+    pub tag: String,
+    pub pattern: Option<String>,
+    pub source_app: Option<String>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(CbError::Config(e.to_string())),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        toml::from_str(contents).map_err(|e| CbError::Config(e.to_string()))
+    }
+
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        self.retention.clone().unwrap_or_default()
+    }
+
+    pub fn capture_debounce(&self) -> Duration {
+        Duration::from_millis(self.capture_debounce_ms.unwrap_or(DEFAULT_CAPTURE_DEBOUNCE_MS))
+    }
+
+    /// Whether the daemon should skip `text` under `min_text_length`.
+    pub fn too_short(&self, text: &str) -> bool {
+        text.trim().chars().count() < self.min_text_length
+    }
+
+    pub fn screenshot_dir(&self) -> Option<PathBuf> {
+        self.screenshot_dir.clone().or_else(screenshots::default_dir)
+    }
+
+    pub fn machine_name(&self) -> Option<String> {
+        self.machine_name.clone().or_else(hostname)
+    }
+
+    pub fn enrich_workers(&self) -> usize {
+        self.enrich_workers.unwrap_or(DEFAULT_ENRICH_WORKERS).clamp(1, MAX_ENRICH_WORKERS)
+    }
+
+    pub fn append_separator(&self) -> &str {
+        self.append_separator.as_deref().unwrap_or("\n")
+    }
+}
+
+/// This machine's hostname, without a trailing `.local`.
+pub fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer outlives the call and its length is passed along.
+    let rc = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) };
+    if rc != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    let name = String::from_utf8_lossy(&buf[..len]);
+    // "studio.local" → "studio"
+    let name = name.strip_suffix(".local").unwrap_or(&name);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -45,6 +250,131 @@ mod tests {
         assert_eq!(paths.images_dir, PathBuf::from("/tmp/test-cb/images"));
         assert_eq!(paths.pid_file, PathBuf::from("/tmp/test-cb/cb.pid"));
         assert_eq!(paths.log_file, PathBuf::from("/tmp/test-cb/cb.log"));
+        assert_eq!(paths.config_file, PathBuf::from("/tmp/test-cb/config.toml"));
+    }
+
+    #[test]
+    fn test_for_profile() {
+        let paths = AppPaths::for_profile(PathBuf::from("/tmp/test-cb"), "work");
+        assert_eq!(paths.profile, "work");
+        assert_eq!(paths.db_path, PathBuf::from("/tmp/test-cb/profiles/work/cb.db"));
+        assert_eq!(paths.images_dir, PathBuf::from("/tmp/test-cb/profiles/work/images"));
+        assert_eq!(paths.pid_file, PathBuf::from("/tmp/test-cb/cb.pid"));
+        assert_eq!(paths.config_file, PathBuf::from("/tmp/test-cb/config.toml"));
+    }
+
+    #[test]
+    fn test_load_missing_config_is_default() {
+        let config = Config::load(Path::new("/nonexistent/cb/config.toml")).unwrap();
+        assert!(config.rules.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let config = Config::parse(
+            r#"
+            [[rules]]
+            pattern = "jira\\.company\\.com"
+            tag = "jira"
+
+            [[rules]]
+            source_app = "com.apple.dt.Xcode"
+            tag = "code"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.rules.len(), 2);
+        assert_eq!(config.rules[0].pattern.as_deref(), Some("jira\\.company\\.com"));
+        assert_eq!(config.rules[1].source_app.as_deref(), Some("com.apple.dt.Xcode"));
+    }
+
+    #[test]
+    fn test_parse_retention() {
+        let config = Config::parse(
+            r#"
+            [retention]
+            max_clips = 5000
+            max_size_mb = 500
+            "#,
+        )
+        .unwrap();
+        let policy = config.retention_policy();
+        assert_eq!(policy.max_age_days, Some(crate::retention::DEFAULT_MAX_AGE_DAYS));
+        assert_eq!(policy.max_clips, Some(5000));
+        assert_eq!(policy.max_size_mb, Some(500));
+        assert!(Config::default().retention.is_none());
+    }
+
+    #[test]
+    fn test_parse_upsert_duplicates() {
+        assert!(!Config::default().upsert_duplicates);
+        assert!(Config::parse("upsert_duplicates = true").unwrap().upsert_duplicates);
+    }
+
+    #[test]
+    fn test_capture_debounce() {
+        assert_eq!(Config::default().capture_debounce(), Duration::from_millis(500));
+        let config = Config::parse("capture_debounce_ms = 0").unwrap();
+        assert_eq!(config.capture_debounce(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_parse_ignore_patterns() {
+        let config = Config::parse(r#"ignore_patterns = ['^[0-9]{6}$', '^-{5}BEGIN']"#).unwrap();
+        assert_eq!(config.ignore_patterns, vec!["^[0-9]{6}$", "^-{5}BEGIN"]);
+    }
+
+    #[test]
+    fn test_min_text_length() {
+        assert!(!Config::default().too_short(""));
+        let config = Config::parse("min_text_length = 3").unwrap();
+        assert!(config.too_short(" ab\n"));
+        assert!(config.too_short("é!"));
+        assert!(!config.too_short("abc"));
+    }
+
+    #[test]
+    fn test_parse_read_only_profiles() {
+        let config = Config::parse("read_only_profiles = [\"backup\"]").unwrap();
+        assert_eq!(config.read_only_profiles, vec!["backup"]);
+    }
+
+    #[test]
+    fn test_append_separator() {
+        assert_eq!(Config::default().append_separator(), "\n");
+        let config = Config::parse("append_separator = \" | \"").unwrap();
+        assert_eq!(config.append_separator(), " | ");
+    }
+
+    #[test]
+    fn test_parse_dedup_mode() {
+        assert_eq!(Config::default().dedup, DedupMode::Exact);
+        assert_eq!(Config::parse("dedup = \"ignore-case\"").unwrap().dedup, DedupMode::IgnoreCase);
+        assert!(Config::parse("dedup = \"fuzzy\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_capture_hook() {
+        let config = Config::parse(
+            r#"
+            [capture_hook]
+            command = ["python3", "filter.py"]
+            "#,
+        )
+        .unwrap();
+        let hook = config.capture_hook.unwrap();
+        assert_eq!(hook.command, vec!["python3", "filter.py"]);
+        assert_eq!(hook.timeout_ms, crate::hook::DEFAULT_TIMEOUT_MS);
+        assert!(!hook.skip_on_error);
+        assert!(config.on_copy.is_none());
+
+        let config = Config::parse("[on_copy]\ncommand = [\"strip-watermark\"]").unwrap();
+        assert_eq!(config.on_copy.unwrap().command, vec!["strip-watermark"]);
+    }
+
+    #[test]
+    fn test_parse_invalid_config() {
+        assert!(matches!(Config::parse("rules = 3"), Err(CbError::Config(_))));
     }
 
     #[test]
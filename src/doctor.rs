@@ -0,0 +1,458 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+
+use crate::clipboard;
+use crate::config::{AppPaths, Config};
+use crate::daemon;
+use crate::encryption;
+use crate::ignore::{IgnoreList, IgnoreStats};
+use crate::repair;
+use crate::resources::{HIGH_CPU_PERCENT, ResourceUsage};
+use crate::storage::schema;
+
+/// launchd label a user agent for the daemon is expected to use.
+pub const LAUNCHD_LABEL: &str = "com.faltawy.cb";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to run or change to resolve a warning or failure.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Run every health check. Nothing here writes to the database, so a broken
+/// installation can be inspected without making it worse.
+pub fn run_checks(paths: &AppPaths) -> Vec<Check> {
+    let mut checks = check_database(paths);
+    checks.push(check_daemon(paths));
+    if let Some(check) = check_ignore_patterns(paths) {
+        checks.push(check);
+    }
+    checks.push(check_images_dir(&paths.images_dir));
+    checks.extend(permission_checks());
+    checks.push(check_launchd());
+    checks
+}
+
+/// Clipboard access, and Accessibility permission on macOS (needed to paste
+/// from `cb tui --quick`). Run alone by `cb doctor --check-permissions`.
+pub fn permission_checks() -> Vec<Check> {
+    let mut checks = vec![match clipboard::check_access() {
+        Ok(()) => Check::ok("clipboard", "accessible"),
+        Err(e) => Check::fail(
+            "clipboard",
+            e.to_string(),
+            "grant your terminal clipboard access, or run cb from a GUI session",
+        ),
+    }];
+    match clipboard::accessibility_trusted() {
+        Some(true) => checks.push(Check::ok("accessibility", "granted")),
+        Some(false) => checks.push(Check::warn(
+            "accessibility",
+            "not granted to this terminal; `cb tui --quick` can copy but not paste",
+            "System Settings → Privacy & Security → Accessibility: turn on your terminal \
+             (`cb doctor --check-permissions` opens it)",
+        )),
+        None => {}
+    }
+    checks
+}
+
+/// Database, integrity, schema version, WAL mode, and orphan image checks.
+/// Later checks are skipped when the database can't be opened.
+fn check_database(paths: &AppPaths) -> Vec<Check> {
+    if !paths.db_path.exists() {
+        return vec![Check::warn(
+            "database",
+            format!("{} does not exist yet", paths.db_path.display()),
+            "run `cb daemon start` to begin recording history",
+        )];
+    }
+    let conn = match encryption::connect(&paths.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+        Ok(conn) => conn,
+        Err(e) => {
+            return vec![Check::fail(
+                "database",
+                e.to_string(),
+                format!("check permissions on {}", paths.db_path.display()),
+            )];
+        }
+    };
+
+    let mut checks = Vec::new();
+    let version: i64 = match conn.query_row("PRAGMA user_version", [], |row| row.get(0)) {
+        Ok(v) => v,
+        Err(e) => {
+            return vec![Check::fail(
+                "database",
+                e.to_string(),
+                "the database may be corrupt; run `cb repair`",
+            )];
+        }
+    };
+    checks.push(Check::ok("database", paths.db_path.display().to_string()));
+
+    let problems = repair::integrity_check(&conn);
+    checks.push(match problems.first() {
+        None => Check::ok("integrity", "ok"),
+        Some(first) => Check::fail(
+            "integrity",
+            format!("{} problem(s), e.g. {}", problems.len(), first),
+            "cb daemon stop && cb repair",
+        ),
+    });
+
+    let latest = schema::MIGRATIONS.len() as i64;
+    checks.push(if version == latest {
+        Check::ok("schema", format!("version {}", version))
+    } else if version < latest {
+        Check::warn(
+            "schema",
+            format!("version {} (latest {})", version, latest),
+            "run any cb command (e.g. `cb stats`) to migrate; a backup is taken first",
+        )
+    } else {
+        Check::fail(
+            "schema",
+            format!("version {} is newer than this cb supports ({})", version, latest),
+            "upgrade cb",
+        )
+    });
+
+    checks.push(match conn.query_row("PRAGMA journal_mode", [], |row| row.get::<_, String>(0)) {
+        Ok(mode) if mode.eq_ignore_ascii_case("wal") => Check::ok("wal", "journal_mode=wal"),
+        Ok(mode) => Check::warn(
+            "wal",
+            format!("journal_mode={}", mode),
+            format!(
+                "sqlite3 {} 'PRAGMA journal_mode=WAL' so the daemon and CLI don't block each other",
+                paths.db_path.display()
+            ),
+        ),
+        Err(e) => Check::fail("wal", e.to_string(), "the database may be corrupt"),
+    });
+
+    checks.push(match orphan_images(&conn, &paths.images_dir) {
+        Ok(orphans) if orphans.is_empty() => Check::ok("orphan images", "none"),
+        Ok(orphans) => Check::warn(
+            "orphan images",
+            format!(
+                "{} file(s) in {} not referenced by any clip",
+                orphans.len(),
+                paths.images_dir.display()
+            ),
+            "delete the unreferenced files to reclaim space",
+        ),
+        Err(e) => Check::fail("orphan images", e.to_string(), "the database may be corrupt"),
+    });
+    checks
+}
+
+/// Files in `images_dir` that no clip's `image_path` points at.
+pub fn orphan_images(conn: &Connection, images_dir: &Path) -> rusqlite::Result<Vec<PathBuf>> {
+    let mut stmt = conn.prepare("SELECT image_path FROM clips WHERE image_path IS NOT NULL")?;
+    let referenced: HashSet<PathBuf> = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+
+    let Ok(entries) = fs::read_dir(images_dir) else {
+        return Ok(Vec::new());
+    };
+    let mut orphans: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && !referenced.contains(p))
+        .collect();
+    orphans.sort();
+    Ok(orphans)
+}
+
+fn check_daemon(paths: &AppPaths) -> Check {
+    match daemon::daemon_status(paths) {
+        Ok(Some(pid)) => match process_state(pid) {
+            Some(state) if state.starts_with('T') || state.starts_with('Z') => Check::fail(
+                "daemon",
+                format!("pid {} is not responding (state {})", pid, state),
+                "cb daemon stop && cb daemon start",
+            ),
+            _ => match ResourceUsage::for_pid(&paths.resource_stats_file, pid) {
+                Some(usage) if usage.settled() && usage.cpu_percent() > HIGH_CPU_PERCENT => Check::warn(
+                    "daemon",
+                    format!(
+                        "running (pid {}) but averaging {:.1}% CPU ({:.0} wakeups/min)",
+                        pid,
+                        usage.cpu_percent(),
+                        usage.wakeups_per_minute()
+                    ),
+                    format!(
+                        "check {} for errors repeating every poll, then restart with `cb daemon stop && cb daemon start`",
+                        paths.log_file.display()
+                    ),
+                ),
+                Some(usage) => Check::ok(
+                    "daemon",
+                    format!("running (pid {}, {:.2}% CPU)", pid, usage.cpu_percent()),
+                ),
+                None => Check::ok("daemon", format!("running (pid {})", pid)),
+            },
+        },
+        Ok(None) => Check::warn("daemon", "not running", "cb daemon start"),
+        Err(e) => Check::fail(
+            "daemon",
+            e.to_string(),
+            format!("remove {} and run `cb daemon start`", paths.pid_file.display()),
+        ),
+    }
+}
+
+/// Whether the configured `ignore_patterns` compile, and how many captures
+/// each has skipped since the daemon started. `None` when none are set.
+fn check_ignore_patterns(paths: &AppPaths) -> Option<Check> {
+    let config = Config::load(&paths.config_file).ok()?;
+    if config.ignore_patterns.is_empty() {
+        return None;
+    }
+    if let Err(e) = IgnoreList::compile(&config.ignore_patterns) {
+        return Some(Check::fail(
+            "ignore",
+            e.to_string(),
+            format!("fix ignore_patterns in {}", paths.config_file.display()),
+        ));
+    }
+
+    let stats = IgnoreStats::load(&paths.ignore_stats_file)
+        .filter(|stats| {
+            stats.patterns.iter().map(|p| &p.pattern).eq(config.ignore_patterns.iter())
+        });
+    let detail = match stats {
+        Some(stats) => {
+            let counts: Vec<String> = stats
+                .patterns
+                .iter()
+                .map(|p| format!("{} → {} skipped", p.pattern, p.skipped))
+                .collect();
+            format!(
+                "since {}: {}",
+                stats.since.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                counts.join(", ")
+            )
+        }
+        None => format!(
+            "{} pattern(s); no counts yet (restart the daemon to apply changes)",
+            config.ignore_patterns.len()
+        ),
+    };
+    Some(Check::ok("ignore", detail))
+}
+
+/// The `ps` state code of `pid` (e.g. `S`, `R`, `T` for stopped, `Z` for zombie).
+fn process_state(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", "stat=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    let state = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!state.is_empty()).then_some(state)
+}
+
+fn check_images_dir(images_dir: &Path) -> Check {
+    if !images_dir.exists() {
+        return Check::warn(
+            "images dir",
+            format!("{} does not exist", images_dir.display()),
+            format!("mkdir -p {} (the daemon also creates it on start)", images_dir.display()),
+        );
+    }
+    let probe = images_dir.join(".cb-doctor-probe");
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            Check::ok("images dir", images_dir.display().to_string())
+        }
+        Err(e) => Check::fail(
+            "images dir",
+            format!("{} is not writable: {}", images_dir.display(), e),
+            format!("chmod u+w {}", images_dir.display()),
+        ),
+    }
+}
+
+fn check_launchd() -> Check {
+    let Some(home) = dirs::home_dir() else {
+        return Check::warn("launchd", "home directory unknown", "set $HOME");
+    };
+    let plist = home
+        .join("Library/LaunchAgents")
+        .join(format!("{}.plist", LAUNCHD_LABEL));
+    if !plist.exists() {
+        return Check::ok("launchd", "no agent installed (start the daemon manually)");
+    }
+    let loaded = Command::new("launchctl")
+        .args(["list", LAUNCHD_LABEL])
+        .output()
+        .is_ok_and(|o| o.status.success());
+    if loaded {
+        Check::ok("launchd", format!("{} loaded", LAUNCHD_LABEL))
+    } else {
+        Check::warn(
+            "launchd",
+            format!("{} installed but not loaded", plist.display()),
+            format!("launchctl load {}", plist.display()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::sqlite::SqliteStorage;
+    use tempfile::TempDir;
+
+    fn find<'a>(checks: &'a [Check], name: &str) -> &'a Check {
+        checks.iter().find(|c| c.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_missing_database_warns() {
+        let dir = TempDir::new().unwrap();
+        let paths = AppPaths::from_base(dir.path().to_path_buf());
+        let checks = check_database(&paths);
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].status, CheckStatus::Warn);
+        assert!(!paths.db_path.exists());
+    }
+
+    #[test]
+    fn test_database_checks() {
+        let dir = TempDir::new().unwrap();
+        let paths = AppPaths::from_base(dir.path().to_path_buf());
+        SqliteStorage::new(Connection::open(&paths.db_path).unwrap()).unwrap();
+
+        let checks = check_database(&paths);
+        assert_eq!(find(&checks, "database").status, CheckStatus::Ok);
+        assert_eq!(find(&checks, "integrity").status, CheckStatus::Ok);
+        assert_eq!(find(&checks, "schema").status, CheckStatus::Ok);
+        assert_eq!(find(&checks, "wal").status, CheckStatus::Warn);
+        assert_eq!(find(&checks, "orphan images").status, CheckStatus::Ok);
+    }
+
+    #[test]
+    fn test_orphan_images() {
+        let dir = TempDir::new().unwrap();
+        let paths = AppPaths::from_base(dir.path().to_path_buf());
+        fs::create_dir_all(&paths.images_dir).unwrap();
+        let kept = paths.images_dir.join("kept.png");
+        let orphan = paths.images_dir.join("orphan.png");
+        fs::write(&kept, b"").unwrap();
+        fs::write(&orphan, b"").unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(schema::CREATE_CLIPS_TABLE, []).unwrap();
+        conn.execute(
+            "INSERT INTO clips (content_type, image_path, hash, size_bytes, created_at, updated_at)
+             VALUES ('image', ?, 'h', 1, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+            [kept.to_string_lossy()],
+        )
+        .unwrap();
+        assert_eq!(orphan_images(&conn, &paths.images_dir).unwrap(), vec![orphan]);
+    }
+
+    #[test]
+    fn test_images_dir_checks() {
+        let dir = TempDir::new().unwrap();
+        let images = dir.path().join("images");
+        assert_eq!(check_images_dir(&images).status, CheckStatus::Warn);
+        fs::create_dir_all(&images).unwrap();
+        assert_eq!(check_images_dir(&images).status, CheckStatus::Ok);
+        assert_eq!(fs::read_dir(&images).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_daemon_cpu_check() {
+        let dir = TempDir::new().unwrap();
+        let paths = AppPaths::from_base(dir.path().to_path_buf());
+        daemon::write_pid_file(&paths.pid_file).unwrap();
+        let since = chrono::Utc::now() - chrono::Duration::hours(1);
+        let mut usage = ResourceUsage {
+            updated_at: chrono::Utc::now(),
+            cpu_ms: 6 * 60 * 1000,
+            ..ResourceUsage::new(std::process::id(), since)
+        };
+        usage.save(&paths.resource_stats_file).unwrap();
+        let check = check_daemon(&paths);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.detail.contains("10.0% CPU"), "{}", check.detail);
+
+        usage.cpu_ms = 1000;
+        usage.save(&paths.resource_stats_file).unwrap();
+        assert_eq!(check_daemon(&paths).status, CheckStatus::Ok);
+    }
+
+    #[test]
+    fn test_ignore_pattern_check() {
+        let dir = TempDir::new().unwrap();
+        let paths = AppPaths::from_base(dir.path().to_path_buf());
+        assert!(check_ignore_patterns(&paths).is_none());
+
+        fs::write(&paths.config_file, "ignore_patterns = ['^[0-9]{6}$']").unwrap();
+        let check = check_ignore_patterns(&paths).unwrap();
+        assert!(check.detail.contains("no counts yet"), "{}", check.detail);
+
+        let mut list = IgnoreList::compile(&["^[0-9]{6}$".to_string()]).unwrap();
+        list.check("123456");
+        list.stats().save(&paths.ignore_stats_file).unwrap();
+        let check = check_ignore_patterns(&paths).unwrap();
+        assert!(check.detail.contains("1 skipped"), "{}", check.detail);
+
+        fs::write(&paths.config_file, "ignore_patterns = ['(']").unwrap();
+        assert_eq!(check_ignore_patterns(&paths).unwrap().status, CheckStatus::Fail);
+    }
+}
@@ -0,0 +1,369 @@
+use std::fs::{self, File};
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::errors::{CbError, Result};
+use crate::repair::{unused_path, with_suffix};
+use crate::storage::sqlite::SqliteStorage;
+
+/// Environment variable checked first for the passphrase, for scripts and
+/// for running the daemon without a keychain.
+pub const PASSPHRASE_ENV: &str = "CB_PASSPHRASE";
+
+/// Keychain service the passphrase is stored under, one item per database
+/// path.
+const KEYCHAIN_SERVICE: &str = "cb";
+
+/// Every plain SQLite database starts with this; encrypted ones don't.
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+
+/// Passphrases that unlocked a database in this process, so the daemon's
+/// workers and repeated opens don't ask again.
+static UNLOCKED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Whether this build can open encrypted databases.
+pub fn is_available() -> bool {
+    cfg!(feature = "encryption")
+}
+
+/// Whether the database at `path` exists and is encrypted. Missing and
+/// empty files aren't: SQLite creates those as plain databases.
+pub fn is_encrypted(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    match File::open(path).and_then(|mut f| f.read_exact(&mut header)) {
+        Ok(()) => header != SQLITE_MAGIC,
+        Err(_) => false,
+    }
+}
+
+/// Open the database at `path`, unlocking it first when it's encrypted. The
+/// passphrase is taken from `CB_PASSPHRASE`, then the keychain, then asked
+/// for when stdin is a terminal.
+pub fn connect(path: &Path, flags: OpenFlags) -> Result<Connection> {
+    if !is_encrypted(path) {
+        return Ok(Connection::open_with_flags(path, flags)?);
+    }
+    if !is_available() {
+        return Err(CbError::Encrypted(format!(
+            "{} is encrypted, but cb was built without the `encryption` feature",
+            path.display()
+        )));
+    }
+    let known = UNLOCKED.lock().unwrap().clone();
+    for key in &known {
+        if let Ok(conn) = unlock(path, flags, key) {
+            return Ok(conn);
+        }
+    }
+
+    let from_env = std::env::var(PASSPHRASE_ENV).ok().filter(|k| !k.is_empty());
+    let candidates = [from_env, keychain_lookup(path)];
+    let mut tried_any = false;
+    for key in candidates.into_iter().flatten() {
+        tried_any = true;
+        if let Ok(conn) = unlock(path, flags, &key) {
+            remember(key);
+            return Ok(conn);
+        }
+    }
+    if !std::io::stdin().is_terminal() {
+        let why = if tried_any { "the passphrase is wrong" } else { "no passphrase was given" };
+        return Err(CbError::Encrypted(format!(
+            "{} is encrypted and {}; set {} or store it in the keychain with `cb init --encrypt`",
+            path.display(),
+            why,
+            PASSPHRASE_ENV
+        )));
+    }
+    let key = read_passphrase(&format!("Passphrase for {}: ", path.display()))?;
+    let conn = unlock(path, flags, &key)?;
+    remember(key);
+    Ok(conn)
+}
+
+/// Open `path`, which doesn't exist yet, encrypted with the same passphrase
+/// as the database at `like` when that one is encrypted, or plain otherwise.
+pub fn create_like(path: &Path, like: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)?;
+    if is_encrypted(like) {
+        // Opening `like` finds its passphrase and leaves it in `UNLOCKED`.
+        drop(connect(like, OpenFlags::SQLITE_OPEN_READ_ONLY)?);
+        let key = UNLOCKED.lock().unwrap().last().cloned().unwrap_or_default();
+        conn.pragma_update(None, "key", &key)?;
+    }
+    Ok(conn)
+}
+
+/// Open `path` keyed with `key`, failing with a clear error when the key is
+/// wrong rather than the "not a database" SQLite reports on first read.
+fn unlock(path: &Path, flags: OpenFlags, key: &str) -> Result<Connection> {
+    let conn = Connection::open_with_flags(path, flags)?;
+    // SQLCipher otherwise logs every failed attempt to stderr.
+    conn.execute_batch("PRAGMA cipher_log_level = NONE;")?;
+    conn.pragma_update(None, "key", key)?;
+    match conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)) {
+        Ok(_) => Ok(conn),
+        Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::NotADatabase => {
+            Err(CbError::Encrypted(format!("wrong passphrase for {}", path.display())))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn remember(key: String) {
+    let mut unlocked = UNLOCKED.lock().unwrap();
+    if !unlocked.contains(&key) {
+        unlocked.push(key);
+    }
+}
+
+/// Plain copies of a database that `encrypt` found next to it: migration
+/// backups, databases set aside by restore or repair, and the like.
+#[derive(Debug, Default)]
+pub struct EncryptReport {
+    /// Copies now encrypted with the same passphrase.
+    pub encrypted_copies: Vec<PathBuf>,
+    /// Copies that couldn't be encrypted (typically damaged ones) and are
+    /// still readable.
+    pub plain_copies: Vec<PathBuf>,
+}
+
+/// Create an encrypted database at `path`, or encrypt the plain one there
+/// in place. The plain file is replaced, not kept. Plain copies of it next
+/// to it (`<db>.bak-*`, `<db>.before-restore-*`, `<db>.corrupt-*`, ...) are
+/// encrypted with the same passphrase too.
+pub fn encrypt(path: &Path, key: &str) -> Result<EncryptReport> {
+    if !is_available() {
+        return Err(CbError::Encrypted(
+            "cb was built without the `encryption` feature; rebuild with `--features encryption`".into(),
+        ));
+    }
+    if key.is_empty() {
+        return Err(CbError::InvalidInput("the passphrase is empty".into()));
+    }
+    if is_encrypted(path) {
+        return Err(CbError::Encrypted(format!("{} is already encrypted", path.display())));
+    }
+    if fs::metadata(path).is_ok_and(|m| m.len() > 0) {
+        encrypt_in_place(path, key)?;
+    } else {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "key", key)?;
+        SqliteStorage::new(conn)?;
+    }
+    remember(key.to_string());
+
+    let mut report = EncryptReport::default();
+    for copy in plain_copies(path) {
+        match encrypt_in_place(&copy, key) {
+            Ok(()) => report.encrypted_copies.push(copy),
+            Err(_) => report.plain_copies.push(copy),
+        }
+    }
+    Ok(report)
+}
+
+/// Export the plain database at `path` into an encrypted one and swap it in,
+/// dropping the plain WAL.
+fn encrypt_in_place(path: &Path, key: &str) -> Result<()> {
+    let encrypted = unused_path(path, ".encrypting");
+    let conn = Connection::open(path)?;
+    conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+        [encrypted.to_string_lossy().as_ref(), key],
+    )?;
+    let exported = conn
+        .query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))
+        .and_then(|()| {
+            let version: i64 = conn.query_row("PRAGMA main.user_version", [], |row| row.get(0))?;
+            conn.execute_batch(&format!("PRAGMA encrypted.user_version = {};", version))?;
+            conn.execute_batch("DETACH DATABASE encrypted;")
+        });
+    drop(conn);
+    if let Err(e) = exported {
+        let _ = fs::remove_file(&encrypted);
+        return Err(e.into());
+    }
+    let io = |e: std::io::Error| CbError::Encrypted(format!("{}: {}", path.display(), e));
+    fs::rename(&encrypted, path).map_err(io)?;
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(with_suffix(path, suffix));
+    }
+    Ok(())
+}
+
+/// Plain SQLite files beside `path` whose name extends its name, such as
+/// `cb.db.bak-3`. Their WAL and shared-memory files aren't databases and
+/// are left out.
+fn plain_copies(path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.", name.to_string_lossy());
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut copies: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .filter(|copy| is_plain(copy))
+        .collect();
+    copies.sort();
+    copies
+}
+
+/// Whether `path` starts with the plain SQLite header.
+fn is_plain(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    File::open(path)
+        .and_then(|mut f| f.read_exact(&mut header))
+        .is_ok_and(|()| header == SQLITE_MAGIC)
+}
+
+/// Store `key` in the macOS keychain for the database at `path`, replacing
+/// any earlier one. The passphrase is briefly visible to other processes of
+/// the same user as an argument to `security`.
+pub fn keychain_store(path: &Path, key: &str) -> Result<()> {
+    let output = Command::new("security")
+        .args(["add-generic-password", "-U", "-s", KEYCHAIN_SERVICE, "-a"])
+        .arg(account(path))
+        .args(["-w", key])
+        .output()
+        .map_err(|e| CbError::Encrypted(format!("failed to run security: {}", e)))?;
+    if !output.status.success() {
+        return Err(CbError::Encrypted(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The keychain's passphrase for the database at `path`, if there is one.
+fn keychain_lookup(path: &Path) -> Option<String> {
+    let output = Command::new("security")
+        .args(["find-generic-password", "-s", KEYCHAIN_SERVICE, "-a"])
+        .arg(account(path))
+        .arg("-w")
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let key = String::from_utf8_lossy(&output.stdout).trim_end_matches('\n').to_string();
+    (!key.is_empty()).then_some(key)
+}
+
+/// Keychain account name for a database: its absolute path.
+fn account(path: &Path) -> String {
+    std::path::absolute(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Read a line from the terminal without echoing it.
+pub fn read_passphrase(prompt: &str) -> Result<String> {
+    eprint!("{}", prompt);
+    let fd = libc::STDIN_FILENO;
+    // SAFETY: termios is plain data, filled in by tcgetattr before use.
+    let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+    let have_tty = unsafe { libc::tcgetattr(fd, &mut saved) } == 0;
+    if have_tty {
+        let mut quiet = saved;
+        quiet.c_lflag &= !libc::ECHO;
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &quiet) };
+    }
+    let mut line = String::new();
+    let read = std::io::stdin().read_line(&mut line);
+    if have_tty {
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &saved) };
+        eprintln!();
+    }
+    read.map_err(|e| CbError::InvalidInput(e.to_string()))?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_plain_databases_open_as_before() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("clips.db");
+        assert!(!is_encrypted(&path));
+        let storage = SqliteStorage::new(connect(&path, OpenFlags::default()).unwrap()).unwrap();
+        drop(storage);
+        assert!(!is_encrypted(&path));
+
+        fs::write(tmp.path().join("other.db"), b"\x8f\x01not sqlite at all").unwrap();
+        assert!(is_encrypted(&tmp.path().join("other.db")));
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    #[test]
+    fn test_encrypt_without_feature_errors() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("clips.db");
+        assert!(!is_available());
+        assert!(matches!(encrypt(&path, "pw"), Err(CbError::Encrypted(_))));
+        fs::write(&path, b"\x8f\x01encrypted bytes here").unwrap();
+        assert!(matches!(connect(&path, OpenFlags::default()), Err(CbError::Encrypted(_))));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypt_in_place_and_unlock() {
+        use crate::storage::ClipStorage;
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("clips.db");
+        {
+            let storage = SqliteStorage::new(Connection::open(&path).unwrap()).unwrap();
+            storage.set_slot("k", "secret text").unwrap();
+        }
+        encrypt(&path, "correct horse").unwrap();
+        assert!(is_encrypted(&path));
+        assert!(fs::read(&path).unwrap().windows(11).all(|w| w != b"secret text"));
+        assert!(matches!(
+            unlock(&path, OpenFlags::default(), "wrong"),
+            Err(CbError::Encrypted(_))
+        ));
+
+        let storage = SqliteStorage::new(connect(&path, OpenFlags::default()).unwrap()).unwrap();
+        assert_eq!(storage.get_slot("k").unwrap().text, "secret text");
+        assert!(matches!(encrypt(&path, "again"), Err(CbError::Encrypted(_))));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn test_encrypt_covers_plain_copies() {
+        use crate::storage::ClipStorage;
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("clips.db");
+        {
+            let storage = SqliteStorage::new(Connection::open(&path).unwrap()).unwrap();
+            storage.set_slot("k", "secret text").unwrap();
+        }
+        let backup = tmp.path().join("clips.db.bak-3");
+        fs::copy(&path, &backup).unwrap();
+        let damaged = tmp.path().join("clips.db.corrupt-20240501090000");
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.truncate(100);
+        fs::write(&damaged, bytes).unwrap();
+        fs::write(with_suffix(&damaged, "-wal"), b"not a database").unwrap();
+
+        let report = encrypt(&path, "correct horse").unwrap();
+        assert_eq!(report.encrypted_copies, vec![backup.clone()]);
+        assert_eq!(report.plain_copies, vec![damaged.clone()]);
+        assert!(is_encrypted(&backup));
+        assert!(fs::read(&backup).unwrap().windows(11).all(|w| w != b"secret text"));
+        let storage = SqliteStorage::new(unlock(&backup, OpenFlags::default(), "correct horse").unwrap()).unwrap();
+        assert_eq!(storage.get_slot("k").unwrap().text, "secret text");
+    }
+}
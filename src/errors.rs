@@ -1,24 +1,56 @@
 use thiserror::Error;
 
+use crate::i18n::{t, tf};
+
 #[derive(Debug, Error)]
 pub enum CbError {
-    #[error("Storage error: {0}")]
+    #[error("{}: {}", t("Storage error"), .0)]
     Storage(#[from] rusqlite::Error),
 
-    #[error("Clipboard error: {0}")]
+    #[error("{}: {}", t("Clipboard error"), .0)]
     Clipboard(String),
 
-    #[error("Image error: {0}")]
+    #[error("{}: {}", t("Image error"), .0)]
     Image(String),
 
-    #[error("Daemon error: {0}")]
+    #[error("{}: {}", t("OCR error"), .0)]
+    Ocr(String),
+
+    #[error("{}: {}", t("Daemon error"), .0)]
     Daemon(String),
 
-    #[error("Not found: {0}")]
+    #[error("{}: {}", t("Config error"), .0)]
+    Config(String),
+
+    #[error("{}: {}", t("Not found"), .0)]
     NotFound(String),
 
-    #[error("Invalid input: {0}")]
+    #[error("{}: {}", t("Invalid input"), .0)]
     InvalidInput(String),
+
+    #[error("{}", tf("Clip #{0} is protected", &[.0]))]
+    Protected(i64),
+
+    #[error("{}: {}", t("Database is corrupt"), .0)]
+    Corrupt(String),
+
+    #[error("{}: {}", t("Encrypted database"), .0)]
+    Encrypted(String),
+}
+
+impl CbError {
+    /// Whether this error means the database file itself is damaged, as
+    /// opposed to a failed query or a locked database.
+    pub fn is_corruption(&self) -> bool {
+        match self {
+            CbError::Corrupt(_) => true,
+            CbError::Storage(rusqlite::Error::SqliteFailure(e, _)) => matches!(
+                e.code,
+                rusqlite::ErrorCode::DatabaseCorrupt | rusqlite::ErrorCode::NotADatabase
+            ),
+            _ => false,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, CbError>;
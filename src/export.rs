@@ -0,0 +1,461 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::errors::{CbError, Result};
+use crate::lang;
+use crate::storage::ClipStorage;
+use crate::storage::models::{Clip, ClipFilter, ContentType};
+use crate::table::Table;
+use crate::title;
+use crate::transform;
+
+/// Version of the JSON export layout, bumped when a field changes meaning.
+pub const VERSION: u32 = 1;
+
+/// What `cb export` writes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Every clip field, tags included; what `cb import` reads back.
+    Json,
+    /// One row per clip with the common fields, for spreadsheets.
+    Csv,
+    /// A readable document with a section per clip.
+    Markdown,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "md" | "markdown" => Ok(Format::Markdown),
+            _ => Err(CbError::InvalidInput(format!(
+                "unknown export format \"{}\" (expected json, csv, or md)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Where to copy image files to. Copied paths are recorded relative to
+/// `base`, the folder the export itself is written to, when they're inside
+/// it, so the two can be moved together.
+pub struct ImageCopy<'a> {
+    pub dir: &'a Path,
+    pub base: &'a Path,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct ExportSummary {
+    pub clips: usize,
+    /// Image files copied alongside.
+    pub images: usize,
+    /// Image clips whose file could no longer be read.
+    pub missing_images: usize,
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    version: u32,
+    exported_at: DateTime<Utc>,
+    clips: Vec<Entry<'a>>,
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    #[serde(flatten)]
+    clip: &'a Clip,
+    /// The copy of the clip's image made for this export.
+    image_file: Option<String>,
+}
+
+/// Write every live clip matching `filter`, oldest first, to `out` as
+/// `format`, copying image files when `images` is given.
+pub fn export<S: ClipStorage>(
+    storage: &S,
+    filter: &ClipFilter,
+    format: Format,
+    out: &mut dyn Write,
+    images: Option<&ImageCopy>,
+) -> Result<ExportSummary> {
+    let count = storage.count_matching(filter)?;
+    let mut clips = storage.list(ClipFilter {
+        limit: count.max(1),
+        offset: 0,
+        ..filter.clone()
+    })?;
+    clips.reverse();
+
+    let mut summary = ExportSummary {
+        clips: clips.len(),
+        ..Default::default()
+    };
+    let mut copies = Vec::with_capacity(clips.len());
+    for clip in &clips {
+        let copy = match images {
+            Some(images) if clip.content_type == ContentType::Image => {
+                let copied = copy_image(clip, images)?;
+                match copied {
+                    Some(_) => summary.images += 1,
+                    None => summary.missing_images += 1,
+                }
+                copied
+            }
+            _ => None,
+        };
+        copies.push(copy);
+    }
+
+    let text = match format {
+        Format::Json => {
+            let document = Document {
+                version: VERSION,
+                exported_at: Utc::now(),
+                clips: clips
+                    .iter()
+                    .zip(copies)
+                    .map(|(clip, image_file)| Entry { clip, image_file })
+                    .collect(),
+            };
+            serde_json::to_string_pretty(&document).unwrap() + "\n"
+        }
+        Format::Csv => to_csv(&clips),
+        Format::Markdown => to_markdown(&clips, &copies),
+    };
+    out.write_all(text.as_bytes())
+        .map_err(|e| CbError::InvalidInput(format!("writing export: {}", e)))?;
+    Ok(summary)
+}
+
+/// Copy `clip`'s image into `images.dir`, returning the path to record, or
+/// `None` when the file is gone.
+fn copy_image(clip: &Clip, images: &ImageCopy) -> Result<Option<String>> {
+    let Some(source) = clip.image_path.as_deref().map(Path::new) else {
+        return Ok(None);
+    };
+    let Some(name) = source.file_name() else {
+        return Ok(None);
+    };
+    if !source.is_file() {
+        return Ok(None);
+    }
+    let io = |e: std::io::Error| CbError::Image(format!("{}: {}", images.dir.display(), e));
+    fs::create_dir_all(images.dir).map_err(io)?;
+    let target = images.dir.join(name);
+    fs::copy(source, &target).map_err(io)?;
+    Ok(Some(recorded_path(&target, images.base)))
+}
+
+/// Copy the image of every live image clip matching `filter` into `dir`,
+/// oldest first, named after when and where it was copied, e.g.
+/// `2024-05-01 14.03.22 Safari - Login error.png`. Files already in `dir`
+/// are never overwritten; a clashing name gets a ` (2)` suffix.
+pub fn export_images<S: ClipStorage>(storage: &S, filter: &ClipFilter, dir: &Path) -> Result<ExportSummary> {
+    let filter = ClipFilter {
+        content_type: Some(ContentType::Image),
+        ..filter.clone()
+    };
+    let count = storage.count_matching(&filter)?;
+    let mut clips = storage.list(ClipFilter {
+        limit: count.max(1),
+        offset: 0,
+        ..filter
+    })?;
+    clips.reverse();
+
+    let io = |e: std::io::Error| CbError::Image(format!("{}: {}", dir.display(), e));
+    fs::create_dir_all(dir).map_err(io)?;
+    let mut summary = ExportSummary {
+        clips: clips.len(),
+        ..Default::default()
+    };
+    for clip in &clips {
+        let Some(source) = clip.image_path.as_deref().map(Path::new).filter(|p| p.is_file()) else {
+            summary.missing_images += 1;
+            continue;
+        };
+        let extension = source.extension().and_then(|e| e.to_str()).unwrap_or("png");
+        let stem = image_name(clip);
+        let mut target = dir.join(format!("{}.{}", stem, extension));
+        let mut n = 2;
+        while target.exists() {
+            target = dir.join(format!("{} ({}).{}", stem, n, extension));
+            n += 1;
+        }
+        fs::copy(source, &target).map_err(io)?;
+        summary.images += 1;
+    }
+    Ok(summary)
+}
+
+/// Longest title put in an exported image's name, in columns.
+const IMAGE_NAME_TITLE: usize = 40;
+
+/// File name, without extension, for `clip`'s image: its local capture
+/// time, the app it came from, and a title from the page it was taken from
+/// or its recognized text, when known.
+fn image_name(clip: &Clip) -> String {
+    let mut name = clip
+        .created_at
+        .with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H.%M.%S")
+        .to_string();
+    // "com.apple.Safari" → "Safari"
+    if let Some(app) = clip.source_app.as_deref().and_then(|a| a.rsplit('.').next()) {
+        name.push(' ');
+        name.push_str(&file_safe(app));
+    }
+    let title = clip
+        .title
+        .clone()
+        .or_else(|| clip.source_title.clone())
+        .or_else(|| clip.ocr_text.as_deref().and_then(title::derive));
+    if let Some(title) = title.map(|t| file_safe(&title::shorten(&t, IMAGE_NAME_TITLE)))
+        && !title.is_empty()
+    {
+        name.push_str(" - ");
+        name.push_str(&title);
+    }
+    name
+}
+
+/// `s` with path separators, colons, and control characters made spaces,
+/// runs of whitespace collapsed, and no leading dots, so it can't escape
+/// the folder or hide.
+fn file_safe(s: &str) -> String {
+    let cleaned: String = s
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':') || c.is_control() { ' ' } else { c })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    cleaned.trim_start_matches('.').trim_start().to_string()
+}
+
+/// `path` relative to `base` when it's inside it, absolute otherwise.
+fn recorded_path(path: &Path, base: &Path) -> String {
+    let absolute = |p: &Path| std::path::absolute(p).unwrap_or_else(|_| p.to_path_buf());
+    let path = absolute(path);
+    let relative: PathBuf = path
+        .strip_prefix(absolute(base))
+        .map(Path::to_path_buf)
+        .unwrap_or(path);
+    relative.to_string_lossy().into_owned()
+}
+
+const CSV_HEADER: &[&str] = &[
+    "id",
+    "type",
+    "created_at",
+    "updated_at",
+    "pinned",
+    "tags",
+    "lang",
+    "source_app",
+    "title",
+    "content",
+];
+
+fn to_csv(clips: &[Clip]) -> String {
+    let mut rows = vec![CSV_HEADER.iter().map(|h| h.to_string()).collect()];
+    for clip in clips {
+        let content = match clip.content_type {
+            ContentType::Text => clip.text_content.clone(),
+            ContentType::Image | ContentType::FileRef => clip.image_path.clone(),
+        };
+        rows.push(vec![
+            clip.id.to_string(),
+            clip.content_type.as_str().to_string(),
+            clip.created_at.to_rfc3339(),
+            clip.updated_at.to_rfc3339(),
+            clip.pinned.to_string(),
+            clip.tags.join(" "),
+            clip.lang.clone().unwrap_or_default(),
+            clip.source_app.clone().unwrap_or_default(),
+            clip.title.clone().unwrap_or_default(),
+            content.unwrap_or_default(),
+        ]);
+    }
+    Table { rows }.to_csv()
+}
+
+fn to_markdown(clips: &[Clip], copies: &[Option<String>]) -> String {
+    let mut out = String::from("# Clipboard history\n");
+    for (clip, copy) in clips.iter().zip(copies) {
+        let heading = match (&clip.content_type, clip.title.as_deref()) {
+            (ContentType::Text, Some(title)) => title.to_string(),
+            (ContentType::Image, _) => match (clip.image_width, clip.image_height) {
+                (Some(w), Some(h)) => format!("Image {}×{}", w, h),
+                _ => "Image".to_string(),
+            },
+            (ContentType::FileRef, _) => "File".to_string(),
+            _ => "Clip".to_string(),
+        };
+        out.push_str(&format!("\n## #{} {}\n\n", clip.id, heading));
+
+        let mut meta = vec![clip.created_at.format("%Y-%m-%d %H:%M UTC").to_string()];
+        if clip.pinned {
+            meta.push("pinned".to_string());
+        }
+        if !clip.tags.is_empty() {
+            meta.push(format!("tags: {}", clip.tags.join(", ")));
+        }
+        if let Some(ref app) = clip.source_app {
+            meta.push(format!("from {}", app));
+        }
+        out.push_str(&format!("{}\n\n", meta.join(" · ")));
+
+        match clip.content_type {
+            ContentType::Text => {
+                let text = clip.text_content.as_deref().unwrap_or("");
+                let lang = clip.lang.as_deref().filter(|l| lang::is_code(l));
+                out.push_str(&transform::fence(text, lang));
+            }
+            ContentType::Image => {
+                let path = copy.as_deref().or(clip.image_path.as_deref()).unwrap_or("");
+                out.push_str(&format!("![#{}](<{}>)\n", clip.id, path));
+            }
+            ContentType::FileRef => {
+                out.push_str(&format!("`{}`\n", clip.image_path.as_deref().unwrap_or("")));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::hash_content;
+    use crate::storage::models::NewClip;
+    use crate::storage::sqlite::SqliteStorage;
+    use tempfile::TempDir;
+
+    fn add(storage: &SqliteStorage, text: &str) -> Clip {
+        storage
+            .insert(NewClip {
+                content_type: ContentType::Text,
+                text_content: Some(text.to_string()),
+                hash: hash_content(text.as_bytes()),
+                size_bytes: text.len() as i64,
+                ..Default::default()
+            })
+            .unwrap()
+    }
+
+    fn run(storage: &SqliteStorage, format: Format, images: Option<&ImageCopy>) -> (String, ExportSummary) {
+        let mut out = Vec::new();
+        let summary = export(storage, &ClipFilter::default(), format, &mut out, images).unwrap();
+        (String::from_utf8(out).unwrap(), summary)
+    }
+
+    #[test]
+    fn test_json_keeps_tags_and_pins_and_copies_images() {
+        let tmp = TempDir::new().unwrap();
+        let source = tmp.path().join("abc.png");
+        fs::write(&source, b"png").unwrap();
+        let storage = SqliteStorage::in_memory().unwrap();
+        let note = add(&storage, "first note");
+        storage.add_tag(note.id, "work").unwrap();
+        storage.set_pinned(note.id, true).unwrap();
+        storage
+            .insert(NewClip {
+                content_type: ContentType::Image,
+                image_path: Some(source.to_string_lossy().into_owned()),
+                hash: "img".into(),
+                size_bytes: 3,
+                ..Default::default()
+            })
+            .unwrap();
+
+        let base = tmp.path().join("backup");
+        let dir = base.join("images");
+        let copy = ImageCopy { dir: &dir, base: &base };
+        let (json, summary) = run(&storage, Format::Json, Some(&copy));
+        assert_eq!(summary, ExportSummary { clips: 2, images: 1, missing_images: 0 });
+        assert_eq!(fs::read(dir.join("abc.png")).unwrap(), b"png");
+
+        let doc: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(doc["version"], VERSION);
+        let clips = doc["clips"].as_array().unwrap();
+        assert_eq!(clips[0]["text_content"], "first note");
+        assert_eq!(clips[0]["pinned"], true);
+        assert_eq!(clips[0]["tags"], serde_json::json!(["work"]));
+        assert_eq!(clips[1]["image_file"], "images/abc.png");
+    }
+
+    #[test]
+    fn test_csv_and_markdown() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        let clip = add(&storage, "a, \"quoted\"\nline");
+        storage.add_tag(clip.id, "x").unwrap();
+
+        let (csv, _) = run(&storage, Format::Csv, None);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), CSV_HEADER.join(","));
+        assert!(csv.ends_with(",x,,,\"a, \"\"quoted\"\"\",\"a, \"\"quoted\"\"\nline\"\n"));
+
+        let (md, _) = run(&storage, Format::Markdown, None);
+        assert!(md.starts_with("# Clipboard history\n\n## #1 a, \"quoted\"\n\n"));
+        assert!(md.contains("tags: x\n\n```\na, \"quoted\"\nline\n```\n"));
+
+        let prose = "The weather is nice and we are going to the park with all of the kids today.";
+        let note = add(&storage, prose);
+        assert_eq!(note.lang.as_deref(), Some("en"));
+        let code = add(&storage, "fn main() {\n    let mut x = String::new();\n    println!(\"{}\", x);\n}");
+        assert_eq!(code.lang.as_deref(), Some("rust"));
+        let (md, _) = run(&storage, Format::Markdown, None);
+        assert!(md.contains(&format!("\n\n```\n{}\n```\n", prose)));
+        assert!(md.contains("```rust\nfn main()"));
+    }
+
+    #[test]
+    fn test_export_images_with_readable_names() {
+        let tmp = TempDir::new().unwrap();
+        let storage = SqliteStorage::in_memory().unwrap();
+        add(&storage, "not an image");
+        let captured_at = Utc::now();
+        for (hash, exists) in [("a", true), ("b", true), ("c", false)] {
+            let path = tmp.path().join(format!("{}.png", hash));
+            if exists {
+                fs::write(&path, hash).unwrap();
+            }
+            let clip = storage
+                .insert(NewClip {
+                    content_type: ContentType::Image,
+                    image_path: Some(path.to_string_lossy().into_owned()),
+                    hash: hash.into(),
+                    size_bytes: 1,
+                    created_at: Some(captured_at),
+                    ..Default::default()
+                })
+                .unwrap();
+            storage
+                .record_capture(clip.id, Some("com.apple.Safari"), Some("Safari"), None, Default::default())
+                .unwrap();
+            storage.set_ocr_text(clip.id, "Error: login/failed").unwrap();
+        }
+
+        let out = tmp.path().join("shots");
+        let summary = export_images(&storage, &ClipFilter::default(), &out).unwrap();
+        assert_eq!(summary, ExportSummary { clips: 3, images: 2, missing_images: 1 });
+        let mut names: Vec<String> = fs::read_dir(&out)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        // Both were captured at the same time, so the second is numbered.
+        let stem = image_name(&storage.get_by_id(2).unwrap());
+        assert!(stem.ends_with(" Safari - Error login failed"), "{}", stem);
+        assert_eq!(names, vec![format!("{} (2).png", stem), format!("{}.png", stem)]);
+    }
+
+    #[test]
+    fn test_format_names() {
+        assert_eq!(Format::parse("md").unwrap(), Format::Markdown);
+        assert!(Format::parse("xml").is_err());
+    }
+}
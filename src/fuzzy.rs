@@ -0,0 +1,125 @@
+/// Points for each query character matched.
+const MATCH: i64 = 16;
+/// Extra points for a character matched right after the previous one.
+const CONSECUTIVE: i64 = 16;
+/// Extra points for a match at the start of a word (after a space,
+/// punctuation, or at a lowercase-to-uppercase change, as in `camelCase`).
+const WORD_START: i64 = 8;
+/// Points lost for a gap between two matches, plus `GAP` for each character
+/// skipped, up to `MAX_GAP_PENALTY` per gap.
+const GAP_START: i64 = 3;
+const GAP: i64 = 1;
+const MAX_GAP_PENALTY: i64 = 10;
+
+/// Score `text` against `query` as a fuzzy match: every query character
+/// must appear in `text` in order, ignoring case, though not necessarily
+/// next to each other. Higher is better; runs of consecutive characters and
+/// matches at word starts score highest. `None` when `text` doesn't match.
+///
+/// Matching takes the first place the whole query fits, then tightens it
+/// from the end, so long texts cost one pass rather than trying every
+/// alignment.
+pub fn score(query: &str, text: &str) -> Option<i64> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let eq = |idx: usize, q: char| chars[idx].to_lowercase().eq(std::iter::once(q));
+
+    // Forward: where the first complete match ends.
+    let mut qi = 0;
+    let mut end = None;
+    for idx in 0..chars.len() {
+        if eq(idx, query[qi]) {
+            qi += 1;
+            if qi == query.len() {
+                end = Some(idx);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    // Backward from there: the latest start that still fits, so the match
+    // is as tight as possible.
+    let mut positions = vec![0; query.len()];
+    let mut qi = query.len();
+    for idx in (0..=end).rev() {
+        if eq(idx, query[qi - 1]) {
+            qi -= 1;
+            positions[qi] = idx;
+            if qi == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut total = 0;
+    for (n, &pos) in positions.iter().enumerate() {
+        total += MATCH;
+        if is_word_start(&chars, pos) {
+            total += WORD_START;
+        }
+        if n > 0 {
+            let gap = (pos - positions[n - 1] - 1) as i64;
+            if gap == 0 {
+                total += CONSECUTIVE;
+            } else {
+                total -= (GAP_START + gap * GAP).min(MAX_GAP_PENALTY);
+            }
+        }
+    }
+    Some(total)
+}
+
+fn is_word_start(chars: &[char], idx: usize) -> bool {
+    let Some(&prev) = idx.checked_sub(1).and_then(|i| chars.get(i)) else {
+        return true;
+    };
+    let cur = chars[idx];
+    !prev.is_alphanumeric() || (prev.is_lowercase() && cur.is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requires_characters_in_order() {
+        assert!(score("dkr", "docker login").is_some());
+        assert!(score("DL", "docker login").is_some());
+        assert!(score("rkd", "docker login").is_none());
+        assert!(score("dockerx", "docker").is_none());
+        assert_eq!(score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn test_prefers_consecutive_and_word_starts() {
+        let exact = score("login", "docker login").unwrap();
+        let spread = score("login", "large old gin bin").unwrap();
+        assert!(exact > spread);
+
+        let initials = score("gc", "git commit").unwrap();
+        let inside = score("gc", "magic").unwrap();
+        assert!(initials > inside);
+
+        assert!(score("pc", "parseConfig").unwrap() > score("pc", "epic").unwrap());
+    }
+
+    #[test]
+    fn test_tightens_to_the_shortest_window() {
+        // The forward pass alone would start at the first `a`.
+        assert_eq!(score("ab", "a xxxxxxxxxxxxxxx ab"), score("ab", "ab"));
+    }
+
+    #[test]
+    fn test_handles_multibyte_text() {
+        assert!(score("über", "Grüße über alles").is_some());
+        assert!(score("日語", "日本語").is_some());
+    }
+}
@@ -0,0 +1,146 @@
+use chrono::{Datelike, Local, NaiveDate};
+use serde::Serialize;
+
+use crate::errors::{CbError, Result};
+use crate::i18n::t;
+use crate::storage::models::Clip;
+
+/// What `cb list --group-by` groups clips under.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    Day,
+    App,
+    Tag,
+    /// Clips that are variants of the same URL, by canonical URL.
+    Url,
+}
+
+impl GroupBy {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "day" => Ok(GroupBy::Day),
+            "app" => Ok(GroupBy::App),
+            "tag" => Ok(GroupBy::Tag),
+            "url" => Ok(GroupBy::Url),
+            _ => Err(CbError::InvalidInput(format!(
+                "unknown grouping \"{}\" (expected day, app, tag, or url)",
+                s
+            ))),
+        }
+    }
+
+    /// Keys `clip` belongs under: its local creation date (`YYYY-MM-DD`), its
+    /// source app, each of its tags, or its canonical URL. `None` collects
+    /// clips without one.
+    pub fn keys(&self, clip: &Clip) -> Vec<Option<String>> {
+        match self {
+            GroupBy::Day => vec![Some(local_date(clip).to_string())],
+            GroupBy::App => vec![clip.source_app.clone()],
+            GroupBy::Tag if clip.tags.is_empty() => vec![None],
+            GroupBy::Tag => clip.tags.iter().cloned().map(Some).collect(),
+            GroupBy::Url => vec![clip.canonical_url.clone()],
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Group<'a> {
+    pub group: Option<String>,
+    pub clips: Vec<&'a Clip>,
+}
+
+/// Split `clips` into groups by `key`, keeping groups in the order their
+/// first clip appears and clips in their original order within each. A clip
+/// with several keys (e.g. tags) appears in each of those groups.
+pub fn group<'a>(clips: &'a [Clip], key: impl Fn(&Clip) -> Vec<Option<String>>) -> Vec<Group<'a>> {
+    let mut groups: Vec<Group<'a>> = Vec::new();
+    for clip in clips {
+        for k in key(clip) {
+            match groups.iter_mut().find(|g| g.group == k) {
+                Some(g) => g.clips.push(clip),
+                None => groups.push(Group {
+                    group: k,
+                    clips: vec![clip],
+                }),
+            }
+        }
+    }
+    groups
+}
+
+/// Human heading for a day: `Today`, `Yesterday`, `Last week` (2-7 days
+/// ago), `Last month` (up to 30 days), then the month, e.g. `March 2024`.
+pub fn day_heading(date: NaiveDate, today: NaiveDate) -> String {
+    let month = || t(&date.format("%B").to_string()).to_string();
+    match (today - date).num_days() {
+        i64::MIN..=0 => t("Today").to_string(),
+        1 => t("Yesterday").to_string(),
+        2..=7 => t("Last week").to_string(),
+        8..=30 => t("Last month").to_string(),
+        _ if date.year() == today.year() => month(),
+        _ => format!("{} {}", month(), date.year()),
+    }
+}
+
+/// `day_heading` for a clip's local creation date, relative to now.
+pub fn clip_day_heading(clip: &Clip) -> String {
+    day_heading(local_date(clip), Local::now().date_naive())
+}
+
+fn local_date(clip: &Clip) -> NaiveDate {
+    clip.created_at.with_timezone(&Local).date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(id: i64, tags: &[&str], app: Option<&str>) -> Clip {
+        Clip {
+            id,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            source_app: app.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_day_heading() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 20).unwrap();
+        let day = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        assert_eq!(day_heading(today, today), "Today");
+        assert_eq!(day_heading(day(5, 19), today), "Yesterday");
+        assert_eq!(day_heading(day(5, 14), today), "Last week");
+        assert_eq!(day_heading(day(5, 1), today), "Last month");
+        assert_eq!(day_heading(day(3, 2), today), "March");
+        let last_year = NaiveDate::from_ymd_opt(2023, 12, 24).unwrap();
+        assert_eq!(day_heading(last_year, today), "December 2023");
+    }
+
+    #[test]
+    fn test_group_keeps_order_and_repeats_multi_tagged() {
+        let clips = vec![
+            clip(3, &["work"], Some("com.apple.Safari")),
+            clip(2, &[], None),
+            clip(1, &["home", "work"], Some("com.apple.Safari")),
+        ];
+        let by_tag = group(&clips, |c| GroupBy::Tag.keys(c));
+        let summary: Vec<(Option<&str>, Vec<i64>)> = by_tag
+            .iter()
+            .map(|g| (g.group.as_deref(), g.clips.iter().map(|c| c.id).collect()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Some("work"), vec![3, 1]),
+                (None, vec![2]),
+                (Some("home"), vec![1])
+            ]
+        );
+
+        let by_app = group(&clips, |c| GroupBy::App.keys(c));
+        assert_eq!(by_app.len(), 2);
+        assert_eq!(by_app[0].clips.len(), 2);
+        assert!(GroupBy::parse("week").is_err());
+    }
+}
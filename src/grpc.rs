@@ -0,0 +1,587 @@
+#[cfg(feature = "grpc")]
+use std::pin::Pin;
+#[cfg(feature = "grpc")]
+use std::sync::{Arc, Mutex, MutexGuard};
+#[cfg(feature = "grpc")]
+use std::time::Duration;
+
+#[cfg(feature = "grpc")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "grpc")]
+use tokio_stream::Stream;
+#[cfg(feature = "grpc")]
+use tonic::{Request, Response, Status};
+
+use crate::errors::{CbError, Result};
+#[cfg(feature = "grpc")]
+use crate::hash::hash_content;
+use crate::storage::ClipStorage;
+#[cfg(feature = "grpc")]
+use crate::storage::models::{Clip, ClipFilter, ContentType, NewClip, TokenScope, Tone};
+#[cfg(feature = "grpc")]
+use crate::web;
+
+/// Messages and service stubs generated from proto/cb.proto.
+#[cfg(feature = "grpc")]
+pub mod pb {
+    tonic::include_proto!("cb.v1");
+}
+
+#[cfg(feature = "grpc")]
+use pb::clip_storage_server::{ClipStorage as ClipStorageRpc, ClipStorageServer};
+
+/// How often `WatchClips` checks for new clips, and the most it sends per check.
+#[cfg(feature = "grpc")]
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+#[cfg(feature = "grpc")]
+const WATCH_BATCH: i64 = 500;
+
+/// Whether this build can serve gRPC.
+pub fn is_available() -> bool {
+    cfg!(feature = "grpc")
+}
+
+/// Serve `storage` over gRPC on `addr` until the process is killed.
+///
+/// Like `cb serve --http`, every call must carry a token from
+/// `cb token create`, or `shared` when given, as `authorization: Bearer`
+/// metadata. Calls that change history need a read-write token.
+#[cfg(feature = "grpc")]
+pub fn serve_grpc<S>(storage: S, addr: &str, shared: Option<&str>) -> Result<()>
+where
+    S: ClipStorage + Send + 'static,
+{
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CbError::InvalidInput(format!("can't start the gRPC server: {}", e)))?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| CbError::InvalidInput(format!("can't listen on {}: {}", addr, e)))?;
+        serve_on(Service::new(storage, shared), listener).await
+    })
+}
+
+#[cfg(not(feature = "grpc"))]
+pub fn serve_grpc<S: ClipStorage>(_storage: S, _addr: &str, _shared: Option<&str>) -> Result<()> {
+    Err(CbError::InvalidInput(
+        "cb was built without the `grpc` feature".into(),
+    ))
+}
+
+#[cfg(feature = "grpc")]
+async fn serve_on<S>(service: Service<S>, listener: tokio::net::TcpListener) -> Result<()>
+where
+    S: ClipStorage + Send + 'static,
+{
+    tonic::transport::Server::builder()
+        .add_service(ClipStorageServer::new(service))
+        .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+        .await
+        .map_err(|e| CbError::InvalidInput(format!("gRPC server failed: {}", e)))
+}
+
+/// The `ClipStorage` service, dispatching every call to one storage backend.
+#[cfg(feature = "grpc")]
+struct Service<S> {
+    storage: Arc<Mutex<S>>,
+    shared: Option<String>,
+}
+
+#[cfg(feature = "grpc")]
+impl<S: ClipStorage> Service<S> {
+    fn new(storage: S, shared: Option<&str>) -> Self {
+        Service {
+            storage: Arc::new(Mutex::new(storage)),
+            shared: shared.map(str::to_string),
+        }
+    }
+
+    /// The storage, once the request's token allows the call.
+    fn authorize<T>(
+        &self,
+        request: &Request<T>,
+        write: bool,
+    ) -> std::result::Result<MutexGuard<'_, S>, Status> {
+        let given = request
+            .metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        let storage = lock(&self.storage);
+        match web::token_scope(&*storage, given, self.shared.as_deref()) {
+            None => Err(Status::unauthenticated("missing or wrong token")),
+            Some(TokenScope::Read) if write => {
+                Err(Status::permission_denied("this token is read-only"))
+            }
+            Some(_) => Ok(storage),
+        }
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn lock<S>(storage: &Mutex<S>) -> MutexGuard<'_, S> {
+    storage.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(feature = "grpc")]
+type RpcResult<T> = std::result::Result<Response<T>, Status>;
+
+#[cfg(feature = "grpc")]
+#[tonic::async_trait]
+impl<S: ClipStorage + Send + 'static> ClipStorageRpc for Service<S> {
+    async fn insert(&self, request: Request<pb::NewClip>) -> RpcResult<pb::Clip> {
+        let storage = self.authorize(&request, true)?;
+        let clip = new_clip(request.into_inner())?;
+        let clip = storage.insert(clip).map_err(status)?;
+        Ok(Response::new(clip_message(&clip)))
+    }
+
+    async fn get(&self, request: Request<pb::ClipId>) -> RpcResult<pb::Clip> {
+        let storage = self.authorize(&request, false)?;
+        let clip = storage.get_by_id(request.get_ref().id).map_err(status)?;
+        Ok(Response::new(clip_message(&clip)))
+    }
+
+    async fn list(&self, request: Request<pb::ClipFilter>) -> RpcResult<pb::ClipList> {
+        let storage = self.authorize(&request, false)?;
+        let clips = storage
+            .list(clip_filter(request.into_inner())?)
+            .map_err(status)?;
+        Ok(Response::new(clip_list(&clips)))
+    }
+
+    async fn search(&self, request: Request<pb::SearchRequest>) -> RpcResult<pb::ClipList> {
+        let storage = self.authorize(&request, false)?;
+        let search = request.get_ref();
+        let limit = if search.limit <= 0 { 50 } else { search.limit };
+        let clips = storage.search(&search.query, limit).map_err(status)?;
+        Ok(Response::new(clip_list(&clips)))
+    }
+
+    async fn count(&self, request: Request<pb::ClipFilter>) -> RpcResult<pb::ClipCount> {
+        let storage = self.authorize(&request, false)?;
+        let count = storage
+            .count_matching(&clip_filter(request.into_inner())?)
+            .map_err(status)?;
+        Ok(Response::new(pb::ClipCount { count }))
+    }
+
+    async fn delete(&self, request: Request<pb::ClipId>) -> RpcResult<pb::Deleted> {
+        let storage = self.authorize(&request, true)?;
+        let deleted = storage.delete(request.get_ref().id).map_err(status)?;
+        Ok(Response::new(pb::Deleted { deleted }))
+    }
+
+    async fn delete_matching(&self, request: Request<pb::ClipFilter>) -> RpcResult<pb::ClipCount> {
+        let storage = self.authorize(&request, true)?;
+        let count = storage
+            .delete_matching(&clip_filter(request.into_inner())?)
+            .map_err(status)?;
+        Ok(Response::new(pb::ClipCount { count }))
+    }
+
+    async fn add_tag(&self, request: Request<pb::TagRequest>) -> RpcResult<pb::Empty> {
+        let storage = self.authorize(&request, true)?;
+        let tag = request.get_ref();
+        storage.add_tag(tag.clip_id, &tag.tag).map_err(status)?;
+        Ok(Response::new(pb::Empty {}))
+    }
+
+    async fn remove_tag(&self, request: Request<pb::TagRequest>) -> RpcResult<pb::Empty> {
+        let storage = self.authorize(&request, true)?;
+        let tag = request.get_ref();
+        storage.remove_tag(tag.clip_id, &tag.tag).map_err(status)?;
+        Ok(Response::new(pb::Empty {}))
+    }
+
+    async fn set_pinned(&self, request: Request<pb::FlagRequest>) -> RpcResult<pb::Empty> {
+        let storage = self.authorize(&request, true)?;
+        let flag = request.get_ref();
+        storage.set_pinned(flag.id, flag.value).map_err(status)?;
+        Ok(Response::new(pb::Empty {}))
+    }
+
+    async fn set_protected(&self, request: Request<pb::FlagRequest>) -> RpcResult<pb::Empty> {
+        let storage = self.authorize(&request, true)?;
+        let flag = request.get_ref();
+        storage.set_protected(flag.id, flag.value).map_err(status)?;
+        Ok(Response::new(pb::Empty {}))
+    }
+
+    async fn captures(&self, request: Request<pb::ClipId>) -> RpcResult<pb::CaptureList> {
+        let storage = self.authorize(&request, false)?;
+        let captures = storage.captures(request.get_ref().id).map_err(status)?;
+        let captures = captures
+            .into_iter()
+            .map(|c| pb::Capture {
+                captured_at: Some(timestamp(c.captured_at)),
+                source_app: c.source_app,
+                machine: c.machine,
+            })
+            .collect();
+        Ok(Response::new(pb::CaptureList { captures }))
+    }
+
+    async fn stats(&self, request: Request<pb::Empty>) -> RpcResult<pb::StorageStats> {
+        let storage = self.authorize(&request, false)?;
+        let stats = storage.stats().map_err(status)?;
+        Ok(Response::new(pb::StorageStats {
+            total_clips: stats.total_clips,
+            text_clips: stats.text_clips,
+            image_clips: stats.image_clips,
+            fileref_clips: stats.fileref_clips,
+            total_size: stats.total_size,
+            oldest: stats.oldest.map(timestamp),
+            newest: stats.newest.map(timestamp),
+        }))
+    }
+
+    type WatchClipsStream =
+        Pin<Box<dyn Stream<Item = std::result::Result<pb::Clip, Status>> + Send>>;
+
+    async fn watch_clips(
+        &self,
+        request: Request<pb::WatchRequest>,
+    ) -> RpcResult<Self::WatchClipsStream> {
+        let mut last = {
+            let storage = self.authorize(&request, false)?;
+            let newest = storage
+                .list(ClipFilter {
+                    limit: 1,
+                    ..Default::default()
+                })
+                .map_err(status)?;
+            newest.first().map_or(0, |c| c.id)
+        };
+        let filter = clip_filter(request.into_inner().filter.unwrap_or_default())?;
+        let storage = Arc::clone(&self.storage);
+        let (tx, rx) = tokio::sync::mpsc::channel(16);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(WATCH_INTERVAL);
+            loop {
+                interval.tick().await;
+                let found = lock(&storage).list(ClipFilter {
+                    cursor: Some(last),
+                    oldest_first: true,
+                    as_of: None,
+                    limit: WATCH_BATCH,
+                    offset: 0,
+                    ..filter.clone()
+                });
+                let clips = match found {
+                    Ok(clips) => clips,
+                    Err(e) => {
+                        let _ = tx.send(Err(status(e))).await;
+                        return;
+                    }
+                };
+                for clip in clips {
+                    last = clip.id;
+                    // The client hung up.
+                    if tx.send(Ok(clip_message(&clip))).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(
+            tokio_stream::wrappers::ReceiverStream::new(rx),
+        )))
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn status(e: CbError) -> Status {
+    match e {
+        CbError::NotFound(_) => Status::not_found(e.to_string()),
+        CbError::InvalidInput(_) => Status::invalid_argument(e.to_string()),
+        CbError::Protected(_) => Status::failed_precondition(e.to_string()),
+        _ => Status::internal(e.to_string()),
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn timestamp(time: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn datetime(
+    time: Option<prost_types::Timestamp>,
+) -> std::result::Result<Option<DateTime<Utc>>, Status> {
+    time.map(|t| {
+        u32::try_from(t.nanos)
+            .ok()
+            .and_then(|nanos| DateTime::from_timestamp(t.seconds, nanos))
+            .ok_or_else(|| Status::invalid_argument("timestamp out of range"))
+    })
+    .transpose()
+}
+
+#[cfg(feature = "grpc")]
+fn content_type(value: i32) -> std::result::Result<Option<ContentType>, Status> {
+    match pb::ContentType::try_from(value) {
+        Ok(pb::ContentType::Unspecified) => Ok(None),
+        Ok(pb::ContentType::Text) => Ok(Some(ContentType::Text)),
+        Ok(pb::ContentType::Image) => Ok(Some(ContentType::Image)),
+        Ok(pb::ContentType::Fileref) => Ok(Some(ContentType::FileRef)),
+        Err(_) => Err(Status::invalid_argument(format!(
+            "unknown content type {}",
+            value
+        ))),
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn clip_filter(filter: pb::ClipFilter) -> std::result::Result<ClipFilter, Status> {
+    let tone = match pb::Tone::try_from(filter.tone) {
+        Ok(pb::Tone::Unspecified) => None,
+        Ok(pb::Tone::Dark) => Some(Tone::Dark),
+        Ok(pb::Tone::Light) => Some(Tone::Light),
+        Err(_) => {
+            return Err(Status::invalid_argument(format!(
+                "unknown tone {}",
+                filter.tone
+            )));
+        }
+    };
+    Ok(ClipFilter {
+        content_type: filter.content_type.map(content_type).transpose()?.flatten(),
+        pinned: filter.pinned,
+        tag: filter.tag,
+        lang: filter.lang,
+        origin: filter.origin,
+        app: filter.app,
+        before: datetime(filter.before)?,
+        query: filter.query,
+        protected: filter.protected,
+        as_of: datetime(filter.as_of)?,
+        tone,
+        limit: filter.limit,
+        offset: filter.offset,
+        ..Default::default()
+    })
+}
+
+#[cfg(feature = "grpc")]
+fn new_clip(clip: pb::NewClip) -> std::result::Result<NewClip, Status> {
+    let content_type = content_type(clip.content_type)?.unwrap_or_default();
+    if content_type == ContentType::Image {
+        return Err(Status::invalid_argument(
+            "image clips can't be inserted over gRPC",
+        ));
+    }
+    let text = clip
+        .text_content
+        .ok_or_else(|| Status::invalid_argument("text_content is required"))?;
+    let hash = if clip.hash.is_empty() {
+        hash_content(text.as_bytes())
+    } else {
+        clip.hash
+    };
+    let size_bytes = if clip.size_bytes == 0 {
+        text.len() as i64
+    } else {
+        clip.size_bytes
+    };
+    Ok(NewClip {
+        content_type,
+        text_content: Some(text),
+        hash,
+        size_bytes,
+        origin: clip.origin,
+        ..Default::default()
+    })
+}
+
+#[cfg(feature = "grpc")]
+fn clip_message(clip: &Clip) -> pb::Clip {
+    let content_type = match clip.content_type {
+        ContentType::Text => pb::ContentType::Text,
+        ContentType::Image => pb::ContentType::Image,
+        ContentType::FileRef => pb::ContentType::Fileref,
+    };
+    pb::Clip {
+        id: clip.id,
+        content_type: content_type.into(),
+        text_content: clip.text_content.clone(),
+        image_width: clip.image_width,
+        image_height: clip.image_height,
+        hash: clip.hash.clone(),
+        size_bytes: clip.size_bytes,
+        pinned: clip.pinned,
+        created_at: Some(timestamp(clip.created_at)),
+        updated_at: Some(timestamp(clip.updated_at)),
+        ocr_text: clip.ocr_text.clone(),
+        lang: clip.lang.clone(),
+        link_status: clip.link_status,
+        link_redirect: clip.link_redirect.clone(),
+        link_checked_at: clip.link_checked_at.map(timestamp),
+        copy_count: clip.copy_count,
+        protected: clip.protected,
+        source_app: clip.source_app.clone(),
+        colors: clip.colors.clone(),
+        brightness: clip.brightness,
+        origin: clip.origin.clone(),
+        tags: clip.tags.clone(),
+        app_name: clip.app_name.clone(),
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn clip_list(clips: &[Clip]) -> pb::ClipList {
+    pb::ClipList {
+        clips: clips.iter().map(clip_message).collect(),
+    }
+}
+
+#[cfg(all(test, feature = "grpc"))]
+mod tests {
+    use super::*;
+    use crate::storage::sqlite::SqliteStorage;
+    use pb::clip_storage_client::ClipStorageClient;
+    use tonic::transport::Channel;
+
+    const TOKEN: &str = "secret";
+
+    async fn start(storage: SqliteStorage) -> ClipStorageClient<Channel> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_on(Service::new(storage, Some(TOKEN)), listener));
+        ClipStorageClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap()
+    }
+
+    fn authed<T>(message: T, token: &str) -> Request<T> {
+        let mut request = Request::new(message);
+        let value = format!("Bearer {}", token).parse().unwrap();
+        request.metadata_mut().insert("authorization", value);
+        request
+    }
+
+    fn text_clip(text: &str) -> pb::NewClip {
+        pb::NewClip {
+            content_type: pb::ContentType::Text.into(),
+            text_content: Some(text.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_search_and_get_round_trip() {
+        let mut client = start(SqliteStorage::in_memory().unwrap()).await;
+        for text in ["alpha one", "beta two", "alpha three"] {
+            client.insert(authed(text_clip(text), TOKEN)).await.unwrap();
+        }
+
+        let filter = pb::ClipFilter {
+            limit: 2,
+            ..Default::default()
+        };
+        let listed = client
+            .list(authed(filter, TOKEN))
+            .await
+            .unwrap()
+            .into_inner();
+        let texts: Vec<_> = listed
+            .clips
+            .iter()
+            .map(|c| c.text_content.as_deref().unwrap())
+            .collect();
+        assert_eq!(texts, ["alpha three", "beta two"]);
+
+        let search = pb::SearchRequest {
+            query: "alpha".into(),
+            limit: 0,
+        };
+        let found = client
+            .search(authed(search, TOKEN))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(found.clips.len(), 2);
+
+        let clip = client
+            .get(authed(pb::ClipId { id: 2 }, TOKEN))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(clip.text_content.as_deref(), Some("beta two"));
+        assert_eq!(clip.content_type(), pb::ContentType::Text);
+        assert_eq!(clip.hash, hash_content(b"beta two"));
+
+        let missing = client
+            .get(authed(pb::ClipId { id: 99 }, TOKEN))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_calls_need_a_token_that_allows_them() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        storage
+            .create_token("viewer", &hash_content(b"read-only"), TokenScope::Read)
+            .unwrap();
+        let mut client = start(storage).await;
+
+        let denied = client
+            .list(Request::new(pb::ClipFilter::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(denied.code(), tonic::Code::Unauthenticated);
+        let denied = client
+            .list(authed(pb::ClipFilter::default(), "wrong"))
+            .await
+            .unwrap_err();
+        assert_eq!(denied.code(), tonic::Code::Unauthenticated);
+
+        assert!(
+            client
+                .list(authed(pb::ClipFilter::default(), "read-only"))
+                .await
+                .is_ok()
+        );
+        let denied = client
+            .insert(authed(text_clip("x"), "read-only"))
+            .await
+            .unwrap_err();
+        assert_eq!(denied.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_watch_clips_streams_new_matching_clips() {
+        let mut client = start(SqliteStorage::in_memory().unwrap()).await;
+        client
+            .insert(authed(text_clip("before"), TOKEN))
+            .await
+            .unwrap();
+
+        let watch = pb::WatchRequest {
+            filter: Some(pb::ClipFilter {
+                query: Some("keep".into()),
+                ..Default::default()
+            }),
+        };
+        let mut stream = client
+            .watch_clips(authed(watch, TOKEN))
+            .await
+            .unwrap()
+            .into_inner();
+        client
+            .insert(authed(text_clip("skip me"), TOKEN))
+            .await
+            .unwrap();
+        client
+            .insert(authed(text_clip("keep me"), TOKEN))
+            .await
+            .unwrap();
+
+        let clip = stream.message().await.unwrap().unwrap();
+        assert_eq!(clip.text_content.as_deref(), Some("keep me"));
+    }
+}
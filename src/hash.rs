@@ -1,9 +1,35 @@
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
 pub fn hash_content(data: &[u8]) -> String {
     format!("{:x}", Sha256::digest(data))
 }
 
+/// How loosely the daemon compares text when deciding a copy is a duplicate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DedupMode {
+    /// Only byte-identical content is a duplicate.
+    #[default]
+    Exact,
+    /// Ignore leading/trailing whitespace and differences in runs of
+    /// whitespace (indentation, trailing newlines, tabs vs spaces).
+    Whitespace,
+    /// Like `Whitespace`, and also ignore letter case.
+    IgnoreCase,
+}
+
+/// Hash of `text` after normalizing it per `mode`, or `None` for `Exact`
+/// (the content hash already covers that case).
+pub fn normalized_hash(text: &str, mode: DedupMode) -> Option<String> {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match mode {
+        DedupMode::Exact => None,
+        DedupMode::Whitespace => Some(hash_content(collapsed.as_bytes())),
+        DedupMode::IgnoreCase => Some(hash_content(collapsed.to_lowercase().as_bytes())),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -22,6 +48,16 @@ mod tests {
         assert_ne!(h1, h2);
     }
 
+    #[test]
+    fn test_normalized_hash() {
+        assert!(normalized_hash("a", DedupMode::Exact).is_none());
+        let ws = |s| normalized_hash(s, DedupMode::Whitespace).unwrap();
+        assert_eq!(ws("fn main() {\n    x\n}\n"), ws("fn main() {\n\tx\n}"));
+        assert_ne!(ws("Hello"), ws("hello"));
+        let ic = |s| normalized_hash(s, DedupMode::IgnoreCase).unwrap();
+        assert_eq!(ic("  Hello World\n"), ic("hello world"));
+    }
+
     #[test]
     fn test_hash_is_hex_sha256() {
         let h = hash_content(b"hello");
@@ -0,0 +1,313 @@
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::clipboard::ClipboardContent;
+use crate::errors::{CbError, Result};
+use crate::hash::hash_content;
+use crate::storage::models::ContentType;
+
+pub const DEFAULT_TIMEOUT_MS: u64 = 2000;
+
+/// External command consulted by the daemon before each capture is stored.
+///
+/// It receives the candidate clip as JSON on stdin (text, type, size, source
+/// app; never image pixels) and answers on stdout with
+/// `{"action": "keep"}`, `{"action": "skip"}`, or
+/// `{"action": "modify", "text": "..."}` (text clips only).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CaptureHook {
+    /// Program and arguments, e.g. `["python3", "/Users/me/bin/cb-filter.py"]`.
+    pub command: Vec<String>,
+    /// Give up on the command after this long.
+    pub timeout_ms: u64,
+    /// Drop the capture, rather than keep it, when the command fails, times
+    /// out, or replies with something unexpected.
+    pub skip_on_error: bool,
+}
+
+impl Default for CaptureHook {
+    fn default() -> Self {
+        Self {
+            command: Vec::new(),
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            skip_on_error: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Keep,
+    Skip,
+    /// Store this text instead of what was copied.
+    Modify(String),
+}
+
+#[derive(Serialize)]
+struct Candidate<'a> {
+    content_type: &'a ContentType,
+    text: Option<&'a str>,
+    size_bytes: i64,
+    width: Option<i32>,
+    height: Option<i32>,
+    source_app: Option<&'a str>,
+    app_name: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase", tag = "action")]
+enum Reply {
+    Keep,
+    Skip,
+    Modify { text: String },
+}
+
+impl CaptureHook {
+    /// Ask the command about `content`. Errors are reported rather than
+    /// applied, so the caller decides (see `skip_on_error`).
+    pub fn check(&self, content: &ClipboardContent) -> Result<Verdict> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| CbError::Config("capture_hook.command is empty".into()))?;
+        let input = serde_json::to_vec(&Candidate {
+            content_type: &content.content_type,
+            text: content.text.as_deref(),
+            size_bytes: content.size_bytes,
+            width: content.width,
+            height: content.height,
+            source_app: content.source_app.as_deref(),
+            app_name: content.app_name.as_deref(),
+        })
+        .unwrap();
+
+        let output = run_command(program, args, input, self.timeout_ms, "capture hook")?;
+
+        let reply: Reply = serde_json::from_slice(&output)
+            .map_err(|e| CbError::Config(format!("capture hook reply: {}", e)))?;
+        match reply {
+            Reply::Keep => Ok(Verdict::Keep),
+            Reply::Skip => Ok(Verdict::Skip),
+            Reply::Modify { text } if content.content_type == ContentType::Text => {
+                Ok(Verdict::Modify(text))
+            }
+            Reply::Modify { .. } => Err(CbError::Config(
+                "capture hook can only modify text clips".into(),
+            )),
+        }
+    }
+
+    /// Run the hook and apply its verdict: `None` drops the capture,
+    /// otherwise the (possibly rewritten) content is returned for storage.
+    pub fn filter(&self, mut content: ClipboardContent) -> Option<ClipboardContent> {
+        let verdict = self.check(&content).unwrap_or_else(|e| {
+            eprintln!("cb: {}", e);
+            if self.skip_on_error {
+                Verdict::Skip
+            } else {
+                Verdict::Keep
+            }
+        });
+        match verdict {
+            Verdict::Keep => Some(content),
+            Verdict::Skip => None,
+            Verdict::Modify(text) if text.is_empty() => None,
+            Verdict::Modify(text) => {
+                content.hash = hash_content(text.as_bytes());
+                content.size_bytes = text.len() as i64;
+                content.text = Some(text);
+                Some(content)
+            }
+        }
+    }
+}
+
+/// Command that rewrites text clips on their way back to the system
+/// clipboard, e.g. to strip a watermark. It gets the clip's text on stdin and
+/// prints the text to copy; if it fails, nothing is copied.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CopyHook {
+    /// Program and arguments, e.g. `["sed", "s/ (internal)//"]`.
+    pub command: Vec<String>,
+    /// Give up on the command (and the copy) after this long.
+    pub timeout_ms: u64,
+}
+
+impl Default for CopyHook {
+    fn default() -> Self {
+        Self {
+            command: Vec::new(),
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+        }
+    }
+}
+
+impl CopyHook {
+    pub fn apply(&self, text: &str) -> Result<String> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| CbError::Config("on_copy.command is empty".into()))?;
+        let output = run_command(
+            program,
+            args,
+            text.as_bytes().to_vec(),
+            self.timeout_ms,
+            "on_copy hook",
+        )?;
+        String::from_utf8(output)
+            .map_err(|_| CbError::Config("on_copy hook printed invalid UTF-8".into()))
+    }
+}
+
+/// Run `program` with `input` on stdin and return its stdout, failing if it
+/// exits unsuccessfully or outlives `timeout_ms`. `what` names the hook in
+/// errors.
+fn run_command(
+    program: &str,
+    args: &[String],
+    input: Vec<u8>,
+    timeout_ms: u64,
+    what: &str,
+) -> Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| CbError::Config(format!("{} {}: {}", what, program, e)))?;
+
+    // Feed stdin and drain stdout on threads so a slow or chatty command
+    // can't deadlock us before the timeout kicks in.
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || {
+        let _ = stdin.write_all(&input);
+    });
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = stdout.read_to_end(&mut output);
+        output
+    });
+
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| CbError::Config(e.to_string()))?
+        {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(CbError::Config(format!(
+                "{} timed out after {}ms",
+                what, timeout_ms
+            )));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let _ = writer.join();
+    let output = reader.join().unwrap_or_default();
+    if !status.success() {
+        return Err(CbError::Config(format!("{} exited with {}", what, status)));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::Workspace;
+
+    fn hook(script: &str) -> CaptureHook {
+        CaptureHook {
+            command: vec!["sh".into(), "-c".into(), script.into()],
+            ..Default::default()
+        }
+    }
+
+    fn text(text: &str) -> ClipboardContent {
+        ClipboardContent {
+            content_type: ContentType::Text,
+            text: Some(text.to_string()),
+            image_data: None,
+            width: None,
+            height: None,
+            hash: hash_content(text.as_bytes()),
+            size_bytes: text.len() as i64,
+            source_app: Some("com.example.app".into()),
+            app_name: Some("Example".into()),
+            workspace: Workspace::default(),
+            project: None,
+        }
+    }
+
+    #[test]
+    fn test_keep_skip_and_modify() {
+        let keep = hook(r#"cat >/dev/null; echo '{"action":"keep"}'"#);
+        assert_eq!(keep.check(&text("hi")).unwrap(), Verdict::Keep);
+
+        // The candidate arrives on stdin as JSON.
+        let skip_cards = hook(
+            r#"if grep -q '4111 1111'; then echo '{"action":"skip"}'; else echo '{"action":"keep"}'; fi"#,
+        );
+        assert!(
+            skip_cards
+                .filter(text("card 4111 1111 1111 1111"))
+                .is_none()
+        );
+        assert!(skip_cards.filter(text("hello")).is_some());
+
+        let upper = hook(r#"cat >/dev/null; echo '{"action":"modify","text":"HI"}'"#);
+        let modified = upper.filter(text("hi")).unwrap();
+        assert_eq!(modified.text.as_deref(), Some("HI"));
+        assert_eq!(modified.hash, hash_content(b"HI"));
+        assert_eq!(modified.size_bytes, 2);
+    }
+
+    #[test]
+    fn test_failures_follow_skip_on_error() {
+        let broken = hook("exit 3");
+        assert!(broken.check(&text("x")).is_err());
+        assert!(broken.filter(text("x")).is_some());
+
+        let strict = CaptureHook {
+            skip_on_error: true,
+            ..hook("echo nonsense")
+        };
+        assert!(strict.filter(text("x")).is_none());
+
+        let slow = CaptureHook {
+            timeout_ms: 100,
+            ..hook("sleep 5")
+        };
+        let start = Instant::now();
+        assert!(slow.check(&text("x")).is_err());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_copy_hook_rewrites_text() {
+        let strip = CopyHook {
+            command: vec!["sed".into(), "s/ (internal)//".into()],
+            ..Default::default()
+        };
+        assert_eq!(strip.apply("plan (internal)\n").unwrap(), "plan\n");
+
+        let broken = CopyHook {
+            command: vec!["sh".into(), "-c".into(), "exit 1".into()],
+            ..Default::default()
+        };
+        assert!(broken.apply("x").is_err());
+        assert!(CopyHook::default().apply("x").is_err());
+    }
+}
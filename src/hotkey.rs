@@ -0,0 +1,312 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::{CbError, Result};
+
+/// Shortcut bound when `--key` isn't given.
+pub const DEFAULT_KEY: &str = "cmd+shift+v";
+
+/// Script the hotkey opens in Terminal, written next to the config.
+const LAUNCHER_FILE: &str = "quick.command";
+
+const BEGIN_MARKER: &str = "cb hotkey: begin (managed by `cb hotkey install`)";
+const END_MARKER: &str = "cb hotkey: end";
+
+/// Hotkey daemons whose config `cb hotkey install` can extend.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tool {
+    Hammerspoon,
+    Skhd,
+}
+
+impl Tool {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "hammerspoon" => Ok(Tool::Hammerspoon),
+            "skhd" => Ok(Tool::Skhd),
+            _ => Err(CbError::InvalidInput(format!(
+                "unknown hotkey tool \"{}\" (expected hammerspoon or skhd)",
+                s
+            ))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Hammerspoon => "hammerspoon",
+            Tool::Skhd => "skhd",
+        }
+    }
+
+    /// The tool's config file under `home`.
+    pub fn config_file(&self, home: &Path) -> PathBuf {
+        match self {
+            Tool::Hammerspoon => home.join(".hammerspoon/init.lua"),
+            Tool::Skhd => {
+                let xdg = home.join(".config/skhd/skhdrc");
+                if xdg.exists() {
+                    xdg
+                } else {
+                    home.join(".skhdrc")
+                }
+            }
+        }
+    }
+
+    /// The first tool that looks installed: Hammerspoon if its config
+    /// directory exists, then skhd if it has a config file.
+    pub fn detect(home: &Path) -> Option<Self> {
+        if home.join(".hammerspoon").is_dir() {
+            Some(Tool::Hammerspoon)
+        } else if Tool::Skhd.config_file(home).exists() {
+            Some(Tool::Skhd)
+        } else {
+            None
+        }
+    }
+
+    fn comment(&self) -> &'static str {
+        match self {
+            Tool::Hammerspoon => "--",
+            Tool::Skhd => "#",
+        }
+    }
+
+    /// The config block binding `key` to open `launcher` in Terminal.
+    pub fn block(&self, key: &Shortcut, launcher: &Path) -> String {
+        let launcher = launcher.display();
+        let binding = match self {
+            Tool::Hammerspoon => format!(
+                "hs.hotkey.bind({{{}}}, \"{}\", function()\n  hs.task.new(\"/usr/bin/open\", nil, {{\"-a\", \"Terminal\", \"{}\"}}):start()\nend)",
+                key.modifiers
+                    .iter()
+                    .map(|m| format!("\"{}\"", m))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                key.key,
+                launcher
+            ),
+            Tool::Skhd => format!(
+                "{} - {} : open -a Terminal '{}'",
+                key.modifiers.join(" + "),
+                key.key,
+                launcher
+            ),
+        };
+        format!(
+            "{c} {}\n{}\n{c} {}\n",
+            BEGIN_MARKER,
+            binding,
+            END_MARKER,
+            c = self.comment()
+        )
+    }
+}
+
+/// A parsed shortcut such as `cmd+shift+v`.
+#[derive(Debug, PartialEq)]
+pub struct Shortcut {
+    /// Modifier names as both Hammerspoon and skhd spell them.
+    pub modifiers: Vec<&'static str>,
+    pub key: String,
+}
+
+impl Shortcut {
+    /// Parse `mod+mod+key`. At least one modifier is required, since a bare
+    /// key bound globally would swallow normal typing.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid =
+            |why: &str| CbError::InvalidInput(format!("invalid hotkey \"{}\": {}", spec, why));
+        let parts: Vec<&str> = spec.split('+').map(str::trim).collect();
+        let (key, mods) = parts.split_last().ok_or_else(|| invalid("empty"))?;
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(invalid("missing key"));
+        }
+        if mods.is_empty() {
+            return Err(invalid(
+                "needs at least one modifier (cmd, ctrl, alt, shift)",
+            ));
+        }
+        let mut modifiers = Vec::new();
+        for m in mods {
+            let m = match m.to_ascii_lowercase().as_str() {
+                "cmd" | "command" => "cmd",
+                "ctrl" | "control" => "ctrl",
+                "alt" | "opt" | "option" => "alt",
+                "shift" => "shift",
+                other => return Err(invalid(&format!("unknown modifier \"{}\"", other))),
+            };
+            if !modifiers.contains(&m) {
+                modifiers.push(m);
+            }
+        }
+        Ok(Self {
+            modifiers,
+            key: key.to_ascii_lowercase(),
+        })
+    }
+}
+
+/// `config` with `block` in place of any earlier cb block, or appended.
+pub fn with_block(config: &str, block: &str) -> String {
+    let mut out = without_block(config).unwrap_or_else(|| config.to_string());
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(block);
+    out
+}
+
+/// `config` with the cb block removed, or `None` if it has none.
+pub fn without_block(config: &str) -> Option<String> {
+    let start = config.find(BEGIN_MARKER)?;
+    let start = config[..start].rfind('\n').map_or(0, |i| i + 1);
+    let end = config[start..].find(END_MARKER)? + start;
+    let end = config[end..]
+        .find('\n')
+        .map_or(config.len(), |i| end + i + 1);
+    let before = config[..start].trim_end_matches('\n');
+    let after = &config[end..];
+    let mut out = before.to_string();
+    if !out.is_empty() && !after.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(after.trim_start_matches('\n'));
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    Some(out)
+}
+
+/// Write the launcher script into `base` and the binding into the tool's
+/// config under `home`. Returns the config file that was changed.
+pub fn install(
+    tool: Tool,
+    key: &Shortcut,
+    base: &Path,
+    home: &Path,
+    exe: &Path,
+) -> Result<PathBuf> {
+    let io = |e: std::io::Error| CbError::Config(e.to_string());
+    fs::create_dir_all(base).map_err(io)?;
+    let launcher = base.join(LAUNCHER_FILE);
+    // `exec` so Terminal sees the shell exit cleanly and closes the window.
+    fs::write(
+        &launcher,
+        format!("#!/bin/sh\nexec '{}' tui --quick\n", exe.display()),
+    )
+    .map_err(io)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&launcher, fs::Permissions::from_mode(0o755)).map_err(io)?;
+    }
+
+    let config_file = tool.config_file(home);
+    if let Some(dir) = config_file.parent() {
+        fs::create_dir_all(dir).map_err(io)?;
+    }
+    let existing = match fs::read_to_string(&config_file) {
+        Ok(s) => s,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(io(e)),
+    };
+    fs::write(
+        &config_file,
+        with_block(&existing, &tool.block(key, &launcher)),
+    )
+    .map_err(io)?;
+    Ok(config_file)
+}
+
+/// Remove the binding from the tool's config and the launcher script.
+/// Returns whether a binding was found.
+pub fn uninstall(tool: Tool, base: &Path, home: &Path) -> Result<bool> {
+    let io = |e: std::io::Error| CbError::Config(e.to_string());
+    let _ = fs::remove_file(base.join(LAUNCHER_FILE));
+    let config_file = tool.config_file(home);
+    let Ok(existing) = fs::read_to_string(&config_file) else {
+        return Ok(false);
+    };
+    match without_block(&existing) {
+        Some(config) => {
+            fs::write(&config_file, config).map_err(io)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_shortcut() {
+        let key = Shortcut::parse("Command+Shift+V").unwrap();
+        assert_eq!(key.modifiers, vec!["cmd", "shift"]);
+        assert_eq!(key.key, "v");
+        assert_eq!(Shortcut::parse("opt+space").unwrap().modifiers, vec!["alt"]);
+        assert!(Shortcut::parse("v").is_err());
+        assert!(Shortcut::parse("hyper+v").is_err());
+        assert!(Shortcut::parse("cmd+").is_err());
+    }
+
+    #[test]
+    fn test_blocks() {
+        let key = Shortcut::parse(DEFAULT_KEY).unwrap();
+        let launcher = Path::new("/Users/me/.cb/quick.command");
+        assert!(
+            Tool::Skhd
+                .block(&key, launcher)
+                .contains("cmd + shift - v : open -a Terminal '/Users/me/.cb/quick.command'")
+        );
+        assert!(
+            Tool::Hammerspoon
+                .block(&key, launcher)
+                .contains("hs.hotkey.bind({\"cmd\", \"shift\"}, \"v\", function()")
+        );
+
+        let config = "alt - h : yabai -m window --focus west\n";
+        let installed = with_block(config, &Tool::Skhd.block(&key, launcher));
+        assert!(installed.starts_with(config));
+        // Installing again replaces the block rather than adding another.
+        let other = Shortcut::parse("ctrl+alt+c").unwrap();
+        let reinstalled = with_block(&installed, &Tool::Skhd.block(&other, launcher));
+        assert_eq!(reinstalled.matches(BEGIN_MARKER).count(), 1);
+        assert!(reinstalled.contains("ctrl + alt - c"));
+        assert_eq!(without_block(&reinstalled).unwrap(), config);
+        assert!(without_block(config).is_none());
+    }
+
+    #[test]
+    fn test_install_and_uninstall() {
+        let dir = TempDir::new().unwrap();
+        let (base, home) = (dir.path().join(".cb"), dir.path().to_path_buf());
+        assert_eq!(Tool::detect(&home), None);
+        fs::create_dir_all(home.join(".hammerspoon")).unwrap();
+        assert_eq!(Tool::detect(&home), Some(Tool::Hammerspoon));
+
+        let key = Shortcut::parse(DEFAULT_KEY).unwrap();
+        let file = install(
+            Tool::Hammerspoon,
+            &key,
+            &base,
+            &home,
+            Path::new("/usr/local/bin/cb"),
+        )
+        .unwrap();
+        assert_eq!(file, home.join(".hammerspoon/init.lua"));
+        let launcher = fs::read_to_string(base.join(LAUNCHER_FILE)).unwrap();
+        assert!(launcher.contains("exec '/usr/local/bin/cb' tui --quick"));
+
+        assert!(uninstall(Tool::Hammerspoon, &base, &home).unwrap());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "");
+        assert!(!base.join(LAUNCHER_FILE).exists());
+        assert!(!uninstall(Tool::Hammerspoon, &base, &home).unwrap());
+    }
+}
@@ -0,0 +1,303 @@
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+/// Language for human-readable output. JSON output, command names, and
+/// config keys stay in English whatever the locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    De,
+}
+
+impl Locale {
+    /// Parse a locale name such as `de`, `de_DE.UTF-8`, or `de-AT`. `C` and
+    /// `POSIX` are English. `None` for languages without a translation.
+    pub fn parse(name: &str) -> Option<Locale> {
+        let lang = name.split(['_', '-', '.', '@']).next().unwrap_or_default();
+        match lang.to_ascii_lowercase().as_str() {
+            "en" | "c" | "posix" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            _ => None,
+        }
+    }
+
+    /// The locale from `LC_ALL`, `LC_MESSAGES`, or `LANG`, whichever is set
+    /// first, as gettext picks it. English when that language has no
+    /// translation.
+    pub fn detect() -> Locale {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Locale::parse(&value))
+            .unwrap_or(Locale::En)
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::En => &[],
+            Locale::De => DE,
+        }
+    }
+}
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+/// Set the locale for this process: `configured` (the `locale` config
+/// setting) when it names a translated language, otherwise the system
+/// locale. Has no effect once a message has been translated.
+pub fn init(configured: Option<&str>) {
+    let _ = LOCALE.set(configured.and_then(Locale::parse).unwrap_or_else(Locale::detect));
+}
+
+/// The locale messages are translated into.
+pub fn current() -> Locale {
+    // Tests assert on English output whatever the machine's locale is.
+    *LOCALE.get_or_init(|| if cfg!(test) { Locale::En } else { Locale::detect() })
+}
+
+/// `msg` in the current locale. Messages are keyed by their English text,
+/// which is also the fallback when a translation is missing.
+pub fn t(msg: &str) -> &str {
+    translate(current(), msg)
+}
+
+/// `t(msg)` with `{0}`, `{1}`, … replaced by `args`, so translations can
+/// reorder them.
+pub fn tf(msg: &str, args: &[&dyn Display]) -> String {
+    fill(t(msg), args)
+}
+
+fn translate(locale: Locale, msg: &str) -> &str {
+    locale
+        .catalog()
+        .iter()
+        .find(|(en, _)| *en == msg)
+        .map_or(msg, |(_, translated)| translated)
+}
+
+/// `template` with each `{n}` replaced by `args[n]`, in one pass so braces
+/// inside an argument are left alone.
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let arg = after
+            .find('}')
+            .and_then(|end| Some((end, args.get(after[..end].parse::<usize>().ok()?)?)));
+        match arg {
+            Some((end, arg)) => {
+                out.push_str(&arg.to_string());
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// German translations, keyed by the English message.
+const DE: &[(&str, &str)] = &[
+    // Errors
+    ("error", "Fehler"),
+    ("hint", "Hinweis"),
+    ("run `cb repair` to recover what can be salvaged", "`cb repair` rettet, was noch zu retten ist"),
+    ("Storage error", "Speicherfehler"),
+    ("Clipboard error", "Fehler der Zwischenablage"),
+    ("Image error", "Bildfehler"),
+    ("OCR error", "OCR-Fehler"),
+    ("Daemon error", "Daemon-Fehler"),
+    ("Config error", "Konfigurationsfehler"),
+    ("Not found", "Nicht gefunden"),
+    ("Invalid input", "Ungültige Eingabe"),
+    ("Clip #{0} is protected", "Clip #{0} ist geschützt"),
+    ("Database is corrupt", "Datenbank ist beschädigt"),
+    ("Encrypted database", "Verschlüsselte Datenbank"),
+    // Listings
+    ("No clips found.", "Keine Clips gefunden."),
+    ("No matching clips.", "Keine passenden Clips."),
+    ("No suggestions.", "Keine Vorschläge."),
+    ("No results for \"{0}\".", "Keine Ergebnisse für „{0}“."),
+    ("(untagged)", "(ohne Tag)"),
+    ("(not a URL)", "(keine URL)"),
+    ("(unknown app)", "(unbekannte App)"),
+    ("Today", "Heute"),
+    ("Yesterday", "Gestern"),
+    ("Last week", "Letzte Woche"),
+    ("Last month", "Letzter Monat"),
+    ("January", "Januar"),
+    ("February", "Februar"),
+    ("March", "März"),
+    ("May", "Mai"),
+    ("June", "Juni"),
+    ("July", "Juli"),
+    ("October", "Oktober"),
+    ("December", "Dezember"),
+    ("TYPE", "TYP"),
+    ("SIZE", "GRÖSSE"),
+    ("COPIES", "KOPIEN"),
+    ("SUBTYPE", "UNTERTYP"),
+    ("CREATED", "ERSTELLT"),
+    ("AGE", "ALTER"),
+    ("PREVIEW", "VORSCHAU"),
+    ("Group:", "Gruppe:"),
+    ("Type", "Typ"),
+    ("Pinned", "Angeheftet"),
+    ("Size", "Größe"),
+    ("Copies", "Kopien"),
+    ("Subtype", "Untertyp"),
+    ("Created", "Erstellt"),
+    ("Age", "Alter"),
+    ("yes", "ja"),
+    ("no", "nein"),
+    ("none", "keine"),
+    ("dead", "tot"),
+    ("File", "Datei"),
+    ("missing", "fehlt"),
+    // TUI statuses
+    ("Error: {0}", "Fehler: {0}"),
+    ("Config error: {0}", "Konfigurationsfehler: {0}"),
+    ("Copy failed: {0}", "Kopieren fehlgeschlagen: {0}"),
+    ("Copied image #{0}", "Bild #{0} kopiert"),
+    ("Copied #{0} as {1}", "#{0} als {1} kopiert"),
+    ("Copied #{0}", "#{0} kopiert"),
+    ("File ref: {0}", "Dateiverweis: {0}"),
+    ("Image clip has no path", "Bild-Clip hat keinen Pfad"),
+    ("Delete #{0}? [y/n]", "#{0} löschen? [y/n]"),
+    ("Moved #{0} to the trash", "#{0} in den Papierkorb verschoben"),
+    ("#{0} not found", "#{0} nicht gefunden"),
+    ("Delete error: {0}", "Fehler beim Löschen: {0}"),
+    ("Delete cancelled", "Löschen abgebrochen"),
+    ("Pinned #{0}", "#{0} angeheftet"),
+    ("Unpinned #{0}", "#{0} losgelöst"),
+    ("Pin error: {0}", "Fehler beim Anheften: {0}"),
+    ("Empty tag", "Leerer Tag"),
+    ("Tagged #{0} \"{1}\"", "#{0} mit „{1}“ getaggt"),
+    ("Tag error: {0}", "Fehler beim Taggen: {0}"),
+    ("Removed tag \"{0}\" from #{1}", "Tag „{0}“ von #{1} entfernt"),
+    ("Remove tag error: {0}", "Fehler beim Entfernen des Tags: {0}"),
+    ("Stopped daemon (was pid {0})", "Daemon gestoppt (war PID {0})"),
+    ("Daemon already stopped", "Daemon ist bereits gestoppt"),
+    ("Stop error: {0}", "Fehler beim Stoppen: {0}"),
+    ("Started daemon (pid {0})", "Daemon gestartet (PID {0})"),
+    ("Start error: {0}", "Fehler beim Starten: {0}"),
+    ("Status error: {0}", "Statusfehler: {0}"),
+    ("Stats error: {0}", "Statistikfehler: {0}"),
+    ("Cleared {0} clip(s) ({1})", "{0} Clip(s) entfernt ({1})"),
+    ("Clear error: {0}", "Fehler beim Aufräumen: {0}"),
+    ("Refreshed", "Aktualisiert"),
+    ("Upgraded database; backup at {0}", "Datenbank aktualisiert; Sicherung unter {0}"),
+    (
+        "No Accessibility permission: Enter will copy but not paste (see cb doctor)",
+        "Keine Bedienungshilfen-Berechtigung: Enter kopiert, fügt aber nicht ein (siehe cb doctor)",
+    ),
+    ("copied, but not pasted. {0}", "kopiert, aber nicht eingefügt. {0}"),
+    (
+        "the image file of clip #{0} is missing ({1}); `cb verify --fix` re-links or removes it",
+        "die Bilddatei von Clip #{0} fehlt ({1}); `cb verify --fix` verknüpft sie neu oder entfernt den Clip",
+    ),
+    // TUI panes
+    (" CB — {0} clips — {1} — {2} ", " CB — {0} Clips — {1} — {2} "),
+    ("daemon: running (pid {0})", "Daemon: läuft (PID {0})"),
+    ("daemon: not running", "Daemon: läuft nicht"),
+    ("{0}x{1} img", "{0}x{1} Bild"),
+    ("file ref", "Dateiverweis"),
+    ("image file missing", "Bilddatei fehlt"),
+    ("Search", "Suche"),
+    ("Fuzzy", "Unscharf"),
+    ("Type:", "Typ:"),
+    ("Pinned:", "Angeheftet:"),
+    (" (until {0})", " (bis {0})"),
+    ("Size:", "Größe:"),
+    ("Created:", "Erstellt:"),
+    ("Origin:", "Herkunft:"),
+    ("Source:", "Quelle:"),
+    ("Counts:", "Umfang:"),
+    ("Path:", "Pfad:"),
+    ("Dims:", "Maße:"),
+    ("File:", "Datei:"),
+    ("Captured:", "Erfasst:"),
+    (" on {0}", " auf {0}"),
+    (" in Space {0}", " auf Schreibtisch {0}"),
+    ("No clip selected", "Kein Clip ausgewählt"),
+    ("No clips", "Keine Clips"),
+    ("Stats", "Statistik"),
+    ("Remove tag: {0}_", "Tag entfernen: {0}_"),
+    ("Clips (oldest first)", "Clips (älteste zuerst)"),
+    ("oldest first", "älteste zuerst"),
+    ("Preview", "Vorschau"),
+    ("Preview [scroll: {0}]", "Vorschau [Scroll: {0}]"),
+    ("Total clips:", "Clips insgesamt:"),
+    ("Text / Image / File:", "Text / Bild / Datei:"),
+    ("Total size:", "Gesamtgröße:"),
+    ("Most copied", "Am häufigsten kopiert"),
+    ("Top apps", "Häufigste Apps"),
+    ("Clips by hour", "Clips nach Stunde"),
+    // TUI help bar
+    (
+        " [q]uit [/]search [Enter]copy [f]enced [d]el [p]in [t]ag [T]untag [r]efresh [D]aemon [c]lear [s]tats [o]rder [</>]resize [J/K]scroll",
+        " [q] Beenden [/] Suchen [Enter] Kopieren [f] Codeblock [d] Löschen [p] Anheften [t] Tag [T] Tag entfernen [r] Aktualisieren [D] Daemon [c] Aufräumen [s] Statistik [o] Reihenfolge [</>] Breite [J/K] Scrollen",
+    ),
+    (
+        " Type to search · [↑/↓] select · [Tab] fuzzy · [Enter] paste · [Esc] quit",
+        " Tippen zum Suchen · [↑/↓] Auswahl · [Tab] unscharf · [Enter] Einfügen · [Esc] Beenden",
+    ),
+    (
+        " Type to search (live) · [↑/↓] select · [Tab] fuzzy · [Enter] done · [Esc] cancel",
+        " Tippen zum Suchen (live) · [↑/↓] Auswahl · [Tab] unscharf · [Enter] Fertig · [Esc] Abbrechen",
+    ),
+    (" [s/Esc] back · [q]uit", " [s/Esc] Zurück · [q] Beenden"),
+    (" Type tag name · [Enter] add · [Esc] cancel", " Tag eingeben · [Enter] Hinzufügen · [Esc] Abbrechen"),
+    (" Type tag name · [Enter] remove · [Esc] cancel", " Tag eingeben · [Enter] Entfernen · [Esc] Abbrechen"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_locale_names() {
+        assert_eq!(Locale::parse("de_DE.UTF-8"), Some(Locale::De));
+        assert_eq!(Locale::parse("de-AT"), Some(Locale::De));
+        assert_eq!(Locale::parse("DE"), Some(Locale::De));
+        assert_eq!(Locale::parse("C"), Some(Locale::En));
+        assert_eq!(Locale::parse("en_GB.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::parse("fr_FR"), None);
+    }
+
+    #[test]
+    fn test_translate_falls_back_to_english() {
+        assert_eq!(translate(Locale::De, "Today"), "Heute");
+        assert_eq!(translate(Locale::En, "Today"), "Today");
+        assert_eq!(translate(Locale::De, "not in the catalog"), "not in the catalog");
+    }
+
+    #[test]
+    fn test_fill_replaces_numbered_arguments() {
+        assert_eq!(fill("Removed tag \"{0}\" from #{1}", &[&"work", &7]), "Removed tag \"work\" from #7");
+        assert_eq!(
+            fill(translate(Locale::De, "Removed tag \"{0}\" from #{1}"), &[&"work", &7]),
+            "Tag „work“ von #7 entfernt"
+        );
+        assert_eq!(fill("Tagged #{0} \"{1}\"", &[&1, &"{0}"]), "Tagged #1 \"{0}\"");
+        assert_eq!(fill("{x} {5}", &[&1]), "{x} {5}");
+    }
+
+    #[test]
+    fn test_translations_keep_placeholders() {
+        let placeholders = |s: &str| (0..4).filter(|n| s.contains(&format!("{{{}}}", n))).count();
+        for (en, de) in DE {
+            assert_eq!(placeholders(en), placeholders(de), "{en:?} → {de:?}");
+        }
+        let mut keys: Vec<&str> = DE.iter().map(|(en, _)| *en).collect();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), DE.len(), "duplicate catalog entry");
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{CbError, Result};
+use crate::export;
+use crate::hash::hash_content;
+use crate::storage::ClipStorage;
+use crate::storage::models::{Clip, ContentType, JobKind, NewClip};
+
+pub const DEFAULT_MAX_IMPORT_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub imported: i64,
+    pub duplicates: i64,
+    pub too_large: i64,
+    pub skipped: i64,
+}
+
+/// Create one text clip per regular file in `dir`, backdated to the file's
+/// modification time. Hidden files, empty files, and anything that isn't
+/// valid UTF-8 are skipped; content already in history is counted as a
+/// duplicate but still receives `tag`.
+pub fn import_dir<S: ClipStorage>(
+    storage: &S,
+    dir: &Path,
+    tag: Option<&str>,
+    max_size: u64,
+) -> Result<ImportSummary> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| CbError::InvalidInput(format!("{}: {}", dir.display(), e)))?;
+
+    let mut files: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .filter(|p| {
+            !p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'))
+        })
+        .collect();
+    files.sort();
+
+    let mut summary = ImportSummary::default();
+    for path in files {
+        let Ok(meta) = fs::metadata(&path) else {
+            summary.skipped += 1;
+            continue;
+        };
+        if meta.len() > max_size {
+            summary.too_large += 1;
+            continue;
+        }
+        let text = match fs::read_to_string(&path) {
+            Ok(t) if !t.is_empty() => t,
+            _ => {
+                summary.skipped += 1;
+                continue;
+            }
+        };
+
+        let hash = hash_content(text.as_bytes());
+        let clip = match storage.find_by_hash(&hash)? {
+            Some(existing) => {
+                if existing.deleted_at.is_some() {
+                    storage.restore(existing.id)?;
+                }
+                summary.duplicates += 1;
+                existing
+            }
+            None => {
+                let created_at = meta.modified().ok().map(DateTime::<Utc>::from);
+                summary.imported += 1;
+                storage.insert(NewClip {
+                    content_type: ContentType::Text,
+                    size_bytes: text.len() as i64,
+                    text_content: Some(text),
+                    hash,
+                    created_at,
+                    ..Default::default()
+                })?
+            }
+        };
+
+        if let Some(tag) = tag {
+            storage.add_tag(clip.id, tag)?;
+        }
+    }
+    Ok(summary)
+}
+
+/// The parts of a `cb export --format json` document that import reads.
+#[derive(Deserialize)]
+struct ExportDocument {
+    version: u32,
+    clips: Vec<ExportedClip>,
+}
+
+#[derive(Deserialize)]
+struct ExportedClip {
+    content_type: String,
+    text_content: Option<String>,
+    image_path: Option<String>,
+    image_width: Option<i32>,
+    image_height: Option<i32>,
+    hash: String,
+    size_bytes: i64,
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default)]
+    protected: bool,
+    created_at: Option<DateTime<Utc>>,
+    ocr_text: Option<String>,
+    origin: Option<String>,
+    source_url: Option<String>,
+    source_title: Option<String>,
+    link_title: Option<String>,
+    external_id: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    meta: BTreeMap<String, String>,
+    /// Copy of the image made by `cb export --images`, relative to the
+    /// export file.
+    image_file: Option<String>,
+}
+
+/// Add the clips in a `cb export --format json` file to history, oldest
+/// first, keeping their tags, pins, and capture times. Image files are
+/// copied into `images_dir` from the export's image copies, or from their
+/// original paths when those still exist; images found in neither are
+/// skipped, as are entries whose hash isn't hex. Clips already in history
+/// (by hash) are counted as duplicates, taking on the exported tags and pin.
+pub fn import_export<S: ClipStorage>(
+    storage: &S,
+    file: &Path,
+    images_dir: &Path,
+    tag: Option<&str>,
+) -> Result<ImportSummary> {
+    let invalid = |e: &dyn std::fmt::Display| CbError::InvalidInput(format!("{}: {}", file.display(), e));
+    let data = fs::read_to_string(file).map_err(|e| invalid(&e))?;
+    let document: ExportDocument = serde_json::from_str(&data).map_err(|e| invalid(&e))?;
+    if document.version > export::VERSION {
+        return Err(invalid(&format!(
+            "export version {} is newer than this cb understands ({})",
+            document.version,
+            export::VERSION
+        )));
+    }
+    let base = file.parent().unwrap_or(Path::new("."));
+
+    let mut summary = ImportSummary::default();
+    for entry in document.clips {
+        // The hash names image files, so anything but hex could leave
+        // `images_dir`.
+        let Some(content_type) = ContentType::parse(&entry.content_type)
+            .filter(|_| !entry.hash.is_empty() && entry.hash.chars().all(|c| c.is_ascii_hexdigit()))
+        else {
+            summary.skipped += 1;
+            continue;
+        };
+        let clip = match storage.find_by_hash(&entry.hash)? {
+            Some(existing) => {
+                if existing.deleted_at.is_some() {
+                    storage.restore(existing.id)?;
+                }
+                summary.duplicates += 1;
+                existing
+            }
+            None => {
+                let image_path = match content_type {
+                    ContentType::Image => match copy_image(&entry, base, images_dir)? {
+                        Some(path) => Some(path),
+                        None => {
+                            summary.skipped += 1;
+                            continue;
+                        }
+                    },
+                    _ => entry.image_path.clone(),
+                };
+                summary.imported += 1;
+                insert_exported(storage, &entry, content_type, image_path)?
+            }
+        };
+
+        if entry.pinned && !clip.pinned {
+            storage.set_pinned(clip.id, true)?;
+        }
+        for t in entry.tags.iter().map(String::as_str).chain(tag) {
+            storage.add_tag(clip.id, t)?;
+        }
+        for (key, value) in &entry.meta {
+            storage.set_meta(clip.id, key, value)?;
+        }
+    }
+    Ok(summary)
+}
+
+fn insert_exported<S: ClipStorage>(
+    storage: &S,
+    entry: &ExportedClip,
+    content_type: ContentType,
+    image_path: Option<String>,
+) -> Result<Clip> {
+    let is_image = content_type == ContentType::Image;
+    let clip = storage.insert(NewClip {
+        content_type,
+        text_content: entry.text_content.clone(),
+        image_path,
+        image_width: entry.image_width,
+        image_height: entry.image_height,
+        hash: entry.hash.clone(),
+        size_bytes: entry.size_bytes,
+        created_at: entry.created_at,
+        origin: entry.origin.clone(),
+        ..Default::default()
+    })?;
+    if let Some(ref text) = entry.ocr_text {
+        storage.set_ocr_text(clip.id, text)?;
+    }
+    if let Some(ref url) = entry.source_url {
+        storage.set_source_page(clip.id, url, entry.source_title.as_deref())?;
+    }
+    if let Some(ref title) = entry.link_title {
+        storage.set_link_title(clip.id, title)?;
+    }
+    // A key already taken here stays with the clip that has it.
+    if let Some(ref key) = entry.external_id
+        && storage.find_by_external_id(key)?.is_none()
+    {
+        storage.set_external_id(clip.id, key)?;
+    }
+    if is_image {
+        storage.enqueue_job(clip.id, JobKind::Colors)?;
+        storage.enqueue_job(clip.id, JobKind::Thumbnail)?;
+    }
+    if entry.protected {
+        storage.set_protected(clip.id, true)?;
+    }
+    Ok(clip)
+}
+
+/// Copy an exported image into `images_dir`, named by its hash as the
+/// daemon names captures. Returns the new path, or `None` when neither the
+/// export's copy nor the original file can be found.
+fn copy_image(entry: &ExportedClip, base: &Path, images_dir: &Path) -> Result<Option<String>> {
+    let exported = entry.image_file.as_deref().map(|f| base.join(f));
+    let original = entry.image_path.as_deref().map(PathBuf::from);
+    let Some(source) = exported.into_iter().chain(original).find(|p| p.is_file()) else {
+        return Ok(None);
+    };
+    let ext = source.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    let target = images_dir.join(format!("{}.{}", &entry.hash[..16.min(entry.hash.len())], ext));
+    if target != source && !target.exists() {
+        let io = |e: std::io::Error| CbError::Image(format!("{}: {}", target.display(), e));
+        fs::create_dir_all(images_dir).map_err(io)?;
+        fs::copy(&source, &target).map_err(io)?;
+    }
+    Ok(Some(target.to_string_lossy().into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::ClipFilter;
+    use crate::storage::sqlite::SqliteStorage;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_import_dir_creates_tagged_clips() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.txt"), "first snippet").unwrap();
+        fs::write(dir.path().join("b.md"), "second snippet").unwrap();
+        fs::write(dir.path().join(".hidden"), "ignored").unwrap();
+        fs::write(dir.path().join("empty.txt"), "").unwrap();
+        fs::write(dir.path().join("binary.bin"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let storage = SqliteStorage::in_memory().unwrap();
+        let summary = import_dir(&storage, dir.path(), Some("snippets"), DEFAULT_MAX_IMPORT_SIZE).unwrap();
+        assert_eq!(summary.imported, 2);
+        assert_eq!(summary.skipped, 2);
+
+        let clips = storage
+            .list(ClipFilter { tag: Some("snippets".into()), ..Default::default() })
+            .unwrap();
+        assert_eq!(clips.len(), 2);
+    }
+
+    #[test]
+    fn test_import_dir_dedups_and_respects_max_size() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("small.txt"), "tiny").unwrap();
+        fs::write(dir.path().join("big.txt"), "x".repeat(64)).unwrap();
+
+        let storage = SqliteStorage::in_memory().unwrap();
+        let first = import_dir(&storage, dir.path(), None, 32).unwrap();
+        assert_eq!(first.imported, 1);
+        assert_eq!(first.too_large, 1);
+
+        let second = import_dir(&storage, dir.path(), Some("again"), 32).unwrap();
+        assert_eq!(second.imported, 0);
+        assert_eq!(second.duplicates, 1);
+        assert_eq!(storage.stats().unwrap().total_clips, 1);
+        let clip = storage.get_by_id(1).unwrap();
+        assert_eq!(clip.tags, vec!["again"]);
+    }
+
+    #[test]
+    fn test_import_export_round_trip() {
+        let tmp = TempDir::new().unwrap();
+        let original = tmp.path().join("shot.png");
+        fs::write(&original, b"png").unwrap();
+        let source = SqliteStorage::in_memory().unwrap();
+        let note = source
+            .insert(NewClip {
+                content_type: ContentType::Text,
+                text_content: Some("meeting notes".into()),
+                hash: hash_content(b"meeting notes"),
+                size_bytes: 13,
+                ..Default::default()
+            })
+            .unwrap();
+        source.add_tag(note.id, "work").unwrap();
+        source.set_pinned(note.id, true).unwrap();
+        source
+            .insert(NewClip {
+                content_type: ContentType::Image,
+                image_path: Some(original.to_string_lossy().into_owned()),
+                hash: "0123456789abcdef0123".into(),
+                size_bytes: 3,
+                ..Default::default()
+            })
+            .unwrap();
+
+        let backup = tmp.path().join("backup");
+        fs::create_dir_all(&backup).unwrap();
+        let file = backup.join("clips.json");
+        let images = backup.join("images");
+        let copy = export::ImageCopy { dir: &images, base: &backup };
+        let mut out = fs::File::create(&file).unwrap();
+        let filter = crate::storage::models::ClipFilter::default();
+        export::export(&source, &filter, export::Format::Json, &mut out, Some(&copy)).unwrap();
+        fs::remove_file(&original).unwrap();
+
+        let local = tmp.path().join("local-images");
+        let target = SqliteStorage::in_memory().unwrap();
+        let summary = import_export(&target, &file, &local, Some("restored")).unwrap();
+        assert_eq!((summary.imported, summary.duplicates, summary.skipped), (2, 0, 0));
+        let clips = target.list(ClipFilter::default()).unwrap();
+        let text = clips.iter().find(|c| c.content_type == ContentType::Text).unwrap();
+        assert!(text.pinned);
+        assert_eq!(text.tags, vec!["restored", "work"]);
+        assert_eq!(text.created_at, note.created_at);
+        let image = clips.iter().find(|c| c.content_type == ContentType::Image).unwrap();
+        let path = local.join("0123456789abcdef.png");
+        assert_eq!(image.image_path.as_deref(), Some(path.to_string_lossy().as_ref()));
+        assert_eq!(fs::read(&path).unwrap(), b"png");
+
+        let again = import_export(&target, &file, &local, None).unwrap();
+        assert_eq!((again.imported, again.duplicates), (0, 2));
+        assert_eq!(target.stats().unwrap().total_clips, 2);
+    }
+
+    #[test]
+    fn test_import_export_skips_malformed_hashes() {
+        let tmp = TempDir::new().unwrap();
+        let shot = tmp.path().join("shot.png");
+        fs::write(&shot, b"png").unwrap();
+        let clip = |hash: &str| {
+            serde_json::json!({
+                "content_type": "image",
+                "image_path": shot.to_string_lossy(),
+                "hash": hash,
+                "size_bytes": 3,
+                "created_at": "2024-05-01T09:00:00Z",
+            })
+        };
+        let document = serde_json::json!({
+            "version": export::VERSION,
+            "clips": [clip("../../../../tmp/x"), clip("0123456789abcdeé0"), clip("0123456789abcdef")],
+        });
+        let file = tmp.path().join("clips.json");
+        fs::write(&file, document.to_string()).unwrap();
+
+        let local = tmp.path().join("images");
+        let storage = SqliteStorage::in_memory().unwrap();
+        let summary = import_export(&storage, &file, &local, None).unwrap();
+        assert_eq!((summary.imported, summary.skipped), (1, 2));
+        let names: Vec<_> = fs::read_dir(&local).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(names, vec!["0123456789abcdef.png"]);
+    }
+
+    #[test]
+    fn test_import_dir_missing_directory() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        let result = import_dir(&storage, Path::new("/nonexistent/cb-import"), None, 1024);
+        assert!(matches!(result, Err(CbError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::table;
+
+/// Heuristic language detection for text clips.
+///
+/// Returns `table` for tab- or comma-separated data, a programming language
+/// name (`rust`, `python`, …) when the text looks like code, otherwise an
+/// ISO 639-1 code (`en`, `de`, …) for natural language, or `None` when there
+/// isn't enough signal.
+pub fn detect(text: &str) -> Option<String> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if table::parse(trimmed).is_some() {
+        return Some("table".to_string());
+    }
+    if let Some(lang) = detect_code(trimmed) {
+        return Some(lang.to_string());
+    }
+    detect_natural(trimmed).map(String::from)
+}
+
+/// Languages `detect` can report, for validating `--lang` input and help text.
+pub const KNOWN: &[&str] = &[
+    "rust", "python", "javascript", "typescript", "go", "shell", "sql", "html", "json", "en", "de",
+    "fr", "es", "table",
+];
+
+const CODE_MARKERS: &[(&str, &[&str])] = &[
+    ("rust", &["fn ", "let mut ", "impl ", "pub fn", "-> ", "&str", "println!", "use std", "::new(", "#[derive"]),
+    ("python", &["def ", "import ", "self.", "elif ", "print(", "__init__", "None", "from ", "):\n"]),
+    ("typescript", &[": string", ": number", "interface ", "export type", ": boolean"]),
+    ("javascript", &["const ", "function", "=> ", "console.log", "require(", "export ", "===", "document."]),
+    ("go", &["func ", "package ", ":= ", "fmt.", "err != nil", "go "]),
+    ("shell", &["#!/bin/", "sudo ", "echo ", "| grep", " && ", "export ", "$(", "--"]),
+    ("sql", &["select ", " from ", " where ", "insert into", "create table", "update ", " join "]),
+    ("html", &["<div", "</", "<html", "<!doctype", "<span", "<p>", "href="]),
+];
+
+/// Whether `lang` (as reported by `detect`) is a programming or data
+/// language rather than natural language or a table.
+pub fn is_code(lang: &str) -> bool {
+    lang == "json" || CODE_MARKERS.iter().any(|(name, _)| *name == lang)
+}
+
+fn detect_code(text: &str) -> Option<&'static str> {
+    if (text.starts_with('{') || text.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(text).is_ok()
+    {
+        return Some("json");
+    }
+    if text.starts_with("#!/bin/") || text.starts_with("#!/usr/bin/env bash") {
+        return Some("shell");
+    }
+
+    let lower = text.to_lowercase();
+    let mut best: Option<(&str, usize)> = None;
+    for (lang, markers) in CODE_MARKERS {
+        let haystack = if *lang == "sql" || *lang == "html" { &lower } else { text };
+        let score = markers.iter().filter(|m| haystack.contains(*m)).count();
+        if score >= 2 && best.is_none_or(|(_, s)| score > s) {
+            best = Some((lang, score));
+        }
+    }
+    best.map(|(lang, _)| lang)
+}
+
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "is", "of", "to", "in", "that", "it", "for", "you", "with", "this", "are"]),
+    ("de", &["der", "die", "und", "das", "ist", "nicht", "ein", "ich", "zu", "mit", "sie", "auf", "dem"]),
+    ("fr", &["le", "la", "les", "et", "est", "un", "une", "des", "pas", "je", "que", "du", "pour"]),
+    ("es", &["el", "la", "los", "las", "y", "es", "en", "que", "un", "una", "por", "con", "del"]),
+];
+
+fn detect_natural(text: &str) -> Option<&'static str> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < 3 {
+        return None;
+    }
+
+    let mut best: Option<(&str, usize)> = None;
+    for (lang, stopwords) in STOPWORDS {
+        let score = words.iter().filter(|w| stopwords.contains(&w.as_str())).count();
+        if score >= 2 && best.is_none_or(|(_, s)| score > s) {
+            best = Some((lang, score));
+        }
+    }
+    best.map(|(lang, _)| lang)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_code() {
+        assert_eq!(detect("pub fn main() -> Result<()> {\n    let mut x = Vec::new();\n}").as_deref(), Some("rust"));
+        assert_eq!(detect("def greet(self):\n    print(self.name)").as_deref(), Some("python"));
+        assert_eq!(detect("const x = require('fs');\nconsole.log(x)").as_deref(), Some("javascript"));
+        assert_eq!(detect("package main\n\nfunc main() {\n\tx := 1\n\tfmt.Println(x)\n}").as_deref(), Some("go"));
+        assert_eq!(detect("SELECT id FROM clips WHERE pinned = 1").as_deref(), Some("sql"));
+        assert_eq!(detect("#!/bin/sh\necho hi").as_deref(), Some("shell"));
+        assert_eq!(detect(r#"{"a": [1, 2]}"#).as_deref(), Some("json"));
+        assert_eq!(detect("Name\tQty\nApples\t3\n").as_deref(), Some("table"));
+        assert_eq!(detect("id,email\n1,a@example.com").as_deref(), Some("table"));
+    }
+
+    #[test]
+    fn test_detects_natural_language() {
+        assert_eq!(detect("This is the plan for the week and it is fine").as_deref(), Some("en"));
+        assert_eq!(detect("Ich weiß nicht, ob das Wetter und die Sonne mitspielen").as_deref(), Some("de"));
+        assert_eq!(detect("Je ne sais pas si le train est à l'heure pour les vacances").as_deref(), Some("fr"));
+        assert_eq!(detect("El perro y los gatos duermen en la casa con el niño").as_deref(), Some("es"));
+    }
+
+    #[test]
+    fn test_is_code() {
+        assert!(is_code("rust") && is_code("json") && is_code("sql"));
+        assert!(!is_code("en") && !is_code("table"));
+    }
+
+    #[test]
+    fn test_no_signal() {
+        assert_eq!(detect(""), None);
+        assert_eq!(detect("hello"), None);
+        assert_eq!(detect("https://example.com/path"), None);
+    }
+}
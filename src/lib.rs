@@ -1,7 +1,49 @@
+pub mod backup;
+pub mod bundle;
 pub mod clipboard;
+pub mod colors;
 pub mod config;
 pub mod daemon;
+pub mod doctor;
+pub mod encryption;
 pub mod errors;
+pub mod export;
+pub mod fuzzy;
+pub mod group;
+pub mod grpc;
 pub mod hash;
+pub mod hook;
+pub mod hotkey;
+pub mod i18n;
+pub mod ignore;
+pub mod import;
+pub mod lang;
+pub mod links;
+pub mod metrics;
+pub mod normalize;
+pub mod ocr;
+pub mod profile;
+pub mod project;
+pub mod render;
+pub mod repair;
+pub mod resources;
+pub mod retention;
+pub mod rules;
+pub mod screenshots;
+pub mod secrets;
+pub mod serve;
+pub mod shell;
+pub mod snippet;
+pub mod split;
+pub mod spotlight;
 pub mod storage;
+pub mod suggest;
+pub mod table;
+pub mod thumbs;
+pub mod title;
+pub mod transform;
 pub mod tui;
+pub mod verify;
+pub mod web;
+pub mod when;
+pub mod width;
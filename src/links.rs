@@ -0,0 +1,264 @@
+use std::io::Read;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use regex::Regex;
+use serde::Serialize;
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Status recorded for links that couldn't be reached at all (DNS, TLS, timeout).
+pub const STATUS_UNREACHABLE: i32 = 0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkCheck {
+    pub clip_id: i64,
+    pub url: String,
+    pub status: i32,
+    pub redirect: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The URL a clip holds, if its whole (trimmed) content is a single http(s) URL.
+pub fn extract_url(text: &str) -> Option<&str> {
+    let trimmed = text.trim();
+    let is_http = trimmed.starts_with("http://") || trimmed.starts_with("https://");
+    if is_http && !trimmed.contains(char::is_whitespace) {
+        Some(trimmed)
+    } else {
+        None
+    }
+}
+
+/// A key shared by variants of the same address, for grouping URL clips:
+/// scheme and host lowercased, `www.`, default ports, the fragment,
+/// tracking parameters, and a trailing slash removed, and the remaining
+/// query parameters sorted.
+pub fn canonical_url(url: &str) -> String {
+    let without_fragment = url.split('#').next().unwrap_or_default();
+    let stripped = crate::normalize::strip_tracking_params(without_fragment);
+    let (scheme, rest) = stripped.split_once("://").unwrap_or(("", &stripped));
+    let scheme = scheme.to_ascii_lowercase();
+    let (authority, path_and_query) = match rest.find(['/', '?']) {
+        Some(idx) => rest.split_at(idx),
+        None => (rest, ""),
+    };
+    let mut host = authority.to_ascii_lowercase();
+    let default_port = if scheme == "https" { ":443" } else { ":80" };
+    if let Some(bare) = host.strip_suffix(default_port) {
+        host = bare.to_string();
+    }
+    if let Some(bare) = host.strip_prefix("www.") {
+        host = bare.to_string();
+    }
+    let (path, query) = match path_and_query.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path_and_query, None),
+    };
+    let mut out = format!("{}://{}{}", scheme, host, path.trim_end_matches('/'));
+    if let Some(query) = query {
+        let mut params: Vec<&str> = query.split('&').filter(|p| !p.is_empty()).collect();
+        params.sort_unstable();
+        if !params.is_empty() {
+            out.push('?');
+            out.push_str(&params.join("&"));
+        }
+    }
+    out
+}
+
+pub fn is_dead(status: i32) -> bool {
+    status == STATUS_UNREACHABLE || status >= 400
+}
+
+/// HEAD-request `url` without following redirects, so the redirect target
+/// can be recorded alongside the status code.
+pub fn check(clip_id: i64, url: &str) -> LinkCheck {
+    let agent = ureq::AgentBuilder::new()
+        .redirects(0)
+        .timeout(CHECK_TIMEOUT)
+        .build();
+    let (status, redirect, error) = match agent.head(url).call() {
+        Ok(resp) => (
+            resp.status() as i32,
+            resp.header("location").map(String::from),
+            None,
+        ),
+        Err(ureq::Error::Status(code, resp)) => {
+            (code as i32, resp.header("location").map(String::from), None)
+        }
+        Err(e) => (STATUS_UNREACHABLE, None, Some(e.to_string())),
+    };
+    LinkCheck {
+        clip_id,
+        url: url.to_string(),
+        status,
+        redirect,
+        error,
+    }
+}
+
+/// Bytes of a page read while looking for its `<title>`.
+const TITLE_READ_LIMIT: u64 = 256 * 1024;
+
+/// GET `url` (following redirects) and return its HTML `<title>`, if any.
+pub fn fetch_title(url: &str) -> Option<String> {
+    let agent = ureq::AgentBuilder::new().timeout(CHECK_TIMEOUT).build();
+    let resp = agent.get(url).call().ok()?;
+    let mut html = String::new();
+    resp.into_reader()
+        .take(TITLE_READ_LIMIT)
+        .read_to_string(&mut html)
+        .ok()?;
+    parse_title(&html)
+}
+
+/// The text of the first `<title>` element, with entities decoded and
+/// whitespace collapsed.
+pub fn parse_title(html: &str) -> Option<String> {
+    let re = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+    let raw = re.captures(html)?.get(1)?.as_str();
+    let title = raw
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    (!title.is_empty()).then_some(title)
+}
+
+/// Check every `(clip_id, url)` pair using up to `concurrency` worker threads.
+/// Results come back in input order.
+pub fn check_all(targets: &[(i64, String)], concurrency: usize) -> Vec<LinkCheck> {
+    let next = Mutex::new(0usize);
+    let results: Mutex<Vec<Option<LinkCheck>>> = Mutex::new(vec![None; targets.len()]);
+    let workers = concurrency.clamp(1, targets.len().max(1));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let idx = {
+                        let mut n = next.lock().unwrap();
+                        let idx = *n;
+                        *n += 1;
+                        idx
+                    };
+                    let Some((id, url)) = targets.get(idx) else {
+                        break;
+                    };
+                    let result = check(*id, url);
+                    results.lock().unwrap()[idx] = Some(result);
+                }
+            });
+        }
+    });
+
+    results.into_inner().unwrap().into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    /// Serve `responses.len()` connections, answering each with the next canned response.
+    fn fake_server(responses: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_extract_url() {
+        assert_eq!(extract_url("  https://example.com/a?b=1\n"), Some("https://example.com/a?b=1"));
+        assert_eq!(extract_url("http://x"), Some("http://x"));
+        assert_eq!(extract_url("see https://example.com"), None);
+        assert_eq!(extract_url("ftp://example.com"), None);
+    }
+
+    #[test]
+    fn test_canonical_url() {
+        let canonical = "https://example.com/docs/page?a=1&b=2";
+        for variant in [
+            "https://example.com/docs/page?a=1&b=2",
+            "https://www.Example.com/docs/page/?b=2&a=1",
+            "HTTPS://example.com:443/docs/page?a=1&utm_source=x&b=2#section",
+            "https://example.com/docs/page?a=1&b=2&fbclid=abc",
+        ] {
+            assert_eq!(canonical_url(variant), canonical, "{}", variant);
+        }
+        assert_eq!(canonical_url("http://example.com:80/"), "http://example.com");
+        assert_eq!(canonical_url("https://example.com:8443/x"), "https://example.com:8443/x");
+        // Paths stay case-sensitive.
+        assert_ne!(canonical_url("https://example.com/A"), canonical_url("https://example.com/a"));
+    }
+
+    #[test]
+    fn test_is_dead() {
+        assert!(is_dead(STATUS_UNREACHABLE));
+        assert!(is_dead(404));
+        assert!(is_dead(500));
+        assert!(!is_dead(200));
+        assert!(!is_dead(301));
+    }
+
+    #[test]
+    fn test_check_records_redirect() {
+        let base = fake_server(vec![
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: https://example.com/new\r\nContent-Length: 0\r\n\r\n",
+        ]);
+        let result = check(7, &base);
+        assert_eq!(result.clip_id, 7);
+        assert_eq!(result.status, 301);
+        assert_eq!(result.redirect.as_deref(), Some("https://example.com/new"));
+    }
+
+    #[test]
+    fn test_check_all_preserves_order() {
+        let ok = fake_server(vec!["HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"]);
+        let missing = fake_server(vec!["HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"]);
+        let results = check_all(&[(1, ok), (2, missing)], 4);
+        assert_eq!(results.len(), 2);
+        assert_eq!((results[0].clip_id, results[0].status), (1, 200));
+        assert_eq!((results[1].clip_id, results[1].status), (2, 404));
+    }
+
+    #[test]
+    fn test_fetch_title() {
+        let base = fake_server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 61\r\n\r\n<html><head><title>\n  Rust &amp; Cargo\n</title></head></html>",
+        ]);
+        assert_eq!(fetch_title(&base).as_deref(), Some("Rust & Cargo"));
+        assert_eq!(parse_title("<TITLE lang=en>A  b</TITLE>").as_deref(), Some("A b"));
+        assert_eq!(parse_title("<title></title>"), None);
+        assert_eq!(parse_title("<p>no title</p>"), None);
+    }
+
+    #[test]
+    fn test_check_unreachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let result = check(1, &format!("http://{}", addr));
+        assert_eq!(result.status, STATUS_UNREACHABLE);
+        assert!(result.error.is_some());
+    }
+}
@@ -1,16 +1,23 @@
+use std::io::{IsTerminal, Read, Write};
+use std::path::Path;
 use std::process;
 
-use chrono::{Duration, Utc};
 use clap::{Parser, Subcommand};
-use rusqlite::Connection;
+use rusqlite::OpenFlags;
 use serde::Serialize;
 
-use cb::clipboard::{write_image_to_clipboard, write_text_to_clipboard};
-use cb::config::AppPaths;
+use cb::clipboard::{ClipboardContent, ClipboardProvider, SystemClipboard, copy_clip};
+use cb::config::{AppPaths, Config};
 use cb::daemon;
-use cb::storage::models::{ClipFilter, ContentType};
+use cb::group::GroupBy;
+use cb::render::{Column, RowStyle, format_bytes, parse_columns};
+use cb::hash::hash_content;
+use cb::i18n::{t, tf};
+use cb::storage::models::{ClipFilter, ContentType, JobKind, SourcePage, TokenScope, Tone};
 use cb::storage::sqlite::SqliteStorage;
 use cb::storage::ClipStorage;
+use cb::transform::Transform;
+use cb::when::{parse_date, parse_deadline, parse_when};
 
 #[derive(Parser)]
 #[command(name = "cb", version, about = "A clipboard manager for macOS")]
@@ -19,6 +26,24 @@ struct Cli {
     #[arg(short = 'j', long = "json", global = true)]
     json: bool,
 
+    /// Disable colored output (also honors NO_COLOR)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Plain output for screen readers and dumb terminals: no colors, symbols,
+    /// or box drawing, and every field labeled in a fixed order (also on
+    /// when TERM=dumb)
+    #[arg(long, global = true)]
+    plain: bool,
+
+    /// Use this profile's history instead of the active one
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Open history read-only and refuse commands that would modify it
+    #[arg(long, global = true)]
+    read_only: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -46,250 +71,3574 @@ enum Commands {
         /// Filter by tag
         #[arg(long)]
         tag: Option<String>,
+
+        /// Filter by detected language (e.g. rust, python, en, de)
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Only clips first captured on this machine (see `cb get`)
+        #[arg(long, value_name = "MACHINE")]
+        origin: Option<String>,
+
+        /// Only clips last copied from this app, by name (Safari) or bundle
+        /// identifier (com.apple.Safari)
+        #[arg(long, value_name = "APP")]
+        app: Option<String>,
+
+        /// Only clips captured during this session (see `cb session`)
+        #[arg(long, value_name = "NAME", conflicts_with = "tag")]
+        session: Option<String>,
+
+        /// Only clips captured in this macOS Space (see `cb current`; needs
+        /// `record_workspace = true`)
+        #[arg(long, value_name = "ID")]
+        space: Option<i64>,
+
+        /// Only clips captured with the pointer on this display (see `cb current`)
+        #[arg(long, value_name = "ID")]
+        display: Option<i64>,
+
+        /// Only clips with this metadata key, or with it set to VALUE
+        /// (repeatable; see `cb meta`)
+        #[arg(long, value_name = "KEY[=VALUE]", value_parser = parse_meta_filter)]
+        meta: Vec<(String, Option<String>)>,
+
+        /// Only clips of this subtype: url for clips that are a single link
+        #[arg(long, value_parser = ["url"])]
+        subtype: Option<String>,
+
+        /// Long format: size, copy count, source app, subtype, and created date.
+        /// Short form -L, since -l is --limit
+        #[arg(short = 'L', long)]
+        long: bool,
+
+        /// Comma-separated columns for the long format (implies --long), e.g. id,size,preview
+        #[arg(long)]
+        columns: Option<String>,
+
+        /// Only clips captured at or after this time: an age like 30m, 2d, or 1w,
+        /// or a date (YYYY-MM-DD [HH:MM[:SS]] or RFC 3339)
+        #[arg(long, value_name = "WHEN")]
+        since: Option<String>,
+
+        /// Only clips captured before this time (an age or a date, as for --since)
+        #[arg(long, value_name = "WHEN")]
+        until: Option<String>,
+
+        /// Show history as it was at this time (YYYY-MM-DD [HH:MM[:SS]] or RFC 3339)
+        #[arg(long, value_name = "TIME")]
+        as_of: Option<String>,
+
+        /// Group under headings by day (the default), app, tag, or url (variants
+        /// of one link together); with --json, output groups instead of a flat list
+        #[arg(long, value_parser = ["day", "app", "tag", "url"])]
+        group_by: Option<String>,
+
+        /// With --json, inline image thumbnails as base64 instead of file URLs
+        #[arg(long)]
+        embed_images: bool,
     },
 
     /// Search clipboard history
     Search {
-        /// Search query
-        query: String,
+        /// Search query (optional when filtering by type, color, tone, or time)
+        #[arg(required_unless_present_any = ["type", "color", "dark", "light", "since", "until"])]
+        query: Option<String>,
 
         /// Maximum results
         #[arg(short, long, default_value = "10")]
         limit: i64,
+
+        /// Filter by type (text, image, fileref)
+        #[arg(short, long)]
+        r#type: Option<String>,
+
+        /// Images with a dominant color near this one (#rrggbb or a name like red)
+        #[arg(long, value_name = "COLOR")]
+        color: Option<String>,
+
+        /// Mostly dark images
+        #[arg(long, conflicts_with = "light")]
+        dark: bool,
+
+        /// Mostly light images
+        #[arg(long)]
+        light: bool,
+
+        /// Only clips captured at or after this time (an age like 2d, or a date)
+        #[arg(long, value_name = "WHEN")]
+        since: Option<String>,
+
+        /// Only clips captured before this time (an age, or a date)
+        #[arg(long, value_name = "WHEN")]
+        until: Option<String>,
+
+        /// Match the query's characters in order rather than as one substring,
+        /// best match first (e.g. "dkrlg" finds "docker login")
+        #[arg(long, requires = "query", conflicts_with_all = ["type", "color", "dark", "light"])]
+        fuzzy: bool,
+
+        /// With --json, inline image thumbnails as base64 instead of file URLs
+        #[arg(long)]
+        embed_images: bool,
+    },
+
+    /// Rank recent clips by how likely they are what's needed for a task,
+    /// for editor and assistant plugins
+    Suggest {
+        /// What the user is doing, e.g. "writing an email to Bob about invoices"
+        /// (read from stdin when omitted)
+        #[arg(short, long)]
+        context: Option<String>,
+
+        /// Maximum suggestions
+        #[arg(short, long, default_value_t = cb::suggest::DEFAULT_LIMIT)]
+        limit: i64,
+
+        /// How to score clips: blend, overlap (shared words only), or
+        /// frecency (recent and often copied)
+        #[arg(long, default_value = "blend")]
+        scorer: String,
+
+        /// How many recent clips to consider
+        #[arg(long, default_value_t = cb::suggest::DEFAULT_POOL)]
+        pool: i64,
     },
 
     /// Get a specific clip by ID
     Get {
         /// Clip ID
-        id: i64,
+        #[arg(required_unless_present = "external_id")]
+        id: Option<i64>,
+
+        /// Look the clip up by the key it was added with (`cb add --external-id`)
+        #[arg(long, value_name = "KEY", conflicts_with = "id")]
+        external_id: Option<String>,
+
+        /// Show when and from which app the content was captured
+        #[arg(long)]
+        history: bool,
     },
 
     /// Copy a clip back to the clipboard
     Copy {
+        /// Clip ID
+        #[arg(required_unless_present = "search")]
+        id: Option<i64>,
+
+        /// Copy the best match for this query instead of an ID
+        #[arg(short, long, conflicts_with = "id")]
+        search: Option<String>,
+
+        /// Choose among the matches when the query is ambiguous
+        #[arg(short, long, requires = "search")]
+        pick: bool,
+
+        /// Put the previous clipboard back after this long (e.g. 30s, 2m),
+        /// unless something else is copied first. Waits in the foreground.
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+        restore_after: Option<std::time::Duration>,
+
+        /// Copy reshaped: tables as csv, markdown, or json; URLs as md-link
+        /// or html-link (titled with the fetched page title); code as fence;
+        /// multi-line text as oneline or shell-escape
+        #[arg(
+            long = "as",
+            value_name = "FORMAT",
+            value_parser = [
+                "csv", "markdown", "md", "json", "md-link", "html-link", "fence", "oneline",
+                "shell-escape",
+            ]
+        )]
+        transform: Option<String>,
+    },
+
+    /// Copy a clip and paste it into the frontmost app with Cmd+V
+    Paste {
+        /// Clip ID
+        #[arg(required_unless_present = "search")]
+        id: Option<i64>,
+
+        /// Paste the best match for this query instead of an ID
+        #[arg(short, long, conflicts_with = "id")]
+        search: Option<String>,
+
+        /// Wait this long before pasting, so a launcher's window can close and
+        /// hand focus back (e.g. 0, 300ms)
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "150ms")]
+        delay: std::time::Duration,
+    },
+
+    /// Open an image or file clip in its default app. Screenshots open from
+    /// their full-quality original when one was paired.
+    Open {
         /// Clip ID
         id: i64,
+
+        /// Reveal the file in Finder instead of opening it
+        #[arg(short, long)]
+        reveal: bool,
     },
 
-    /// Delete a clip
+    /// Move a clip to the trash
     Delete {
+        /// Clip ID
+        #[arg(required_unless_present_any = ["tag", "type", "before"])]
+        id: Option<i64>,
+
+        /// Trash every unpinned clip with this tag
+        #[arg(long, conflicts_with = "id")]
+        tag: Option<String>,
+
+        /// Trash every unpinned clip of this type: text, image, fileref
+        #[arg(short = 't', long, conflicts_with = "id")]
+        r#type: Option<String>,
+
+        /// Trash every unpinned clip created before this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long, conflicts_with = "id")]
+        before: Option<String>,
+
+        /// Show what would be trashed without trashing it
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Bring a clip back from the trash
+    Restore {
         /// Clip ID
         id: i64,
     },
 
+    /// Set, remove, or show a clip's metadata: key/value pairs for
+    /// integrations, shown in JSON output and matched with `--meta`
+    Meta {
+        #[command(subcommand)]
+        action: MetaAction,
+    },
+
+    /// List or empty the trash
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+
     /// Pin or unpin a clip
     Pin {
         /// Clip ID
-        id: i64,
+        #[arg(required_unless_present_any = ["tag", "search"])]
+        id: Option<i64>,
+
+        /// Pin every clip with this tag
+        #[arg(long, conflicts_with = "id")]
+        tag: Option<String>,
+
+        /// Pin every clip whose text contains this
+        #[arg(short, long, conflicts_with = "id")]
+        search: Option<String>,
 
         /// Unpin instead of pin
         #[arg(short, long)]
         unpin: bool,
+
+        /// Unpin automatically at this time: a date (pinned through that
+        /// day), a date and time, or a duration like 3d or 2w from now
+        #[arg(long, requires = "id", conflicts_with = "unpin")]
+        until: Option<String>,
     },
 
-    /// Add or remove tags
-    Tag {
+    /// Append text to a text clip
+    Append {
+        /// Clip ID
+        id: i64,
+
+        /// Text to append (read from stdin when omitted)
+        text: Option<String>,
+
+        /// Separator between the existing and appended text (default from
+        /// config, else a newline)
+        #[arg(short, long)]
+        separator: Option<String>,
+    },
+
+    /// Edit a text clip in $VISUAL or $EDITOR
+    Edit {
+        /// Clip ID
+        id: i64,
+    },
+
+    /// Split a text clip into one clip per line, paragraph, or pattern match
+    Split {
+        /// Clip ID
+        id: i64,
+
+        /// How to cut the text: lines, blank-lines, or regex
+        #[arg(long, default_value = "lines", value_parser = ["lines", "blank-lines", "regex"])]
+        by: String,
+
+        /// Separator pattern for --by regex
+        #[arg(short, long)]
+        pattern: Option<String>,
+
+        /// Tag for the new clips (default: split-<ID>)
+        #[arg(long)]
+        tag: Option<String>,
+    },
+
+    /// Protect a clip from deletion, editing, and clearing
+    Protect {
         /// Clip ID
         id: i64,
 
+        /// Remove protection instead
+        #[arg(short, long)]
+        unprotect: bool,
+    },
+
+    /// Add or remove tags
+    Tag {
+        /// Clip ID, or the tag name when using --search
+        #[arg(value_name = "ID")]
+        target: String,
+
         /// Tag name
-        tag: String,
+        tag: Option<String>,
+
+        /// Tag every clip whose text contains this
+        #[arg(short, long)]
+        search: Option<String>,
 
         /// Remove the tag instead of adding
         #[arg(short, long)]
         remove: bool,
     },
 
-    /// Clear old entries
+    /// Clear old entries according to the retention policy
     Clear {
-        /// Clear entries older than N days
-        #[arg(short, long, default_value = "30")]
-        days: i64,
+        /// Clear entries older than N days (overrides the policy's age limit)
+        #[arg(short, long)]
+        days: Option<i64>,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
     },
 
     /// Show storage statistics
-    Stats,
+    Stats {
+        /// Aggregate text metrics only over clips with this tag
+        #[arg(long)]
+        tag: Option<String>,
 
-    /// Interactive TUI
-    Tui,
+        /// Aggregate text metrics only over clips in this language
+        #[arg(long)]
+        lang: Option<String>,
 
-    /// Manage the clipboard watcher daemon
-    Daemon {
-        #[command(subcommand)]
-        action: DaemonAction,
+        /// Aggregate text metrics only over pinned clips
+        #[arg(short, long)]
+        pinned: bool,
     },
-}
 
-#[derive(Subcommand)]
-enum DaemonAction {
-    /// Start the clipboard watcher
-    Start,
-    /// Stop the clipboard watcher
-    Stop,
-    /// Check daemon status
-    Status,
-    /// Run watcher in foreground (used internally)
-    #[command(hide = true)]
-    Run,
-}
+    /// Print the ID of the clip holding this text or hash; exits 1 when
+    /// there is none
+    Exists {
+        /// Text to look for (read from stdin when neither it nor --hash is given)
+        #[arg(conflicts_with = "hash")]
+        text: Option<String>,
 
-#[derive(Serialize)]
-struct StatusResponse {
-    success: bool,
-    message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    removed: Option<i64>,
-}
+        /// Content hash to look for, as shown by `cb get --json`
+        #[arg(long)]
+        hash: Option<String>,
+    },
 
-fn main() {
-    let cli = Cli::parse();
-    let json = cli.json;
+    /// Print how many clips match the filters
+    Count {
+        /// Count only clips of this type: text, image, fileref
+        #[arg(short = 't', long)]
+        r#type: Option<String>,
 
-    if let Err(e) = run(cli) {
-        if json {
-            eprintln!("{}", serde_json::json!({"error": e.to_string()}));
-        } else {
-            eprintln!("error: {}", e);
-        }
-        process::exit(1);
-    }
-}
+        /// Count only pinned clips
+        #[arg(short, long)]
+        pinned: bool,
 
-fn run(cli: Cli) -> cb::errors::Result<()> {
-    let paths = AppPaths::new();
-    let json = cli.json;
+        /// Count only clips with this tag
+        #[arg(long)]
+        tag: Option<String>,
 
-    match cli.command {
-        None => cmd_list(
-            &paths,
-            ClipFilter {
-                limit: 10,
-                ..Default::default()
-            },
-            json,
-        ),
-        Some(Commands::List {
-            limit,
-            offset,
-            r#type,
-            pinned,
-            tag,
-        }) => {
-            let content_type = r#type.as_deref().and_then(ContentType::parse);
-            cmd_list(
-                &paths,
-                ClipFilter {
-                    content_type,
-                    pinned: if pinned { Some(true) } else { None },
-                    tag,
-                    limit,
-                    offset,
-                },
-                json,
-            )
-        }
-        Some(Commands::Search { query, limit }) => cmd_search(&paths, &query, limit, json),
-        Some(Commands::Get { id }) => cmd_get(&paths, id, json),
-        Some(Commands::Copy { id }) => cmd_copy(&paths, id, json),
-        Some(Commands::Delete { id }) => cmd_delete(&paths, id, json),
-        Some(Commands::Pin { id, unpin }) => cmd_pin(&paths, id, !unpin, json),
-        Some(Commands::Tag { id, tag, remove }) => cmd_tag(&paths, id, &tag, remove, json),
-        Some(Commands::Clear { days }) => cmd_clear(&paths, days, json),
-        Some(Commands::Stats) => cmd_stats(&paths, json),
-        Some(Commands::Tui) => cb::tui::run(&paths),
-        Some(Commands::Daemon { action }) => cmd_daemon(&paths, action, json),
-    }
-}
+        /// Count only clips in this detected language
+        #[arg(long)]
+        lang: Option<String>,
 
-fn open_storage(paths: &AppPaths) -> cb::errors::Result<SqliteStorage> {
-    std::fs::create_dir_all(&paths.base_dir)
-        .map_err(|e| cb::errors::CbError::Storage(rusqlite::Error::ToSqlConversionFailure(e.into())))?;
-    let conn = Connection::open(&paths.db_path)?;
-    SqliteStorage::new(conn)
-}
+        /// Only clips with this metadata key, or with it set to VALUE
+        /// (repeatable; see `cb meta`)
+        #[arg(long, value_name = "KEY[=VALUE]", value_parser = parse_meta_filter)]
+        meta: Vec<(String, Option<String>)>,
 
-fn cmd_list(paths: &AppPaths, filter: ClipFilter, json: bool) -> cb::errors::Result<()> {
-    let storage = open_storage(paths)?;
-    let clips = storage.list(filter)?;
+        /// Count only clips containing this text
+        #[arg(long)]
+        search: Option<String>,
 
-    if json {
-        println!("{}", serde_json::to_string(&clips).unwrap());
-        return Ok(());
-    }
+        /// Count only clips captured in this macOS Space
+        #[arg(long, value_name = "ID")]
+        space: Option<i64>,
 
-    if clips.is_empty() {
-        println!("No clips found.");
-        return Ok(());
-    }
+        /// Count only clips captured with the pointer on this display
+        #[arg(long, value_name = "ID")]
+        display: Option<i64>,
 
-    for clip in &clips {
-        print_clip_row(clip);
-    }
-    Ok(())
-}
+        /// Only clips captured at or after this time (an age like 7d, or a date)
+        #[arg(long, value_name = "WHEN")]
+        since: Option<String>,
 
-fn cmd_search(paths: &AppPaths, query: &str, limit: i64, json: bool) -> cb::errors::Result<()> {
-    let storage = open_storage(paths)?;
-    let clips = storage.search(query, limit)?;
+        /// Only clips captured before this time (an age or a date)
+        #[arg(long, value_name = "WHEN")]
+        until: Option<String>,
+    },
 
-    if json {
-        println!("{}", serde_json::to_string(&clips).unwrap());
-        return Ok(());
-    }
+    /// Store what's on the clipboard now, as the daemon would
+    Capture {
+        /// Print nothing, and exit quietly when there's nothing to capture
+        /// (for shell hooks)
+        #[arg(short, long)]
+        quiet: bool,
+    },
 
-    if clips.is_empty() {
-        println!("No results for \"{}\".", query);
-        return Ok(());
-    }
+    /// Print a shell snippet that runs `cb capture` at each prompt, for
+    /// history without the daemon: eval "$(cb hook zsh)"
+    Hook {
+        /// zsh or bash
+        shell: String,
+    },
 
-    for clip in &clips {
-        print_clip_row(clip);
-    }
-    Ok(())
-}
+    /// Add text to history without going through the clipboard
+    Add {
+        /// Text to add (default: stdin, text or a PNG image)
+        #[arg(conflicts_with = "file")]
+        text: Option<String>,
 
-fn cmd_get(paths: &AppPaths, id: i64, json: bool) -> cb::errors::Result<()> {
-    let storage = open_storage(paths)?;
-    let clip = storage.get_by_id(id)?;
+        /// Add this file's contents: a PNG image, or UTF-8 text
+        #[arg(long, value_name = "PATH")]
+        file: Option<std::path::PathBuf>,
 
-    if json {
-        println!("{}", serde_json::to_string(&clip).unwrap());
-        return Ok(());
-    }
+        /// Also put the content on the system clipboard
+        #[arg(short, long)]
+        copy: bool,
 
-    print_clip_detail(&clip);
-    Ok(())
-}
+        /// Store the clip under this key; adding again with the same key
+        /// replaces its text instead of adding another clip
+        #[arg(long, value_name = "KEY")]
+        external_id: Option<String>,
 
-fn cmd_copy(paths: &AppPaths, id: i64, json: bool) -> cb::errors::Result<()> {
-    let storage = open_storage(paths)?;
-    let clip = storage.get_by_id(id)?;
+        /// Web page the text was taken from
+        #[arg(long, value_name = "URL")]
+        source_url: Option<String>,
 
-    let message = match clip.content_type {
-        ContentType::Text => {
-            if let Some(ref text) = clip.text_content {
-                write_text_to_clipboard(text)?;
-                format!("Copied clip #{} to clipboard.", id)
-            } else {
-                format!("Text clip #{} has no content.", id)
-            }
-        }
-        ContentType::Image => {
-            if let Some(ref path) = clip.image_path {
-                write_image_to_clipboard(std::path::Path::new(path))?;
-                format!("Copied image clip #{} to clipboard.", id)
-            } else {
-                format!("Image clip #{} has no stored path.", id)
-            }
-        }
-        ContentType::FileRef => {
-            format!(
-                "File reference: {}",
-                clip.text_content.as_deref().unwrap_or("unknown")
-            )
-        }
-    };
+        /// Title of that page
+        #[arg(long, value_name = "TITLE", requires = "source_url")]
+        source_title: Option<String>,
+    },
 
-    storage.touch(id)?;
+    /// Import a `cb export` JSON file, or a directory of text files with
+    /// one clip per file
+    Import {
+        /// JSON file written by `cb export`
+        #[arg(required_unless_present = "dir", conflicts_with = "dir")]
+        file: Option<std::path::PathBuf>,
+
+        /// Directory of text files to read instead
+        #[arg(long)]
+        dir: Option<std::path::PathBuf>,
+
+        /// Tag to apply to every imported clip
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Skip files larger than this many bytes
+        #[arg(long, default_value_t = cb::import::DEFAULT_MAX_IMPORT_SIZE)]
+        max_size: u64,
+    },
+
+    /// Write matching clips to a .zip or .tar, one file per clip plus a manifest
+    Bundle {
+        /// Archive to create; the format follows the extension
+        #[arg(long)]
+        out: std::path::PathBuf,
+
+        /// Only clips with this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Only clips whose text contains this
+        #[arg(short, long)]
+        search: Option<String>,
+
+        /// Only clips of this type: text, image, fileref
+        #[arg(short = 't', long)]
+        r#type: Option<String>,
+
+        /// Only pinned clips
+        #[arg(short, long)]
+        pinned: bool,
+
+        /// Overwrite the archive if it already exists
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Dump matching clips, with their tags and pinned state, as JSON, CSV,
+    /// or Markdown
+    Export {
+        /// Output format; JSON keeps every field and is what `cb import` reads
+        #[arg(long, default_value = "json", value_parser = ["json", "csv", "md"])]
+        format: String,
+
+        /// File to write; standard output when omitted. With `--type image`,
+        /// a folder (existing, or ending in `/`) gets just the image files,
+        /// named by capture time, app, and title
+        #[arg(short, long, visible_alias = "out", value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+
+        /// Also copy image files into this directory
+        #[arg(long, value_name = "DIR")]
+        images: Option<std::path::PathBuf>,
+
+        /// Only clips with this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Only clips with this metadata key, or with it set to VALUE
+        /// (repeatable; see `cb meta`)
+        #[arg(long, value_name = "KEY[=VALUE]", value_parser = parse_meta_filter)]
+        meta: Vec<(String, Option<String>)>,
+
+        /// Only clips whose text contains this
+        #[arg(short, long)]
+        search: Option<String>,
+
+        /// Only clips of this type: text, image, fileref
+        #[arg(short = 't', long)]
+        r#type: Option<String>,
+
+        /// Only pinned clips
+        #[arg(short, long)]
+        pinned: bool,
+
+        /// Only clips captured at or after this time (an age like 7d, or a date)
+        #[arg(long, value_name = "WHEN")]
+        since: Option<String>,
+
+        /// Only clips captured before this time (an age or a date)
+        #[arg(long, value_name = "WHEN")]
+        until: Option<String>,
+
+        /// Overwrite the output file if it already exists
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Show what's on the clipboard right now and whether it would be
+    /// captured, without storing anything
+    Current,
+
+    /// Create the profile's database, optionally encrypted
+    Init {
+        /// Encrypt the database with a passphrase (an existing one is
+        /// encrypted in place); needs a build with `--features encryption`
+        #[arg(long)]
+        encrypt: bool,
+
+        /// Don't store the passphrase in the keychain; it's then read from
+        /// CB_PASSPHRASE or asked for each time
+        #[arg(long, requires = "encrypt")]
+        no_keychain: bool,
+    },
+
+    /// Check the installation and suggest fixes for problems
+    Doctor {
+        /// Only check clipboard and Accessibility permissions, opening
+        /// System Settings when one is missing; fails if any is
+        #[arg(long)]
+        check_permissions: bool,
+    },
+
+    /// Check that every image clip's file is still there, flagging those
+    /// whose file is missing
+    Verify {
+        /// Re-link each damaged clip to a file of the same name in the
+        /// images directory, or move it to the trash when there's none
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Save the database and images to a tarball, or restore from one
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+
+    /// Rebuild a corrupt database from the rows that can still be read
+    Repair {
+        /// Rebuild even if no corruption is found
+        #[arg(long)]
+        force: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Interactive TUI
+    Tui {
+        /// Launcher mode for a global hotkey: start in search, Enter copies,
+        /// pastes into the frontmost app, and quits; Esc quits
+        #[arg(long)]
+        quick: bool,
+    },
+
+    /// Serve history to editor plugins over a line-based JSON protocol, or
+    /// as a read-only web page for other devices on the network
+    Serve {
+        /// Read requests from stdin and write responses to stdout
+        #[arg(long, conflicts_with = "http")]
+        stdio: bool,
+
+        /// Serve a web viewer on ADDR, guarded by the API token
+        #[arg(long, value_name = "ADDR", num_args = 0..=1, default_missing_value = "0.0.0.0:8420")]
+        http: Option<String>,
+
+        /// Serve the gRPC API in proto/cb.proto on ADDR (needs the `grpc` feature)
+        #[arg(long, value_name = "ADDR", num_args = 0..=1, default_missing_value = "127.0.0.1:8421", conflicts_with_all = ["stdio", "http"])]
+        grpc: Option<String>,
+    },
+
+    /// Manage the clipboard watcher daemon
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonAction,
+    },
+
+    /// Inspect auto-tagging rules from config.toml
+    Rules {
+        #[command(subcommand)]
+        action: RulesAction,
+    },
+
+    /// Check stored URL clips
+    Links {
+        #[command(subcommand)]
+        action: LinksAction,
+    },
+
+    /// Bind a global shortcut that opens `cb tui --quick`
+    Hotkey {
+        #[command(subcommand)]
+        action: HotkeyAction,
+    },
+
+    /// Manage profiles, each with its own separate history
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+
+    /// Named scratch slots kept outside history, like vim registers
+    Slot {
+        #[command(subcommand)]
+        action: SlotAction,
+    },
+
+    /// Tag everything captured between start and stop with a session name
+    Session {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+
+    /// Manage API tokens for `cb serve --http`
+    Token {
+        #[command(subcommand)]
+        action: TokenAction,
+    },
+
+    /// Inspect the daemon's background enrichment queue
+    Jobs {
+        #[command(subcommand)]
+        action: JobsAction,
+    },
+
+    /// Backfill OCR text, colors, thumbnails, and link titles for existing clips
+    Enrich {
+        /// Every clip missing them (required; stopping and rerunning resumes)
+        #[arg(long, required = true)]
+        all: bool,
+
+        /// Only this kind of enrichment
+        #[arg(long, value_name = "KIND", value_parser = ["ocr", "colors", "thumbnail", "title"])]
+        only: Option<String>,
+    },
+}
+
+impl Commands {
+    /// Name of the command when it writes to the history database, so
+    /// read-only mode can refuse it before opening anything.
+    fn writes_history(&self) -> Option<&'static str> {
+        match self {
+            Commands::Delete { .. } => Some("delete"),
+            Commands::Restore { .. } => Some("restore"),
+            Commands::Trash {
+                action: TrashAction::Empty { .. },
+            } => Some("trash empty"),
+            Commands::Pin { .. } => Some("pin"),
+            Commands::Protect { .. } => Some("protect"),
+            Commands::Append { .. } => Some("append"),
+            Commands::Edit { .. } => Some("edit"),
+            Commands::Split { .. } => Some("split"),
+            Commands::Tag { .. } => Some("tag"),
+            Commands::Meta {
+                action: MetaAction::Set { .. },
+            } => Some("meta set"),
+            Commands::Meta {
+                action: MetaAction::Rm { .. },
+            } => Some("meta rm"),
+            Commands::Clear { .. } => Some("clear"),
+            Commands::Import { .. } => Some("import"),
+            Commands::Add { .. } => Some("add"),
+            Commands::Capture { .. } => Some("capture"),
+            Commands::Enrich { .. } => Some("enrich"),
+            Commands::Repair { .. } => Some("repair"),
+            Commands::Init { .. } => Some("init"),
+            Commands::Verify { .. } => Some("verify"),
+            Commands::Backup {
+                action: BackupAction::Restore { .. },
+            } => Some("backup restore"),
+            Commands::Links { .. } => Some("links check"),
+            Commands::Slot {
+                action: SlotAction::Set { .. },
+            } => Some("slot set"),
+            Commands::Slot {
+                action: SlotAction::Rm { .. },
+            } => Some("slot rm"),
+            Commands::Session {
+                action: SessionAction::Start { .. },
+            } => Some("session start"),
+            Commands::Session {
+                action: SessionAction::Stop { .. },
+            } => Some("session stop"),
+            Commands::Token {
+                action: TokenAction::Create { .. },
+            } => Some("token create"),
+            Commands::Token {
+                action: TokenAction::Revoke { .. },
+            } => Some("token revoke"),
+            Commands::Daemon {
+                action: DaemonAction::Start | DaemonAction::Run,
+            } => Some("daemon start"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum TrashAction {
+    /// List trashed clips, most recently deleted first
+    List {
+        /// Maximum number of clips to show
+        #[arg(short = 'n', long, default_value = "50")]
+        limit: i64,
+    },
+    /// Permanently delete every trashed clip
+    Empty {
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MetaAction {
+    /// Set KEY to VALUE on a clip, replacing any earlier value
+    Set { id: i64, key: String, value: String },
+    /// Remove KEY from a clip
+    Rm { id: i64, key: String },
+    /// Print a clip's metadata, or just the value of KEY
+    Get { id: i64, key: Option<String> },
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Snapshot the database and images into a .tar (safe while the daemon runs)
+    Create {
+        /// Tarball to write (default: cb-backup-<profile>-<time>.tar here)
+        path: Option<std::path::PathBuf>,
+
+        /// Overwrite the tarball if it already exists
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Replace the history with a backup's; the current database is kept
+    /// alongside
+    Restore {
+        /// Tarball written by `cb backup create`
+        path: std::path::PathBuf,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SlotAction {
+    /// Store text in a slot, replacing what was there
+    Set {
+        /// Slot name
+        name: String,
+
+        /// Text to store (default: stdin when piped, else the clipboard)
+        text: Option<String>,
+    },
+    /// Print a slot's text
+    Get {
+        /// Slot name
+        name: String,
+    },
+    /// Copy a slot's text to the clipboard
+    Copy {
+        /// Slot name
+        name: String,
+    },
+    /// List slots
+    List,
+    /// Remove a slot
+    Rm {
+        /// Slot name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionAction {
+    /// Start tagging new captures with NAME
+    Start {
+        /// Session name, also the tag (no spaces or commas)
+        name: String,
+    },
+    /// Stop the running session
+    Stop {
+        /// Only stop if this is the running session
+        name: Option<String>,
+    },
+    /// List sessions with when they ran and how many clips they hold
+    List,
+}
+
+#[derive(Subcommand)]
+enum TokenAction {
+    /// Create a token and print it; it can't be shown again
+    Create {
+        /// Name to list and revoke the token by, e.g. the device using it
+        name: String,
+
+        /// What the token may do
+        #[arg(long, default_value = "read", value_parser = ["read", "read-write"])]
+        scope: String,
+    },
+    /// Revoke a token so it is no longer accepted
+    Revoke {
+        /// Token name
+        name: String,
+    },
+    /// List tokens (names and scopes; the secrets aren't stored)
+    List,
+}
+
+#[derive(Subcommand)]
+enum JobsAction {
+    /// Show queued, running, and failed jobs of each kind
+    Status,
+}
+
+#[derive(Subcommand)]
+enum HotkeyAction {
+    /// Add the shortcut to Hammerspoon or skhd config
+    Install {
+        /// Shortcut as modifiers and a key, e.g. ctrl+alt+v
+        #[arg(long, default_value = cb::hotkey::DEFAULT_KEY)]
+        key: String,
+        /// Tool to configure: hammerspoon or skhd (detected when omitted)
+        #[arg(long, value_name = "TOOL")]
+        with: Option<String>,
+    },
+    /// Remove the shortcut again
+    Uninstall {
+        /// Tool to configure: hammerspoon or skhd (detected when omitted)
+        #[arg(long, value_name = "TOOL")]
+        with: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileAction {
+    /// Create a new, empty profile
+    Create {
+        /// Profile name (letters, digits, '-' and '_')
+        name: String,
+    },
+    /// Make a profile the one used when --profile is not given
+    Switch {
+        /// Profile name
+        name: String,
+    },
+    /// List profiles, marking the active one
+    List,
+}
+
+#[derive(Subcommand)]
+enum LinksAction {
+    /// HEAD-request stored URLs and record status codes and redirects
+    Check {
+        /// Only check clips with this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Number of recent clips to scan for URLs
+        #[arg(short, long, default_value = "500")]
+        limit: i64,
+
+        /// Number of concurrent requests
+        #[arg(short, long, default_value_t = cb::links::DEFAULT_CONCURRENCY)]
+        concurrency: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum RulesAction {
+    /// List configured rules
+    List,
+    /// Show which existing clips each rule would tag
+    Test {
+        /// Number of recent clips to evaluate
+        #[arg(short, long, default_value = "100")]
+        limit: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum DaemonAction {
+    /// Start the clipboard watcher
+    Start,
+    /// Stop the clipboard watcher
+    Stop,
+    /// Check daemon status
+    Status,
+    /// Run watcher in foreground (used internally)
+    #[command(hide = true)]
+    Run,
+}
+
+/// Snippet width in `search --json` output.
+const SNIPPET_WIDTH: usize = 80;
+
+#[derive(Serialize)]
+struct StatusResponse {
+    success: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    removed: Option<i64>,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let json = cli.json;
+
+    if let Err(e) = run(cli) {
+        if json {
+            eprintln!("{}", serde_json::json!({"error": e.to_string()}));
+        } else {
+            eprintln!("{}: {}", t("error"), e);
+            if e.is_corruption() {
+                eprintln!("{}: {}", t("hint"), t("run `cb repair` to recover what can be salvaged"));
+            }
+        }
+        process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> cb::errors::Result<()> {
+    let mut paths = cb::profile::resolve(AppPaths::default_base(), cli.profile.as_deref())?;
+    let config = Config::load(&paths.config_file).ok();
+    cb::i18n::init(config.as_ref().and_then(|c| c.locale.as_deref()));
+    paths.read_only = cli.read_only
+        || config.is_some_and(|config| config.read_only_profiles.contains(&paths.profile));
+    if paths.read_only
+        && let Some(name) = cli.command.as_ref().and_then(Commands::writes_history)
+    {
+        return Err(cb::errors::CbError::InvalidInput(format!(
+            "`cb {}` modifies history and is disabled in read-only mode",
+            name
+        )));
+    }
+    let json = cli.json;
+    let style = RowStyle::detect(cli.no_color, cli.plain);
+
+    match cli.command {
+        None => cmd_list(
+            &paths,
+            ClipFilter {
+                limit: 10,
+                ..Default::default()
+            },
+            None,
+            None,
+            false,
+            &style,
+            json,
+        ),
+        Some(Commands::List {
+            limit,
+            offset,
+            r#type,
+            pinned,
+            tag,
+            lang,
+            origin,
+            app,
+            session,
+            space,
+            display,
+            meta,
+            subtype,
+            long,
+            columns,
+            since,
+            until,
+            as_of,
+            group_by,
+            embed_images,
+        }) => {
+            let content_type = r#type.as_deref().and_then(ContentType::parse);
+            let (after, before) = parse_window(since.as_deref(), until.as_deref())?;
+            if let Some(ref l) = lang
+                && !cb::lang::KNOWN.contains(&l.as_str())
+            {
+                return Err(cb::errors::CbError::InvalidInput(format!(
+                    "unknown language \"{}\" (expected one of: {})",
+                    l,
+                    cb::lang::KNOWN.join(", ")
+                )));
+            }
+            let columns = match columns {
+                Some(spec) => Some(parse_columns(&spec)?),
+                None if long => Some(Column::LONG.to_vec()),
+                None => None,
+            };
+            // Sessions tag what they capture, so filtering is by that tag.
+            let tag = match session {
+                Some(name) => {
+                    let storage = open_storage(&paths)?;
+                    if !storage.sessions()?.iter().any(|s| s.name == name) {
+                        return Err(cb::errors::CbError::NotFound(format!("session \"{}\"", name)));
+                    }
+                    Some(name)
+                }
+                None => tag,
+            };
+            cmd_list(
+                &paths,
+                ClipFilter {
+                    content_type,
+                    pinned: if pinned { Some(true) } else { None },
+                    tag,
+                    extra_tags: Vec::new(),
+                    lang,
+                    origin,
+                    app,
+                    before,
+                    after,
+                    query: None,
+                    protected: None,
+                    url: subtype.map(|_| true),
+                    as_of: as_of.as_deref().map(parse_date).transpose()?,
+                    tone: None,
+                    oldest_first: false,
+                    cursor: None,
+                    trashed: false,
+                    meta,
+                    space,
+                    display,
+                    limit,
+                    offset,
+                },
+                columns.as_deref(),
+                group_by.as_deref().map(GroupBy::parse).transpose()?,
+                embed_images,
+                &style,
+                json,
+            )
+        }
+        Some(Commands::Search {
+            query,
+            limit,
+            r#type,
+            color,
+            dark,
+            light,
+            since,
+            until,
+            fuzzy,
+            embed_images,
+        }) => {
+            let (after, before) = parse_window(since.as_deref(), until.as_deref())?;
+            let tone = match (dark, light) {
+                (true, _) => Some(Tone::Dark),
+                (_, true) => Some(Tone::Light),
+                _ => None,
+            };
+            let color = color.as_deref().map(cb::colors::parse_color).transpose()?;
+            // Colors and tone only exist for images, so imply the type.
+            let content_type = match r#type {
+                Some(t) => Some(parse_content_type(&t)?),
+                None if color.is_some() || tone.is_some() => Some(ContentType::Image),
+                None => None,
+            };
+            cmd_search(
+                &paths,
+                ClipFilter {
+                    content_type,
+                    query,
+                    tone,
+                    after,
+                    before,
+                    limit,
+                    ..Default::default()
+                },
+                color,
+                fuzzy,
+                embed_images,
+                &style,
+                json,
+            )
+        }
+        Some(Commands::Suggest {
+            context,
+            limit,
+            scorer,
+            pool,
+        }) => cmd_suggest(&paths, context, limit, &scorer, pool, &style, json),
+        Some(Commands::Get {
+            id,
+            external_id,
+            history,
+        }) => {
+            let id = match (id, external_id) {
+                (Some(id), _) => id,
+                (None, Some(key)) => open_storage(&paths)?
+                    .find_by_external_id(&key)?
+                    .filter(|clip| clip.deleted_at.is_none())
+                    .map(|clip| clip.id)
+                    .ok_or_else(|| {
+                        cb::errors::CbError::NotFound(format!("clip with external ID \"{}\"", key))
+                    })?,
+                (None, None) => unreachable!("clap requires an id or --external-id"),
+            };
+            cmd_get(&paths, id, history, &style, json)
+        }
+        Some(Commands::Copy {
+            id,
+            search,
+            pick,
+            restore_after,
+            transform,
+        }) => {
+            let transform = transform.as_deref().map(Transform::parse).transpose()?;
+            let storage = open_storage(&paths)?;
+            let id = match (id, search) {
+                (Some(id), _) => id,
+                (None, Some(query)) => resolve_search(&storage, &query, pick, &style)?,
+                (None, None) => unreachable!("clap requires an id or --search"),
+            };
+            cmd_copy(&paths, &storage, id, transform, restore_after, json)
+        }
+        Some(Commands::Paste { id, search, delay }) => {
+            let storage = open_storage(&paths)?;
+            let id = match (id, search) {
+                (Some(id), _) => id,
+                // No terminal to pick in when run from a launcher.
+                (None, Some(query)) => resolve_search(&storage, &query, false, &style)?,
+                (None, None) => unreachable!("clap requires an id or --search"),
+            };
+            cmd_paste(&paths, &storage, id, delay, json)
+        }
+        Some(Commands::Open { id, reveal }) => cmd_open(&paths, id, reveal),
+        Some(Commands::Delete {
+            id: Some(id), ..
+        }) => cmd_delete(&paths, id, json),
+        Some(Commands::Delete {
+            id: None,
+            tag,
+            r#type,
+            before,
+            dry_run,
+            yes,
+        }) => {
+            let filter = ClipFilter {
+                content_type: r#type.as_deref().map(parse_content_type).transpose()?,
+                pinned: Some(false),
+                tag,
+                before: before.as_deref().map(parse_date).transpose()?,
+                protected: Some(false),
+                ..Default::default()
+            };
+            cmd_delete_matching(&paths, filter, dry_run, yes, &style, json)
+        }
+        Some(Commands::Restore { id }) => cmd_restore(&paths, id, json),
+        Some(Commands::Meta { action }) => cmd_meta(&paths, action, json),
+        Some(Commands::Trash { action }) => cmd_trash(&paths, action, &style, json),
+        Some(Commands::Pin {
+            id: Some(id),
+            unpin,
+            until,
+            ..
+        }) => {
+            let until = until.as_deref().map(parse_deadline).transpose()?;
+            cmd_pin(&paths, id, !unpin, until, json)
+        }
+        Some(Commands::Pin {
+            id: None,
+            tag,
+            search,
+            unpin,
+            ..
+        }) => {
+            let filter = ClipFilter {
+                tag,
+                query: search,
+                ..Default::default()
+            };
+            cmd_pin_matching(&paths, &filter, !unpin, json)
+        }
+        Some(Commands::Append {
+            id,
+            text,
+            separator,
+        }) => cmd_append(&paths, id, text, separator, json),
+        Some(Commands::Edit { id }) => cmd_edit(&paths, id, json),
+        Some(Commands::Split {
+            id,
+            by,
+            pattern,
+            tag,
+        }) => cmd_split(&paths, id, &by, pattern.as_deref(), tag.as_deref(), json),
+        Some(Commands::Protect { id, unprotect }) => cmd_protect(&paths, id, !unprotect, json),
+        Some(Commands::Tag {
+            target,
+            tag,
+            search: Some(search),
+            remove,
+        }) => {
+            if let Some(extra) = tag {
+                return Err(cb::errors::CbError::InvalidInput(format!(
+                    "unexpected argument \"{}\": with --search, give only the tag name",
+                    extra
+                )));
+            }
+            let filter = ClipFilter {
+                query: Some(search),
+                ..Default::default()
+            };
+            cmd_tag_matching(&paths, &filter, &target, remove, json)
+        }
+        Some(Commands::Tag {
+            target,
+            tag,
+            search: None,
+            remove,
+        }) => {
+            let id = target.parse::<i64>().map_err(|_| {
+                cb::errors::CbError::InvalidInput(format!("invalid clip ID \"{}\"", target))
+            })?;
+            let tag = tag.ok_or_else(|| {
+                cb::errors::CbError::InvalidInput("missing tag name".to_string())
+            })?;
+            cmd_tag(&paths, id, &tag, remove, json)
+        }
+        Some(Commands::Clear { days, yes }) => cmd_clear(&paths, days, yes, json),
+        Some(Commands::Stats { tag, lang, pinned }) => cmd_stats(
+            &paths,
+            ClipFilter {
+                tag,
+                lang,
+                pinned: if pinned { Some(true) } else { None },
+                ..Default::default()
+            },
+            &style,
+            json,
+        ),
+        Some(Commands::Exists { text, hash }) => {
+            if !cmd_exists(&paths, text, hash, json)? {
+                process::exit(1);
+            }
+            Ok(())
+        }
+        Some(Commands::Count {
+            r#type,
+            pinned,
+            tag,
+            lang,
+            meta,
+            search,
+            space,
+            display,
+            since,
+            until,
+        }) => {
+            let (after, before) = parse_window(since.as_deref(), until.as_deref())?;
+            cmd_count(
+                &paths,
+                &ClipFilter {
+                    content_type: r#type.as_deref().map(parse_content_type).transpose()?,
+                    pinned: if pinned { Some(true) } else { None },
+                    tag,
+                    lang,
+                    meta,
+                    space,
+                    display,
+                    query: search,
+                    after,
+                    before,
+                    ..Default::default()
+                },
+                json,
+            )
+        }
+        Some(Commands::Bundle {
+            out,
+            tag,
+            search,
+            r#type,
+            pinned,
+            force,
+        }) => {
+            let filter = ClipFilter {
+                content_type: r#type.as_deref().map(parse_content_type).transpose()?,
+                pinned: pinned.then_some(true),
+                tag,
+                query: search,
+                ..Default::default()
+            };
+            cmd_bundle(&paths, &filter, &out, force, json)
+        }
+        Some(Commands::Export {
+            format,
+            output,
+            images,
+            tag,
+            meta,
+            search,
+            r#type,
+            pinned,
+            since,
+            until,
+            force,
+        }) => {
+            let (after, before) = parse_window(since.as_deref(), until.as_deref())?;
+            let filter = ClipFilter {
+                content_type: r#type.as_deref().map(parse_content_type).transpose()?,
+                pinned: pinned.then_some(true),
+                tag,
+                meta,
+                query: search,
+                after,
+                before,
+                ..Default::default()
+            };
+            let format = cb::export::Format::parse(&format)?;
+            cmd_export(&paths, &filter, format, output.as_deref(), images.as_deref(), force, json)
+        }
+        Some(Commands::Capture { quiet }) => cmd_capture(&paths, quiet, json),
+        Some(Commands::Hook { shell }) => {
+            let exe = std::env::current_exe().map_err(|e| cb::errors::CbError::Config(e.to_string()))?;
+            print!("{}", cb::shell::prompt_hook(&shell, &exe)?);
+            Ok(())
+        }
+        Some(Commands::Add {
+            text,
+            file,
+            copy,
+            external_id,
+            source_url,
+            source_title,
+        }) => {
+            let source = source_url.map(|url| SourcePage {
+                url,
+                title: source_title,
+            });
+            cmd_add(&paths, text, file.as_deref(), copy, external_id.as_deref(), source.as_ref(), json)
+        }
+        Some(Commands::Import {
+            file,
+            dir,
+            tag,
+            max_size,
+        }) => cmd_import(&paths, file.as_deref(), dir.as_deref(), tag.as_deref(), max_size, json),
+        Some(Commands::Current) => cmd_current(&paths, json),
+        Some(Commands::Init {
+            encrypt,
+            no_keychain,
+        }) => cmd_init(&paths, encrypt, no_keychain, json),
+        Some(Commands::Doctor { check_permissions }) => {
+            cmd_doctor(&paths, check_permissions, &style, json)
+        }
+        Some(Commands::Verify { fix }) => cmd_verify(&paths, fix, json),
+        Some(Commands::Backup { action }) => cmd_backup(&paths, action, json),
+        Some(Commands::Repair { force, yes }) => cmd_repair(&paths, force, yes, json),
+        Some(Commands::Tui { quick }) => cb::tui::run(&paths, quick),
+        Some(Commands::Serve { stdio, http, grpc }) => cmd_serve(&paths, stdio, http, grpc),
+        Some(Commands::Daemon { action }) => cmd_daemon(&paths, action, json),
+        Some(Commands::Rules { action }) => cmd_rules(&paths, action, &style, json),
+        Some(Commands::Links { action }) => cmd_links(&paths, action, json),
+        Some(Commands::Hotkey { action }) => cmd_hotkey(&paths, action, json),
+        Some(Commands::Profile { action }) => cmd_profile(&paths, action, json),
+        Some(Commands::Slot { action }) => cmd_slot(&paths, action, json),
+        Some(Commands::Session { action }) => cmd_session(&paths, action, json),
+        Some(Commands::Token { action }) => cmd_token(&paths, action, json),
+        Some(Commands::Jobs { action }) => cmd_jobs(&paths, action, json),
+        Some(Commands::Enrich { all: _, only }) => cmd_enrich(&paths, only.as_deref(), json),
+    }
+}
+
+fn open_storage(paths: &AppPaths) -> cb::errors::Result<SqliteStorage> {
+    if paths.read_only {
+        return SqliteStorage::open_read_only(&paths.db_path);
+    }
+    std::fs::create_dir_all(&paths.data_dir)
+        .map_err(|e| cb::errors::CbError::Storage(rusqlite::Error::ToSqlConversionFailure(e.into())))?;
+    let conn = cb::encryption::connect(&paths.db_path, OpenFlags::default())?;
+    let storage = SqliteStorage::new(conn)?;
+    if let Some(backup) = storage.migration_backup() {
+        eprintln!(
+            "cb: upgraded the database schema; the previous version is saved at {}",
+            backup.display()
+        );
+    }
+    Ok(storage)
+}
+
+fn cmd_list(
+    paths: &AppPaths,
+    filter: ClipFilter,
+    columns: Option<&[Column]>,
+    group_by: Option<GroupBy>,
+    embed_images: bool,
+    style: &RowStyle,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let clips = storage.list(filter)?;
+
+    if json {
+        let mut value = match group_by {
+            Some(by) => serde_json::to_value(cb::group::group(&clips, |c| by.keys(c))),
+            None => serde_json::to_value(&clips),
+        }
+        .unwrap();
+        add_previews(&mut value, embed_images);
+        println!("{}", value);
+        return Ok(());
+    }
+
+    if clips.is_empty() {
+        println!("{}", t("No clips found."));
+        return Ok(());
+    }
+
+    let groups = match group_by.unwrap_or(GroupBy::Day) {
+        GroupBy::Day => cb::group::group(&clips, |c| vec![Some(cb::group::clip_day_heading(c))]),
+        by => cb::group::group(&clips, |c| by.keys(c)),
+    };
+    let fallback = match group_by {
+        Some(GroupBy::Tag) => t("(untagged)"),
+        Some(GroupBy::Url) => t("(not a URL)"),
+        _ => t("(unknown app)"),
+    };
+    let groups: Vec<(String, Vec<&cb::storage::models::Clip>)> = groups
+        .into_iter()
+        .map(|g| (g.group.unwrap_or_else(|| fallback.to_string()), g.clips))
+        .collect();
+    style.print_grouped(&groups, columns);
+    Ok(())
+}
+
+/// Plain text (or with `fuzzy`, fuzzy) search when only `filter.query` is
+/// set; otherwise a filtered listing, narrowed to images near `color` if given.
+fn cmd_search(
+    paths: &AppPaths,
+    mut filter: ClipFilter,
+    color: Option<cb::colors::Rgb>,
+    fuzzy: bool,
+    embed_images: bool,
+    style: &RowStyle,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let limit = filter.limit;
+    let query = filter.query.clone();
+    let clips = match (&query, &filter.content_type) {
+        (Some(_), None) if fuzzy => storage.search_fuzzy(filter)?,
+        (Some(query), None) if filter.after.is_none() && filter.before.is_none() => {
+            storage.search(query, limit)?
+        }
+        _ => {
+            // Colors are matched by distance, which SQL can't do, so fetch
+            // every candidate and narrow down here.
+            if color.is_some() {
+                filter.limit = storage.count_matching(&filter)?.max(1);
+            }
+            let mut clips = storage.list(filter)?;
+            if let Some(target) = color {
+                clips.retain(|clip| cb::colors::has_color(clip, target));
+                clips.truncate(limit.max(0) as usize);
+            }
+            clips
+        }
+    };
+    let query = query.as_deref().unwrap_or_default();
+
+    if json {
+        let results: Vec<serde_json::Value> = clips
+            .iter()
+            .map(|clip| {
+                let mut obj = serde_json::to_value(clip).unwrap();
+                obj.as_object_mut().unwrap().insert(
+                    "snippet".into(),
+                    serde_json::to_value(cb::snippet::for_clip(clip, query, SNIPPET_WIDTH))
+                        .unwrap(),
+                );
+                cb::thumbs::add_preview(&mut obj, embed_images);
+                obj
+            })
+            .collect();
+        println!("{}", serde_json::to_string(&results).unwrap());
+        return Ok(());
+    }
+
+    if clips.is_empty() {
+        if query.is_empty() {
+            println!("{}", t("No matching clips."));
+        } else {
+            println!("{}", tf("No results for \"{0}\".", &[&query]));
+        }
+        return Ok(());
+    }
+
+    style.print_search_rows(&clips, query);
+    Ok(())
+}
+
+fn cmd_suggest(
+    paths: &AppPaths,
+    context: Option<String>,
+    limit: i64,
+    scorer: &str,
+    pool: i64,
+    style: &RowStyle,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let scorer = cb::suggest::scorer(scorer)?;
+    let context = match context {
+        Some(context) => context,
+        None => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|e| cb::errors::CbError::InvalidInput(e.to_string()))?;
+            input
+        }
+    };
+    let storage = open_storage(paths)?;
+    let found = cb::suggest::suggest(&storage, &context, scorer.as_ref(), pool, limit.max(0) as usize)?;
+
+    if json {
+        let mut results = serde_json::to_value(&found).unwrap();
+        add_previews(&mut results, false);
+        println!("{}", serde_json::to_string(&results).unwrap());
+        return Ok(());
+    }
+    if found.is_empty() {
+        println!("{}", t("No suggestions."));
+        return Ok(());
+    }
+    let clips: Vec<_> = found.into_iter().map(|s| s.clip).collect();
+    style.print_rows(&clips);
+    Ok(())
+}
+
+/// Give each image clip in a JSON list (or in each group of one) a thumbnail
+/// preview; see `thumbs::add_preview`.
+fn add_previews(value: &mut serde_json::Value, embed: bool) {
+    for item in value.as_array_mut().into_iter().flatten() {
+        match item.get_mut("clips") {
+            Some(clips) => add_previews(clips, embed),
+            None => cb::thumbs::add_preview(item, embed),
+        }
+    }
+}
+
+fn cmd_get(
+    paths: &AppPaths,
+    id: i64,
+    history: bool,
+    style: &RowStyle,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let clip = storage.get_by_id(id)?;
+    let captures = if history { storage.captures(id)? } else { Vec::new() };
+
+    if json {
+        let mut obj = serde_json::to_value(&clip).unwrap();
+        if let Some(ref text) = clip.text_content {
+            obj.as_object_mut().unwrap().insert(
+                "metrics".into(),
+                serde_json::to_value(cb::metrics::TextMetrics::of(text)).unwrap(),
+            );
+        }
+        if history {
+            obj.as_object_mut()
+                .unwrap()
+                .insert("captures".into(), serde_json::to_value(&captures).unwrap());
+        }
+        println!("{}", serde_json::to_string(&obj).unwrap());
+        return Ok(());
+    }
+
+    print_clip_detail(&clip, style.labels);
+    if history {
+        if style.labels {
+            println!("Captures:");
+        } else {
+            println!("─────────────────────────");
+        }
+        if captures.is_empty() {
+            println!("No captures recorded.");
+        }
+        for capture in &captures {
+            println!(
+                "{}  {}{}{}{}",
+                capture.captured_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
+                capture.source_app.as_deref().unwrap_or("unknown app"),
+                capture.machine.as_deref().map(|m| format!(" on {}", m)).unwrap_or_default(),
+                capture.space.map(|s| format!(" in Space {}", s)).unwrap_or_default(),
+                capture.display.map(|d| format!(", display {}", d)).unwrap_or_default()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The file behind an image or fileref clip: for images, the paired
+/// screenshot when it still exists, else the stored copy.
+fn cmd_open(paths: &AppPaths, id: i64, reveal: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let clip = storage.get_by_id(id)?;
+    let path = match clip.content_type {
+        ContentType::Image => clip
+            .original_path
+            .as_deref()
+            .filter(|p| Path::new(p).exists())
+            .or(clip.image_path.as_deref()),
+        ContentType::FileRef => clip.text_content.as_deref(),
+        ContentType::Text => None,
+    }
+    .ok_or_else(|| {
+        cb::errors::CbError::InvalidInput(format!("clip #{} has no file to open", id))
+    })?;
+    if !Path::new(path).exists() {
+        return Err(cb::errors::CbError::NotFound(format!("{} no longer exists", path)));
+    }
+
+    let mut open = process::Command::new("open");
+    if reveal {
+        open.arg("-R");
+    }
+    let status = open
+        .arg(path)
+        .status()
+        .map_err(|e| cb::errors::CbError::InvalidInput(format!("open: {}", e)))?;
+    if !status.success() {
+        return Err(cb::errors::CbError::InvalidInput(format!("open exited with {}", status)));
+    }
+    Ok(())
+}
+
+/// Number of search results `copy --search` chooses from.
+const COPY_SEARCH_LIMIT: i64 = 20;
+
+/// The clip `copy --search` should copy: the only match, or the one that
+/// ranks strictly best. Ties are an error unless `pick` lets the user choose.
+fn resolve_search(
+    storage: &SqliteStorage,
+    query: &str,
+    pick: bool,
+    style: &RowStyle,
+) -> cb::errors::Result<i64> {
+    let mut clips = storage.search(query, COPY_SEARCH_LIMIT)?;
+    cb::snippet::rank(&mut clips, query);
+    let score = |clip| cb::snippet::score(clip, query);
+
+    match clips.as_slice() {
+        [] => Err(cb::errors::CbError::NotFound(format!("No clips match \"{}\"", query))),
+        [only] => Ok(only.id),
+        _ if pick => pick_clip(&clips, query, style),
+        [first, second, ..] if score(first) > score(second) => Ok(first.id),
+        _ => {
+            let ids: Vec<String> = clips.iter().take(5).map(|c| format!("#{}", c.id)).collect();
+            Err(cb::errors::CbError::InvalidInput(format!(
+                "\"{}\" matches {} clips ({}{}); use --pick or copy by id",
+                query,
+                clips.len(),
+                ids.join(", "),
+                if clips.len() > 5 { ", ..." } else { "" }
+            )))
+        }
+    }
+}
+
+/// Prompt on stderr for one of `clips`, keeping stdout for the result.
+fn pick_clip(
+    clips: &[cb::storage::models::Clip],
+    query: &str,
+    style: &RowStyle,
+) -> cb::errors::Result<i64> {
+    let id_width = clips.iter().map(|c| c.id.to_string().len()).max().unwrap_or(1);
+    let narrowed = RowStyle {
+        width: style.width.map(|w| w.saturating_sub(4)),
+        ..*style
+    };
+    for (idx, clip) in clips.iter().enumerate() {
+        eprintln!("{:>2}) {}", idx + 1, narrowed.search_row(clip, id_width, query));
+    }
+    eprint!("Copy which? [1-{}] ", clips.len());
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| cb::errors::CbError::InvalidInput(e.to_string()))?;
+    answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|idx| clips.get(idx))
+        .map(|clip| clip.id)
+        .ok_or_else(|| {
+            cb::errors::CbError::InvalidInput(format!("invalid choice \"{}\"", answer.trim()))
+        })
+}
+
+fn cmd_paste(
+    paths: &AppPaths,
+    storage: &SqliteStorage,
+    id: i64,
+    delay: std::time::Duration,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let clip = storage.get_by_id(id)?;
+    cb::verify::check_file(storage, &clip)?;
+    let config = Config::load(&paths.config_file)?;
+    if !copy_clip(&SystemClipboard, &clip, config.on_copy.as_ref())? {
+        return Err(cb::errors::CbError::InvalidInput(format!(
+            "clip #{} has nothing to paste",
+            id
+        )));
+    }
+    if !storage.is_read_only() {
+        storage.record_copy(id)?;
+    }
+    cb::clipboard::paste_after(delay)?;
+
+    let message = format!("Pasted clip #{}.", id);
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: true,
+                message,
+                removed: None,
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_copy(
+    paths: &AppPaths,
+    storage: &SqliteStorage,
+    id: i64,
+    transform: Option<Transform>,
+    restore_after: Option<std::time::Duration>,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let mut clip = storage.get_by_id(id)?;
+    cb::verify::check_file(storage, &clip)?;
+    if let Some(t) = transform {
+        clip.text_content = Some(t.apply(&clip)?);
+    }
+    let config = Config::load(&paths.config_file)?;
+    let previous = match restore_after {
+        Some(_) => SystemClipboard.read()?,
+        None => None,
+    };
+    let copied = copy_clip(&SystemClipboard, &clip, config.on_copy.as_ref())?;
+
+    let message = match (clip.content_type, copied) {
+        (ContentType::Text, true) => match transform {
+            Some(t) => format!("Copied clip #{} to clipboard as {}.", id, t.name()),
+            None => format!("Copied clip #{} to clipboard.", id),
+        },
+        (ContentType::Text, false) => format!("Text clip #{} has no content.", id),
+        (ContentType::Image, true) => format!("Copied image clip #{} to clipboard.", id),
+        (ContentType::Image, false) => format!("Image clip #{} has no stored path.", id),
+        (ContentType::FileRef, _) => format!(
+            "File reference: {}",
+            clip.text_content.as_deref().unwrap_or("unknown")
+        ),
+    };
+
+    if !storage.is_read_only() {
+        storage.record_copy(id)?;
+    }
+
+    let restored = match restore_after {
+        Some(wait) if copied => {
+            if !json {
+                println!("{}", message);
+                eprintln!(
+                    "Restoring the previous clipboard in {}s (Ctrl-C to keep this one)...",
+                    wait.as_secs_f64()
+                );
+            }
+            let restored = cb::clipboard::restore_after(&SystemClipboard, previous.as_ref(), wait)?;
+            if !json {
+                if restored {
+                    println!("Restored the previous clipboard.");
+                } else {
+                    println!("Something else was copied; left the clipboard alone.");
+                }
+                return Ok(());
+            }
+            Some(restored)
+        }
+        _ => None,
+    };
+
+    if json {
+        let mut value = serde_json::to_value(StatusResponse {
+            success: true,
+            message,
+            removed: None,
+        })
+        .unwrap();
+        if let Some(restored) = restored {
+            value["restored"] = serde_json::json!(restored);
+        }
+        println!("{}", value);
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_delete(paths: &AppPaths, id: i64, json: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let found = storage.delete(id)?;
+    let message = if found {
+        format!("Moved clip #{} to the trash (`cb restore {}` brings it back).", id, id)
+    } else {
+        format!("Clip #{} not found.", id)
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: found,
+                message,
+                removed: None,
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_restore(paths: &AppPaths, id: i64, json: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let found = storage.restore(id)?;
+    let message = if found {
+        format!("Restored clip #{}.", id)
+    } else {
+        format!("Clip #{} is not in the trash.", id)
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: found,
+                message,
+                removed: None,
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_trash(paths: &AppPaths, action: TrashAction, style: &RowStyle, json: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    match action {
+        TrashAction::List { limit } => {
+            let clips = storage.list(ClipFilter {
+                trashed: true,
+                limit,
+                ..Default::default()
+            })?;
+            if json {
+                println!("{}", serde_json::to_string(&clips).unwrap());
+            } else if clips.is_empty() {
+                println!("The trash is empty.");
+            } else {
+                style.print_rows(&clips);
+            }
+        }
+        TrashAction::Empty { yes } => {
+            let trashed = storage.count_matching(&ClipFilter {
+                trashed: true,
+                ..Default::default()
+            })?;
+            if trashed > 0 && !yes && !json && std::io::stdin().is_terminal() {
+                println!("{} clip(s) in the trash.", trashed);
+                if !confirm("Delete them permanently?")? {
+                    println!("Aborted.");
+                    return Ok(());
+                }
+            }
+            let removed = storage.empty_trash()?;
+            storage.release_unused_images(&paths.images_dir)?;
+            let message = format!("Permanently deleted {} clip(s).", removed);
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string(&StatusResponse {
+                        success: true,
+                        message,
+                        removed: Some(removed),
+                    })
+                    .unwrap()
+                );
+            } else {
+                println!("{}", message);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Number of matching clips listed by `delete --dry-run`.
+const DRY_RUN_PREVIEW: i64 = 20;
+
+fn cmd_delete_matching(
+    paths: &AppPaths,
+    filter: ClipFilter,
+    dry_run: bool,
+    yes: bool,
+    style: &RowStyle,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let matched = storage.count_matching(&filter)?;
+
+    if dry_run {
+        let clips = storage.list(ClipFilter {
+            limit: DRY_RUN_PREVIEW,
+            ..filter
+        })?;
+        if json {
+            println!(
+                "{}",
+                serde_json::json!({"dry_run": true, "matched": matched, "clips": clips})
+            );
+            return Ok(());
+        }
+        println!("Would trash {} clip(s).", matched);
+        style.print_rows(&clips);
+        if matched > clips.len() as i64 {
+            println!("... and {} more", matched - clips.len() as i64);
+        }
+        return Ok(());
+    }
+
+    if matched > 0 && !yes && !json && std::io::stdin().is_terminal() {
+        println!("{} clip(s) match; pinned and protected clips are never bulk-deleted.", matched);
+        if !confirm("Move them to the trash?")? {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    let removed = storage.delete_matching(&filter)?;
+    let message = format!("Moved {} clip(s) to the trash.", removed);
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: true,
+                message,
+                removed: Some(removed),
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn parse_content_type(s: &str) -> cb::errors::Result<ContentType> {
+    ContentType::parse(s).ok_or_else(|| {
+        cb::errors::CbError::InvalidInput(format!(
+            "unknown type \"{}\" (expected text, image, or fileref)",
+            s
+        ))
+    })
+}
+
+/// `KEY=VALUE` or a bare `KEY` for `--meta`.
+fn parse_meta_filter(s: &str) -> Result<(String, Option<String>), String> {
+    let (key, value) = match s.split_once('=') {
+        Some((key, value)) => (key, Some(value.to_string())),
+        None => (s, None),
+    };
+    if key.is_empty() {
+        return Err(format!("invalid metadata filter \"{}\" (expected KEY or KEY=VALUE)", s));
+    }
+    Ok((key.to_string(), value))
+}
+
+/// `500ms`, `30s`, `2m`, `1h`, or a bare number of seconds.
+fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let n: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration \"{}\" (e.g. 30s, 2m)", s))?;
+    let millis = match unit {
+        "ms" => n,
+        "" | "s" => n * 1000,
+        "m" => n * 60 * 1000,
+        "h" => n * 60 * 60 * 1000,
+        _ => return Err(format!("invalid duration \"{}\" (e.g. 30s, 2m)", s)),
+    };
+    Ok(std::time::Duration::from_millis(millis))
+}
+
+type TimeBound = Option<chrono::DateTime<chrono::Utc>>;
+
+/// `--since` and `--until` as `(after, before)` bounds for a `ClipFilter`.
+fn parse_window(since: Option<&str>, until: Option<&str>) -> cb::errors::Result<(TimeBound, TimeBound)> {
+    let after = since.map(parse_when).transpose()?;
+    let before = until.map(parse_when).transpose()?;
+    if let (Some(after), Some(before)) = (after, before)
+        && after >= before
+    {
+        return Err(cb::errors::CbError::InvalidInput(
+            "--since must be earlier than --until".into(),
+        ));
+    }
+    Ok((after, before))
+}
+
+fn cmd_pin(
+    paths: &AppPaths,
+    id: i64,
+    pinned: bool,
+    until: TimeBound,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let message = match until {
+        Some(until) => {
+            storage.pin_until(id, until)?;
+            format!(
+                "Pinned clip #{} until {}.",
+                id,
+                until.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+            )
+        }
+        None if pinned => {
+            storage.set_pinned(id, true)?;
+            format!("Pinned clip #{}.", id)
+        }
+        None => {
+            storage.set_pinned(id, false)?;
+            format!("Unpinned clip #{}.", id)
+        }
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: true,
+                message,
+                removed: None,
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_tag(paths: &AppPaths, id: i64, tag: &str, remove: bool, json: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let message = if remove {
+        storage.remove_tag(id, tag)?;
+        format!("Removed tag \"{}\" from clip #{}.", tag, id)
+    } else {
+        storage.add_tag(id, tag)?;
+        format!("Added tag \"{}\" to clip #{}.", tag, id)
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: true,
+                message,
+                removed: None,
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_meta(paths: &AppPaths, action: MetaAction, json: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let message = match action {
+        MetaAction::Set { id, key, value } => {
+            if key.is_empty() {
+                return Err(cb::errors::CbError::InvalidInput("metadata key can't be empty".into()));
+            }
+            storage.set_meta(id, &key, &value)?;
+            format!("Set {} on clip #{}.", key, id)
+        }
+        MetaAction::Rm { id, key } => {
+            if !storage.remove_meta(id, &key)? {
+                return Err(cb::errors::CbError::NotFound(format!("clip #{} has no \"{}\"", id, key)));
+            }
+            format!("Removed {} from clip #{}.", key, id)
+        }
+        MetaAction::Get { id, key: Some(key) } => {
+            let meta = storage.get_by_id(id)?.meta;
+            let value = meta
+                .get(&key)
+                .ok_or_else(|| cb::errors::CbError::NotFound(format!("clip #{} has no \"{}\"", id, key)))?;
+            if json {
+                println!("{}", serde_json::json!({ "key": key, "value": value }));
+            } else {
+                println!("{}", value);
+            }
+            return Ok(());
+        }
+        MetaAction::Get { id, key: None } => {
+            let meta = storage.get_by_id(id)?.meta;
+            if json {
+                println!("{}", serde_json::to_string(&meta).unwrap());
+            } else {
+                for (key, value) in &meta {
+                    println!("{}={}", key, value);
+                }
+            }
+            return Ok(());
+        }
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: true,
+                message,
+                removed: None,
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_append(
+    paths: &AppPaths,
+    id: i64,
+    text: Option<String>,
+    separator: Option<String>,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let text = match text {
+        Some(text) => text,
+        None => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|e| cb::errors::CbError::InvalidInput(e.to_string()))?;
+            // `echo url | cb append 3` shouldn't leave a trailing blank line.
+            input.strip_suffix('\n').unwrap_or(&input).to_string()
+        }
+    };
+    if text.is_empty() {
+        return Err(cb::errors::CbError::InvalidInput("nothing to append".into()));
+    }
+    let separator = match separator {
+        Some(separator) => separator,
+        None => Config::load(&paths.config_file)?.append_separator().to_string(),
+    };
+
+    let storage = open_storage(paths)?;
+    let clip = storage.append_text(id, &text, &separator)?;
+    let message = format!("Appended to clip #{} ({}).", id, format_bytes(clip.size_bytes));
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: true,
+                message,
+                removed: None,
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_edit(paths: &AppPaths, id: i64, json: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let clip = storage.get_by_id(id)?;
+    if clip.content_type != ContentType::Text {
+        return Err(cb::errors::CbError::InvalidInput(format!(
+            "clip #{} is {}, only text clips can be edited",
+            id,
+            clip.content_type.as_str()
+        )));
+    }
+    if clip.protected {
+        return Err(cb::errors::CbError::Protected(id));
+    }
+    let original = clip.text_content.unwrap_or_default();
+
+    let io = |e: std::io::Error| cb::errors::CbError::InvalidInput(e.to_string());
+    let file = std::env::temp_dir().join(format!("cb-{}-{}.txt", id, process::id()));
+    std::fs::write(&file, &original).map_err(io)?;
+    let edited = run_editor(&file).and_then(|()| std::fs::read_to_string(&file).map_err(io));
+    let _ = std::fs::remove_file(&file);
+    let mut edited = edited?;
+    // Most editors end the file with a newline; don't count that as an edit.
+    if !original.ends_with('\n') && edited.ends_with('\n') {
+        edited.pop();
+    }
+
+    let message = if edited == original {
+        format!("No changes to clip #{}.", id)
+    } else if edited.trim().is_empty() {
+        return Err(cb::errors::CbError::InvalidInput(format!(
+            "the edited text is empty, so clip #{} was left as it was (`cb delete {}` removes it)",
+            id, id
+        )));
+    } else {
+        let clip = storage.update_content(id, &edited)?;
+        format!("Updated clip #{} ({}).", id, format_bytes(clip.size_bytes))
+    };
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: true,
+                message,
+                removed: None,
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+/// Open `file` in `$VISUAL`, `$EDITOR`, or `vi`, through the shell so
+/// settings such as `code --wait` work, and wait for it to exit.
+fn run_editor(file: &Path) -> cb::errors::Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let status = process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(file)
+        .status()
+        .map_err(|e| cb::errors::CbError::InvalidInput(format!("running {}: {}", editor, e)))?;
+    if !status.success() {
+        return Err(cb::errors::CbError::InvalidInput(format!(
+            "{} exited with {}, nothing saved",
+            editor, status
+        )));
+    }
+    Ok(())
+}
+
+fn cmd_protect(paths: &AppPaths, id: i64, protected: bool, json: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    storage.set_protected(id, protected)?;
+    let message = if protected {
+        format!("Protected clip #{}.", id)
+    } else {
+        format!("Unprotected clip #{}.", id)
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: true,
+                message,
+                removed: None,
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_pin_matching(
+    paths: &AppPaths,
+    filter: &ClipFilter,
+    pinned: bool,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let changed = storage.set_pinned_matching(filter, pinned)?;
+    let message = if pinned {
+        format!("Pinned {} clip(s).", changed)
+    } else {
+        format!("Unpinned {} clip(s).", changed)
+    };
+    print_bulk_status(message, changed, json);
+    Ok(())
+}
+
+fn cmd_tag_matching(
+    paths: &AppPaths,
+    filter: &ClipFilter,
+    tag: &str,
+    remove: bool,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let changed = storage.tag_matching(filter, tag, remove)?;
+    let message = if remove {
+        format!("Removed tag \"{}\" from {} clip(s).", tag, changed)
+    } else {
+        format!("Added tag \"{}\" to {} clip(s).", tag, changed)
+    };
+    print_bulk_status(message, changed, json);
+    Ok(())
+}
+
+fn print_bulk_status(message: String, affected: i64, json: bool) {
+    if json {
+        let mut value = serde_json::to_value(StatusResponse {
+            success: true,
+            message,
+            removed: None,
+        })
+        .unwrap();
+        value["affected"] = affected.into();
+        println!("{}", value);
+    } else {
+        println!("{}", message);
+    }
+}
+
+fn cmd_clear(paths: &AppPaths, days: Option<i64>, yes: bool, json: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    // Pins that ran out don't hold clips back, daemon or not.
+    storage.unpin_expired(chrono::Utc::now())?;
+    let mut policy = Config::load(&paths.config_file)?.retention_policy();
+    if days.is_some() {
+        policy.max_age_days = days;
+    }
+
+    if !yes && !json && std::io::stdin().is_terminal() {
+        let preview = storage.preview_retention(&policy)?;
+        if preview.clips == 0 {
+            println!("Nothing to clear (policy: {}).", policy.describe());
+            return Ok(());
+        }
+        println!(
+            "{} clip(s) match (policy: {}), reclaiming {}.",
+            preview.clips,
+            policy.describe(),
+            format_bytes(preview.bytes)
+        );
+        if preview.protected > 0 {
+            println!("{} pinned/exempt clip(s) will be kept.", preview.protected);
+        }
+        if !confirm("Delete them?")? {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    let removed = storage.apply_retention(&policy)?;
+    storage.release_unused_images(&paths.images_dir)?;
+    let message = format!("Removed {} clip(s) (policy: {}).", removed, policy.describe());
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: true,
+                message,
+                removed: Some(removed),
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+/// Whether a live clip holds `text` (or stdin) or has `hash`, printing its ID
+/// if so.
+fn cmd_exists(
+    paths: &AppPaths,
+    text: Option<String>,
+    hash: Option<String>,
+    json: bool,
+) -> cb::errors::Result<bool> {
+    let hash = match (hash, text) {
+        (Some(hash), _) => hash,
+        (None, Some(text)) => hash_content(text.as_bytes()),
+        (None, None) => {
+            let mut input = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut input)
+                .map_err(|e| cb::errors::CbError::InvalidInput(e.to_string()))?;
+            // As with `cb append`, `echo text | cb exists` means "text".
+            if input.last() == Some(&b'\n') {
+                input.pop();
+            }
+            hash_content(&input)
+        }
+    };
+    let storage = open_storage(paths)?;
+    let id = storage
+        .find_by_hash(&hash)?
+        .filter(|clip| clip.deleted_at.is_none())
+        .map(|clip| clip.id);
+    if json {
+        println!("{}", serde_json::json!({"exists": id.is_some(), "id": id}));
+    } else if let Some(id) = id {
+        println!("{}", id);
+    }
+    Ok(id.is_some())
+}
+
+fn cmd_count(paths: &AppPaths, filter: &ClipFilter, json: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let count = storage.count_matching(filter)?;
+    if json {
+        println!("{}", serde_json::json!({"count": count}));
+    } else {
+        println!("{}", count);
+    }
+    Ok(())
+}
+
+fn confirm(prompt: &str) -> cb::errors::Result<bool> {
+    print!("{} [y/N] ", prompt);
+    std::io::stdout()
+        .flush()
+        .map_err(|e| cb::errors::CbError::InvalidInput(e.to_string()))?;
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| cb::errors::CbError::InvalidInput(e.to_string()))?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn cmd_stats(paths: &AppPaths, filter: ClipFilter, style: &RowStyle, json: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let stats = storage.stats()?;
+    let usage = storage.usage_stats(10)?;
+    let (metric_clips, metrics) = cb::metrics::aggregate(&storage, &filter)?;
+    let config = Config::load(&paths.config_file)?;
+    let policy = config.retention_policy();
+    let daemon_enforced = config.retention.is_some();
+
+    if json {
+        let daemon_pid = daemon::daemon_status(paths).ok().flatten();
+        let mut obj = serde_json::to_value(&stats).unwrap();
+        let m = obj.as_object_mut().unwrap();
+        m.insert("daemon_running".into(), serde_json::json!(daemon_pid.is_some()));
+        m.insert("daemon_pid".into(), serde_json::json!(daemon_pid));
+        m.insert("usage".into(), serde_json::to_value(&usage).unwrap());
+        m.insert(
+            "retention".into(),
+            serde_json::json!({
+                "policy": policy,
+                "summary": policy.describe(),
+                "daemon_enforced": daemon_enforced,
+            }),
+        );
+        m.insert(
+            "text_metrics".into(),
+            serde_json::json!({"clips": metric_clips, "metrics": metrics}),
+        );
+        println!("{}", serde_json::to_string(&obj).unwrap());
+        return Ok(());
+    }
+
+    let daemon = match daemon::daemon_status(paths) {
+        Ok(Some(pid)) => format!("running (pid {})", pid),
+        _ => "not running".to_string(),
+    };
+    // (aligned label, plain label, value)
+    let mut fields = vec![
+        ("Total clips:", "Total clips:", stats.total_clips.to_string()),
+        ("  Text:", "Text clips:", stats.text_clips.to_string()),
+        ("  Image:", "Image clips:", stats.image_clips.to_string()),
+        ("  File refs:", "File ref clips:", stats.fileref_clips.to_string()),
+        ("Total size:", "Total size:", format_bytes(stats.total_size)),
+        (
+            "Text:",
+            "Text metrics:",
+            format!(
+                "{} words, {} chars, {} lines across {} clip(s)",
+                metrics.words, metrics.chars, metrics.lines, metric_clips
+            ),
+        ),
+    ];
+    if let Some(oldest) = stats.oldest {
+        fields.push(("Oldest:", "Oldest:", oldest.format("%Y-%m-%d %H:%M").to_string()));
+    }
+    if let Some(newest) = stats.newest {
+        fields.push(("Newest:", "Newest:", newest.format("%Y-%m-%d %H:%M").to_string()));
+    }
+    fields.push(("Daemon:", "Daemon:", daemon));
+    fields.push((
+        "Policy:",
+        "Policy:",
+        format!(
+            "{}{}",
+            policy.describe(),
+            if daemon_enforced { " (enforced by daemon)" } else { " (on cb clear)" }
+        ),
+    ));
+
+    println!("Clipboard Statistics");
+    if !style.labels {
+        println!("────────────────────");
+    }
+    for (aligned, plain, value) in fields {
+        if style.labels {
+            println!("{} {}", plain, value);
+        } else {
+            println!("{:<14}{}", aligned, value);
+        }
+    }
+
+    if !usage.most_copied.is_empty() {
+        println!();
+        println!("Most copied");
+        let narrowed = RowStyle {
+            width: style.width.map(|w| w.saturating_sub(6)),
+            ..*style
+        };
+        for clip in &usage.most_copied {
+            if style.labels {
+                println!("Copies: {}; {}", clip.copy_count, narrowed.row(clip, 4));
+            } else {
+                println!("  {:>3}× {}", clip.copy_count, narrowed.row(clip, 4));
+            }
+        }
+    }
+
+    if !usage.top_apps.is_empty() {
+        println!();
+        println!("Top apps");
+        for app in &usage.top_apps {
+            if style.labels {
+                println!("{}: {} capture(s)", app.label(), app.captures);
+            } else {
+                println!("  {:>4}  {}", app.captures, app.label());
+            }
+        }
+    }
+
+    let busiest = usage.busiest_hours(3);
+    if !busiest.is_empty() {
+        println!();
+        println!("Busiest hours");
+        for (hour, count) in busiest {
+            if style.labels {
+                println!("{:02}:00: {} clip(s)", hour, count);
+            } else {
+                println!("  {:02}:00  {} clip(s)", hour, count);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_split(
+    paths: &AppPaths,
+    id: i64,
+    by: &str,
+    pattern: Option<&str>,
+    tag: Option<&str>,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let mode = cb::split::SplitMode::parse(by, pattern)?;
+    let storage = open_storage(paths)?;
+    let summary = cb::split::split_clip(&storage, id, &mode, tag)?;
+
+    if json {
+        println!("{}", serde_json::to_string(&summary).unwrap());
+        return Ok(());
+    }
+
+    println!(
+        "Split clip #{} into {} new clip(s) ({} already in history), tagged \"{}\".",
+        id, summary.created, summary.duplicates, summary.tag
+    );
+    Ok(())
+}
+
+fn cmd_capture(paths: &AppPaths, quiet: bool, json: bool) -> cb::errors::Result<()> {
+    use daemon::Captured;
+
+    let Some(mut content) = SystemClipboard.read()? else {
+        if quiet {
+            return Ok(());
+        }
+        return Err(cb::errors::CbError::NotFound("text or image on the clipboard".into()));
+    };
+    if let Some(app) = cb::clipboard::frontmost_app() {
+        content.source_app = Some(app.bundle_id);
+        content.app_name = app.name;
+    }
+    let storage = open_storage(paths)?;
+    let config = Config::load(&paths.config_file)?;
+    let rules = cb::rules::RuleSet::compile(&config.rules)?;
+    let mut ignore = cb::ignore::IgnoreList::compile(&config.ignore_patterns)?;
+    // Password managers mark what they copy; that stays out of history.
+    let (clip, message) = if let Some(marker) = SystemClipboard.marked_private() {
+        (None, format!("Not captured: the copying app marked it private ({}).", marker))
+    } else {
+        match daemon::capture_content(&storage, paths, &rules, &config, &mut ignore, content)? {
+            Captured::Stored(clip) => {
+                let message = format!("Captured clip #{}.", clip.id);
+                (Some(clip), message)
+            }
+            Captured::Duplicate(clip) => {
+                let message = format!("Already in history as clip #{}.", clip.id);
+                (Some(clip), message)
+            }
+            Captured::TooShort => (
+                None,
+                format!("Not captured: shorter than min_text_length ({}).", config.min_text_length),
+            ),
+            Captured::Ignored(pattern) => (None, format!("Not captured: matches ignore pattern {}.", pattern)),
+            Captured::Secret(kind) => (None, format!("Not captured: looks like it holds a {}.", kind.name())),
+            Captured::Rejected => (None, "Not captured: capture_hook skipped it.".to_string()),
+        }
+    };
+    if quiet {
+        return Ok(());
+    }
+    if json {
+        match clip {
+            Some(clip) => println!("{}", serde_json::to_string(&clip).unwrap()),
+            None => println!(
+                "{}",
+                serde_json::to_string(&StatusResponse {
+                    success: false,
+                    message,
+                    removed: None,
+                })
+                .unwrap()
+            ),
+        }
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_add(
+    paths: &AppPaths,
+    text: Option<String>,
+    file: Option<&Path>,
+    copy: bool,
+    external_id: Option<&str>,
+    source: Option<&SourcePage>,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let content = match (text, file) {
+        (Some(text), _) => ClipboardContent::from_text(text),
+        (None, Some(path)) => {
+            let data = std::fs::read(path).map_err(|e| {
+                cb::errors::CbError::InvalidInput(format!("{}: {}", path.display(), e))
+            })?;
+            content_from_bytes(data, path.display())?
+        }
+        (None, None) => {
+            let mut input = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut input)
+                .map_err(|e| cb::errors::CbError::InvalidInput(e.to_string()))?;
+            if !cb::clipboard::is_png(&input) && input.last() == Some(&b'\n') {
+                input.pop();
+            }
+            content_from_bytes(input, "stdin")?
+        }
+    };
+    if content.text.as_deref() == Some("") {
+        return Err(cb::errors::CbError::InvalidInput("nothing to add".into()));
+    }
+    let storage = open_storage(paths)?;
+    let config = Config::load(&paths.config_file)?;
+    let rules = cb::rules::RuleSet::compile(&config.rules)?;
+    let (clip, outcome) = match external_id {
+        Some(key) => daemon::upsert_external(&storage, paths, &rules, &config, key, content, source)?,
+        None => match daemon::add_content(&storage, paths, &rules, &config, content, source)? {
+            (clip, true) => (clip, daemon::Upserted::Added),
+            (clip, false) => (clip, daemon::Upserted::Unchanged),
+        },
+    };
+    if copy {
+        // The daemon sees the same content come back and dedups it.
+        copy_clip(&SystemClipboard, &clip, None)?;
+    }
+
+    if json {
+        println!("{}", serde_json::to_string(&clip).unwrap());
+    } else {
+        match outcome {
+            daemon::Upserted::Added => println!("Added clip #{}.", clip.id),
+            daemon::Upserted::Updated => println!("Updated clip #{}.", clip.id),
+            daemon::Upserted::Unchanged => println!("Already in history as clip #{}.", clip.id),
+        }
+    }
+    Ok(())
+}
+
+/// A PNG image or UTF-8 text from `data`, read from `what`.
+fn content_from_bytes(data: Vec<u8>, what: impl std::fmt::Display) -> cb::errors::Result<ClipboardContent> {
+    if cb::clipboard::is_png(&data) {
+        return ClipboardContent::from_png(&data);
+    }
+    String::from_utf8(data).map(ClipboardContent::from_text).map_err(|_| {
+        cb::errors::CbError::InvalidInput(format!("{} is neither a PNG image nor UTF-8 text", what))
+    })
+}
+
+fn cmd_import(
+    paths: &AppPaths,
+    file: Option<&std::path::Path>,
+    dir: Option<&std::path::Path>,
+    tag: Option<&str>,
+    max_size: u64,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let (summary, from) = match (file, dir) {
+        (Some(file), _) => (
+            cb::import::import_export(&storage, file, &paths.images_dir, tag)?,
+            file,
+        ),
+        (None, Some(dir)) => (cb::import::import_dir(&storage, dir, tag, max_size)?, dir),
+        (None, None) => unreachable!("clap requires a file or --dir"),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string(&summary).unwrap());
+        return Ok(());
+    }
+
+    if file.is_some() {
+        println!(
+            "Imported {} clip(s) from {} ({} already in history, {} skipped).",
+            summary.imported,
+            from.display(),
+            summary.duplicates,
+            summary.skipped
+        );
+    } else {
+        println!(
+            "Imported {} clip(s) from {} ({} duplicate, {} too large, {} skipped).",
+            summary.imported,
+            from.display(),
+            summary.duplicates,
+            summary.too_large,
+            summary.skipped
+        );
+    }
+    Ok(())
+}
+
+fn cmd_bundle(
+    paths: &AppPaths,
+    filter: &ClipFilter,
+    out: &std::path::Path,
+    force: bool,
+    json: bool,
+) -> cb::errors::Result<()> {
+    if out.exists() && !force {
+        return Err(cb::errors::CbError::InvalidInput(format!(
+            "{} already exists (use --force to overwrite)",
+            out.display()
+        )));
+    }
+    let storage = open_storage(paths)?;
+    let summary = cb::bundle::write_bundle(&storage, filter, out)?;
+
+    if json {
+        println!("{}", serde_json::to_string(&summary).unwrap());
+        return Ok(());
+    }
+
+    print!("Bundled {} clip(s) into {}", summary.clips, out.display());
+    if summary.skipped > 0 {
+        print!(" ({} with missing files skipped)", summary.skipped);
+    }
+    println!(".");
+    Ok(())
+}
+
+fn cmd_export(
+    paths: &AppPaths,
+    filter: &ClipFilter,
+    format: cb::export::Format,
+    output: Option<&std::path::Path>,
+    images: Option<&std::path::Path>,
+    force: bool,
+    json: bool,
+) -> cb::errors::Result<()> {
+    if let Some(dir) = output.filter(|out| {
+        filter.content_type == Some(ContentType::Image)
+            && (out.is_dir() || out.to_string_lossy().ends_with(std::path::MAIN_SEPARATOR))
+    }) {
+        let storage = open_storage(paths)?;
+        let summary = cb::export::export_images(&storage, filter, dir)?;
+        if json {
+            println!("{}", serde_json::to_string(&summary).unwrap());
+            return Ok(());
+        }
+        print!("Copied {} image(s) to {}", summary.images, dir.display());
+        if summary.missing_images > 0 {
+            print!(" ({} with missing files)", summary.missing_images);
+        }
+        println!(".");
+        return Ok(());
+    }
+    if let Some(out) = output
+        && out.exists()
+        && !force
+    {
+        return Err(cb::errors::CbError::InvalidInput(format!(
+            "{} already exists (use --force to overwrite)",
+            out.display()
+        )));
+    }
+    let storage = open_storage(paths)?;
+    let base = output
+        .and_then(|out| out.parent())
+        .unwrap_or(std::path::Path::new("."));
+    let copy = images.map(|dir| cb::export::ImageCopy { dir, base });
+
+    let Some(out) = output else {
+        let summary = cb::export::export(&storage, filter, format, &mut std::io::stdout(), copy.as_ref())?;
+        if summary.missing_images > 0 {
+            eprintln!("cb: {} image(s) had no file to copy", summary.missing_images);
+        }
+        return Ok(());
+    };
+    let mut file = std::fs::File::create(out)
+        .map_err(|e| cb::errors::CbError::InvalidInput(format!("{}: {}", out.display(), e)))?;
+    let summary = cb::export::export(&storage, filter, format, &mut file, copy.as_ref())?;
+
+    if json {
+        println!("{}", serde_json::to_string(&summary).unwrap());
+        return Ok(());
+    }
+    print!("Exported {} clip(s) to {}", summary.clips, out.display());
+    if let Some(dir) = images {
+        print!(", {} image(s) to {}", summary.images, dir.display());
+    }
+    if summary.missing_images > 0 {
+        print!(" ({} with missing files)", summary.missing_images);
+    }
+    println!(".");
+    Ok(())
+}
+
+fn cmd_verify(paths: &AppPaths, fix: bool, json: bool) -> cb::errors::Result<()> {
+    let storage = open_storage(paths)?;
+    let report = cb::verify::verify(&storage, &paths.images_dir, fix)?;
+    if json {
+        println!("{}", serde_json::to_string(&report).unwrap());
+        return Ok(());
+    }
+
+    for damaged in &report.damaged {
+        let outcome = match damaged.action {
+            cb::verify::Action::Flagged => "file missing".to_string(),
+            cb::verify::Action::Relinked => {
+                format!("re-linked to {}", damaged.relinked_to.as_deref().unwrap_or(""))
+            }
+            cb::verify::Action::Pruned => "moved to the trash".to_string(),
+            cb::verify::Action::Protected => "file missing; protected, left alone".to_string(),
+        };
+        println!("#{}  {}  ({})", damaged.id, outcome, damaged.path);
+    }
+    print!(
+        "Checked {} image clip(s): {} with a missing file",
+        report.checked,
+        report.damaged.len()
+    );
+    if report.healed > 0 {
+        print!(", {} found again", report.healed);
+    }
+    println!(".");
+    if !fix && !report.damaged.is_empty() {
+        println!("Run `cb verify --fix` to re-link or remove them.");
+    }
+    Ok(())
+}
+
+/// Integrity problems printed before asking to repair.
+const REPAIR_PREVIEW: usize = 5;
+
+fn cmd_backup(paths: &AppPaths, action: BackupAction, json: bool) -> cb::errors::Result<()> {
+    match action {
+        BackupAction::Create { path, force } => {
+            let out = path.unwrap_or_else(|| cb::backup::default_path(paths));
+            if out.exists() && !force {
+                return Err(cb::errors::CbError::InvalidInput(format!(
+                    "{} already exists (use --force to overwrite)",
+                    out.display()
+                )));
+            }
+            let summary = cb::backup::create(paths, &out)?;
+            if json {
+                println!("{}", serde_json::to_string(&summary).unwrap());
+            } else {
+                println!(
+                    "Backed up the database and {} image(s) to {} ({}).",
+                    summary.images,
+                    out.display(),
+                    format_bytes(summary.bytes as i64)
+                );
+            }
+        }
+        BackupAction::Restore { path, yes } => {
+            if let Ok(Some(pid)) = daemon::daemon_status(paths) {
+                return Err(cb::errors::CbError::Daemon(format!(
+                    "the daemon (pid {}) is using the database; run `cb daemon stop` first",
+                    pid
+                )));
+            }
+            if paths.db_path.exists() && !yes && !json && std::io::stdin().is_terminal() {
+                println!("This replaces the history of profile \"{}\" with the backup's.", paths.profile);
+                if !confirm("Restore?")? {
+                    println!("Aborted.");
+                    return Ok(());
+                }
+            }
+            let summary = cb::backup::restore(paths, &path)?;
+            if json {
+                println!("{}", serde_json::to_string(&summary).unwrap());
+                return Ok(());
+            }
+            println!("Restored {} clip(s) and {} image(s) from {}.", summary.clips, summary.images, path.display());
+            if let Some(previous) = summary.previous {
+                println!("The previous database was moved to {}.", previous.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_repair(paths: &AppPaths, force: bool, yes: bool, json: bool) -> cb::errors::Result<()> {
+    if !paths.db_path.exists() {
+        return Err(cb::errors::CbError::NotFound(format!(
+            "no database at {}",
+            paths.db_path.display()
+        )));
+    }
+    if let Ok(Some(pid)) = daemon::daemon_status(paths) {
+        return Err(cb::errors::CbError::Daemon(format!(
+            "the daemon (pid {}) is using the database; run `cb daemon stop` first",
+            pid
+        )));
+    }
+
+    let problems = cb::repair::check_file(&paths.db_path).unwrap_or_else(|e| vec![e.to_string()]);
+    if problems.is_empty() && !force {
+        let message = "No corruption found.".to_string();
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string(&StatusResponse {
+                    success: true,
+                    message,
+                    removed: None,
+                })
+                .unwrap()
+            );
+        } else {
+            println!("{}", message);
+        }
+        return Ok(());
+    }
+
+    if !yes && !json && std::io::stdin().is_terminal() {
+        if !problems.is_empty() {
+            println!("Integrity check found {} problem(s):", problems.len());
+            for problem in problems.iter().take(REPAIR_PREVIEW) {
+                println!("  {}", problem);
+            }
+            if problems.len() > REPAIR_PREVIEW {
+                println!("  ... and {} more", problems.len() - REPAIR_PREVIEW);
+            }
+        }
+        if !confirm("Rebuild the database from readable rows?")? {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    let report = cb::repair::repair(&paths.db_path)?;
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({"problems": problems, "tables": report.tables, "corrupt_path": report.corrupt_path})
+        );
+        return Ok(());
+    }
+    for table in &report.tables {
+        println!("{:<9} recovered {:>6}, lost {:>6}", table.table, table.recovered, table.lost);
+    }
+    println!("The original database was moved to {}.", report.corrupt_path.display());
+    Ok(())
+}
+
+fn cmd_init(paths: &AppPaths, encrypt: bool, no_keychain: bool, json: bool) -> cb::errors::Result<()> {
+    let message = if !encrypt {
+        drop(open_storage(paths)?);
+        format!("Database ready at {}.", paths.db_path.display())
+    } else {
+        if !cb::encryption::is_available() {
+            return Err(cb::errors::CbError::Encrypted(
+                "cb was built without the `encryption` feature; rebuild with `--features encryption`".into(),
+            ));
+        }
+        if cb::encryption::is_encrypted(&paths.db_path) {
+            return Err(cb::errors::CbError::Encrypted(format!(
+                "{} is already encrypted",
+                paths.db_path.display()
+            )));
+        }
+        if let Ok(Some(pid)) = daemon::daemon_status(paths) {
+            return Err(cb::errors::CbError::Daemon(format!(
+                "the daemon (pid {}) is using the database; run `cb daemon stop` first",
+                pid
+            )));
+        }
+        let key = match std::env::var(cb::encryption::PASSPHRASE_ENV) {
+            Ok(key) if !key.is_empty() => key,
+            _ if std::io::stdin().is_terminal() => {
+                let key = cb::encryption::read_passphrase("New passphrase: ")?;
+                if cb::encryption::read_passphrase("Repeat it: ")? != key {
+                    return Err(cb::errors::CbError::InvalidInput("the passphrases don't match".into()));
+                }
+                key
+            }
+            _ => {
+                return Err(cb::errors::CbError::InvalidInput(format!(
+                    "set {} or run this in a terminal to choose a passphrase",
+                    cb::encryption::PASSPHRASE_ENV
+                )));
+            }
+        };
+        std::fs::create_dir_all(&paths.data_dir)
+            .map_err(|e| cb::errors::CbError::Encrypted(e.to_string()))?;
+        let report = cb::encryption::encrypt(&paths.db_path, &key)?;
+        let mut message = format!("Encrypted {}.", paths.db_path.display());
+        if !report.encrypted_copies.is_empty() {
+            message.push_str(&format!(" Also encrypted {} older copy(ies) of it.", report.encrypted_copies.len()));
+        }
+        if !report.plain_copies.is_empty() {
+            let names: Vec<String> = report.plain_copies.iter().map(|p| p.display().to_string()).collect();
+            message.push_str(&format!(
+                " These copies couldn't be encrypted and are still readable; delete them if you don't need them: {}.",
+                names.join(", ")
+            ));
+        }
+        if !no_keychain {
+            match cb::encryption::keychain_store(&paths.db_path, &key) {
+                Ok(()) => message.push_str(" The passphrase is in your keychain."),
+                Err(e) => eprintln!(
+                    "cb: couldn't store the passphrase in the keychain ({}); set {} instead",
+                    e,
+                    cb::encryption::PASSPHRASE_ENV
+                ),
+            }
+        }
+        message
+    };
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: true,
+                message,
+                removed: None,
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_current(paths: &AppPaths, json: bool) -> cb::errors::Result<()> {
+    let content = SystemClipboard.read()?;
+    let types = cb::clipboard::pasteboard_types();
+    let workspace = cb::clipboard::active_workspace();
+    let config = Config::load(&paths.config_file)?;
+    let marker = types.as_deref().and_then(cb::clipboard::private_marker);
+    // Only compare against history that exists; don't create a database.
+    let capture = match (&content, marker) {
+        (Some(_), Some(marker)) => Some(format!("skipped: the copying app marked it private ({})", marker)),
+        (Some(content), None) if paths.db_path.exists() => {
+            let storage = SqliteStorage::open_read_only(&paths.db_path)?;
+            Some(daemon::explain_capture(&storage, &config, content)?)
+        }
+        (Some(_), None) => Some("captured as a new clip".to_string()),
+        (None, _) => None,
+    };
+    let preview = content.as_ref().map(|c| match c.text {
+        Some(ref text) => cb::width::truncate(text, 200),
+        None => format!("{}×{} image", c.width.unwrap_or(0), c.height.unwrap_or(0)),
+    });
+
+    if json {
+        let value = serde_json::json!({
+            "content_type": content.as_ref().map(|c| c.content_type.as_str()),
+            "size_bytes": content.as_ref().map(|c| c.size_bytes),
+            "width": content.as_ref().and_then(|c| c.width),
+            "height": content.as_ref().and_then(|c| c.height),
+            "hash": content.as_ref().map(|c| &c.hash),
+            "preview": preview,
+            "capture": capture,
+            "types": types,
+            "workspace": workspace,
+        });
+        println!("{}", value);
+        return Ok(());
+    }
+
+    match content {
+        Some(ref c) => {
+            println!("Type:     {} ({})", c.content_type.as_str(), format_bytes(c.size_bytes));
+            println!("Preview:  {}", preview.unwrap_or_default().replace('\n', "↵"));
+            println!("Capture:  {}", capture.unwrap_or_default());
+        }
+        None => println!("The clipboard has no text or image cb can read."),
+    }
+    if let Some(space) = workspace.space {
+        let display = workspace.display.map(|d| format!(", display {}", d)).unwrap_or_default();
+        println!("Space:    {}{}", space, display);
+    }
+    match types {
+        Some(types) if !types.is_empty() => {
+            println!("Pasteboard types:");
+            let width = types.iter().map(|t| t.name.chars().count()).max().unwrap_or(0);
+            for t in types {
+                let size = t.size.map(format_bytes).unwrap_or_else(|| "-".to_string());
+                println!("  {:<width$}  {}", t.name, size, width = width);
+            }
+        }
+        Some(_) => println!("Pasteboard types: none"),
+        None => {}
+    }
+    Ok(())
+}
+
+fn cmd_doctor(
+    paths: &AppPaths,
+    check_permissions: bool,
+    style: &RowStyle,
+    json: bool,
+) -> cb::errors::Result<()> {
+    use cb::doctor::CheckStatus;
+
+    let checks = if check_permissions {
+        cb::doctor::permission_checks()
+    } else {
+        cb::doctor::run_checks(paths)
+    };
+    let healthy = checks.iter().all(|c| c.status != CheckStatus::Fail);
+    let granted = checks.iter().all(|c| c.status == CheckStatus::Ok);
+
+    if json {
+        println!("{}", serde_json::json!({"healthy": healthy, "checks": checks}));
+    } else {
+        print_checks(&checks, style.labels);
+    }
+    if check_permissions && !granted {
+        if checks
+            .iter()
+            .any(|c| c.name == "accessibility" && c.status != CheckStatus::Ok)
+        {
+            let _ = cb::clipboard::open_accessibility_settings();
+        }
+        return Err(cb::errors::CbError::Clipboard("missing permissions".into()));
+    }
+    Ok(())
+}
+
+fn print_checks(checks: &[cb::doctor::Check], plain: bool) {
+    use cb::doctor::CheckStatus;
+
+    for check in checks {
+        if plain {
+            let status = match check.status {
+                CheckStatus::Ok => "OK",
+                CheckStatus::Warn => "Warning",
+                CheckStatus::Fail => "Failed",
+            };
+            println!("{}: {}: {}", status, check.name, check.detail);
+            if let Some(ref fix) = check.fix {
+                println!("Fix: {}", fix);
+            }
+            continue;
+        }
+        let mark = match check.status {
+            CheckStatus::Ok => "✓",
+            CheckStatus::Warn => "!",
+            CheckStatus::Fail => "✗",
+        };
+        println!("{} {:<14} {}", mark, check.name, check.detail);
+        if let Some(ref fix) = check.fix {
+            println!("  {:<14} → {}", "", fix);
+        }
+    }
+}
+
+fn cmd_serve(
+    paths: &AppPaths,
+    stdio: bool,
+    http: Option<String>,
+    grpc: Option<String>,
+) -> cb::errors::Result<()> {
+    if let Some(addr) = grpc {
+        if !cb::grpc::is_available() {
+            return Err(cb::errors::CbError::InvalidInput(
+                "cb was built without the `grpc` feature".into(),
+            ));
+        }
+        let storage = open_storage(paths)?;
+        let shared = shared_token(paths, &storage)?;
+        match shared {
+            Some(ref token) => println!("Serving gRPC at {} (token {})", addr, token),
+            None => println!("Serving gRPC at {} (token from `cb token create`)", addr),
+        }
+        return cb::grpc::serve_grpc(storage, &addr, shared.as_deref());
+    }
+    if let Some(addr) = http {
+        let storage = SqliteStorage::open_read_only(&paths.db_path)?;
+        let shared = shared_token(paths, &storage)?;
+        // Phones can reach a Mac by its Bonjour name; 0.0.0.0 isn't a URL.
+        let shown = match addr.strip_prefix("0.0.0.0:") {
+            Some(port) => match cb::config::hostname() {
+                Some(host) => format!("{}.local:{}", host, port),
+                None => addr.clone(),
+            },
+            None => addr.clone(),
+        };
+        match shared {
+            Some(ref token) => println!("Serving history at http://{}/?token={}", shown, token),
+            None => println!("Serving history at http://{}/?token=<token from `cb token create`>", shown),
+        }
+        return cb::web::serve_http(paths, &addr, shared.as_deref());
+    }
+    if !stdio {
+        return Err(cb::errors::CbError::InvalidInput(
+            "choose a transport: --stdio, --http, or --grpc".into(),
+        ));
+    }
+    let storage = open_storage(paths)?;
+    let stdin = std::io::stdin();
+    cb::serve::serve_lines(&storage, stdin.lock(), std::io::stdout().lock())
+}
+
+/// The shared token network transports accept besides those from
+/// `cb token create`: `api_token` from config, or else, when there are no
+/// created tokens either, a throwaway one for this run.
+fn shared_token(paths: &AppPaths, storage: &SqliteStorage) -> cb::errors::Result<Option<String>> {
+    let has_tokens = storage.tokens().is_ok_and(|t| !t.is_empty());
+    Ok(match Config::load(&paths.config_file)?.api_token {
+        Some(token) => Some(token),
+        None if has_tokens => None,
+        None => Some(cb::web::random_token()?),
+    })
+}
+
+fn cmd_daemon(paths: &AppPaths, action: DaemonAction, json: bool) -> cb::errors::Result<()> {
+    match action {
+        DaemonAction::Start => {
+            if let Ok(Some(pid)) = daemon::daemon_status(paths) {
+                let msg = format!("Daemon already running (pid {}).", pid);
+                if json {
+                    println!(
+                        "{}",
+                        serde_json::to_string(&StatusResponse {
+                            success: true,
+                            message: msg,
+                            removed: None,
+                        })
+                        .unwrap()
+                    );
+                } else {
+                    println!("{}", msg);
+                }
+                return Ok(());
+            }
+
+            let exe = std::env::current_exe()
+                .map_err(|e| cb::errors::CbError::Daemon(e.to_string()))?;
+
+            std::fs::create_dir_all(&paths.base_dir)
+                .map_err(|e| cb::errors::CbError::Daemon(e.to_string()))?;
+            let log_file = std::fs::File::create(&paths.log_file)
+                .map_err(|e| cb::errors::CbError::Daemon(e.to_string()))?;
+
+            let child = std::process::Command::new(exe)
+                .args(["--profile", &paths.profile, "daemon", "run"])
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::from(log_file))
+                .spawn()
+                .map_err(|e| cb::errors::CbError::Daemon(e.to_string()))?;
+
+            let msg = format!("Started clipboard watcher (pid {}).", child.id());
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string(&StatusResponse {
+                        success: true,
+                        message: msg,
+                        removed: None,
+                    })
+                    .unwrap()
+                );
+            } else {
+                println!("{}", msg);
+            }
+            Ok(())
+        }
+        DaemonAction::Stop => {
+            let stopped = daemon::stop_daemon(paths)?;
+            let msg = if stopped {
+                "Stopped clipboard watcher."
+            } else {
+                "Daemon is not running."
+            };
+            if json {
+                println!(
+                    "{}",
+                    serde_json::to_string(&StatusResponse {
+                        success: stopped,
+                        message: msg.into(),
+                        removed: None,
+                    })
+                    .unwrap()
+                );
+            } else {
+                println!("{}", msg);
+            }
+            Ok(())
+        }
+        DaemonAction::Status => {
+            use cb::resources::ResourceUsage;
+
+            let pid = daemon::daemon_status(paths)?;
+            let usage = pid.and_then(|pid| ResourceUsage::for_pid(&paths.resource_stats_file, pid));
+            if json {
+                let mut value = serde_json::json!({
+                    "running": pid.is_some(),
+                    "pid": pid,
+                });
+                if let Some(usage) = usage {
+                    value["resources"] = serde_json::json!({
+                        "since": usage.since,
+                        "updated_at": usage.updated_at,
+                        "cpu_ms": usage.cpu_ms,
+                        "wakeups": usage.wakeups,
+                        "cpu_percent": usage.cpu_percent(),
+                        "wakeups_per_minute": usage.wakeups_per_minute(),
+                    });
+                }
+                println!("{}", value);
+            } else {
+                match (pid, usage) {
+                    (Some(pid), Some(usage)) => println!(
+                        "Daemon running (pid {}): {:.2}% CPU, {:.0} wakeups/min on average since {}.",
+                        pid,
+                        usage.cpu_percent(),
+                        usage.wakeups_per_minute(),
+                        usage.since.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+                    ),
+                    (Some(pid), None) => println!("Daemon running (pid {}).", pid),
+                    (None, _) => println!("Daemon is not running."),
+                }
+            }
+            Ok(())
+        }
+        DaemonAction::Run => {
+            daemon::run_watcher(paths)
+        }
+    }
+}
+
+fn cmd_hotkey(paths: &AppPaths, action: HotkeyAction, json: bool) -> cb::errors::Result<()> {
+    use cb::hotkey::{Shortcut, Tool};
+
+    let home = dirs::home_dir()
+        .ok_or_else(|| cb::errors::CbError::Config("could not determine home directory".into()))?;
+    let tool = |with: Option<String>| match with {
+        Some(name) => Tool::parse(&name),
+        None => Tool::detect(&home).ok_or_else(|| {
+            cb::errors::CbError::Config(
+                "neither Hammerspoon (~/.hammerspoon) nor skhd (~/.skhdrc) is set up; install one, or pass --with"
+                    .into(),
+            )
+        }),
+    };
+    let message = match action {
+        HotkeyAction::Install { key, with } => {
+            let tool = tool(with)?;
+            let shortcut = Shortcut::parse(&key)?;
+            let exe = std::env::current_exe().map_err(|e| cb::errors::CbError::Config(e.to_string()))?;
+            let file = cb::hotkey::install(tool, &shortcut, &paths.base_dir, &home, &exe)?;
+            let reload = match tool {
+                Tool::Hammerspoon => "reload the Hammerspoon config",
+                Tool::Skhd => "run `skhd --reload`",
+            };
+            format!(
+                "Bound {} to `cb tui --quick` in {}; {} to use it. Pasting needs Accessibility permission for Terminal.",
+                key,
+                file.display(),
+                reload
+            )
+        }
+        HotkeyAction::Uninstall { with } => {
+            let tool = tool(with)?;
+            if !cb::hotkey::uninstall(tool, &paths.base_dir, &home)? {
+                return Err(cb::errors::CbError::NotFound(format!("cb hotkey in {} config", tool.name())));
+            }
+            format!("Removed the hotkey from {} config.", tool.name())
+        }
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&StatusResponse {
+                success: true,
+                message,
+                removed: None,
+            })
+            .unwrap()
+        );
+    } else {
+        println!("{}", message);
+    }
+    Ok(())
+}
+
+fn cmd_profile(paths: &AppPaths, action: ProfileAction, json: bool) -> cb::errors::Result<()> {
+    let base = &paths.base_dir;
+    let message = match action {
+        ProfileAction::Create { name } => {
+            let created = cb::profile::create(base, &name)?;
+            format!("Created profile \"{}\" in {}.", name, created.data_dir.display())
+        }
+        ProfileAction::Switch { name } => {
+            cb::profile::set_active(base, &name)?;
+            let mut message = format!("Switched to profile \"{}\".", name);
+            if let Ok(Some(_)) = daemon::daemon_status(paths) {
+                message.push_str(" Restart the daemon to capture into it.");
+            }
+            message
+        }
+        ProfileAction::List => {
+            let active = cb::profile::active(base);
+            let profiles = cb::profile::list(base);
+            if json {
+                let out: Vec<_> = profiles
+                    .iter()
+                    .map(|name| serde_json::json!({"name": name, "active": *name == active}))
+                    .collect();
+                println!("{}", serde_json::to_string(&out).unwrap());
+            } else {
+                for name in profiles {
+                    let mark = if name == active { "*" } else { " " };
+                    println!("{} {}", mark, name);
+                }
+            }
+            return Ok(());
+        }
+    };
 
     if json {
         println!(
@@ -307,20 +3656,76 @@ fn cmd_copy(paths: &AppPaths, id: i64, json: bool) -> cb::errors::Result<()> {
     Ok(())
 }
 
-fn cmd_delete(paths: &AppPaths, id: i64, json: bool) -> cb::errors::Result<()> {
+fn cmd_slot(paths: &AppPaths, action: SlotAction, json: bool) -> cb::errors::Result<()> {
     let storage = open_storage(paths)?;
-    let found = storage.delete(id)?;
-    let message = if found {
-        format!("Deleted clip #{}.", id)
-    } else {
-        format!("Clip #{} not found.", id)
+    let message = match action {
+        SlotAction::Set { name, text } => {
+            let text = match text {
+                Some(text) => text,
+                None if !std::io::stdin().is_terminal() => {
+                    let mut input = String::new();
+                    std::io::stdin()
+                        .read_to_string(&mut input)
+                        .map_err(|e| cb::errors::CbError::InvalidInput(e.to_string()))?;
+                    input.strip_suffix('\n').unwrap_or(&input).to_string()
+                }
+                None => SystemClipboard.read()?.and_then(|c| c.text).ok_or_else(|| {
+                    cb::errors::CbError::InvalidInput("the clipboard holds no text".into())
+                })?,
+            };
+            if text.is_empty() {
+                return Err(cb::errors::CbError::InvalidInput("nothing to store".into()));
+            }
+            let slot = storage.set_slot(&name, &text)?;
+            format!("Set slot \"{}\" ({}).", slot.name, format_bytes(slot.text.len() as i64))
+        }
+        SlotAction::Get { name } => {
+            let slot = storage.get_slot(&name)?;
+            if json {
+                println!("{}", serde_json::to_string(&slot).unwrap());
+            } else {
+                println!("{}", slot.text);
+            }
+            return Ok(());
+        }
+        SlotAction::Copy { name } => {
+            let slot = storage.get_slot(&name)?;
+            let config = Config::load(&paths.config_file)?;
+            let text = match config.on_copy {
+                Some(ref hook) => hook.apply(&slot.text)?,
+                None => slot.text,
+            };
+            SystemClipboard.set_text(&text)?;
+            format!("Copied slot \"{}\" to clipboard.", name)
+        }
+        SlotAction::List => {
+            let slots = storage.slots()?;
+            if json {
+                println!("{}", serde_json::to_string(&slots).unwrap());
+            } else if slots.is_empty() {
+                println!("No slots. Set one with `cb slot set <name> <text>`.");
+            } else {
+                let width = slots.iter().map(|s| cb::width::of(&s.name)).max().unwrap_or(0);
+                for slot in slots {
+                    let preview = slot.text.replace('\n', "\\n");
+                    println!("{}  {}", cb::width::pad_right(&slot.name, width), cb::width::truncate(&preview, 60));
+                }
+            }
+            return Ok(());
+        }
+        SlotAction::Rm { name } => {
+            if !storage.delete_slot(&name)? {
+                return Err(cb::errors::CbError::NotFound(format!("slot \"{}\"", name)));
+            }
+            format!("Removed slot \"{}\".", name)
+        }
     };
 
     if json {
         println!(
             "{}",
             serde_json::to_string(&StatusResponse {
-                success: found,
+                success: true,
                 message,
                 removed: None,
             })
@@ -332,13 +3737,65 @@ fn cmd_delete(paths: &AppPaths, id: i64, json: bool) -> cb::errors::Result<()> {
     Ok(())
 }
 
-fn cmd_pin(paths: &AppPaths, id: i64, pinned: bool, json: bool) -> cb::errors::Result<()> {
+fn cmd_session(paths: &AppPaths, action: SessionAction, json: bool) -> cb::errors::Result<()> {
     let storage = open_storage(paths)?;
-    storage.set_pinned(id, pinned)?;
-    let message = if pinned {
-        format!("Pinned clip #{}.", id)
-    } else {
-        format!("Unpinned clip #{}.", id)
+    let message = match action {
+        SessionAction::Start { name } => {
+            storage.start_session(&name)?;
+            let mut message = format!("Started session \"{}\"; new clips are tagged {}.", name, name);
+            if !matches!(daemon::daemon_status(paths), Ok(Some(_))) {
+                message += " The daemon isn't running, so nothing will be captured until `cb daemon start`.";
+            }
+            message
+        }
+        SessionAction::Stop { name } => {
+            if let (Some(name), Some(active)) = (&name, storage.active_session()?)
+                && active.name != *name
+            {
+                return Err(cb::errors::CbError::InvalidInput(format!(
+                    "the running session is \"{}\", not \"{}\"",
+                    active.name, name
+                )));
+            }
+            let session = storage.stop_session()?;
+            let count = storage.count_matching(&ClipFilter {
+                tag: Some(session.name.clone()),
+                ..Default::default()
+            })?;
+            format!(
+                "Stopped session \"{}\" ({} clip(s)). List them with `cb list --session {}`.",
+                session.name, count, session.name
+            )
+        }
+        SessionAction::List => {
+            let sessions = storage.sessions()?;
+            if json {
+                println!("{}", serde_json::to_string(&sessions).unwrap());
+            } else if sessions.is_empty() {
+                println!("No sessions. Start one with `cb session start <name>`.");
+            } else {
+                let width = sessions.iter().map(|s| s.name.chars().count()).max().unwrap_or(0);
+                let local = |t: chrono::DateTime<chrono::Utc>| {
+                    t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()
+                };
+                for session in sessions {
+                    let count = storage.count_matching(&ClipFilter {
+                        tag: Some(session.name.clone()),
+                        ..Default::default()
+                    })?;
+                    let end = session.ended_at.map_or("running".to_string(), local);
+                    println!(
+                        "{:<width$}  {} → {:<16}  {} clip(s)",
+                        session.name,
+                        local(session.started_at),
+                        end,
+                        count,
+                        width = width
+                    );
+                }
+            }
+            return Ok(());
+        }
     };
 
     if json {
@@ -357,14 +3814,49 @@ fn cmd_pin(paths: &AppPaths, id: i64, pinned: bool, json: bool) -> cb::errors::R
     Ok(())
 }
 
-fn cmd_tag(paths: &AppPaths, id: i64, tag: &str, remove: bool, json: bool) -> cb::errors::Result<()> {
+fn cmd_token(paths: &AppPaths, action: TokenAction, json: bool) -> cb::errors::Result<()> {
     let storage = open_storage(paths)?;
-    let message = if remove {
-        storage.remove_tag(id, tag)?;
-        format!("Removed tag \"{}\" from clip #{}.", tag, id)
-    } else {
-        storage.add_tag(id, tag)?;
-        format!("Added tag \"{}\" to clip #{}.", tag, id)
+    let message = match action {
+        TokenAction::Create { name, scope } => {
+            let scope = TokenScope::parse(&scope).unwrap_or_default();
+            let secret = format!("cb_{}", cb::web::random_token()?);
+            let token = storage.create_token(&name, &hash_content(secret.as_bytes()), scope)?;
+            if json {
+                let mut value = serde_json::to_value(&token).unwrap();
+                value["token"] = secret.into();
+                println!("{}", value);
+            } else {
+                println!("Created {} token \"{}\". It won't be shown again:", scope.as_str(), name);
+                println!("{}", secret);
+            }
+            return Ok(());
+        }
+        TokenAction::Revoke { name } => {
+            if !storage.revoke_token(&name)? {
+                return Err(cb::errors::CbError::NotFound(format!("token \"{}\"", name)));
+            }
+            format!("Revoked token \"{}\".", name)
+        }
+        TokenAction::List => {
+            let tokens = storage.tokens()?;
+            if json {
+                println!("{}", serde_json::to_string(&tokens).unwrap());
+            } else if tokens.is_empty() {
+                println!("No tokens. Create one with `cb token create <name>`.");
+            } else {
+                let width = tokens.iter().map(|t| t.name.chars().count()).max().unwrap_or(0);
+                for token in tokens {
+                    println!(
+                        "{:<width$}  {:<10}  created {}",
+                        token.name,
+                        token.scope.as_str(),
+                        token.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                        width = width
+                    );
+                }
+            }
+            return Ok(());
+        }
     };
 
     if json {
@@ -383,251 +3875,337 @@ fn cmd_tag(paths: &AppPaths, id: i64, tag: &str, remove: bool, json: bool) -> cb
     Ok(())
 }
 
-fn cmd_clear(paths: &AppPaths, days: i64, json: bool) -> cb::errors::Result<()> {
+fn cmd_jobs(paths: &AppPaths, action: JobsAction, json: bool) -> cb::errors::Result<()> {
     let storage = open_storage(paths)?;
-    let cutoff = Utc::now() - Duration::days(days);
-    let removed = storage.clear_older_than(cutoff)?;
-
-    if json {
-        println!(
-            "{}",
-            serde_json::to_string(&StatusResponse {
-                success: true,
-                message: format!("Removed {} clip(s) older than {} days.", removed, days),
-                removed: Some(removed),
-            })
-            .unwrap()
-        );
-    } else {
-        println!("Removed {} clip(s) older than {} days.", removed, days);
+    match action {
+        JobsAction::Status => {
+            let counts = storage.job_counts()?;
+            if json {
+                println!("{}", serde_json::to_string(&counts).unwrap());
+                return Ok(());
+            }
+            let workable = daemon::workable_jobs();
+            for c in &counts {
+                let note = if !workable.iter().any(|k| k.as_str() == c.kind) && c.queued > 0 {
+                    "  (waiting: built without OCR)"
+                } else {
+                    ""
+                };
+                println!(
+                    "{:<10} {:>5} queued  {:>2} running  {:>4} failed{}",
+                    c.kind, c.queued, c.running, c.failed, note
+                );
+                if let Some(ref error) = c.last_error {
+                    println!("           last error: {}", error);
+                }
+            }
+            if daemon::daemon_status(paths).ok().flatten().is_none() && counts.iter().any(|c| c.queued > 0) {
+                println!("The daemon isn't running; start it with `cb daemon start` to work through the queue.");
+            }
+        }
     }
     Ok(())
 }
 
-fn cmd_stats(paths: &AppPaths, json: bool) -> cb::errors::Result<()> {
+fn cmd_enrich(paths: &AppPaths, only: Option<&str>, json: bool) -> cb::errors::Result<()> {
+    let config = Config::load(&paths.config_file)?;
     let storage = open_storage(paths)?;
-    let stats = storage.stats()?;
-
-    if json {
-        let daemon_pid = daemon::daemon_status(paths).ok().flatten();
-        let mut obj = serde_json::to_value(&stats).unwrap();
-        let m = obj.as_object_mut().unwrap();
-        m.insert("daemon_running".into(), serde_json::json!(daemon_pid.is_some()));
-        m.insert("daemon_pid".into(), serde_json::json!(daemon_pid));
-        println!("{}", serde_json::to_string(&obj).unwrap());
-        return Ok(());
-    }
+    let workable = daemon::workable_jobs();
+    let kinds: Vec<JobKind> = match only.and_then(JobKind::parse) {
+        Some(kind) if !workable.contains(&kind) => {
+            return Err(cb::errors::CbError::InvalidInput(format!(
+                "{} isn't available in this build",
+                kind.as_str()
+            )));
+        }
+        Some(kind) => vec![kind],
+        // Titles mean a request per URL, so they're only fetched by default
+        // when the daemon would fetch them too.
+        None => workable
+            .into_iter()
+            .filter(|kind| *kind != JobKind::Title || config.fetch_link_titles)
+            .collect(),
+    };
 
-    println!("Clipboard Statistics");
-    println!("────────────────────");
-    println!("Total clips:  {}", stats.total_clips);
-    println!("  Text:       {}", stats.text_clips);
-    println!("  Image:      {}", stats.image_clips);
-    println!("  File refs:  {}", stats.fileref_clips);
-    println!("Total size:   {}", format_bytes(stats.total_size));
-    if let Some(oldest) = stats.oldest {
-        println!("Oldest:       {}", oldest.format("%Y-%m-%d %H:%M"));
+    for kind in &kinds {
+        daemon::queue_backfill(&storage, *kind)?;
     }
-    if let Some(newest) = stats.newest {
-        println!("Newest:       {}", newest.format("%Y-%m-%d %H:%M"));
+    // Jobs queued before (by the daemon or an interrupted run) count too.
+    let show_progress = !json && std::io::stderr().is_terminal();
+    let mut total = None;
+    let left = daemon::drain_jobs(paths, &storage, &kinds, config.enrich_workers(), |left| {
+        let total = *total.get_or_insert(left);
+        if show_progress && total > 0 {
+            eprint!("\rEnriching: {} of {} job(s) done ", total - left, total);
+            let _ = std::io::stderr().flush();
+        }
+    })?;
+    let total = total.unwrap_or(0);
+    if show_progress && total > 0 {
+        eprintln!();
     }
 
-    if let Ok(Some(pid)) = daemon::daemon_status(paths) {
-        println!("Daemon:       running (pid {})", pid);
+    let failed: i64 = storage
+        .job_counts()?
+        .iter()
+        .filter(|c| kinds.iter().any(|k| k.as_str() == c.kind))
+        .map(|c| c.failed)
+        .sum();
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({
+                "kinds": kinds.iter().map(|k| k.as_str()).collect::<Vec<_>>(),
+                "queued": total,
+                "remaining": left,
+                "failed": failed,
+            })
+        );
+    } else if total == 0 {
+        println!("Nothing to enrich.");
+    } else if left > 0 {
+        println!(
+            "Stopped with {} of {} job(s) left; run `cb enrich --all` again to resume.",
+            left, total
+        );
     } else {
-        println!("Daemon:       not running");
+        println!("Finished {} job(s).", total);
+    }
+    if !json && failed > 0 {
+        println!("{} job(s) failed; see `cb jobs status`.", failed);
     }
-
     Ok(())
 }
 
-fn cmd_daemon(paths: &AppPaths, action: DaemonAction, json: bool) -> cb::errors::Result<()> {
+fn cmd_rules(
+    paths: &AppPaths,
+    action: RulesAction,
+    style: &RowStyle,
+    json: bool,
+) -> cb::errors::Result<()> {
+    let config = Config::load(&paths.config_file)?;
+    let rules = cb::rules::RuleSet::compile(&config.rules)?;
+
     match action {
-        DaemonAction::Start => {
-            if let Ok(Some(pid)) = daemon::daemon_status(paths) {
-                let msg = format!("Daemon already running (pid {}).", pid);
-                if json {
-                    println!(
-                        "{}",
-                        serde_json::to_string(&StatusResponse {
-                            success: true,
-                            message: msg,
-                            removed: None,
-                        })
-                        .unwrap()
-                    );
-                } else {
-                    println!("{}", msg);
-                }
+        RulesAction::List => {
+            if json {
+                println!("{}", serde_json::to_string(&config.rules).unwrap());
                 return Ok(());
             }
-
-            let exe = std::env::current_exe()
-                .map_err(|e| cb::errors::CbError::Daemon(e.to_string()))?;
-
-            std::fs::create_dir_all(&paths.base_dir)
-                .map_err(|e| cb::errors::CbError::Daemon(e.to_string()))?;
-            let log_file = std::fs::File::create(&paths.log_file)
-                .map_err(|e| cb::errors::CbError::Daemon(e.to_string()))?;
-
-            let child = std::process::Command::new(exe)
-                .args(["daemon", "run"])
-                .stdin(std::process::Stdio::null())
-                .stdout(std::process::Stdio::null())
-                .stderr(std::process::Stdio::from(log_file))
-                .spawn()
-                .map_err(|e| cb::errors::CbError::Daemon(e.to_string()))?;
-
-            let msg = format!("Started clipboard watcher (pid {}).", child.id());
-            if json {
-                println!(
-                    "{}",
-                    serde_json::to_string(&StatusResponse {
-                        success: true,
-                        message: msg,
-                        removed: None,
-                    })
-                    .unwrap()
-                );
-            } else {
-                println!("{}", msg);
+            if rules.is_empty() {
+                println!("No rules configured in {}.", paths.config_file.display());
+                return Ok(());
             }
-            Ok(())
-        }
-        DaemonAction::Stop => {
-            let stopped = daemon::stop_daemon(paths)?;
-            let msg = if stopped {
-                "Stopped clipboard watcher."
-            } else {
-                "Daemon is not running."
-            };
-            if json {
-                println!(
-                    "{}",
-                    serde_json::to_string(&StatusResponse {
-                        success: stopped,
-                        message: msg.into(),
-                        removed: None,
-                    })
-                    .unwrap()
-                );
-            } else {
-                println!("{}", msg);
+            for (idx, rule) in config.rules.iter().enumerate() {
+                let mut conditions = Vec::new();
+                if let Some(ref p) = rule.pattern {
+                    conditions.push(format!("pattern /{}/", p));
+                }
+                if let Some(ref app) = rule.source_app {
+                    conditions.push(format!("source_app {}", app));
+                }
+                println!("{:>3}. {} → {}", idx + 1, conditions.join(" and "), rule.tag);
             }
             Ok(())
         }
-        DaemonAction::Status => {
+        RulesAction::Test { limit } => {
+            let storage = open_storage(paths)?;
+            let clips = storage.list(ClipFilter {
+                limit,
+                ..Default::default()
+            })?;
+            let matches: Vec<_> = clips
+                .iter()
+                .map(|clip| (clip, rules.tags_for(clip.text_content.as_deref(), None)))
+                .filter(|(_, tags)| !tags.is_empty())
+                .collect();
+
             if json {
-                let pid = daemon::daemon_status(paths)?;
-                println!(
-                    "{}",
-                    serde_json::json!({
-                        "running": pid.is_some(),
-                        "pid": pid,
-                    })
-                );
-            } else {
-                match daemon::daemon_status(paths)? {
-                    Some(pid) => println!("Daemon running (pid {}).", pid),
-                    None => println!("Daemon is not running."),
-                }
+                let out: Vec<_> = matches
+                    .iter()
+                    .map(|(clip, tags)| serde_json::json!({"id": clip.id, "tags": tags}))
+                    .collect();
+                println!("{}", serde_json::to_string(&out).unwrap());
+                return Ok(());
+            }
+
+            if matches.is_empty() {
+                println!("No rules matched the last {} clip(s).", clips.len());
+                return Ok(());
+            }
+            for (clip, tags) in &matches {
+                println!("{}", style.row(clip, 4));
+                println!("       → {}", tags.join(", "));
             }
             Ok(())
         }
-        DaemonAction::Run => {
-            daemon::run_watcher(paths)
-        }
     }
 }
 
-fn print_clip_row(clip: &cb::storage::models::Clip) {
-    let type_icon = match clip.content_type {
-        ContentType::Text => "T",
-        ContentType::Image => "I",
-        ContentType::FileRef => "F",
-    };
+fn cmd_links(paths: &AppPaths, action: LinksAction, json: bool) -> cb::errors::Result<()> {
+    let LinksAction::Check {
+        tag,
+        limit,
+        concurrency,
+    } = action;
 
-    let pin = if clip.pinned { "*" } else { " " };
+    let storage = open_storage(paths)?;
+    let clips = storage.list(ClipFilter {
+        content_type: Some(ContentType::Text),
+        tag,
+        limit,
+        ..Default::default()
+    })?;
+    let targets: Vec<(i64, String)> = clips
+        .iter()
+        .filter_map(|c| {
+            let url = c.text_content.as_deref().and_then(cb::links::extract_url)?;
+            Some((c.id, url.to_string()))
+        })
+        .collect();
 
-    let preview = match clip.content_type {
-        ContentType::Text => {
-            let text = clip.text_content.as_deref().unwrap_or("");
-            let oneline = text.replace('\n', "\\n");
-            if oneline.len() > 60 {
-                format!("{}...", &oneline[..57])
-            } else {
-                oneline
-            }
-        }
-        ContentType::Image => {
-            format!("{}x{} image",
-                clip.image_width.unwrap_or(0),
-                clip.image_height.unwrap_or(0))
+    let results = cb::links::check_all(&targets, concurrency);
+    for r in &results {
+        storage.set_link_status(r.clip_id, r.status, r.redirect.as_deref())?;
+    }
+
+    if json {
+        println!("{}", serde_json::to_string(&results).unwrap());
+        return Ok(());
+    }
+
+    if results.is_empty() {
+        println!("No URL clips found.");
+        return Ok(());
+    }
+
+    let mut dead = 0;
+    for r in &results {
+        let status = if r.status == cb::links::STATUS_UNREACHABLE {
+            "ERR".to_string()
+        } else {
+            r.status.to_string()
+        };
+        let flag = if cb::links::is_dead(r.status) {
+            dead += 1;
+            "✗"
+        } else {
+            " "
+        };
+        let target = r
+            .redirect
+            .as_deref()
+            .map(|t| format!(" → {}", t))
+            .unwrap_or_default();
+        println!("{:>4} {} {:>3}  {}{}", r.clip_id, flag, status, r.url, target);
+    }
+    println!("Checked {} link(s), {} dead.", results.len(), dead);
+    Ok(())
+}
+
+/// Print a clip's fields and content. Plain mode (`--plain`) drops the
+/// alignment and rules, naming each section instead.
+fn print_clip_detail(clip: &cb::storage::models::Clip, plain: bool) {
+    let field = |label: &str, value: &dyn std::fmt::Display| {
+        if plain {
+            println!("{} {}", label, value);
+        } else {
+            println!("{:<9}{}", label, value);
         }
-        ContentType::FileRef => {
-            clip.image_path.as_deref().unwrap_or("file").to_string()
+    };
+    let section = |label: &str| {
+        if plain {
+            println!("{}", label);
+        } else {
+            println!("─────────────────────────");
         }
     };
 
-    let age = format_age(clip.updated_at);
-    let tags = if clip.tags.is_empty() {
-        String::new()
+    field("ID:", &clip.id);
+    field("Type:", &clip.content_type.as_str());
+    if let Some(ref lang) = clip.lang {
+        field("Lang:", lang);
+    }
+    if let Some(ref origin) = clip.origin {
+        field("Origin:", origin);
+    }
+    if let Some(ref app) = clip.app_label() {
+        field("App:", app);
+    }
+    if let Some(ref url) = clip.source_url {
+        match clip.source_title {
+            Some(ref title) => field("Source:", &format!("{} ({})", title, url)),
+            None => field("Source:", url),
+        }
+    }
+    if let Some(ref title) = clip.link_title {
+        field("Title:", title);
+    }
+    let pin_ends = clip
+        .pinned_until
+        .map(|until| tf(" (until {0})", &[&until.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")]))
+        .unwrap_or_default();
+    if plain {
+        field("Pinned:", &format!("{}{}", cb::render::yes_no(clip.pinned), pin_ends));
     } else {
-        format!(" [{}]", clip.tags.join(", "))
-    };
-
-    println!("{:>4} {}{} {:>6}  {}{}", clip.id, type_icon, pin, age, preview, tags);
-}
-
-fn print_clip_detail(clip: &cb::storage::models::Clip) {
-    println!("ID:      {}", clip.id);
-    println!("Type:    {}", clip.content_type.as_str());
-    println!("Pinned:  {}", clip.pinned);
-    println!("Created: {}", clip.created_at.format("%Y-%m-%d %H:%M:%S"));
-    println!("Updated: {}", clip.updated_at.format("%Y-%m-%d %H:%M:%S"));
-    println!("Hash:    {}", &clip.hash[..16]);
-    println!("Size:    {}", format_bytes(clip.size_bytes));
+        field("Pinned:", &format!("{}{}", clip.pinned, pin_ends));
+    }
+    field("Created:", &clip.created_at.format("%Y-%m-%d %H:%M:%S"));
+    field("Updated:", &clip.updated_at.format("%Y-%m-%d %H:%M:%S"));
+    field("Hash:", &&clip.hash[..16]);
+    if let Some(ref key) = clip.external_id {
+        field("Ext ID:", key);
+    }
+    field("Size:", &format_bytes(clip.size_bytes));
 
     if !clip.tags.is_empty() {
-        println!("Tags:    {}", clip.tags.join(", "));
+        field("Tags:", &clip.tags.join(", "));
+    }
+    if !clip.meta.is_empty() {
+        let pairs: Vec<String> = clip.meta.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        field("Meta:", &pairs.join(", "));
+    }
+    if let Some(status) = clip.link_status {
+        let checked = clip
+            .link_checked_at
+            .map(|t| format!(" (checked {})", t.format("%Y-%m-%d %H:%M")))
+            .unwrap_or_default();
+        field("Link:", &format!("{}{}", status, checked));
+        if let Some(ref target) = clip.link_redirect {
+            field("Moved:", target);
+        }
     }
 
     match clip.content_type {
         ContentType::Text => {
-            println!("─────────────────────────");
-            println!("{}", clip.text_content.as_deref().unwrap_or(""));
+            let text = clip.text_content.as_deref().unwrap_or("");
+            let m = cb::metrics::TextMetrics::of(text);
+            field("Counts:", &format!("{} words, {} chars, {} lines", m.words, m.chars, m.lines));
+            section("Content:");
+            match cb::table::parse(text).filter(|_| clip.lang.as_deref() == Some("table")) {
+                Some(table) => println!("{}", table.aligned().join("\n")),
+                None => println!("{}", text),
+            }
         }
         ContentType::Image => {
-            println!("Path:    {}", clip.image_path.as_deref().unwrap_or("unknown"));
-            println!("Size:    {}x{}",
-                clip.image_width.unwrap_or(0),
-                clip.image_height.unwrap_or(0));
+            field("Path:", &clip.image_path.as_deref().unwrap_or("unknown"));
+            if cb::verify::file_missing(clip) {
+                field("File:", &"missing (`cb verify --fix` re-links or removes it)");
+            }
+            if let Some(ref original) = clip.original_path {
+                field("Saved:", original);
+            }
+            field(
+                "Size:",
+                &format!("{}x{}", clip.image_width.unwrap_or(0), clip.image_height.unwrap_or(0)),
+            );
+            if let Some(text) = clip.ocr_text.as_deref().filter(|t| !t.is_empty()) {
+                section("OCR text:");
+                println!("{}", text);
+            }
         }
         ContentType::FileRef => {
-            println!("Path:    {}", clip.text_content.as_deref().unwrap_or("unknown"));
+            field("Path:", &clip.text_content.as_deref().unwrap_or("unknown"));
         }
     }
 }
-
-fn format_age(dt: chrono::DateTime<Utc>) -> String {
-    let dur = Utc::now() - dt;
-    if dur.num_seconds() < 60 {
-        "now".to_string()
-    } else if dur.num_minutes() < 60 {
-        format!("{}m", dur.num_minutes())
-    } else if dur.num_hours() < 24 {
-        format!("{}h", dur.num_hours())
-    } else {
-        format!("{}d", dur.num_days())
-    }
-}
-
-fn format_bytes(bytes: i64) -> String {
-    if bytes < 1024 {
-        format!("{} B", bytes)
-    } else if bytes < 1024 * 1024 {
-        format!("{:.1} KB", bytes as f64 / 1024.0)
-    } else {
-        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
-    }
-}
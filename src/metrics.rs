@@ -0,0 +1,121 @@
+use std::ops::AddAssign;
+
+use serde::Serialize;
+
+use crate::errors::Result;
+use crate::storage::ClipStorage;
+use crate::storage::models::{ClipFilter, ContentType};
+
+const PAGE_SIZE: i64 = 500;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct TextMetrics {
+    pub words: usize,
+    pub chars: usize,
+    pub lines: usize,
+}
+
+impl TextMetrics {
+    pub fn of(text: &str) -> Self {
+        Self {
+            words: text.split_whitespace().count(),
+            chars: text.chars().count(),
+            lines: text.lines().count(),
+        }
+    }
+}
+
+impl AddAssign for TextMetrics {
+    fn add_assign(&mut self, other: Self) {
+        self.words += other.words;
+        self.chars += other.chars;
+        self.lines += other.lines;
+    }
+}
+
+/// Sum metrics over every text clip matching `filter`, paging through results
+/// so large histories aren't loaded at once. `limit`/`offset` are ignored.
+pub fn aggregate<S: ClipStorage>(storage: &S, filter: &ClipFilter) -> Result<(usize, TextMetrics)> {
+    let mut total = TextMetrics::default();
+    let mut clips = 0;
+    let mut offset = 0;
+    loop {
+        let page = storage.list(ClipFilter {
+            content_type: Some(ContentType::Text),
+            pinned: filter.pinned,
+            tag: filter.tag.clone(),
+            extra_tags: filter.extra_tags.clone(),
+            lang: filter.lang.clone(),
+            origin: filter.origin.clone(),
+            app: filter.app.clone(),
+            before: filter.before,
+            after: filter.after,
+            query: filter.query.clone(),
+            protected: filter.protected,
+            url: filter.url,
+            as_of: filter.as_of,
+            tone: filter.tone,
+            oldest_first: filter.oldest_first,
+            cursor: filter.cursor,
+            trashed: filter.trashed,
+            meta: filter.meta.clone(),
+            space: filter.space,
+            display: filter.display,
+            limit: PAGE_SIZE,
+            offset,
+        })?;
+        for clip in &page {
+            if let Some(ref text) = clip.text_content {
+                total += TextMetrics::of(text);
+                clips += 1;
+            }
+        }
+        if (page.len() as i64) < PAGE_SIZE {
+            break;
+        }
+        offset += PAGE_SIZE;
+    }
+    Ok((clips, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::hash_content;
+    use crate::storage::models::NewClip;
+    use crate::storage::sqlite::SqliteStorage;
+
+    #[test]
+    fn test_metrics_of() {
+        let m = TextMetrics::of("héllo wörld\nsecond  line\n");
+        assert_eq!(m, TextMetrics { words: 4, chars: 25, lines: 2 });
+        assert_eq!(TextMetrics::of(""), TextMetrics::default());
+    }
+
+    #[test]
+    fn test_aggregate_respects_filter() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        for (i, text) in ["one two", "three\nfour five", "six"].iter().enumerate() {
+            let clip = storage
+                .insert(NewClip {
+                    text_content: Some(text.to_string()),
+                    hash: hash_content(text.as_bytes()),
+                    size_bytes: text.len() as i64,
+                    ..Default::default()
+                })
+                .unwrap();
+            if i < 2 {
+                storage.add_tag(clip.id, "draft").unwrap();
+            }
+        }
+
+        let (count, all) = aggregate(&storage, &ClipFilter::default()).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(all.words, 6);
+
+        let filter = ClipFilter { tag: Some("draft".into()), ..Default::default() };
+        let (count, drafts) = aggregate(&storage, &filter).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(drafts, TextMetrics { words: 5, chars: 22, lines: 3 });
+    }
+}
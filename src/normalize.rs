@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+use crate::clipboard::ClipboardContent;
+use crate::hash::hash_content;
+use crate::links;
+use crate::storage::models::ContentType;
+
+/// Query parameters dropped by `strip_tracking_params`, besides any
+/// starting with `utm_`.
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "msclkid", "mc_cid", "mc_eid", "igshid"];
+
+/// Cleanup applied to copied text before it is hashed, so copies that
+/// differ only in line endings or tracking parameters are duplicates.
+/// Everything is off by default.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Normalize {
+    /// Remove spaces and tabs at the end of each line, and trailing blank
+    /// lines.
+    pub trim_trailing_whitespace: bool,
+    /// Convert Windows line endings (CRLF) to LF.
+    pub crlf_to_lf: bool,
+    /// Remove `utm_*` and other tracking parameters from URL clips.
+    pub strip_tracking_params: bool,
+}
+
+impl Normalize {
+    /// `text` with the enabled cleanups applied.
+    pub fn text(&self, text: &str) -> String {
+        let mut out = if self.crlf_to_lf {
+            text.replace("\r\n", "\n")
+        } else {
+            text.to_string()
+        };
+        if self.trim_trailing_whitespace {
+            out = out
+                .split('\n')
+                .map(|line| line.trim_end_matches([' ', '\t']))
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim_end()
+                .to_string();
+        }
+        if self.strip_tracking_params
+            && let Some(url) = links::extract_url(&out)
+        {
+            out = strip_tracking_params(url);
+        }
+        out
+    }
+
+    /// `content` with its text cleaned up and its hash and size updated to
+    /// match. Images, and text that would be left empty, pass through.
+    pub fn content(&self, mut content: ClipboardContent) -> ClipboardContent {
+        if content.content_type != ContentType::Text {
+            return content;
+        }
+        let Some(ref text) = content.text else {
+            return content;
+        };
+        let cleaned = self.text(text);
+        if cleaned != *text && !cleaned.is_empty() {
+            content.hash = hash_content(cleaned.as_bytes());
+            content.size_bytes = cleaned.len() as i64;
+            content.text = Some(cleaned);
+        }
+        content
+    }
+}
+
+/// `url` without `utm_*` and other well-known tracking parameters. The
+/// fragment and the remaining parameters are kept in their order.
+pub fn strip_tracking_params(url: &str) -> String {
+    let (rest, fragment) = match url.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (url, None),
+    };
+    let Some((base, query)) = rest.split_once('?') else {
+        return url.to_string();
+    };
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|param| {
+            let name = param.split('=').next().unwrap_or_default();
+            !param.is_empty()
+                && !name.to_ascii_lowercase().starts_with("utm_")
+                && !TRACKING_PARAMS.contains(&name)
+        })
+        .collect();
+    let mut out = base.to_string();
+    if !kept.is_empty() {
+        out.push('?');
+        out.push_str(&kept.join("&"));
+    }
+    if let Some(fragment) = fragment {
+        out.push('#');
+        out.push_str(fragment);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all() -> Normalize {
+        Normalize {
+            trim_trailing_whitespace: true,
+            crlf_to_lf: true,
+            strip_tracking_params: true,
+        }
+    }
+
+    #[test]
+    fn test_default_leaves_text_alone() {
+        let text = "a  \r\nb\t\n\n";
+        assert_eq!(Normalize::default().text(text), text);
+    }
+
+    #[test]
+    fn test_line_endings_and_trailing_whitespace() {
+        assert_eq!(all().text("fn main() {  \r\n\tx;\t\r\n}\r\n\r\n"), "fn main() {\n\tx;\n}");
+        let crlf_only = Normalize {
+            crlf_to_lf: true,
+            ..Default::default()
+        };
+        assert_eq!(crlf_only.text("a \r\nb \r\n"), "a \nb \n");
+    }
+
+    #[test]
+    fn test_strip_tracking_params() {
+        assert_eq!(
+            strip_tracking_params("https://example.com/a?utm_source=x&id=3&UTM_Medium=y&fbclid=z#top"),
+            "https://example.com/a?id=3#top"
+        );
+        assert_eq!(strip_tracking_params("https://example.com/?utm_source=x"), "https://example.com/");
+        assert_eq!(strip_tracking_params("https://example.com/a#b?utm_x=1"), "https://example.com/a#b?utm_x=1");
+        // Only whole-URL clips are rewritten.
+        assert_eq!(all().text("see https://x.com/?utm_source=a"), "see https://x.com/?utm_source=a");
+        assert_eq!(all().text("https://x.com/p?utm_source=a \n"), "https://x.com/p");
+    }
+
+    #[test]
+    fn test_content_rehashes_cleaned_text() {
+        let cleaned = all().content(ClipboardContent::from_text("hello \r\n".into()));
+        let plain = ClipboardContent::from_text("hello".into());
+        assert_eq!(cleaned.text, plain.text);
+        assert_eq!(cleaned.hash, plain.hash);
+        assert_eq!(cleaned.size_bytes, 5);
+        // Text that would be left empty is kept as copied.
+        let blank = all().content(ClipboardContent::from_text("  \n".into()));
+        assert_eq!(blank.text.as_deref(), Some("  \n"));
+    }
+}
@@ -0,0 +1,58 @@
+use std::path::Path;
+#[cfg(feature = "ocr")]
+use std::process::Command;
+
+use crate::errors::{CbError, Result};
+
+/// Whether this build can recognize text in images.
+pub fn is_available() -> bool {
+    cfg!(feature = "ocr")
+}
+
+/// Recognize text in the image at `path` using the `tesseract` CLI.
+#[cfg(feature = "ocr")]
+pub fn recognize(path: &Path) -> Result<String> {
+    let output = Command::new("tesseract")
+        .arg(path)
+        .arg("stdout")
+        .output()
+        .map_err(|e| CbError::Ocr(format!("failed to run tesseract: {}", e)))?;
+    if !output.status.success() {
+        return Err(CbError::Ocr(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(normalize(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(not(feature = "ocr"))]
+pub fn recognize(_path: &Path) -> Result<String> {
+    Err(CbError::Ocr("cb was built without the `ocr` feature".into()))
+}
+
+/// Drop blank lines and trailing whitespace that OCR engines tend to emit.
+pub fn normalize(raw: &str) -> String {
+    raw.lines()
+        .map(str::trim_end)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_strips_blank_lines() {
+        assert_eq!(normalize("Error code 42  \n\n\nat main.rs\n\u{c}"), "Error code 42\nat main.rs");
+        assert_eq!(normalize("\n \n"), "");
+    }
+
+    #[cfg(not(feature = "ocr"))]
+    #[test]
+    fn test_recognize_without_feature_errors() {
+        assert!(!is_available());
+        assert!(matches!(recognize(Path::new("/x.png")), Err(CbError::Ocr(_))));
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{AppPaths, DEFAULT_PROFILE};
+use crate::errors::{CbError, Result};
+
+/// File in the base directory naming the profile used when `--profile` is
+/// not given.
+const ACTIVE_FILE: &str = "profile";
+
+fn profiles_dir(base: &Path) -> PathBuf {
+    base.join("profiles")
+}
+
+/// Profile names become directory names, so keep them to a safe alphabet.
+pub fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(CbError::InvalidInput(format!(
+            "invalid profile name \"{}\" (use letters, digits, '-' and '_')",
+            name
+        )))
+    }
+}
+
+pub fn exists(base: &Path, name: &str) -> bool {
+    name == DEFAULT_PROFILE || profiles_dir(base).join(name).is_dir()
+}
+
+/// Every profile, `default` first, then the rest alphabetically.
+pub fn list(base: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(profiles_dir(base))
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().is_dir())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter(|name| validate_name(name).is_ok() && name != DEFAULT_PROFILE)
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names
+}
+
+pub fn create(base: &Path, name: &str) -> Result<AppPaths> {
+    validate_name(name)?;
+    if exists(base, name) {
+        return Err(CbError::InvalidInput(format!("profile \"{}\" already exists", name)));
+    }
+    let paths = AppPaths::for_profile(base.to_path_buf(), name);
+    fs::create_dir_all(&paths.images_dir).map_err(|e| CbError::Config(e.to_string()))?;
+    Ok(paths)
+}
+
+/// The profile selected with `cb profile switch`, or `default`.
+pub fn active(base: &Path) -> String {
+    fs::read_to_string(base.join(ACTIVE_FILE))
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|name| validate_name(name).is_ok() && exists(base, name))
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+pub fn set_active(base: &Path, name: &str) -> Result<()> {
+    ensure_exists(base, name)?;
+    fs::create_dir_all(base).map_err(|e| CbError::Config(e.to_string()))?;
+    fs::write(base.join(ACTIVE_FILE), name).map_err(|e| CbError::Config(e.to_string()))
+}
+
+/// Paths for `requested` (from `--profile`), falling back to the active one.
+pub fn resolve(base: PathBuf, requested: Option<&str>) -> Result<AppPaths> {
+    let name = match requested {
+        Some(name) => {
+            validate_name(name)?;
+            ensure_exists(&base, name)?;
+            name.to_string()
+        }
+        None => active(&base),
+    };
+    Ok(AppPaths::for_profile(base, &name))
+}
+
+fn ensure_exists(base: &Path, name: &str) -> Result<()> {
+    if exists(base, name) {
+        Ok(())
+    } else {
+        Err(CbError::NotFound(format!(
+            "profile \"{}\" (create it with `cb profile create {}`)",
+            name, name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("client-a_2").is_ok());
+        for bad in ["", "../x", "a b", "work/x", "."] {
+            assert!(validate_name(bad).is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn test_create_list_and_switch() {
+        let dir = TempDir::new().unwrap();
+        let base = dir.path();
+        assert_eq!(list(base), vec!["default"]);
+        assert_eq!(active(base), "default");
+
+        let paths = create(base, "work").unwrap();
+        assert!(paths.images_dir.is_dir());
+        create(base, "acme").unwrap();
+        assert!(create(base, "work").is_err());
+        assert!(create(base, "default").is_err());
+        assert_eq!(list(base), vec!["default", "acme", "work"]);
+
+        set_active(base, "work").unwrap();
+        assert_eq!(active(base), "work");
+        assert_eq!(resolve(base.to_path_buf(), None).unwrap().profile, "work");
+        assert_eq!(resolve(base.to_path_buf(), Some("acme")).unwrap().profile, "acme");
+        assert!(matches!(resolve(base.to_path_buf(), Some("nope")), Err(CbError::NotFound(_))));
+        assert!(set_active(base, "nope").is_err());
+
+        // A profile removed behind our back falls back to the default.
+        fs::remove_dir_all(base.join("profiles/work")).unwrap();
+        assert_eq!(active(base), "default");
+    }
+}
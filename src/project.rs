@@ -0,0 +1,131 @@
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{CbError, Result};
+
+/// Tag each capture with the project the frontmost terminal or editor is
+/// working in, read from its window title. A path in the title under one of
+/// `roots` names the project by its folder there; `titles` rules cover apps
+/// whose titles show a project name rather than a path.
+///
+/// ```toml
+/// [project_tags]
+/// roots = ["~/code"]
+///
+/// [[project_tags.titles]]
+/// app = "com.microsoft.VSCode"
+/// pattern = " — (?P<project>[^—]+)$"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProjectTags {
+    /// Folders whose direct subfolders are projects, e.g. `~/code`.
+    pub roots: Vec<PathBuf>,
+    /// Only look at these apps (bundle identifiers); all apps when empty.
+    pub apps: Vec<String>,
+    /// Patterns tried on the title before looking for paths, first match
+    /// wins. Each needs a `project` group.
+    pub titles: Vec<TitleRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TitleRule {
+    /// Only for this app, when given.
+    pub app: Option<String>,
+    pub pattern: String,
+}
+
+impl ProjectTags {
+    /// Project for a window titled `title` in `app`, as a tag.
+    pub fn detect(&self, app: Option<&str>, title: &str) -> Result<Option<String>> {
+        if !self.apps.is_empty() && !app.is_some_and(|a| self.apps.iter().any(|x| x == a)) {
+            return Ok(None);
+        }
+        for (idx, rule) in self.titles.iter().enumerate() {
+            if rule.app.as_deref().is_some_and(|a| Some(a) != app) {
+                continue;
+            }
+            let re = Regex::new(&rule.pattern).map_err(|e| {
+                CbError::Config(format!("project_tags title pattern {}: {}", idx + 1, e))
+            })?;
+            if let Some(name) = re.captures(title).and_then(|c| c.name("project")) {
+                return Ok(tag_name(name.as_str()));
+            }
+        }
+        let Some(home) = dirs::home_dir() else {
+            return Ok(None);
+        };
+        let roots: Vec<PathBuf> = self.roots.iter().map(|r| expand(r, &home)).collect();
+        Ok(title
+            .split(|c: char| c.is_whitespace() || matches!(c, ':' | '"' | '\'' | '(' | ')'))
+            .filter(|word| word.starts_with('/') || word.starts_with('~'))
+            .find_map(|word| project_under(&expand(Path::new(word), &home), &roots)))
+    }
+}
+
+/// `path` with a leading `~` replaced by `home`.
+fn expand(path: &Path, home: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => home.join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// The first folder of `path` below whichever of `roots` contains it.
+fn project_under(path: &Path, roots: &[PathBuf]) -> Option<String> {
+    roots.iter().find_map(|root| {
+        match path.strip_prefix(root).ok()?.components().next()? {
+            Component::Normal(name) => tag_name(&name.to_string_lossy()),
+            _ => None,
+        }
+    })
+}
+
+/// `name` usable as a tag: trimmed, with spaces and commas made dashes.
+fn tag_name(name: &str) -> Option<String> {
+    let name = name.trim().replace([' ', ','], "-");
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(toml: &str) -> ProjectTags {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_paths_under_roots_name_the_project() {
+        let home = dirs::home_dir().unwrap();
+        let config = tags("roots = [\"~/code\", \"/srv/work\"]");
+        let title = format!("me@mac: {}/code/cb/src — zsh", home.display());
+        assert_eq!(config.detect(None, &title).unwrap().as_deref(), Some("cb"));
+        assert_eq!(config.detect(None, "vim (/srv/work/api/x.rs)").unwrap().as_deref(), Some("api"));
+        assert_eq!(config.detect(None, "~/code/cb").unwrap().as_deref(), Some("cb"));
+        // The root itself and paths elsewhere aren't projects.
+        assert_eq!(config.detect(None, "~/code").unwrap(), None);
+        assert_eq!(config.detect(None, "/tmp/cb").unwrap(), None);
+    }
+
+    #[test]
+    fn test_title_rules_and_app_filter() {
+        let config = tags(
+            r#"
+            apps = ["com.microsoft.VSCode", "com.apple.Terminal"]
+            [[titles]]
+            app = "com.microsoft.VSCode"
+            pattern = " — (?P<project>[^—]+)$"
+            "#,
+        );
+        let vscode = Some("com.microsoft.VSCode");
+        assert_eq!(config.detect(vscode, "main.rs — cb").unwrap().as_deref(), Some("cb"));
+        assert_eq!(config.detect(Some("com.apple.Terminal"), "main.rs — cb").unwrap(), None);
+        assert_eq!(config.detect(Some("com.apple.Safari"), "a — b").unwrap(), None);
+
+        let broken = tags("[[titles]]\npattern = \"(\"");
+        assert!(matches!(broken.detect(None, "x"), Err(CbError::Config(_))));
+    }
+}
@@ -0,0 +1,711 @@
+use std::io::IsTerminal;
+
+use chrono::{DateTime, Utc};
+use crossterm::style::Stylize;
+
+use crate::errors::{CbError, Result};
+use crate::i18n::t;
+use crate::links;
+use crate::snippet::{self, Snippet};
+use crate::storage::models::{Clip, ContentType};
+use crate::title;
+use crate::width;
+
+/// Preview width used when output isn't a terminal (or its size is unknown).
+const DEFAULT_PREVIEW: usize = 60;
+/// Never squeeze the preview below this, even on very narrow terminals.
+const MIN_PREVIEW: usize = 16;
+
+/// How clip rows are printed by `list`, `search`, and friends.
+#[derive(Debug, Clone, Copy)]
+pub struct RowStyle {
+    pub color: bool,
+    /// Terminal width in columns; `None` when piped.
+    pub width: Option<usize>,
+    /// Spell out every field with a label, in a fixed order, instead of
+    /// aligned columns and symbols (`--plain`), for screen readers.
+    pub labels: bool,
+}
+
+impl RowStyle {
+    /// Colors are on only for a terminal, and never with `--no-color`, a
+    /// non-empty `NO_COLOR` (https://no-color.org), or in plain mode. Plain
+    /// mode is `--plain` or a `TERM=dumb` terminal.
+    pub fn detect(no_color: bool, plain: bool) -> Self {
+        let tty = std::io::stdout().is_terminal();
+        let env_off = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let labels = plain || std::env::var("TERM").is_ok_and(|term| term == "dumb");
+        Self {
+            color: tty && !no_color && !env_off && !labels,
+            width: if tty {
+                crossterm::terminal::size().ok().map(|(w, _)| w as usize)
+            } else {
+                None
+            },
+            labels,
+        }
+    }
+
+    pub fn plain() -> Self {
+        Self {
+            color: false,
+            width: None,
+            labels: false,
+        }
+    }
+
+    /// Print `clips` as aligned rows, sizing the id column to the widest id.
+    pub fn print_rows(&self, clips: &[Clip]) {
+        let id_width = clips.iter().map(|c| c.id.to_string().len()).max().unwrap_or(1);
+        for clip in clips {
+            println!("{}", self.row(clip, id_width));
+        }
+    }
+
+    /// Print groups of clips, each under its heading, aligned as one list
+    /// (with a single table header when `columns` is given).
+    pub fn print_grouped(&self, groups: &[(String, Vec<&Clip>)], columns: Option<&[Column]>) {
+        for line in self.grouped(groups, columns) {
+            println!("{}", line);
+        }
+    }
+
+    pub fn grouped(&self, groups: &[(String, Vec<&Clip>)], columns: Option<&[Column]>) -> Vec<String> {
+        let flat: Vec<Clip> = groups
+            .iter()
+            .flat_map(|(_, clips)| clips.iter().map(|c| (*c).clone()))
+            .collect();
+        let (mut lines, rows): (Vec<String>, Vec<String>) = match columns {
+            Some(columns) if self.labels => (Vec::new(), self.table(&flat, columns)),
+            Some(columns) => {
+                let mut table = self.table(&flat, columns).into_iter();
+                (table.next().into_iter().collect(), table.collect::<Vec<_>>())
+            }
+            None => {
+                let id_width = flat.iter().map(|c| c.id.to_string().len()).max().unwrap_or(1);
+                (Vec::new(), flat.iter().map(|c| self.row(c, id_width)).collect())
+            }
+        };
+        let mut rows = rows.into_iter();
+        for (idx, (heading, clips)) in groups.iter().enumerate() {
+            if idx > 0 {
+                lines.push(String::new());
+            }
+            lines.push(if self.labels {
+                format!("{} {}", t("Group:"), heading)
+            } else if self.color {
+                heading.clone().bold().to_string()
+            } else {
+                heading.clone()
+            });
+            lines.extend(rows.by_ref().take(clips.len()));
+        }
+        lines
+    }
+
+    /// Like `print_rows`, but previews show a snippet around the first
+    /// match of `query` with the match highlighted.
+    pub fn print_search_rows(&self, clips: &[Clip], query: &str) {
+        let id_width = clips.iter().map(|c| c.id.to_string().len()).max().unwrap_or(1);
+        for clip in clips {
+            println!("{}", self.search_row(clip, id_width, query));
+        }
+    }
+
+    /// One row: `  id T* age  preview [tags] (dead link)`, with the preview
+    /// truncated so the row fits the terminal width.
+    pub fn row(&self, clip: &Clip, id_width: usize) -> String {
+        self.row_with(clip, id_width, |width| title::shorten(&preview_text(clip), width))
+    }
+
+    pub fn search_row(&self, clip: &Clip, id_width: usize, query: &str) -> String {
+        self.row_with(clip, id_width, |width| match snippet::for_clip(clip, query, width) {
+            Some(s) => self.highlight(&s),
+            None => title::shorten(&preview_text(clip), width),
+        })
+    }
+
+    /// Snippet text with its match emphasized when color is on.
+    pub fn highlight(&self, snippet: &Snippet) -> String {
+        let chars: Vec<char> = snippet.text.chars().collect();
+        let before: String = chars[..snippet.match_start].iter().collect();
+        let hit: String = chars[snippet.match_start..snippet.match_end].iter().collect();
+        let after: String = chars[snippet.match_end..].iter().collect();
+        if self.color {
+            format!("{}{}{}", before, hit.yellow().bold().underlined(), after)
+        } else {
+            format!("{}{}{}", before, hit, after)
+        }
+    }
+
+    fn row_with(&self, clip: &Clip, id_width: usize, preview: impl FnOnce(usize) -> String) -> String {
+        if self.labels {
+            return labeled_row(clip, preview(DEFAULT_PREVIEW));
+        }
+        let (type_icon, type_color): (&str, fn(&str) -> String) = match clip.content_type {
+            ContentType::Text => ("T", |s| s.green().to_string()),
+            ContentType::Image => ("I", |s| s.magenta().to_string()),
+            ContentType::FileRef => ("F", |s| s.blue().to_string()),
+        };
+        let pin = if clip.pinned { "*" } else { " " };
+        let age = format!("{:>4}", format_age(clip.updated_at));
+        let tags = if clip.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", clip.tags.join(", "))
+        };
+        let dead = match clip.link_status {
+            _ if clip.damaged => " (file missing)",
+            Some(status) if links::is_dead(status) => " (dead link)",
+            _ => "",
+        };
+
+        // id, space, type+pin, space, age, two spaces
+        let fixed = id_width + 1 + 2 + 1 + age.len() + 2;
+        let preview_width = match self.width {
+            Some(w) => w
+                .saturating_sub(fixed + width::of(&tags) + dead.len())
+                .max(MIN_PREVIEW),
+            None => DEFAULT_PREVIEW,
+        };
+        let preview = preview(preview_width);
+
+        if !self.color {
+            return format!(
+                "{:>id_width$} {}{} {}  {}{}{}",
+                clip.id, type_icon, pin, age, preview, tags, dead
+            );
+        }
+        format!(
+            "{:>id_width$} {}{} {}  {}{}{}",
+            clip.id,
+            type_color(type_icon),
+            if clip.pinned { pin.yellow().bold().to_string() } else { pin.to_string() },
+            age.dim(),
+            preview,
+            tags.cyan(),
+            dead.red(),
+        )
+    }
+}
+
+/// A column of the long (`list --long`) format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Id,
+    Type,
+    Pin,
+    Size,
+    Copies,
+    App,
+    Subtype,
+    Created,
+    Age,
+    Tags,
+    Preview,
+}
+
+impl Column {
+    pub const ALL: &[Column] = &[
+        Column::Id,
+        Column::Type,
+        Column::Pin,
+        Column::Size,
+        Column::Copies,
+        Column::App,
+        Column::Subtype,
+        Column::Created,
+        Column::Age,
+        Column::Tags,
+        Column::Preview,
+    ];
+
+    /// Columns shown by `list --long` without `--columns`.
+    pub const LONG: &[Column] = &[
+        Column::Id,
+        Column::Type,
+        Column::Pin,
+        Column::Size,
+        Column::Copies,
+        Column::App,
+        Column::Subtype,
+        Column::Created,
+        Column::Preview,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::Type => "type",
+            Column::Pin => "pin",
+            Column::Size => "size",
+            Column::Copies => "copies",
+            Column::App => "app",
+            Column::Subtype => "subtype",
+            Column::Created => "created",
+            Column::Age => "age",
+            Column::Tags => "tags",
+            Column::Preview => "preview",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Column> {
+        Column::ALL.iter().copied().find(|c| c.as_str() == s)
+    }
+
+    /// Label for the column in `--plain` rows.
+    fn label(&self) -> &'static str {
+        match self {
+            Column::Id => "ID",
+            Column::Type => "Type",
+            Column::Pin => "Pinned",
+            Column::Size => "Size",
+            Column::Copies => "Copies",
+            Column::App => "App",
+            Column::Subtype => "Subtype",
+            Column::Created => "Created",
+            Column::Age => "Age",
+            Column::Tags => "Tags",
+            Column::Preview => "Preview",
+        }
+    }
+
+    fn right_aligned(&self) -> bool {
+        matches!(self, Column::Id | Column::Size | Column::Copies | Column::Age)
+    }
+
+    fn cell(&self, clip: &Clip) -> String {
+        match self {
+            Column::Id => clip.id.to_string(),
+            Column::Type => clip.content_type.as_str().to_string(),
+            Column::Pin => if clip.pinned { "*" } else { "" }.to_string(),
+            Column::Size => format_bytes(clip.size_bytes),
+            Column::Copies => clip.copy_count.to_string(),
+            Column::App => clip
+                .app_name
+                .clone()
+                .or_else(|| clip.source_app.clone())
+                .unwrap_or_else(|| "-".to_string()),
+            Column::Subtype => subtype(clip),
+            Column::Created => clip
+                .created_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            Column::Age => format_age(clip.updated_at),
+            Column::Tags => clip.tags.join(","),
+            Column::Preview => preview_text(clip),
+        }
+    }
+
+    fn paint(&self, cell: String, clip: &Clip) -> String {
+        match self {
+            Column::Type => match clip.content_type {
+                ContentType::Text => cell.green().to_string(),
+                ContentType::Image => cell.magenta().to_string(),
+                ContentType::FileRef => cell.blue().to_string(),
+            },
+            Column::Pin => cell.yellow().bold().to_string(),
+            Column::Created | Column::Age | Column::Size => cell.dim().to_string(),
+            Column::Tags => cell.cyan().to_string(),
+            Column::Preview if clip.damaged || clip.link_status.is_some_and(links::is_dead) => {
+                cell.red().to_string()
+            }
+            _ => cell,
+        }
+    }
+}
+
+/// Parse a comma-separated `--columns` value such as `id,size,preview`.
+pub fn parse_columns(spec: &str) -> Result<Vec<Column>> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|name| {
+            Column::parse(name).ok_or_else(|| {
+                let known: Vec<&str> = Column::ALL.iter().map(|c| c.as_str()).collect();
+                CbError::InvalidInput(format!(
+                    "unknown column \"{}\" (expected one of: {})",
+                    name,
+                    known.join(", ")
+                ))
+            })
+        })
+        .collect()
+}
+
+impl RowStyle {
+    /// Print `clips` as a table with a header, one column per entry in
+    /// `columns`. A trailing preview column takes whatever width is left.
+    pub fn print_table(&self, clips: &[Clip], columns: &[Column]) {
+        for line in self.table(clips, columns) {
+            println!("{}", line);
+        }
+    }
+
+    pub fn table(&self, clips: &[Clip], columns: &[Column]) -> Vec<String> {
+        if self.labels {
+            return clips
+                .iter()
+                .map(|clip| {
+                    let fields = columns.iter().map(|c| {
+                        let cell = match c {
+                            Column::Pin => yes_no(clip.pinned).to_string(),
+                            Column::Preview => title::shorten(&preview_text(clip), DEFAULT_PREVIEW),
+                            _ => c.cell(clip),
+                        };
+                        (c.label(), cell)
+                    });
+                    labeled(fields)
+                })
+                .collect();
+        }
+        let cells: Vec<Vec<String>> = clips
+            .iter()
+            .map(|clip| columns.iter().map(|c| c.cell(clip)).collect())
+            .collect();
+        let header: Vec<String> = columns.iter().map(|c| t(&c.as_str().to_uppercase()).to_string()).collect();
+        let mut widths: Vec<usize> = header.iter().map(|h| width::of(h)).collect();
+        for row in &cells {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(width::of(cell));
+            }
+        }
+
+        let last = columns.len().saturating_sub(1);
+        if columns.last() == Some(&Column::Preview) {
+            let used: usize = widths[..last].iter().map(|w| w + 2).sum();
+            widths[last] = match self.width {
+                Some(w) => w.saturating_sub(used).max(MIN_PREVIEW),
+                None => DEFAULT_PREVIEW,
+            };
+        }
+
+        let format_row = |row: Vec<String>, clip: Option<&Clip>| -> String {
+            let mut parts = Vec::with_capacity(row.len());
+            for (idx, (cell, col)) in row.into_iter().zip(columns).enumerate() {
+                let cell = width::truncate(&cell, widths[idx]);
+                let padded = if idx == last {
+                    cell
+                } else if col.right_aligned() {
+                    width::pad_left(&cell, widths[idx])
+                } else {
+                    width::pad_right(&cell, widths[idx])
+                };
+                parts.push(match clip {
+                    Some(clip) if self.color => col.paint(padded, clip),
+                    _ => padded,
+                });
+            }
+            parts.join("  ").trim_end().to_string()
+        };
+
+        let header = format_row(header, None);
+        let mut lines = vec![if self.color { header.dim().to_string() } else { header }];
+        for (row, clip) in cells.into_iter().zip(clips) {
+            lines.push(format_row(row, Some(clip)));
+        }
+        lines
+    }
+}
+
+/// One `--plain` row: every field labeled, always in the same order, with
+/// words in place of the type letter and pin star.
+fn labeled_row(clip: &Clip, preview: String) -> String {
+    let tags = if clip.tags.is_empty() {
+        t("none").to_string()
+    } else {
+        clip.tags.join(", ")
+    };
+    let mut fields = vec![
+        ("ID", clip.id.to_string()),
+        ("Type", clip.content_type.as_str().to_string()),
+        ("Pinned", yes_no(clip.pinned).to_string()),
+        ("Age", format_age(clip.updated_at)),
+        ("Tags", tags),
+        ("Preview", preview),
+    ];
+    if clip.link_status.is_some_and(links::is_dead) {
+        fields.push(("Link", t("dead").to_string()));
+    }
+    if clip.damaged {
+        fields.push(("File", t("missing").to_string()));
+    }
+    labeled(fields)
+}
+
+/// `Label: value` pairs joined by `; `, with the labels translated.
+fn labeled(fields: impl IntoIterator<Item = (&'static str, String)>) -> String {
+    fields
+        .into_iter()
+        .map(|(label, value)| format!("{}: {}", t(label), value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// `yes` or `no` in the current locale.
+pub fn yes_no(value: bool) -> &'static str {
+    t(if value { "yes" } else { "no" })
+}
+
+/// Finer-grained kind of a clip: `url` or the detected language for text,
+/// dimensions for images, and the file extension for file references.
+pub fn subtype(clip: &Clip) -> String {
+    match clip.content_type {
+        ContentType::Text => {
+            let text = clip.text_content.as_deref().unwrap_or("");
+            if links::extract_url(text).is_some() {
+                "url".to_string()
+            } else {
+                clip.lang.clone().unwrap_or_else(|| "-".to_string())
+            }
+        }
+        ContentType::Image => format!(
+            "{}x{}",
+            clip.image_width.unwrap_or(0),
+            clip.image_height.unwrap_or(0)
+        ),
+        ContentType::FileRef => clip
+            .image_path
+            .as_deref()
+            .and_then(|p| std::path::Path::new(p).extension())
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_else(|| "-".to_string()),
+    }
+}
+
+/// Single-line preview of a clip's content: its title for text, when it
+/// has one.
+pub fn preview_text(clip: &Clip) -> String {
+    match clip.content_type {
+        ContentType::Text => match clip.title {
+            Some(ref title) => title.clone(),
+            None => clip.text_content.as_deref().unwrap_or("").replace('\n', "\\n"),
+        },
+        ContentType::Image => format!(
+            "{}x{} image",
+            clip.image_width.unwrap_or(0),
+            clip.image_height.unwrap_or(0)
+        ),
+        ContentType::FileRef => clip.image_path.as_deref().unwrap_or("file").to_string(),
+    }
+}
+
+pub fn format_bytes(bytes: i64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+pub fn format_age(dt: DateTime<Utc>) -> String {
+    let dur = Utc::now() - dt;
+    if dur.num_seconds() < 60 {
+        "now".to_string()
+    } else if dur.num_minutes() < 60 {
+        format!("{}m", dur.num_minutes())
+    } else if dur.num_hours() < 24 {
+        format!("{}h", dur.num_hours())
+    } else {
+        format!("{}d", dur.num_days())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(id: i64, text: &str) -> Clip {
+        Clip {
+            id,
+            content_type: ContentType::Text,
+            text_content: Some(text.into()),
+            image_path: None,
+            image_width: None,
+            image_height: None,
+            hash: "h".into(),
+            size_bytes: text.len() as i64,
+            pinned: false,
+            pinned_until: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            ocr_text: None,
+            lang: None,
+            link_status: None,
+            link_redirect: None,
+            link_checked_at: None,
+            copy_count: 0,
+            protected: false,
+            source_app: None,
+            app_name: None,
+            colors: vec![],
+            brightness: None,
+            original_path: None,
+            origin: None,
+            source_url: None,
+            source_title: None,
+            link_title: None,
+            canonical_url: None,
+            title: None,
+            deleted_at: None,
+            external_id: None,
+            damaged: false,
+            meta: Default::default(),
+            tags: vec![],
+        }
+    }
+
+    #[test]
+    fn test_plain_row_aligns_ids() {
+        let style = RowStyle::plain();
+        assert_eq!(style.row(&clip(7, "a\nb"), 3), "  7 T   now  a\\nb");
+        let mut tagged = clip(123, "x");
+        tagged.pinned = true;
+        tagged.tags = vec!["work".into()];
+        assert_eq!(style.row(&tagged, 3), "123 T*  now  x [work]");
+    }
+
+    #[test]
+    fn test_row_prefers_title_cut_at_word() {
+        let style = RowStyle {
+            color: false,
+            width: Some(30),
+            labels: false,
+        };
+        let mut titled = clip(1, "# Release checklist for the spring update\n\n- tag");
+        titled.title = crate::title::derive(titled.text_content.as_deref().unwrap());
+        assert_eq!(style.row(&titled, 1), "1 T   now  Release checklist…");
+    }
+
+    #[test]
+    fn test_row_fits_terminal_width() {
+        let style = RowStyle {
+            color: false,
+            width: Some(40),
+            labels: false,
+        };
+        let row = style.row(&clip(1, &"x".repeat(200)), 2);
+        assert_eq!(row.chars().count(), 40);
+        assert!(row.ends_with('…'));
+    }
+
+    #[test]
+    fn test_row_measures_wide_text_in_columns() {
+        let style = RowStyle {
+            color: false,
+            width: Some(40),
+            labels: false,
+        };
+        let row = style.row(&clip(1, &"日本語".repeat(20)), 2);
+        assert!(crate::width::of(&row) <= 40, "{}", row);
+        assert!(row.ends_with('…'));
+    }
+
+    #[test]
+    fn test_search_row_shows_snippet() {
+        let text = format!("{} docker login {}", "a".repeat(80), "b".repeat(80));
+        let row = RowStyle::plain().search_row(&clip(5, &text), 1, "LOGIN");
+        assert!(row.starts_with("5 T   now  …"));
+        assert!(row.contains("docker login"));
+        assert_eq!(row.chars().count(), 11 + DEFAULT_PREVIEW);
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        assert_eq!(
+            parse_columns("id, size,preview").unwrap(),
+            vec![Column::Id, Column::Size, Column::Preview]
+        );
+        assert!(matches!(parse_columns("id,bogus"), Err(CbError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_table_aligns_columns() {
+        let mut big = clip(12, "https://example.com");
+        big.size_bytes = 2048;
+        big.copy_count = 3;
+        big.source_app = Some("com.apple.Safari".into());
+        let small = clip(3, "hello");
+        let lines = RowStyle::plain().table(
+            &[big, small],
+            &[Column::Id, Column::Size, Column::Copies, Column::App, Column::Subtype],
+        );
+        assert_eq!(
+            lines,
+            vec![
+                "ID    SIZE  COPIES  APP               SUBTYPE",
+                "12  2.0 KB       3  com.apple.Safari  url",
+                " 3     5 B       0  -                 -",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_table_preview_fills_width() {
+        let style = RowStyle {
+            color: false,
+            width: Some(30),
+            labels: false,
+        };
+        let lines = style.table(&[clip(1, &"y".repeat(100))], &[Column::Id, Column::Preview]);
+        assert_eq!(lines[1].chars().count(), 30);
+    }
+
+    #[test]
+    fn test_grouped_rows_share_alignment() {
+        let (a, b, c) = (clip(9, "nine"), clip(10, "ten"), clip(11, "eleven"));
+        let groups = vec![
+            ("Today".to_string(), vec![&a, &b]),
+            ("Yesterday".to_string(), vec![&c]),
+        ];
+        let lines = RowStyle::plain().grouped(&groups, None);
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "Today");
+        assert_eq!(lines[3], "");
+        assert_eq!(lines[4], "Yesterday");
+        assert!(lines[1].starts_with(" 9 "), "{:?}", lines[1]);
+
+        let table = RowStyle::plain().grouped(&groups, Some(&[Column::Id, Column::Preview]));
+        assert!(table[0].starts_with("ID"));
+        assert_eq!(table[1], "Today");
+        assert_eq!(table.len(), 7);
+    }
+
+    #[test]
+    fn test_labeled_rows_spell_out_fields() {
+        let style = RowStyle {
+            labels: true,
+            ..RowStyle::plain()
+        };
+        let mut tagged = clip(7, "hello");
+        tagged.pinned = true;
+        tagged.tags = vec!["work".into(), "todo".into()];
+        assert_eq!(
+            style.row(&tagged, 3),
+            "ID: 7; Type: text; Pinned: yes; Age: now; Tags: work, todo; Preview: hello"
+        );
+        assert_eq!(
+            style.table(&[clip(3, "x")], &[Column::Pin, Column::Id]),
+            vec!["Pinned: no; ID: 3"]
+        );
+
+        let (a, b) = (clip(1, "a"), clip(2, "b"));
+        let groups = vec![("Today".to_string(), vec![&a]), ("Yesterday".to_string(), vec![&b])];
+        let lines = style.grouped(&groups, Some(&[Column::Id]));
+        assert_eq!(lines, vec!["Group: Today", "ID: 1", "", "Group: Yesterday", "ID: 2"]);
+    }
+
+    #[test]
+    fn test_color_row_has_escapes() {
+        let style = RowStyle {
+            color: true,
+            width: None,
+            labels: false,
+        };
+        assert!(style.row(&clip(1, "x"), 1).contains('\u{1b}'));
+        assert!(!RowStyle::plain().row(&clip(1, "x"), 1).contains('\u{1b}'));
+    }
+}
@@ -0,0 +1,253 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use rusqlite::types::Value;
+use rusqlite::{Connection, OpenFlags, params_from_iter};
+use serde::Serialize;
+
+use crate::encryption;
+use crate::errors::{CbError, Result};
+use crate::storage::sqlite::SqliteStorage;
+
+/// Tables copied by `repair`, parents before children so foreign keys hold.
+const TABLES: &[&str] = &["clips", "tags", "captures", "slots", "clip_meta"];
+
+#[derive(Debug, Serialize)]
+pub struct TableReport {
+    pub table: &'static str,
+    pub recovered: usize,
+    /// Rows that were located but could not be read or re-inserted.
+    pub lost: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepairReport {
+    pub tables: Vec<TableReport>,
+    /// Where the original database was moved.
+    pub corrupt_path: PathBuf,
+}
+
+/// Problems reported by `PRAGMA integrity_check`; empty when the database is
+/// healthy. A database too damaged to run the check yields its error.
+pub fn integrity_check(conn: &Connection) -> Vec<String> {
+    let result = conn.prepare("PRAGMA integrity_check").and_then(|mut stmt| {
+        stmt.query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+    });
+    match result {
+        Ok(lines) if lines.len() == 1 && lines[0] == "ok" => Vec::new(),
+        Ok(lines) => lines,
+        Err(e) => vec![e.to_string()],
+    }
+}
+
+/// Open `db_path` without touching it and run `integrity_check`.
+pub fn check_file(db_path: &Path) -> Result<Vec<String>> {
+    let conn = encryption::connect(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    Ok(integrity_check(&conn))
+}
+
+/// Copy every readable row of the database at `db_path` into a fresh one,
+/// then swap it in. The damaged file (and its WAL) is kept alongside as
+/// `<db>.corrupt-<timestamp>` rather than deleted.
+pub fn repair(db_path: &Path) -> Result<RepairReport> {
+    let stamp = Utc::now().format("%Y%m%d%H%M%S");
+    let fresh_path = unused_path(db_path, &format!(".repair-{}", stamp));
+    let corrupt_path = unused_path(db_path, &format!(".corrupt-{}", stamp));
+
+    let src = encryption::connect(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let fresh = SqliteStorage::new(encryption::create_like(&fresh_path, db_path)?)?;
+    drop(fresh);
+    let dst = encryption::connect(&fresh_path, OpenFlags::default())?;
+    dst.execute_batch("PRAGMA foreign_keys = ON;")?;
+
+    let mut tables = Vec::new();
+    for &table in TABLES {
+        tables.push(copy_table(&src, &dst, table));
+    }
+    drop(src);
+    drop(dst);
+
+    for suffix in ["", "-wal", "-shm"] {
+        let from = with_suffix(db_path, suffix);
+        if from.exists() {
+            fs::rename(&from, with_suffix(&corrupt_path, suffix))
+                .map_err(|e| CbError::Corrupt(e.to_string()))?;
+        }
+    }
+    fs::rename(&fresh_path, db_path).map_err(|e| CbError::Corrupt(e.to_string()))?;
+
+    Ok(RepairReport {
+        tables,
+        corrupt_path,
+    })
+}
+
+fn copy_table(src: &Connection, dst: &Connection, table: &'static str) -> TableReport {
+    let mut report = TableReport {
+        table,
+        recovered: 0,
+        lost: 0,
+    };
+    // Only columns both databases know, so older schemas still copy.
+    let wanted = columns(dst, table);
+    let columns: Vec<String> = columns(src, table)
+        .into_iter()
+        .filter(|c| wanted.contains(c))
+        .collect();
+    if !columns.iter().any(|c| c == "id") {
+        return report;
+    }
+
+    let list = columns.join(", ");
+    let select = format!("SELECT {} FROM {} WHERE id = ?", list, table);
+    let insert = format!(
+        "INSERT OR IGNORE INTO {} ({}) VALUES ({})",
+        table,
+        list,
+        vec!["?"; columns.len()].join(", ")
+    );
+    for id in readable_ids(src, table) {
+        let row = src.query_row(&select, [id], |row| {
+            (0..columns.len())
+                .map(|i| row.get::<_, Value>(i))
+                .collect::<rusqlite::Result<Vec<_>>>()
+        });
+        match row.map(|values| dst.execute(&insert, params_from_iter(values))) {
+            Ok(Ok(_)) => report.recovered += 1,
+            _ => report.lost += 1,
+        }
+    }
+    report
+}
+
+fn columns(conn: &Connection, table: &str) -> Vec<String> {
+    conn.prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()
+        })
+        .unwrap_or_default()
+}
+
+/// Row IDs reachable in `table`. A damaged page ends a scan early, so scan
+/// from both ends to get past it.
+fn readable_ids(conn: &Connection, table: &str) -> Vec<i64> {
+    let mut ids = Vec::new();
+    for order in ["ASC", "DESC"] {
+        let sql = format!("SELECT id FROM {} ORDER BY id {}", table, order);
+        let Ok(mut stmt) = conn.prepare(&sql) else {
+            continue;
+        };
+        let Ok(rows) = stmt.query_map([], |row| row.get::<_, i64>(0)) else {
+            continue;
+        };
+        ids.extend(rows.map_while(|r| r.ok()));
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// `path` plus `suffix`, numbered if needed so an earlier file is never
+/// overwritten (two repairs can run within the same second).
+pub(crate) fn unused_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut candidate = with_suffix(path, suffix);
+    let mut n = 1;
+    while candidate.exists() {
+        candidate = with_suffix(path, &format!("{}-{}", suffix, n));
+        n += 1;
+    }
+    candidate
+}
+
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::hash_content;
+    use crate::storage::ClipStorage;
+    use crate::storage::models::NewClip;
+    use tempfile::TempDir;
+
+    const CLIPS: usize = 200;
+
+    fn seeded_db(dir: &Path) -> PathBuf {
+        let path = dir.join("cb.db");
+        let storage = SqliteStorage::new(Connection::open(&path).unwrap()).unwrap();
+        for i in 0..CLIPS {
+            let text = format!("clip {} {}", i, "x".repeat(200));
+            let clip = storage
+                .insert(NewClip {
+                    text_content: Some(text.clone()),
+                    hash: hash_content(text.as_bytes()),
+                    size_bytes: text.len() as i64,
+                    ..Default::default()
+                })
+                .unwrap();
+            storage.add_tag(clip.id, "kept").unwrap();
+        }
+        path
+    }
+
+    fn clip_count(path: &Path) -> i64 {
+        Connection::open(path)
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM clips", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_healthy_database_passes() {
+        let dir = TempDir::new().unwrap();
+        let path = seeded_db(dir.path());
+        assert!(check_file(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_repair_salvages_rows_around_a_damaged_page() {
+        let dir = TempDir::new().unwrap();
+        let path = seeded_db(dir.path());
+
+        // Scribble over the clips table page holding clip 100.
+        let mut bytes = fs::read(&path).unwrap();
+        let needle = b"clip 100 x";
+        let offset = bytes.windows(needle.len()).position(|w| w == needle).unwrap();
+        let page = offset / 4096 * 4096;
+        bytes[page..page + 4096].fill(0x55);
+        fs::write(&path, &bytes).unwrap();
+        assert!(!check_file(&path).unwrap().is_empty());
+
+        let report = repair(&path).unwrap();
+        let clips = &report.tables[0];
+        assert!(clips.recovered > 0 && clips.recovered < CLIPS, "{:?}", report);
+        assert_eq!(clip_count(&path), clips.recovered as i64);
+        assert!(check_file(&path).unwrap().is_empty());
+        assert!(report.corrupt_path.exists());
+
+        // The repaired database opens normally at the latest schema.
+        let storage = SqliteStorage::new(Connection::open(&path).unwrap()).unwrap();
+        assert!(storage.list(Default::default()).is_ok());
+    }
+
+    #[test]
+    fn test_repair_keeps_everything_from_a_healthy_database() {
+        let dir = TempDir::new().unwrap();
+        let path = seeded_db(dir.path());
+        let report = repair(&path).unwrap();
+        assert_eq!(report.tables[0].recovered, CLIPS);
+        assert_eq!(report.tables[1].recovered, CLIPS);
+        assert_eq!(report.tables[0].lost, 0);
+        assert_eq!(clip_count(&path), CLIPS as i64);
+
+        let again = repair(&path).unwrap();
+        assert_ne!(again.corrupt_path, report.corrupt_path);
+        assert!(report.corrupt_path.exists() && again.corrupt_path.exists());
+    }
+}
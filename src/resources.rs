@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{CbError, Result};
+
+/// Average CPU use above which `cb doctor` warns about the daemon, as a
+/// percentage of one core. An idle poll loop stays well under 1%.
+pub const HIGH_CPU_PERCENT: f64 = 5.0;
+
+/// How long the daemon must have run before its average is trusted; the
+/// first minutes include startup and any backlog of image analysis.
+const SETTLE_TIME: chrono::Duration = chrono::Duration::minutes(10);
+
+/// The daemon's own resource use since it started, written periodically to
+/// a small JSON file so `cb daemon status` and `cb doctor` can report it
+/// from another process.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ResourceUsage {
+    pub pid: u32,
+    pub since: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// User plus system CPU time across all the daemon's threads.
+    pub cpu_ms: u64,
+    /// Times the poll loop woke up.
+    pub wakeups: u64,
+}
+
+impl ResourceUsage {
+    pub fn new(pid: u32, since: DateTime<Utc>) -> Self {
+        Self {
+            pid,
+            since,
+            updated_at: since,
+            cpu_ms: 0,
+            wakeups: 0,
+        }
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// The stats written by the daemon running as `pid`, ignoring those left
+    /// behind by an earlier run.
+    pub fn for_pid(path: &Path, pid: u32) -> Option<Self> {
+        Self::load(path).filter(|usage| usage.pid == pid)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self).unwrap())
+            .map_err(|e| CbError::Daemon(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Refresh the CPU time from the OS, as of `now`.
+    pub fn sample(&mut self, now: DateTime<Utc>) {
+        if let Some(cpu) = cpu_time() {
+            self.cpu_ms = cpu.as_millis() as u64;
+        }
+        self.updated_at = now;
+    }
+
+    fn elapsed_secs(&self) -> f64 {
+        (self.updated_at - self.since).num_milliseconds().max(1) as f64 / 1000.0
+    }
+
+    /// Average CPU use over the run, as a percentage of one core.
+    pub fn cpu_percent(&self) -> f64 {
+        self.cpu_ms as f64 / 1000.0 / self.elapsed_secs() * 100.0
+    }
+
+    pub fn wakeups_per_minute(&self) -> f64 {
+        self.wakeups as f64 / self.elapsed_secs() * 60.0
+    }
+
+    /// Whether the run is long enough for its averages to mean something.
+    pub fn settled(&self) -> bool {
+        self.updated_at - self.since >= SETTLE_TIME
+    }
+}
+
+/// CPU time this process has used so far, from `getrusage`.
+pub fn cpu_time() -> Option<Duration> {
+    // SAFETY: `usage` is a plain C struct that getrusage fills in.
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    let micros = |t: libc::timeval| t.tv_sec as u64 * 1_000_000 + t.tv_usec as u64;
+    Some(Duration::from_micros(micros(usage.ru_utime) + micros(usage.ru_stime)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rates() {
+        let since = Utc::now() - chrono::Duration::minutes(20);
+        let usage = ResourceUsage {
+            updated_at: since + chrono::Duration::minutes(20),
+            cpu_ms: 12_000,
+            wakeups: 2400,
+            ..ResourceUsage::new(1, since)
+        };
+        assert!((usage.cpu_percent() - 1.0).abs() < 1e-9);
+        assert!((usage.wakeups_per_minute() - 120.0).abs() < 1e-9);
+        assert!(usage.settled());
+        assert!(!ResourceUsage::new(1, since).settled());
+    }
+
+    #[test]
+    fn test_sample_and_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("daemon.json");
+        let mut usage = ResourceUsage::new(std::process::id(), Utc::now());
+        usage.wakeups = 3;
+        usage.sample(Utc::now());
+        assert!(cpu_time().is_some());
+        usage.save(&path).unwrap();
+        assert_eq!(ResourceUsage::load(&path), Some(usage));
+    }
+}
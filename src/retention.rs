@@ -0,0 +1,123 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_MAX_AGE_DAYS: i64 = 30;
+
+/// How much history to keep. Shared by `cb clear`, the TUI, and the daemon's
+/// housekeeping so every front-end prunes the same way.
+///
+/// Pinned and protected clips, and clips carrying one of `exempt_tags`, are
+/// never removed.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Remove clips not used for this many days.
+    pub max_age_days: Option<i64>,
+    /// Keep at most this many (non-exempt) clips, newest first.
+    pub max_clips: Option<i64>,
+    /// Keep at most this many megabytes of (non-exempt) clips, newest first.
+    pub max_size_mb: Option<i64>,
+    pub exempt_tags: Vec<String>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_days: Some(DEFAULT_MAX_AGE_DAYS),
+            max_clips: None,
+            max_size_mb: None,
+            exempt_tags: vec!["snippets".to_string()],
+        }
+    }
+}
+
+impl RetentionPolicy {
+    pub fn cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.max_age_days.map(|days| now - Duration::days(days))
+    }
+
+    pub fn max_bytes(&self) -> Option<i64> {
+        self.max_size_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    /// Short summary such as `keep 30d / 5k clips / 500MB`.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(days) = self.max_age_days {
+            parts.push(format!("{}d", days));
+        }
+        if let Some(clips) = self.max_clips {
+            if clips >= 1000 && clips % 1000 == 0 {
+                parts.push(format!("{}k clips", clips / 1000));
+            } else {
+                parts.push(format!("{} clips", clips));
+            }
+        }
+        if let Some(mb) = self.max_size_mb {
+            if mb >= 1024 && mb % 1024 == 0 {
+                parts.push(format!("{}GB", mb / 1024));
+            } else {
+                parts.push(format!("{}MB", mb));
+            }
+        }
+        if parts.is_empty() {
+            "keep everything".to_string()
+        } else {
+            format!("keep {}", parts.join(" / "))
+        }
+    }
+}
+
+/// Clips with `tag` are deleted once they're `after_minutes` old, whatever
+/// the retention policy says. Pinned and protected clips are kept.
+///
+/// ```toml
+/// [[expire]]
+/// tag = "scratch"
+/// after_minutes = 25
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ExpireRule {
+    pub tag: String,
+    pub after_minutes: i64,
+}
+
+impl ExpireRule {
+    /// Clips of this tag captured before the returned time have expired.
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::minutes(self.after_minutes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        let policy = RetentionPolicy {
+            max_age_days: Some(30),
+            max_clips: Some(5000),
+            max_size_mb: Some(500),
+            exempt_tags: vec![],
+        };
+        assert_eq!(policy.describe(), "keep 30d / 5k clips / 500MB");
+        assert_eq!(RetentionPolicy::default().describe(), "keep 30d");
+        let unlimited = RetentionPolicy {
+            max_age_days: None,
+            ..Default::default()
+        };
+        assert_eq!(unlimited.describe(), "keep everything");
+    }
+
+    #[test]
+    fn test_cutoff_and_bytes() {
+        let now = Utc::now();
+        let policy = RetentionPolicy {
+            max_size_mb: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(policy.cutoff(now), Some(now - Duration::days(30)));
+        assert_eq!(policy.max_bytes(), Some(2 * 1024 * 1024));
+    }
+}
@@ -0,0 +1,130 @@
+use regex::Regex;
+
+use crate::config::TagRule;
+use crate::errors::{CbError, Result};
+
+/// A `TagRule` with its pattern compiled once up front.
+pub struct CompiledRule {
+    pub rule: TagRule,
+    pattern: Option<Regex>,
+}
+
+impl CompiledRule {
+    pub fn matches(&self, text: Option<&str>, source_app: Option<&str>) -> bool {
+        let pattern_ok = match &self.pattern {
+            Some(re) => text.is_some_and(|t| re.is_match(t)),
+            None => true,
+        };
+        let app_ok = match &self.rule.source_app {
+            Some(app) => source_app == Some(app.as_str()),
+            None => true,
+        };
+        pattern_ok && app_ok
+    }
+}
+
+#[derive(Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    pub fn compile(rules: &[TagRule]) -> Result<Self> {
+        let mut compiled = Vec::with_capacity(rules.len());
+        for (idx, rule) in rules.iter().enumerate() {
+            if rule.tag.trim().is_empty() {
+                return Err(CbError::Config(format!("rule {} has an empty tag", idx + 1)));
+            }
+            if rule.pattern.is_none() && rule.source_app.is_none() {
+                return Err(CbError::Config(format!(
+                    "rule {} (tag \"{}\") needs a pattern or source_app",
+                    idx + 1,
+                    rule.tag
+                )));
+            }
+            let pattern = match &rule.pattern {
+                Some(p) => Some(Regex::new(p).map_err(|e| {
+                    CbError::Config(format!("rule {} has an invalid pattern: {}", idx + 1, e))
+                })?),
+                None => None,
+            };
+            compiled.push(CompiledRule {
+                rule: rule.clone(),
+                pattern,
+            });
+        }
+        Ok(Self { rules: compiled })
+    }
+
+    pub fn rules(&self) -> &[CompiledRule] {
+        &self.rules
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Tags to apply to a clip, deduplicated, in rule order.
+    pub fn tags_for(&self, text: Option<&str>, source_app: Option<&str>) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for rule in &self.rules {
+            if rule.matches(text, source_app) && !tags.contains(&rule.rule.tag) {
+                tags.push(rule.rule.tag.clone());
+            }
+        }
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(tag: &str, pattern: Option<&str>, source_app: Option<&str>) -> TagRule {
+        TagRule {
+            tag: tag.to_string(),
+            pattern: pattern.map(String::from),
+            source_app: source_app.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_pattern_rule() {
+        let rules = RuleSet::compile(&[rule("jira", Some(r"jira\.company\.com"), None)]).unwrap();
+        assert_eq!(rules.tags_for(Some("https://jira.company.com/browse/X-1"), None), vec!["jira"]);
+        assert!(rules.tags_for(Some("https://example.com"), None).is_empty());
+        assert!(rules.tags_for(None, None).is_empty());
+    }
+
+    #[test]
+    fn test_source_app_rule() {
+        let rules = RuleSet::compile(&[rule("code", None, Some("com.apple.dt.Xcode"))]).unwrap();
+        assert_eq!(rules.tags_for(Some("let x = 1"), Some("com.apple.dt.Xcode")), vec!["code"]);
+        assert!(rules.tags_for(Some("let x = 1"), None).is_empty());
+    }
+
+    #[test]
+    fn test_combined_conditions_and_dedup() {
+        let rules = RuleSet::compile(&[
+            rule("code", Some("fn "), Some("com.apple.dt.Xcode")),
+            rule("rust", Some(r"\bfn\b"), None),
+            rule("rust", Some("impl"), None),
+        ])
+        .unwrap();
+        assert_eq!(rules.tags_for(Some("fn main() {} impl X"), None), vec!["rust"]);
+        assert_eq!(
+            rules.tags_for(Some("fn main()"), Some("com.apple.dt.Xcode")),
+            vec!["code", "rust"]
+        );
+    }
+
+    #[test]
+    fn test_compile_rejects_invalid_rules() {
+        assert!(RuleSet::compile(&[rule("x", None, None)]).is_err());
+        assert!(RuleSet::compile(&[rule("", Some("a"), None)]).is_err());
+        assert!(matches!(
+            RuleSet::compile(&[rule("x", Some("("), None)]),
+            Err(CbError::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// How far apart a screenshot file's modification time and the clipboard
+/// capture may be for the two to be paired. macOS writes the file only once
+/// its floating thumbnail goes away, a few seconds after copying.
+pub const PAIR_WINDOW: Duration = Duration::seconds(60);
+
+/// Only PNG is decoded, which is what macOS saves screenshots as by default.
+const EXTENSIONS: &[&str] = &["png"];
+
+/// Where macOS saves screenshots: the `com.apple.screencapture location`
+/// default, or `~/Desktop` when it isn't set.
+pub fn default_dir() -> Option<PathBuf> {
+    let configured = Command::new("defaults")
+        .args(["read", "com.apple.screencapture", "location"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|s| !s.is_empty());
+    let home = dirs::home_dir();
+    match configured {
+        Some(dir) => match (dir.strip_prefix("~/"), home) {
+            (Some(rest), Some(home)) => Some(home.join(rest)),
+            _ => Some(PathBuf::from(dir)),
+        },
+        None => home.map(|h| h.join("Desktop")),
+    }
+}
+
+/// The screenshot in `dir` most likely saved alongside an image copied at
+/// `captured_at`: same pixel dimensions, modified within `PAIR_WINDOW`, and
+/// not in `taken`. The closest in time wins.
+pub fn find_original(
+    dir: &Path,
+    width: u32,
+    height: u32,
+    captured_at: DateTime<Utc>,
+    taken: &[String],
+) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        })
+        .filter(|path| !taken.iter().any(|t| Path::new(t) == path))
+        .filter_map(|path| {
+            let modified: DateTime<Utc> = fs::metadata(&path).ok()?.modified().ok()?.into();
+            let gap = (modified - captured_at).abs();
+            (gap <= PAIR_WINDOW).then_some((gap, path))
+        })
+        .filter(|(_, path)| image::image_dimensions(path).ok() == Some((width, height)))
+        .min_by_key(|(gap, _)| *gap)
+        .map(|(_, path)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_original_matches_size_and_time() {
+        let dir = TempDir::new().unwrap();
+        let shot = dir.path().join("Screenshot 2024-05-20 at 10.00.00.png");
+        RgbaImage::new(40, 30).save(&shot).unwrap();
+        RgbaImage::new(10, 10)
+            .save(dir.path().join("other.png"))
+            .unwrap();
+        fs::write(dir.path().join("notes.txt"), "hi").unwrap();
+
+        let now = Utc::now();
+        assert_eq!(
+            find_original(dir.path(), 40, 30, now, &[]),
+            Some(shot.clone())
+        );
+        assert_eq!(find_original(dir.path(), 20, 20, now, &[]), None);
+        assert_eq!(
+            find_original(dir.path(), 40, 30, now - Duration::minutes(10), &[]),
+            None
+        );
+        let taken = vec![shot.to_string_lossy().to_string()];
+        assert_eq!(find_original(dir.path(), 40, 30, now, &taken), None);
+    }
+}
@@ -0,0 +1,219 @@
+use std::io::{BufRead, Write};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::errors::{CbError, Result};
+use crate::storage::ClipStorage;
+use crate::storage::models::{Clip, ClipFilter};
+use crate::suggest;
+use crate::thumbs;
+
+const DEFAULT_LIMIT: i64 = 50;
+
+/// A single request line: `{"id": 1, "method": "search", "params": {"query": "foo"}}`.
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Serve line-delimited JSON requests until the reader hits EOF.
+///
+/// Every request produces exactly one response line carrying the same `id`,
+/// either `{"id": .., "result": ..}` or `{"id": .., "error": ".."}`, so editor
+/// plugins can keep one process open and pipeline queries as the user types.
+pub fn serve_lines<S, R, W>(storage: &S, reader: R, mut writer: W) -> Result<()>
+where
+    S: ClipStorage,
+    R: BufRead,
+    W: Write,
+{
+    for line in reader.lines() {
+        let line = line.map_err(|e| CbError::InvalidInput(e.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = handle_line(storage, &line);
+        writeln!(writer, "{}", response).map_err(|e| CbError::InvalidInput(e.to_string()))?;
+        writer.flush().map_err(|e| CbError::InvalidInput(e.to_string()))?;
+    }
+    Ok(())
+}
+
+fn handle_line<S: ClipStorage>(storage: &S, line: &str) -> Value {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(r) => r,
+        Err(e) => return json!({"id": Value::Null, "error": format!("Malformed request: {}", e)}),
+    };
+    match dispatch(storage, &request.method, &request.params) {
+        Ok(result) => json!({"id": request.id, "result": result}),
+        Err(e) => json!({"id": request.id, "error": e.to_string()}),
+    }
+}
+
+pub(crate) fn dispatch<S: ClipStorage>(storage: &S, method: &str, params: &Value) -> Result<Value> {
+    match method {
+        "recent" => {
+            let clips = storage.list(ClipFilter {
+                limit: param_i64(params, "limit").unwrap_or(DEFAULT_LIMIT),
+                offset: param_i64(params, "offset").unwrap_or(0),
+                ..Default::default()
+            })?;
+            Ok(clips.iter().map(clip_json).collect())
+        }
+        "search" => {
+            let query = param_str(params, "query")
+                .ok_or_else(|| CbError::InvalidInput("search requires a \"query\" param".into()))?;
+            let limit = param_i64(params, "limit").unwrap_or(DEFAULT_LIMIT);
+            let clips = storage.search(query, limit)?;
+            Ok(clips.iter().map(clip_json).collect())
+        }
+        "get" => {
+            let id = param_i64(params, "id")
+                .ok_or_else(|| CbError::InvalidInput("get requires an \"id\" param".into()))?;
+            let clip = storage.get_by_id(id)?;
+            Ok(clip_json(&clip))
+        }
+        "suggest" => {
+            let context = param_str(params, "context")
+                .ok_or_else(|| CbError::InvalidInput("suggest requires a \"context\" param".into()))?;
+            let scorer = suggest::scorer(param_str(params, "scorer").unwrap_or("blend"))?;
+            let limit = param_i64(params, "limit").unwrap_or(suggest::DEFAULT_LIMIT);
+            let found = suggest::suggest(
+                storage,
+                context,
+                scorer.as_ref(),
+                suggest::DEFAULT_POOL,
+                limit.max(0) as usize,
+            )?;
+            Ok(found
+                .iter()
+                .map(|s| {
+                    let mut value = clip_json(&s.clip);
+                    value["score"] = s.score.into();
+                    value["matched"] = s.matched.clone().into();
+                    value
+                })
+                .collect())
+        }
+        other => Err(CbError::InvalidInput(format!("Unknown method \"{}\"", other))),
+    }
+}
+
+/// A clip as JSON, with `thumb_path` pointing at a small preview for images
+/// so clients don't have to load the full-size original.
+pub(crate) fn clip_json(clip: &Clip) -> Value {
+    let mut value = serde_json::to_value(clip).unwrap();
+    if let Some(thumb) = clip
+        .image_path
+        .as_deref()
+        .and_then(|path| thumbs::ensure(std::path::Path::new(path)).ok())
+    {
+        value["thumb_path"] = thumb.to_string_lossy().into();
+    }
+    value
+}
+
+fn param_i64(params: &Value, key: &str) -> Option<i64> {
+    params.get(key).and_then(Value::as_i64)
+}
+
+fn param_str<'a>(params: &'a Value, key: &str) -> Option<&'a str> {
+    params.get(key).and_then(Value::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::hash_content;
+    use crate::storage::models::{ContentType, NewClip};
+    use crate::storage::sqlite::SqliteStorage;
+
+    fn seeded_storage() -> SqliteStorage {
+        let storage = SqliteStorage::in_memory().unwrap();
+        for text in ["alpha one", "beta two", "alpha three"] {
+            storage
+                .insert(NewClip {
+                    content_type: ContentType::Text,
+                    text_content: Some(text.to_string()),
+                    hash: hash_content(text.as_bytes()),
+                    size_bytes: text.len() as i64,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        storage
+    }
+
+    fn run(storage: &SqliteStorage, input: &str) -> Vec<Value> {
+        let mut out = Vec::new();
+        serve_lines(storage, input.as_bytes(), &mut out).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_recent_respects_limit() {
+        let storage = seeded_storage();
+        let responses = run(&storage, r#"{"id":1,"method":"recent","params":{"limit":2}}"#);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_search_and_get() {
+        let storage = seeded_storage();
+        let input = concat!(
+            r#"{"id":"a","method":"search","params":{"query":"alpha"}}"#,
+            "\n",
+            r#"{"id":"b","method":"get","params":{"id":2}}"#,
+            "\n",
+        );
+        let responses = run(&storage, input);
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["result"].as_array().unwrap().len(), 2);
+        assert_eq!(responses[1]["id"], "b");
+        assert_eq!(responses[1]["result"]["text_content"], "beta two");
+    }
+
+    #[test]
+    fn test_image_results_include_thumbnail() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let original = dir.path().join("shot.png");
+        image::RgbaImage::new(600, 300).save(&original).unwrap();
+
+        let storage = SqliteStorage::in_memory().unwrap();
+        storage
+            .insert(NewClip {
+                content_type: ContentType::Image,
+                image_path: Some(original.to_string_lossy().into()),
+                hash: "img".into(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let responses = run(&storage, r#"{"id":1,"method":"get","params":{"id":1}}"#);
+        let thumb = responses[0]["result"]["thumb_path"].as_str().unwrap();
+        assert_eq!(thumb, thumbs::path_for(&original).to_string_lossy());
+        assert_eq!(image::open(thumb).unwrap().width(), thumbs::THUMB_SIZE);
+    }
+
+    #[test]
+    fn test_errors_are_reported_per_line() {
+        let storage = seeded_storage();
+        let input = "not json\n\n{\"id\":3,\"method\":\"get\",\"params\":{\"id\":99}}\n{\"id\":4,\"method\":\"nope\"}\n";
+        let responses = run(&storage, input);
+        assert_eq!(responses.len(), 3);
+        assert!(responses[0]["error"].as_str().unwrap().starts_with("Malformed"));
+        assert_eq!(responses[1]["id"], 3);
+        assert!(responses[1]["error"].as_str().unwrap().contains("not found"));
+        assert!(responses[2]["error"].as_str().unwrap().contains("Unknown method"));
+    }
+}
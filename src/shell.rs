@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use crate::errors::{CbError, Result};
+use crate::transform::shell_escape;
+
+/// Snippet for `eval "$(cb hook <shell>)"` that samples the clipboard with
+/// `cb capture --quiet` whenever the shell shows a prompt (and, in zsh,
+/// before each command). Captures run in the background so the prompt
+/// never waits on them.
+pub fn prompt_hook(shell: &str, exe: &Path) -> Result<String> {
+    let exe = shell_escape(&exe.display().to_string());
+    match shell {
+        "zsh" => Ok(format!(
+            "# cb: capture the clipboard at each prompt and before each command\n\
+             _cb_capture() {{ {} capture --quiet >/dev/null 2>&1 &! }}\n\
+             autoload -Uz add-zsh-hook\n\
+             add-zsh-hook precmd _cb_capture\n\
+             add-zsh-hook preexec _cb_capture\n",
+            exe
+        )),
+        "bash" => Ok(format!(
+            "# cb: capture the clipboard at each prompt\n\
+             _cb_capture() {{ ({} capture --quiet >/dev/null 2>&1 &) }}\n\
+             case \";$PROMPT_COMMAND;\" in\n  \
+               *\";_cb_capture;\"*) ;;\n  \
+               *) PROMPT_COMMAND=\"_cb_capture${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}\" ;;\n\
+             esac\n",
+            exe
+        )),
+        _ => Err(CbError::InvalidInput(format!(
+            "unsupported shell \"{}\" (expected zsh or bash)",
+            shell
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_hook() {
+        let zsh = prompt_hook("zsh", Path::new("/opt/my tools/cb")).unwrap();
+        assert!(
+            zsh.contains("_cb_capture() { '/opt/my tools/cb' capture --quiet >/dev/null 2>&1 &! }")
+        );
+        assert!(zsh.contains("add-zsh-hook precmd _cb_capture"));
+
+        let bash = prompt_hook("bash", Path::new("/usr/local/bin/cb")).unwrap();
+        assert!(bash.contains("PROMPT_COMMAND=\"_cb_capture${PROMPT_COMMAND:+;$PROMPT_COMMAND}\""));
+        assert!(prompt_hook("fish", Path::new("cb")).is_err());
+    }
+}
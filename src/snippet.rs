@@ -0,0 +1,229 @@
+use serde::Serialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::storage::models::Clip;
+use crate::width;
+
+/// A window of a clip's content around the first match of a search query.
+///
+/// Offsets count characters, not bytes or columns. `match_start..match_end`
+/// locate the match within `text`; `offset` is where it starts in the original
+/// field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snippet {
+    /// Which field matched: `text` or `ocr`.
+    pub field: &'static str,
+    pub text: String,
+    pub match_start: usize,
+    pub match_end: usize,
+    pub offset: usize,
+}
+
+/// Snippet for `clip`, preferring its text over recognized OCR text, at most
+/// `width` terminal columns wide. `None` when neither field contains `query`.
+pub fn for_clip(clip: &Clip, query: &str, width: usize) -> Option<Snippet> {
+    let fields = [("text", clip.text_content.as_deref()), ("ocr", clip.ocr_text.as_deref())];
+    fields.into_iter().find_map(|(field, value)| {
+        let mut snippet = extract(value?, query, width)?;
+        snippet.field = field;
+        Some(snippet)
+    })
+}
+
+/// Cut a single-line window of `haystack`, at most `width` columns wide (see
+/// `width::of`), around the first case-insensitive match of `query`, marking
+/// cut ends with `…`. The window never splits a grapheme cluster. Newlines
+/// become `↵` so character offsets stay valid.
+pub fn extract(haystack: &str, query: &str, width: usize) -> Option<Snippet> {
+    let line: String = haystack
+        .chars()
+        .map(|c| if c == '\n' { '↵' } else { c })
+        .collect();
+    let chars: Vec<char> = line.chars().collect();
+    let needle: Vec<char> = query.chars().collect();
+    let offset = find_ignore_case(&chars, &needle)?;
+    let m = needle.len();
+
+    // Each grapheme cluster as (first char index, its text, columns).
+    let mut graphemes = Vec::new();
+    let mut char_idx = 0;
+    for g in line.graphemes(true) {
+        graphemes.push((char_idx, g, width::of(g)));
+        char_idx += g.chars().count();
+    }
+    let n = graphemes.len();
+    let cols = |range: std::ops::Range<usize>| -> usize {
+        graphemes[range].iter().map(|g| g.2).sum()
+    };
+    // The clusters the match falls in.
+    let first = graphemes.iter().rposition(|g| g.0 <= offset).unwrap_or(0);
+    let last = graphemes.iter().position(|g| g.0 >= offset + m).unwrap_or(n);
+
+    let matched = cols(first..last);
+    let budget = width.max(matched + 2);
+    let (mut start, mut end) = (0, n);
+    if cols(0..n) > budget {
+        // Centre the match, then give any room left on one side to the other.
+        let mut used = matched;
+        start = first;
+        while start > 0 && used + graphemes[start - 1].2 <= matched + (budget - matched) / 2 {
+            start -= 1;
+            used += graphemes[start].2;
+        }
+        end = last;
+        while end < n && used + graphemes[end].2 <= budget {
+            used += graphemes[end].2;
+            end += 1;
+        }
+        while start > 0 && used + graphemes[start - 1].2 <= budget {
+            start -= 1;
+            used += graphemes[start].2;
+        }
+        // Free a column for each ellipsis without cutting into the match.
+        if start > 0 {
+            let freed = start;
+            while start < first && cols(freed..start) == 0 {
+                start += 1;
+            }
+        }
+        if end < n {
+            let freed = end;
+            while end > last && cols(end..freed) == 0 {
+                end -= 1;
+            }
+        }
+    }
+
+    let lead = if start > 0 { "…" } else { "" };
+    let trail = if end < n { "…" } else { "" };
+    let body: String = graphemes[start..end].iter().map(|g| g.1).collect();
+    let start_char = graphemes.get(start).map_or(chars.len(), |g| g.0);
+    let match_start = offset - start_char + lead.chars().count();
+    Some(Snippet {
+        field: "text",
+        text: format!("{}{}{}", lead, body, trail),
+        match_start,
+        match_end: match_start + m,
+        offset,
+    })
+}
+
+/// How well `clip` matches `query`: 3 when it is the whole (trimmed)
+/// content, 2 for a prefix, 1 for a match anywhere else, 0 for none.
+pub fn score(clip: &Clip, query: &str) -> u8 {
+    let needle: Vec<char> = query.trim().chars().collect();
+    [clip.text_content.as_deref(), clip.ocr_text.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(|value| {
+            let chars: Vec<char> = value.trim().chars().collect();
+            match find_ignore_case(&chars, &needle) {
+                Some(0) if chars.len() == needle.len() => 3,
+                Some(0) => 2,
+                Some(_) => 1,
+                None => 0,
+            }
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Sort search results best match first, keeping recency order among ties.
+pub fn rank(clips: &mut [Clip], query: &str) {
+    clips.sort_by_cached_key(|clip| std::cmp::Reverse(score(clip, query)));
+}
+
+fn find_ignore_case(haystack: &[char], needle: &[char]) -> Option<usize> {
+    if needle.is_empty() || needle.len() > haystack.len() {
+        return None;
+    }
+    (0..=haystack.len() - needle.len()).find(|&i| {
+        needle
+            .iter()
+            .zip(&haystack[i..])
+            .all(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marked(s: &Snippet) -> String {
+        let chars: Vec<char> = s.text.chars().collect();
+        let before: String = chars[..s.match_start].iter().collect();
+        let hit: String = chars[s.match_start..s.match_end].iter().collect();
+        let after: String = chars[s.match_end..].iter().collect();
+        format!("{}[{}]{}", before, hit, after)
+    }
+
+    #[test]
+    fn test_short_text_is_kept_whole() {
+        let s = extract("run Docker login now", "docker", 60).unwrap();
+        assert_eq!(marked(&s), "run [Docker] login now");
+        assert_eq!(s.offset, 4);
+    }
+
+    #[test]
+    fn test_long_text_is_windowed_around_match() {
+        let text = format!("{} needle {}", "a".repeat(100), "b".repeat(100));
+        let s = extract(&text, "NEEDLE", 20).unwrap();
+        assert_eq!(s.text.chars().count(), 20);
+        assert!(s.text.starts_with('…') && s.text.ends_with('…'));
+        assert_eq!(marked(&s), "…aaaaa [needle] bbbbb…");
+        assert_eq!(s.offset, 101);
+    }
+
+    #[test]
+    fn test_match_at_edges_and_newlines() {
+        let text = format!("token\n{}", "x".repeat(50));
+        let s = extract(&text, "token", 12).unwrap();
+        assert_eq!(marked(&s), "[token]↵xxxxx…");
+
+        let s = extract(&format!("{}é-end", "y".repeat(50)), "é-END", 12).unwrap();
+        assert_eq!(marked(&s), "…yyyyyy[é-end]");
+    }
+
+    #[test]
+    fn test_score_and_rank() {
+        let clip = |id: i64, text: &str| Clip {
+            id,
+            text_content: Some(text.into()),
+            ..Default::default()
+        };
+        let mut clips = vec![
+            clip(3, "sudo docker login -u me"),
+            clip(2, "docker login"),
+            clip(1, "Docker login -u ci"),
+        ];
+        assert_eq!(score(&clips[0], "docker login"), 1);
+        assert_eq!(score(&clips[1], " Docker Login "), 3);
+        assert_eq!(score(&clips[2], "docker login"), 2);
+        assert_eq!(score(&clips[0], "kubectl"), 0);
+
+        rank(&mut clips, "docker login");
+        let ids: Vec<i64> = clips.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn test_wide_text_is_windowed_by_columns() {
+        let text = format!("{}needle{}", "漢".repeat(30), "字".repeat(30));
+        let s = extract(&text, "needle", 20).unwrap();
+        assert!(width::of(&s.text) <= 20);
+        assert_eq!(marked(&s), "…漢漢[needle]字字字…");
+        assert_eq!(s.offset, 30);
+
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let text = format!("{} key {}", family.repeat(10), family.repeat(10));
+        let s = extract(&text, "key", 13).unwrap();
+        assert!(width::of(&s.text) <= 13);
+        assert_eq!(marked(&s), format!("…{} [key] {}…", family, family));
+    }
+
+    #[test]
+    fn test_no_match() {
+        assert!(extract("hello", "world", 20).is_none());
+        assert!(extract("hello", "", 20).is_none());
+    }
+}
@@ -0,0 +1,221 @@
+use regex::Regex;
+use serde::Serialize;
+
+use crate::errors::{CbError, Result};
+use crate::hash::hash_content;
+use crate::storage::ClipStorage;
+use crate::storage::models::{ContentType, NewClip};
+
+/// How `cb split` cuts a clip's text into segments.
+#[derive(Debug, Clone)]
+pub enum SplitMode {
+    /// One segment per line.
+    Lines,
+    /// Paragraphs separated by one or more blank lines.
+    BlankLines,
+    /// Segments separated by matches of a pattern.
+    Regex(Regex),
+}
+
+impl SplitMode {
+    /// `lines`, `blank-lines`, or `regex` (which needs `pattern`).
+    pub fn parse(by: &str, pattern: Option<&str>) -> Result<Self> {
+        match (by, pattern) {
+            ("lines", None) => Ok(SplitMode::Lines),
+            ("blank-lines", None) => Ok(SplitMode::BlankLines),
+            ("regex", Some(pattern)) => Regex::new(pattern)
+                .map(SplitMode::Regex)
+                .map_err(|e| CbError::InvalidInput(format!("invalid pattern: {}", e))),
+            ("regex", None) => Err(CbError::InvalidInput("--by regex needs a --pattern".into())),
+            ("lines" | "blank-lines", Some(_)) => Err(CbError::InvalidInput(
+                "--pattern only applies to --by regex".into(),
+            )),
+            (other, _) => Err(CbError::InvalidInput(format!(
+                "unknown split mode \"{}\" (use lines, blank-lines, or regex)",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SplitSummary {
+    /// Tag shared by every segment's clip.
+    pub tag: String,
+    pub created: i64,
+    /// Segments already in history; they are tagged but not duplicated.
+    pub duplicates: i64,
+}
+
+/// The non-empty segments of `text`, trimmed, with repeats dropped so each
+/// appears once in the order first seen.
+pub fn segments(text: &str, mode: &SplitMode) -> Vec<String> {
+    let pieces: Vec<&str> = match mode {
+        SplitMode::Lines => text.lines().collect(),
+        SplitMode::BlankLines => {
+            let mut pieces = Vec::new();
+            let mut start = None;
+            let mut end = 0;
+            for (offset, line) in line_offsets(text) {
+                if line.trim().is_empty() {
+                    if let Some(s) = start.take() {
+                        pieces.push(&text[s..end]);
+                    }
+                } else {
+                    start.get_or_insert(offset);
+                    end = offset + line.len();
+                }
+            }
+            if let Some(s) = start {
+                pieces.push(&text[s..end]);
+            }
+            pieces
+        }
+        SplitMode::Regex(re) => re.split(text).collect(),
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    pieces
+        .into_iter()
+        .map(str::trim)
+        .filter(|p| !p.is_empty() && seen.insert(*p))
+        .map(String::from)
+        .collect()
+}
+
+fn line_offsets(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+    text.split_inclusive('\n').map(move |line| {
+        let start = offset;
+        offset += line.len();
+        (start, line.trim_end_matches(['\n', '\r']))
+    })
+}
+
+/// Create one text clip per segment of clip `id`'s text, all tagged with
+/// `tag` (default `split-<id>`). The original clip is left as it is.
+pub fn split_clip<S: ClipStorage>(
+    storage: &S,
+    id: i64,
+    mode: &SplitMode,
+    tag: Option<&str>,
+) -> Result<SplitSummary> {
+    let clip = storage.get_by_id(id)?;
+    let text = match (&clip.content_type, clip.text_content) {
+        (ContentType::Text, Some(text)) => text,
+        _ => {
+            return Err(CbError::InvalidInput(format!(
+                "clip #{} is not a text clip",
+                id
+            )));
+        }
+    };
+    let parts = segments(&text, mode);
+    if parts.len() < 2 {
+        return Err(CbError::InvalidInput(format!(
+            "clip #{} has nothing to split",
+            id
+        )));
+    }
+
+    let mut summary = SplitSummary {
+        tag: tag.map_or_else(|| format!("split-{}", id), String::from),
+        ..Default::default()
+    };
+    for part in parts {
+        let hash = hash_content(part.as_bytes());
+        let clip = match storage.find_by_hash(&hash)? {
+            Some(existing) => {
+                if existing.deleted_at.is_some() {
+                    storage.restore(existing.id)?;
+                }
+                summary.duplicates += 1;
+                existing
+            }
+            None => {
+                summary.created += 1;
+                storage.insert(NewClip {
+                    content_type: ContentType::Text,
+                    size_bytes: part.len() as i64,
+                    text_content: Some(part),
+                    hash,
+                    ..Default::default()
+                })?
+            }
+        };
+        storage.add_tag(clip.id, &summary.tag)?;
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::ClipFilter;
+    use crate::storage::sqlite::SqliteStorage;
+
+    fn text_clip(storage: &SqliteStorage, text: &str) -> i64 {
+        storage
+            .insert(NewClip {
+                text_content: Some(text.into()),
+                hash: hash_content(text.as_bytes()),
+                size_bytes: text.len() as i64,
+                ..Default::default()
+            })
+            .unwrap()
+            .id
+    }
+
+    #[test]
+    fn test_segments() {
+        let text = "  a\n\nb\r\nc\n a \n";
+        assert_eq!(segments(text, &SplitMode::Lines), vec!["a", "b", "c"]);
+
+        let text = "one\ntwo\n\n  \n\nthree\n";
+        assert_eq!(
+            segments(text, &SplitMode::BlankLines),
+            vec!["one\ntwo", "three"]
+        );
+
+        let mode = SplitMode::parse("regex", Some(r"\s*[,;]\s*")).unwrap();
+        assert_eq!(segments("x, y;z,,x", &mode), vec!["x", "y", "z"]);
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert!(matches!(
+            SplitMode::parse("lines", None),
+            Ok(SplitMode::Lines)
+        ));
+        assert!(SplitMode::parse("regex", None).is_err());
+        assert!(SplitMode::parse("regex", Some("(")).is_err());
+        assert!(SplitMode::parse("lines", Some(",")).is_err());
+        assert!(SplitMode::parse("words", None).is_err());
+    }
+
+    #[test]
+    fn test_split_clip_tags_each_segment() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        text_clip(&storage, "https://b.example");
+        let id = text_clip(
+            &storage,
+            "https://a.example\nhttps://b.example\nhttps://c.example\n",
+        );
+
+        let summary = split_clip(&storage, id, &SplitMode::Lines, None).unwrap();
+        assert_eq!((summary.created, summary.duplicates), (2, 1));
+        assert_eq!(summary.tag, format!("split-{}", id));
+
+        let tagged = storage
+            .list(ClipFilter {
+                tag: Some(summary.tag.clone()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(tagged.len(), 3);
+        assert!(storage.get_by_id(id).unwrap().text_content.is_some());
+
+        let single = text_clip(&storage, "just one line");
+        assert!(split_clip(&storage, single, &SplitMode::Lines, Some("x")).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{CbError, Result};
+use crate::storage::ClipStorage;
+use crate::storage::models::{Clip, ClipFilter, ContentType};
+use crate::title;
+
+pub const DEFAULT_MAX_CLIPS: i64 = 200;
+
+/// Longest text written per file, in bytes. Spotlight only needs enough to
+/// find the clip; `cb copy` gets the rest.
+const MAX_FILE_BYTES: usize = 16 * 1024;
+
+/// Widest title used in a file name, in columns.
+const NAME_WIDTH: usize = 60;
+
+/// Files the exporter writes, so a sync only ever removes its own.
+const EXTENSIONS: &[&str] = &["md", "textClipping"];
+
+/// Recent text clips mirrored as small files into a folder Spotlight indexes,
+/// so system search finds them. Kept in sync by the daemon, which removes the
+/// folder's files again once the section is gone from the config.
+///
+/// ```toml
+/// [spotlight]
+/// format = "md"
+/// max_clips = 200
+/// max_age_days = 14
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SpotlightExport {
+    /// Folder to write to (default `~/Documents/cb Clips`).
+    pub dir: Option<PathBuf>,
+    pub format: ExportFormat,
+    /// Export at most this many of the newest text clips.
+    pub max_clips: i64,
+    /// Leave out clips first captured more than this many days ago.
+    pub max_age_days: Option<i64>,
+}
+
+impl Default for SpotlightExport {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            format: ExportFormat::default(),
+            max_clips: DEFAULT_MAX_CLIPS,
+            max_age_days: None,
+        }
+    }
+}
+
+impl SpotlightExport {
+    pub fn dir(&self) -> Option<PathBuf> {
+        self.dir.clone().or_else(default_dir)
+    }
+}
+
+/// `~/Documents/cb Clips`.
+pub fn default_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join("Documents").join("cb Clips"))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub enum ExportFormat {
+    /// Markdown with the clip's title as a heading.
+    #[default]
+    #[serde(rename = "md")]
+    Markdown,
+    /// A macOS text clipping, which opens in a preview window and can be
+    /// dragged back into any app.
+    #[serde(rename = "textClipping")]
+    TextClipping,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::TextClipping => "textClipping",
+        }
+    }
+
+    fn render(self, clip: &Clip, text: &str) -> String {
+        match self {
+            ExportFormat::Markdown => match clip.title {
+                Some(ref title) => format!("# {}\n\n{}\n", title, text),
+                None => format!("{}\n", text),
+            },
+            ExportFormat::TextClipping => format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+                 <plist version=\"1.0\">\n<dict>\n\
+                 \t<key>UTI-Data</key>\n\t<dict>\n\
+                 \t\t<key>public.utf8-plain-text</key>\n\t\t<string>{}</string>\n\
+                 \t</dict>\n</dict>\n</plist>\n",
+                escape_xml(text)
+            ),
+        }
+    }
+}
+
+/// What a sync changed.
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncSummary {
+    pub written: usize,
+    pub removed: usize,
+}
+
+/// Bring the export folder in line with history: write a file for each clip
+/// `export` covers whose file is missing or out of date, and remove exported
+/// files for clips it no longer covers. Other files in the folder are left
+/// alone.
+pub fn sync<S: ClipStorage>(storage: &S, export: &SpotlightExport, dir: &Path) -> Result<SyncSummary> {
+    let io = |e: std::io::Error| CbError::Daemon(format!("{}: {}", dir.display(), e));
+    fs::create_dir_all(dir).map_err(io)?;
+    let clips = storage.list(ClipFilter {
+        content_type: Some(ContentType::Text),
+        after: export
+            .max_age_days
+            .map(|days| Utc::now() - Duration::days(days)),
+        limit: export.max_clips.max(1),
+        ..Default::default()
+    })?;
+
+    let mut summary = SyncSummary::default();
+    let mut wanted = HashSet::new();
+    for clip in &clips {
+        let Some(ref text) = clip.text_content else {
+            continue;
+        };
+        let name = file_name(clip, export.format);
+        let path = dir.join(&name);
+        wanted.insert(name);
+        let contents = export.format.render(clip, cut(text, MAX_FILE_BYTES));
+        if fs::read_to_string(&path).is_ok_and(|existing| existing == contents) {
+            continue;
+        }
+        fs::write(&path, contents).map_err(io)?;
+        // Spotlight shows and sorts by modification date, so match the clip.
+        let modified = SystemTime::from(clip.updated_at);
+        if let Ok(file) = fs::File::options().write(true).open(&path) {
+            let _ = file.set_modified(modified);
+        }
+        summary.written += 1;
+    }
+
+    for path in exported_files(dir)? {
+        let stale = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| !wanted.contains(n));
+        if stale {
+            fs::remove_file(&path).map_err(io)?;
+            summary.removed += 1;
+        }
+    }
+    Ok(summary)
+}
+
+/// Remove every exported file from `dir`, and `dir` itself once it's empty.
+/// Returns how many files were removed.
+pub fn remove(dir: &Path) -> Result<usize> {
+    if !dir.is_dir() {
+        return Ok(0);
+    }
+    let io = |e: std::io::Error| CbError::Daemon(format!("{}: {}", dir.display(), e));
+    let files = exported_files(dir)?;
+    for path in &files {
+        fs::remove_file(path).map_err(io)?;
+    }
+    // Fails, harmlessly, when the user keeps other files there.
+    let _ = fs::remove_dir(dir);
+    Ok(files.len())
+}
+
+/// `<title> #<id>.<ext>`, with characters Finder can't show in a name
+/// replaced.
+fn file_name(clip: &Clip, format: ExportFormat) -> String {
+    let title = clip
+        .title
+        .as_deref()
+        .map(|t| t.replace(['/', ':'], "-"))
+        .map(|t| title::shorten(t.trim_start_matches('.'), NAME_WIDTH))
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "Clip".to_string());
+    format!("{} #{}.{}", title, clip.id, format.extension())
+}
+
+/// Files in `dir` named like `file_name` makes them.
+fn exported_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| CbError::Daemon(format!("{}: {}", dir.display(), e)))?;
+    Ok(entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| is_exported(path))
+        .collect())
+}
+
+fn is_exported(path: &Path) -> bool {
+    let ext_ok = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXTENSIONS.contains(&e));
+    let id_ok = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(|s| s.rsplit_once(" #"))
+        .is_some_and(|(_, id)| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()));
+    ext_ok && id_ok
+}
+
+/// `s` cut to at most `max` bytes on a character boundary.
+fn cut(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::hash_content;
+    use crate::storage::models::NewClip;
+    use crate::storage::sqlite::SqliteStorage;
+    use tempfile::TempDir;
+
+    fn add(storage: &SqliteStorage, text: &str) -> Clip {
+        storage
+            .insert(NewClip {
+                content_type: ContentType::Text,
+                text_content: Some(text.to_string()),
+                hash: hash_content(text.as_bytes()),
+                size_bytes: text.len() as i64,
+                ..Default::default()
+            })
+            .unwrap()
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_sync_writes_newest_and_prunes_the_rest() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().join("export");
+        let storage = SqliteStorage::in_memory().unwrap();
+        let old = add(&storage, "Old note");
+        add(&storage, "Deploy steps\nrsync -a site/ host:");
+        let export = SpotlightExport {
+            max_clips: 1,
+            ..Default::default()
+        };
+
+        let first = sync(&storage, &export, &dir).unwrap();
+        assert_eq!(first, SyncSummary { written: 1, removed: 0 });
+        assert_eq!(names(&dir), vec!["Deploy steps #2.md"]);
+        let md = fs::read_to_string(dir.join("Deploy steps #2.md")).unwrap();
+        assert_eq!(md, "# Deploy steps\n\nDeploy steps\nrsync -a site/ host:\n");
+
+        // Unchanged files aren't rewritten; foreign files are left alone.
+        fs::write(dir.join("notes.md"), "mine").unwrap();
+        assert_eq!(sync(&storage, &export, &dir).unwrap(), SyncSummary::default());
+
+        storage.delete(2).unwrap();
+        let after = sync(&storage, &export, &dir).unwrap();
+        assert_eq!(after, SyncSummary { written: 1, removed: 1 });
+        assert_eq!(names(&dir), vec![format!("Old note #{}.md", old.id), "notes.md".to_string()]);
+
+        assert_eq!(remove(&dir).unwrap(), 1);
+        assert_eq!(names(&dir), vec!["notes.md"]);
+    }
+
+    #[test]
+    fn test_text_clipping_escapes_xml() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        let clip = add(&storage, "a < b && c");
+        let plist = ExportFormat::TextClipping.render(&clip, clip.text_content.as_deref().unwrap());
+        assert!(plist.contains("<string>a &lt; b &amp;&amp; c</string>"));
+        assert_eq!(file_name(&clip, ExportFormat::TextClipping), "a < b && c #1.textClipping");
+    }
+
+    #[test]
+    fn test_file_names_are_safe_and_recognized() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        let clip = add(&storage, "http://example.com/a/b");
+        let name = file_name(&clip, ExportFormat::Markdown);
+        assert_eq!(name, "http---example.com-a-b #1.md");
+        assert!(is_exported(Path::new(&name)));
+        assert!(!is_exported(Path::new("Meeting #notes.md")));
+        assert!(!is_exported(Path::new("Clip #3.txt")));
+    }
+
+    #[test]
+    fn test_config_section() {
+        let export: SpotlightExport = toml::from_str("format = \"textClipping\"\nmax_age_days = 7").unwrap();
+        assert_eq!(export.format, ExportFormat::TextClipping);
+        assert_eq!(export.max_clips, DEFAULT_MAX_CLIPS);
+        assert_eq!(export.max_age_days, Some(7));
+    }
+}
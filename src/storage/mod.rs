@@ -2,22 +2,135 @@ pub mod models;
 pub mod schema;
 pub mod sqlite;
 
+use std::path::Path;
+
 use chrono::{DateTime, Utc};
 
+use crate::colors::Descriptor;
 use crate::errors::Result;
-use models::{Clip, ClipFilter, NewClip, StorageStats};
+use crate::retention::RetentionPolicy;
+use models::{
+    ApiToken, Capture, Clip, ClipFilter, Job, JobCounts, JobKind, NewClip, RetentionPreview,
+    Session, Slot, StorageStats, TokenScope, UsageStats, Workspace,
+};
 
 pub trait ClipStorage {
     fn insert(&self, clip: NewClip) -> Result<Clip>;
     fn get_by_id(&self, id: i64) -> Result<Clip>;
     fn list(&self, filter: ClipFilter) -> Result<Vec<Clip>>;
     fn search(&self, query: &str, limit: i64) -> Result<Vec<Clip>>;
+    /// Clips whose text (or OCR text) fuzzily matches `filter.query`, best
+    /// match first; see `fuzzy::score`. The filter's other conditions narrow
+    /// the candidates, and equal scores keep its order (newest first unless
+    /// `oldest_first`); its offset is ignored.
+    fn search_fuzzy(&self, filter: ClipFilter) -> Result<Vec<Clip>>;
+    /// Move a live clip to the trash; `false` if there was none. Trashed
+    /// clips are hidden from everything but `ClipFilter::trashed` listings,
+    /// `find_by_hash`, and retention, which still removes them with age.
     fn delete(&self, id: i64) -> Result<bool>;
+    /// Bring a trashed clip back; `false` if it isn't in the trash.
+    fn restore(&self, id: i64) -> Result<bool>;
+    /// Permanently remove every trashed clip, returning how many.
+    fn empty_trash(&self) -> Result<i64>;
+    fn count_matching(&self, filter: &ClipFilter) -> Result<i64>;
+    fn delete_matching(&self, filter: &ClipFilter) -> Result<i64>;
+    /// Permanently remove unprotected clips matching `filter`, live or
+    /// already trashed, returning how many.
+    fn purge_matching(&self, filter: &ClipFilter) -> Result<i64>;
     fn find_by_hash(&self, hash: &str) -> Result<Option<Clip>>;
+    fn find_by_norm_hash(&self, norm_hash: &str) -> Result<Option<Clip>>;
+    /// The clip stored under `external_id`, trashed or not.
+    fn find_by_external_id(&self, external_id: &str) -> Result<Option<Clip>>;
+    /// Store `external_id` as clip `id`'s key; fails if another clip has it.
+    fn set_external_id(&self, id: i64, external_id: &str) -> Result<()>;
     fn add_tag(&self, clip_id: i64, tag: &str) -> Result<()>;
     fn remove_tag(&self, clip_id: i64, tag: &str) -> Result<()>;
+    /// Set metadata `key` on a live clip, replacing its value.
+    fn set_meta(&self, clip_id: i64, key: &str, value: &str) -> Result<()>;
+    /// Remove metadata `key`; `false` if the clip didn't have it.
+    fn remove_meta(&self, clip_id: i64, key: &str) -> Result<bool>;
+    /// Pin or unpin clip `id` indefinitely, dropping any expiry.
     fn set_pinned(&self, id: i64, pinned: bool) -> Result<()>;
+    /// Pin clip `id` until `until`, after which `unpin_expired` unpins it.
+    fn pin_until(&self, id: i64, until: DateTime<Utc>) -> Result<()>;
+    /// Unpin clips whose pin lapsed by `now`, returning how many.
+    fn unpin_expired(&self, now: DateTime<Utc>) -> Result<i64>;
+    fn set_protected(&self, id: i64, protected: bool) -> Result<()>;
+    fn set_damaged(&self, id: i64, damaged: bool) -> Result<()>;
+    /// Point an image clip at `path` instead, clearing `damaged`.
+    fn relink_image(&self, id: i64, path: &str) -> Result<()>;
+    fn set_pinned_matching(&self, filter: &ClipFilter, pinned: bool) -> Result<i64>;
+    fn tag_matching(&self, filter: &ClipFilter, tag: &str, remove: bool) -> Result<i64>;
     fn clear_older_than(&self, before: DateTime<Utc>) -> Result<i64>;
+    fn apply_retention(&self, policy: &RetentionPolicy) -> Result<i64>;
+    fn preview_retention(&self, policy: &RetentionPolicy) -> Result<RetentionPreview>;
+    /// Remove image files, and their thumbnails, that no clip points at any
+    /// more since the last call, returning how many. Only files under
+    /// `images_dir` are touched. Call after permanently deleting clips.
+    fn release_unused_images(&self, images_dir: &Path) -> Result<i64>;
     fn stats(&self) -> Result<StorageStats>;
     fn touch(&self, id: i64) -> Result<()>;
+    fn record_copy(&self, id: i64) -> Result<()>;
+    /// Log a capture of the clip. When the app is known it also becomes the
+    /// clip's `source_app` and `app_name`.
+    fn record_capture(
+        &self,
+        clip_id: i64,
+        source_app: Option<&str>,
+        app_name: Option<&str>,
+        machine: Option<&str>,
+        workspace: Workspace,
+    ) -> Result<()>;
+    fn captures(&self, clip_id: i64) -> Result<Vec<Capture>>;
+    fn usage_stats(&self, top: i64) -> Result<UsageStats>;
+    fn append_text(&self, id: i64, text: &str, separator: &str) -> Result<Clip>;
+    /// Replace a text clip's content, recomputing its hash, size, language,
+    /// and title. Fails for protected clips and when another clip already
+    /// holds `text`.
+    fn update_content(&self, id: i64, text: &str) -> Result<Clip>;
+    fn set_ocr_text(&self, id: i64, text: &str) -> Result<()>;
+    /// Clips still missing what a `kind` job produces, newest first. For
+    /// thumbnails that's every image clip and for titles every text clip
+    /// that may be a URL; the caller checks further.
+    fn pending_enrichment(&self, kind: JobKind) -> Result<Vec<Clip>>;
+    /// Store an image's descriptor; `None` marks it analyzed without one
+    /// (e.g. the file was unreadable) so it isn't retried.
+    fn set_image_descriptor(&self, id: i64, descriptor: Option<&Descriptor>) -> Result<()>;
+    /// Image clips created after `since` that aren't paired with a screenshot
+    /// file yet.
+    fn unpaired_images(&self, since: DateTime<Utc>) -> Result<Vec<Clip>>;
+    fn set_original_path(&self, id: i64, path: &str) -> Result<()>;
+    fn set_source_page(&self, id: i64, url: &str, title: Option<&str>) -> Result<()>;
+    fn set_link_status(&self, id: i64, status: i32, redirect: Option<&str>) -> Result<()>;
+    fn set_slot(&self, name: &str, text: &str) -> Result<Slot>;
+    fn get_slot(&self, name: &str) -> Result<Slot>;
+    fn slots(&self) -> Result<Vec<Slot>>;
+    fn delete_slot(&self, name: &str) -> Result<bool>;
+    /// Open a session named `name`; fails if another one is already open.
+    fn start_session(&self, name: &str) -> Result<Session>;
+    /// Close the open session, returning it.
+    fn stop_session(&self) -> Result<Session>;
+    fn active_session(&self) -> Result<Option<Session>>;
+    /// Every span of every session, oldest first.
+    fn sessions(&self) -> Result<Vec<Session>>;
+    fn create_token(&self, name: &str, token_hash: &str, scope: TokenScope) -> Result<ApiToken>;
+    /// The token whose secret hashes to `token_hash`, if it hasn't been revoked.
+    fn find_token(&self, token_hash: &str) -> Result<Option<ApiToken>>;
+    fn tokens(&self) -> Result<Vec<ApiToken>>;
+    fn revoke_token(&self, name: &str) -> Result<bool>;
+    /// Queue `kind` of work for a clip; a job already queued is left as is.
+    fn enqueue_job(&self, clip_id: i64, kind: JobKind) -> Result<()>;
+    /// Take the oldest job of one of `kinds` that isn't running and hasn't
+    /// failed too often, marking it running.
+    fn claim_job(&self, kinds: &[JobKind]) -> Result<Option<Job>>;
+    /// Remove a job that succeeded.
+    fn finish_job(&self, id: i64) -> Result<()>;
+    /// Put a job back in the queue after a failed attempt.
+    fn fail_job(&self, id: i64, error: &str) -> Result<()>;
+    /// Return every running job to the queue, for a daemon starting after
+    /// one that stopped mid-job.
+    fn release_jobs(&self) -> Result<i64>;
+    /// Queue sizes per kind, in `JobKind::ALL` order.
+    fn job_counts(&self) -> Result<Vec<JobCounts>>;
+    fn set_link_title(&self, id: i64, title: &str) -> Result<()>;
 }
@@ -1,8 +1,11 @@
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum ContentType {
+    #[default]
     Text,
     Image,
     FileRef,
@@ -33,7 +36,7 @@ impl Serialize for ContentType {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct Clip {
     pub id: i64,
     pub content_type: ContentType,
@@ -44,12 +47,70 @@ pub struct Clip {
     pub hash: String,
     pub size_bytes: i64,
     pub pinned: bool,
+    /// When a pin set with `cb pin --until` lapses; `None` for pins that
+    /// last until removed.
+    pub pinned_until: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub ocr_text: Option<String>,
+    pub lang: Option<String>,
+    pub link_status: Option<i32>,
+    pub link_redirect: Option<String>,
+    pub link_checked_at: Option<DateTime<Utc>>,
+    pub copy_count: i64,
+    /// Read-only: deleting, editing, and clearing refuse to touch it.
+    pub protected: bool,
+    /// Bundle identifier of the app the content was most recently captured
+    /// from, if known.
+    pub source_app: Option<String>,
+    /// That app's name as shown in the Dock, e.g. `Safari`.
+    pub app_name: Option<String>,
+    /// Dominant colors of an image clip as `#rrggbb`, most common first.
+    pub colors: Vec<String>,
+    /// Mean luminance of an image clip, 0 (black) to 1 (white).
+    pub brightness: Option<f64>,
+    /// Full-quality screenshot file the image was also saved as, if paired.
+    pub original_path: Option<String>,
+    /// Machine the clip was first captured on, if recorded.
+    pub origin: Option<String>,
+    /// Web page the text was taken from, when sent with `cb add --source-url`
+    /// or by a browser extension.
+    pub source_url: Option<String>,
+    pub source_title: Option<String>,
+    /// Title of the page a URL clip points to, fetched in the background
+    /// when `fetch_link_titles` is on.
+    pub link_title: Option<String>,
+    /// For a clip that is a single URL, the address with fragments and
+    /// tracking parameters removed, shared by its variants.
+    pub canonical_url: Option<String>,
+    /// Short display title for a text clip, from its first meaningful line.
+    pub title: Option<String>,
+    /// When the clip was moved to the trash; `None` for live clips.
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Key an integration stored the clip under with `cb add --external-id`,
+    /// unique across history.
+    pub external_id: Option<String>,
+    /// An image clip whose file was found missing; `cb verify --fix` re-links
+    /// or prunes it.
+    pub damaged: bool,
+    /// Key/value pairs set with `cb meta set`, for integrations.
+    pub meta: BTreeMap<String, String>,
     pub tags: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+impl Clip {
+    /// The source app for display: its name with the bundle identifier in
+    /// parentheses, or whichever of the two is known.
+    pub fn app_label(&self) -> Option<String> {
+        match (&self.app_name, &self.source_app) {
+            (Some(name), Some(id)) => Some(format!("{} ({})", name, id)),
+            (Some(name), None) => Some(name.clone()),
+            (None, id) => id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct NewClip {
     pub content_type: ContentType,
     pub text_content: Option<String>,
@@ -57,7 +118,165 @@ pub struct NewClip {
     pub image_width: Option<i32>,
     pub image_height: Option<i32>,
     pub hash: String,
+    /// Hash of the normalized text, when loose dedup is enabled.
+    pub norm_hash: Option<String>,
     pub size_bytes: i64,
+    /// Override for `created_at`; `None` means "now". Used when importing
+    /// content whose original timestamp is known.
+    pub created_at: Option<DateTime<Utc>>,
+    /// Machine the content was captured on.
+    pub origin: Option<String>,
+}
+
+/// The web page a clip's text was taken from.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SourcePage {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+/// One capture of a clip's content by the daemon, oldest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct Capture {
+    pub captured_at: DateTime<Utc>,
+    /// Bundle identifier of the frontmost app, when it could be determined.
+    pub source_app: Option<String>,
+    /// Name of the machine whose daemon made the capture.
+    pub machine: Option<String>,
+    /// Space active at the time, when `record_workspace` is on.
+    pub space: Option<i64>,
+    /// Display the pointer was on at the time, likewise.
+    pub display: Option<i64>,
+}
+
+/// Which macOS Space and display the user was in, for slicing history by
+/// project without tagging.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Workspace {
+    /// ID of the active Space (a Mission Control desktop).
+    pub space: Option<i64>,
+    /// ID of the display the pointer is on.
+    pub display: Option<i64>,
+}
+
+/// A named scratch register set with `cb slot set`. Slots live outside the
+/// clip history, so retention and clearing never touch them.
+#[derive(Debug, Clone, Serialize)]
+pub struct Slot {
+    pub name: String,
+    pub text: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A stretch of time started with `cb session start`. Clips captured while
+/// it is open get its name as a tag. A name can be reused, e.g. to resume a
+/// session the next day; each start records a new span.
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+    /// `None` while the session is still running.
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+/// What requests made with an API token may do.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TokenScope {
+    #[default]
+    Read,
+    ReadWrite,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::Read => "read",
+            TokenScope::ReadWrite => "read-write",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<TokenScope> {
+        match s {
+            "read" => Some(TokenScope::Read),
+            "read-write" => Some(TokenScope::ReadWrite),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for TokenScope {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Enrichment the daemon's workers do after capture, queued in the `jobs`
+/// table so it survives restarts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobKind {
+    /// Recognize text in an image clip.
+    Ocr,
+    /// Analyze an image's colors, when that couldn't happen at capture.
+    Colors,
+    /// Write an image clip's thumbnail.
+    Thumbnail,
+    /// Fetch the page title for a URL clip.
+    Title,
+}
+
+impl JobKind {
+    pub const ALL: [JobKind; 4] = [JobKind::Ocr, JobKind::Colors, JobKind::Thumbnail, JobKind::Title];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::Ocr => "ocr",
+            JobKind::Colors => "colors",
+            JobKind::Thumbnail => "thumbnail",
+            JobKind::Title => "title",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<JobKind> {
+        JobKind::ALL.into_iter().find(|k| k.as_str() == s)
+    }
+}
+
+impl Serialize for JobKind {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// A queued job, as claimed by a worker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    pub id: i64,
+    pub clip_id: i64,
+    pub kind: JobKind,
+    /// Failed attempts so far.
+    pub attempts: i64,
+}
+
+/// The queue for one kind of job, for `cb jobs status`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct JobCounts {
+    pub kind: String,
+    /// Waiting for a worker, including failed jobs that will be retried.
+    pub queued: i64,
+    pub running: i64,
+    /// Given up on after too many failures.
+    pub failed: i64,
+    /// The most recent failure's error, if any.
+    pub last_error: Option<String>,
+}
+
+/// An API token made with `cb token create`. Only a hash of the secret is
+/// stored; the token itself is shown once, when it is created.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiToken {
+    pub name: String,
+    pub scope: TokenScope,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize)]
@@ -71,11 +290,107 @@ pub struct StorageStats {
     pub newest: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Default)]
+/// What applying a retention policy would do, computed without deleting.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct RetentionPreview {
+    pub clips: i64,
+    pub bytes: i64,
+    /// Pinned or exempt-tagged clips past the age limit that will be kept.
+    pub protected: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageStats {
+    /// Clips with the highest copy counts, most copied first.
+    pub most_copied: Vec<Clip>,
+    /// Clips captured per local hour of day, index 0 = midnight.
+    pub clips_by_hour: Vec<i64>,
+    /// Apps content was captured from most often, busiest first.
+    pub top_apps: Vec<AppUsage>,
+}
+
+/// How many captures came from one app.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AppUsage {
+    /// The app's bundle identifier.
+    pub source_app: String,
+    /// Its name, as last recorded on a clip captured from it.
+    pub app_name: Option<String>,
+    pub captures: i64,
+}
+
+impl AppUsage {
+    /// The app's name, or its bundle identifier when the name isn't known.
+    pub fn label(&self) -> &str {
+        self.app_name.as_deref().unwrap_or(&self.source_app)
+    }
+}
+
+impl UsageStats {
+    /// The `n` hours with the most captures, busiest first, skipping empty hours.
+    pub fn busiest_hours(&self, n: usize) -> Vec<(usize, i64)> {
+        let mut hours: Vec<(usize, i64)> = self
+            .clips_by_hour
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        hours.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hours.truncate(n);
+        hours
+    }
+}
+
+/// Overall brightness of an image, as judged by its stored descriptor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tone {
+    Dark,
+    Light,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct ClipFilter {
     pub content_type: Option<ContentType>,
     pub pinned: Option<bool>,
     pub tag: Option<String>,
+    /// More tags a clip must also carry, on top of `tag`.
+    pub extra_tags: Vec<String>,
+    pub lang: Option<String>,
+    /// Only clips first captured on this machine (case-insensitive).
+    pub origin: Option<String>,
+    /// Only clips last copied from this app, by name or bundle identifier
+    /// (case-insensitive).
+    pub app: Option<String>,
+    /// Only clips created before this instant.
+    pub before: Option<DateTime<Utc>>,
+    /// Only clips created at or after this instant.
+    pub after: Option<DateTime<Utc>>,
+    /// Only clips whose text or OCR text contains this, ignoring case.
+    pub query: Option<String>,
+    pub protected: Option<bool>,
+    /// Only clips that are (or aren't) a single URL.
+    pub url: Option<bool>,
+    /// Reconstruct history at this instant: only clips that existed by then,
+    /// most recently captured (as of then) first.
+    pub as_of: Option<DateTime<Utc>>,
+    /// Only image clips this dark or light overall.
+    pub tone: Option<Tone>,
+    /// List oldest first instead of newest first. Ignored with `as_of`.
+    pub oldest_first: bool,
+    /// Keyset pagination: only clips past this id in the listing order, so
+    /// pages stay stable while new clips arrive.
+    pub cursor: Option<i64>,
+    /// Only clips in the trash, most recently deleted first, instead of
+    /// only live ones.
+    pub trashed: bool,
+    /// Only clips with each of these metadata keys, set to the value when
+    /// one is given.
+    pub meta: Vec<(String, Option<String>)>,
+    /// Only clips captured at least once in this Space.
+    pub space: Option<i64>,
+    /// Only clips captured at least once on this display.
+    pub display: Option<i64>,
     pub limit: i64,
     pub offset: i64,
 }
@@ -110,8 +425,31 @@ mod tests {
             hash: "abc123".into(),
             size_bytes: 5,
             pinned: false,
+            pinned_until: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            ocr_text: None,
+            lang: None,
+            link_status: None,
+            link_redirect: None,
+            link_checked_at: None,
+            copy_count: 0,
+            protected: false,
+            source_app: None,
+            app_name: None,
+            colors: vec![],
+            brightness: None,
+            original_path: None,
+            origin: None,
+            source_url: None,
+            source_title: None,
+            link_title: None,
+            canonical_url: None,
+            title: None,
+            deleted_at: None,
+            external_id: None,
+            damaged: false,
+            meta: BTreeMap::new(),
             tags: vec!["test".into()],
         };
         let json: serde_json::Value = serde_json::to_value(&clip).unwrap();
@@ -138,6 +476,21 @@ mod tests {
         assert_eq!(json["oldest"], serde_json::Value::Null);
     }
 
+    #[test]
+    fn busiest_hours_sorted_and_skips_empty() {
+        let mut clips_by_hour = vec![0; 24];
+        clips_by_hour[9] = 5;
+        clips_by_hour[14] = 8;
+        clips_by_hour[22] = 5;
+        let usage = UsageStats {
+            most_copied: vec![],
+            clips_by_hour,
+            top_apps: vec![],
+        };
+        assert_eq!(usage.busiest_hours(2), vec![(14, 8), (9, 5)]);
+        assert_eq!(usage.busiest_hours(10).len(), 3);
+    }
+
     #[test]
     fn empty_clip_vec_serializes() {
         let clips: Vec<Clip> = vec![];
@@ -35,3 +35,147 @@ pub const CREATE_INDEX_TAG: &str =
 
 pub const CREATE_INDEX_CLIP_ID: &str =
     "CREATE INDEX IF NOT EXISTS idx_tags_clip_id ON tags(clip_id)";
+
+/// Incremental schema changes applied on top of the base tables. Entry `n`
+/// upgrades a database from `user_version = n` to `n + 1`; append only.
+pub const MIGRATIONS: &[&str] = &[
+    // 1: recognized text for image clips (NULL = not yet processed)
+    "ALTER TABLE clips ADD COLUMN ocr_text TEXT;",
+    // 2: detected natural/programming language of text clips
+    "ALTER TABLE clips ADD COLUMN lang TEXT;
+     CREATE INDEX IF NOT EXISTS idx_clips_lang ON clips(lang);",
+    // 3: last link-health check for URL clips
+    "ALTER TABLE clips ADD COLUMN link_status INTEGER;
+     ALTER TABLE clips ADD COLUMN link_redirect TEXT;
+     ALTER TABLE clips ADD COLUMN link_checked_at TEXT;",
+    // 4: how many times a clip was copied back to the clipboard
+    "ALTER TABLE clips ADD COLUMN copy_count INTEGER NOT NULL DEFAULT 0;",
+    // 5: every time a clip's content was captured, and from which app
+    "CREATE TABLE IF NOT EXISTS captures (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        clip_id INTEGER NOT NULL,
+        captured_at TEXT NOT NULL,
+        source_app TEXT,
+        FOREIGN KEY (clip_id) REFERENCES clips(id) ON DELETE CASCADE
+     );
+     CREATE INDEX IF NOT EXISTS idx_captures_clip_id ON captures(clip_id);",
+    // 6: hash of whitespace/case-normalized text for loose duplicate detection
+    "ALTER TABLE clips ADD COLUMN norm_hash TEXT;
+     CREATE INDEX IF NOT EXISTS idx_clips_norm_hash ON clips(norm_hash);",
+    // 7: read-only clips; the triggers back up the checks in the storage layer
+    "ALTER TABLE clips ADD COLUMN protected INTEGER NOT NULL DEFAULT 0;
+     CREATE TRIGGER IF NOT EXISTS clips_protected_delete BEFORE DELETE ON clips
+     WHEN OLD.protected = 1
+     BEGIN SELECT RAISE(ABORT, 'clip is protected'); END;
+     CREATE TRIGGER IF NOT EXISTS clips_protected_update
+     BEFORE UPDATE OF text_content, image_path, hash ON clips
+     WHEN OLD.protected = 1
+     BEGIN SELECT RAISE(ABORT, 'clip is protected'); END;",
+    // 8: named scratch slots, kept apart from history and never pruned
+    "CREATE TABLE IF NOT EXISTS slots (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE,
+        text TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+     );",
+    // 9: image descriptors for searching screenshots by color and tone
+    "ALTER TABLE clips ADD COLUMN colors TEXT;
+     ALTER TABLE clips ADD COLUMN brightness REAL;",
+    // 10: the screenshot file an image clip was also saved as, if found
+    "ALTER TABLE clips ADD COLUMN original_path TEXT;",
+    // 11: which machine each capture came from, when several share a database
+    "ALTER TABLE captures ADD COLUMN machine TEXT;",
+    // 12: the machine a clip was first captured on
+    "ALTER TABLE clips ADD COLUMN origin TEXT;
+     CREATE INDEX IF NOT EXISTS idx_clips_origin ON clips(origin);",
+    // 13: API tokens for `cb serve`, stored only as SHA-256 hashes
+    "CREATE TABLE IF NOT EXISTS api_tokens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE,
+        token_hash TEXT NOT NULL UNIQUE,
+        scope TEXT NOT NULL,
+        created_at TEXT NOT NULL
+     );",
+    // 14: the web page text was sent from, by `cb add` or a browser extension
+    "ALTER TABLE clips ADD COLUMN source_url TEXT;
+     ALTER TABLE clips ADD COLUMN source_title TEXT;",
+    // 15: named capture sessions; the open one has no ended_at
+    "CREATE TABLE IF NOT EXISTS sessions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        ended_at TEXT
+     );
+     CREATE INDEX IF NOT EXISTS idx_sessions_name ON sessions(name);",
+    // 16: persistent queue of enrichment work for the daemon's workers, the
+    // titles they fetch for URL clips, and the backlog the old image worker
+    // found by scanning for NULL columns
+    "CREATE TABLE IF NOT EXISTS jobs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        clip_id INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        started_at TEXT,
+        last_error TEXT,
+        FOREIGN KEY (clip_id) REFERENCES clips(id) ON DELETE CASCADE,
+        UNIQUE(clip_id, kind)
+     );
+     ALTER TABLE clips ADD COLUMN link_title TEXT;
+     INSERT OR IGNORE INTO jobs (clip_id, kind)
+        SELECT id, 'ocr' FROM clips WHERE content_type = 'image' AND ocr_text IS NULL;
+     INSERT OR IGNORE INTO jobs (clip_id, kind)
+        SELECT id, 'colors' FROM clips WHERE content_type = 'image' AND colors IS NULL;",
+    // 17: canonical form of URL clips, so variants of one address group
+    // together; existing clips are filled in by `backfill_canonical_urls`
+    "ALTER TABLE clips ADD COLUMN canonical_url TEXT;
+     CREATE INDEX IF NOT EXISTS idx_clips_canonical_url ON clips(canonical_url);",
+    // 18: display title derived from a text clip's first meaningful line
+    "ALTER TABLE clips ADD COLUMN title TEXT;",
+    // 19: soft delete; clips with a deleted_at are in the trash
+    "ALTER TABLE clips ADD COLUMN deleted_at TEXT;
+     CREATE INDEX IF NOT EXISTS idx_clips_deleted_at ON clips(deleted_at);",
+    // 20: caller-supplied keys, so integrations can upsert their clips
+    "ALTER TABLE clips ADD COLUMN external_id TEXT;
+     CREATE UNIQUE INDEX IF NOT EXISTS idx_clips_external_id ON clips(external_id);",
+    // 21: image files are shared by every clip with that image and go once
+    // the last of them is deleted; the trigger queues them for removal
+    "CREATE TABLE IF NOT EXISTS released_images (path TEXT PRIMARY KEY);
+     CREATE INDEX IF NOT EXISTS idx_clips_image_path ON clips(image_path);
+     CREATE TRIGGER IF NOT EXISTS clips_release_image AFTER DELETE ON clips
+     WHEN OLD.content_type = 'image' AND OLD.image_path IS NOT NULL
+       AND NOT EXISTS (SELECT 1 FROM clips WHERE image_path = OLD.image_path)
+     BEGIN INSERT OR IGNORE INTO released_images (path) VALUES (OLD.image_path); END;",
+    // 22: image clips whose file was found missing
+    "ALTER TABLE clips ADD COLUMN damaged INTEGER NOT NULL DEFAULT 0;",
+    // 23: key/value pairs integrations attach to clips
+    "CREATE TABLE IF NOT EXISTS clip_meta (
+        clip_id INTEGER NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (clip_id, key),
+        FOREIGN KEY (clip_id) REFERENCES clips(id) ON DELETE CASCADE
+     );
+     CREATE INDEX IF NOT EXISTS idx_clip_meta_key_value ON clip_meta(key, value);",
+    // 24: the macOS Space and display active at each capture
+    "ALTER TABLE captures ADD COLUMN space INTEGER;
+     ALTER TABLE captures ADD COLUMN display INTEGER;
+     CREATE INDEX IF NOT EXISTS idx_captures_space ON captures(space);",
+    // 25: pins that lapse at a set time
+    "ALTER TABLE clips ADD COLUMN pinned_until TEXT;",
+    // 26: the app each clip was last copied from, kept on the clip for
+    // filtering; bundle identifiers come over from the capture log
+    "ALTER TABLE clips ADD COLUMN source_app TEXT;
+     ALTER TABLE clips ADD COLUMN app_name TEXT;
+     UPDATE clips SET source_app = (
+         SELECT source_app FROM captures
+         WHERE captures.clip_id = clips.id AND source_app IS NOT NULL
+         ORDER BY captures.id DESC LIMIT 1
+     );
+     CREATE INDEX IF NOT EXISTS idx_clips_source_app ON clips(source_app);",
+];
+
+/// Version whose migration adds `canonical_url`, computed in Rust for
+/// existing clips once it has run.
+pub const CANONICAL_URL_VERSION: i64 = 17;
+/// Likewise for `title`.
+pub const TITLE_VERSION: i64 = 18;
@@ -1,28 +1,74 @@
+use std::path::{Path, PathBuf};
+
 use chrono::{DateTime, Utc};
-use rusqlite::{Connection, params, Row};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params, Row};
 
+use crate::colors::{DARK_THRESHOLD, Descriptor, LIGHT_THRESHOLD};
 use crate::errors::{CbError, Result};
+use crate::fuzzy;
+use crate::hash::hash_content;
+use crate::lang;
+use crate::links;
+use crate::retention::RetentionPolicy;
+use crate::thumbs;
+use crate::title;
 use super::ClipStorage;
-use super::models::{Clip, ClipFilter, ContentType, NewClip, StorageStats};
+use super::models::{
+    ApiToken, AppUsage, Capture, Clip, ClipFilter, ContentType, Job, JobCounts, JobKind, NewClip,
+    RetentionPreview, Session, Slot, StorageStats, TokenScope, Tone, UsageStats, Workspace,
+};
 use super::schema;
 
-const BASE_SELECT: &str = "
-    SELECT clips.id, clips.content_type, clips.text_content, clips.image_path,
-           clips.image_width, clips.image_height, clips.hash, clips.size_bytes,
-           clips.pinned, clips.created_at, clips.updated_at,
-           GROUP_CONCAT(t.tag) as tags
+/// Columns read by `row_to_clip`, in positional order. The aggregated `tags`
+/// column is always selected last and read by name.
+macro_rules! clip_columns {
+    () => {
+        "clips.id, clips.content_type, clips.text_content, clips.image_path,
+         clips.image_width, clips.image_height, clips.hash, clips.size_bytes,
+         clips.pinned, clips.created_at, clips.updated_at, clips.ocr_text,
+         clips.lang, clips.link_status, clips.link_redirect, clips.link_checked_at,
+         clips.copy_count, clips.protected, clips.source_app,
+         clips.colors, clips.brightness, clips.original_path, clips.origin,
+         clips.source_url, clips.source_title, clips.link_title, clips.canonical_url,
+         clips.title, clips.deleted_at, clips.external_id, clips.damaged,
+         (SELECT json_group_object(key, value) FROM clip_meta
+          WHERE clip_meta.clip_id = clips.id),
+         clips.pinned_until, clips.app_name"
+    };
+}
+
+const BASE_SELECT: &str = concat!(
+    "SELECT ",
+    clip_columns!(),
+    ", GROUP_CONCAT(t.tag) as tags
     FROM clips
     LEFT JOIN tags t ON t.clip_id = clips.id
-";
+"
+);
+
+/// Most recent clips scored by a fuzzy search, bounding its cost on large
+/// histories.
+const FUZZY_CANDIDATES: i64 = 5000;
+
+/// Failed attempts after which a job is left in the queue as failed.
+const MAX_JOB_ATTEMPTS: i64 = 3;
 
 pub struct SqliteStorage {
     conn: Connection,
+    read_only: bool,
+    backup: Option<PathBuf>,
 }
 
 fn row_to_clip(row: &Row) -> rusqlite::Result<Clip> {
     let type_str: String = row.get(1)?;
     let pinned_int: i32 = row.get(8)?;
-    let tags_str: Option<String> = row.get(11)?;
+    let protected_int: i32 = row.get(17)?;
+    let colors_str: Option<String> = row.get(19)?;
+    let colors = match colors_str {
+        Some(s) if !s.is_empty() => s.split(',').map(String::from).collect(),
+        _ => Vec::new(),
+    };
+    let tags_str: Option<String> = row.get("tags")?;
     let tags = match tags_str {
         Some(s) if !s.is_empty() => s.split(',').map(String::from).collect(),
         _ => Vec::new(),
@@ -37,14 +83,47 @@ fn row_to_clip(row: &Row) -> rusqlite::Result<Clip> {
         hash: row.get(6)?,
         size_bytes: row.get(7)?,
         pinned: pinned_int != 0,
+        pinned_until: row.get(32)?,
         created_at: row.get(9)?,
         updated_at: row.get(10)?,
+        ocr_text: row.get(11)?,
+        lang: row.get(12)?,
+        link_status: row.get(13)?,
+        link_redirect: row.get(14)?,
+        link_checked_at: row.get(15)?,
+        copy_count: row.get(16)?,
+        protected: protected_int != 0,
+        source_app: row.get(18)?,
+        app_name: row.get(33)?,
+        colors,
+        brightness: row.get(20)?,
+        original_path: row.get(21)?,
+        origin: row.get(22)?,
+        source_url: row.get(23)?,
+        source_title: row.get(24)?,
+        link_title: row.get(25)?,
+        canonical_url: row.get(26)?,
+        title: row.get(27)?,
+        deleted_at: row.get(28)?,
+        external_id: row.get(29)?,
+        damaged: row.get::<_, i32>(30)? != 0,
+        meta: row
+            .get::<_, Option<String>>(31)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
         tags,
     })
 }
 
 impl SqliteStorage {
     pub fn new(conn: Connection) -> Result<Self> {
+        Self::init(conn).map_err(|e| match e {
+            CbError::Storage(ref inner) if e.is_corruption() => CbError::Corrupt(inner.to_string()),
+            other => other,
+        })
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
         conn.execute_batch("PRAGMA foreign_keys = ON;")?;
         conn.execute(schema::CREATE_CLIPS_TABLE, [])?;
         conn.execute(schema::CREATE_TAGS_TABLE, [])?;
@@ -52,7 +131,47 @@ impl SqliteStorage {
         conn.execute(schema::CREATE_INDEX_CREATED_AT, [])?;
         conn.execute(schema::CREATE_INDEX_TAG, [])?;
         conn.execute(schema::CREATE_INDEX_CLIP_ID, [])?;
-        Ok(Self { conn })
+        let backup = migrate(&conn)?;
+        Ok(Self {
+            conn,
+            read_only: false,
+            backup,
+        })
+    }
+
+    /// Open an existing database without ever writing to it: no schema setup
+    /// and no migrations, so an outdated database is an error rather than
+    /// silently upgraded.
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        let conn = crate::encryption::connect(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let storage = Self {
+            conn,
+            read_only: true,
+            backup: None,
+        };
+        let version = storage.schema_version()?;
+        let latest = schema::MIGRATIONS.len() as i64;
+        if version < latest {
+            return Err(CbError::InvalidInput(format!(
+                "database schema is at version {} (latest {}); open it once without --read-only to migrate",
+                version, latest
+            )));
+        }
+        Ok(storage)
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Copy of the database taken before this open upgraded its schema.
+    pub fn migration_backup(&self) -> Option<&Path> {
+        self.backup.as_deref()
+    }
+
+    /// Schema version recorded in the database's `user_version`.
+    pub fn schema_version(&self) -> Result<i64> {
+        Ok(self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
     }
 
     pub fn in_memory() -> Result<Self> {
@@ -60,18 +179,281 @@ impl SqliteStorage {
         Self::new(conn)
     }
 
+    /// Refuse with `CbError::Protected` if clip `id` is protected. Missing
+    /// clips pass, so callers keep their own not-found handling.
+    fn ensure_unprotected(&self, id: i64) -> Result<()> {
+        let protected: Option<bool> = self
+            .conn
+            .query_row("SELECT protected FROM clips WHERE id = ?", params![id], |row| row.get(0))
+            .optional()?;
+        if protected == Some(true) {
+            return Err(CbError::Protected(id));
+        }
+        Ok(())
+    }
+
     #[cfg(test)]
     pub fn conn(&self) -> &Connection {
         &self.conn
     }
 }
 
+/// `WHERE` clause (and its parameters) for the conditions in `filter`.
+/// Trashed clips are left out unless `filter.trashed` asks for only them;
+/// with `as_of`, clips trashed after that instant still count as live.
+/// `limit` and `offset` are not included.
+fn filter_clause(filter: &ClipFilter) -> (String, Vec<Box<dyn rusqlite::types::ToSql>>) {
+    let mut conditions = Vec::new();
+    let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+
+    match filter.as_of {
+        _ if filter.trashed => conditions.push("clips.deleted_at IS NOT NULL"),
+        Some(as_of) => {
+            conditions.push("(clips.deleted_at IS NULL OR clips.deleted_at > ?)");
+            params.push(Box::new(as_of));
+        }
+        None => conditions.push("clips.deleted_at IS NULL"),
+    }
+    if let Some(ref ct) = filter.content_type {
+        conditions.push("clips.content_type = ?");
+        params.push(Box::new(ct.as_str().to_string()));
+    }
+    if let Some(pinned) = filter.pinned {
+        conditions.push("clips.pinned = ?");
+        params.push(Box::new(pinned as i32));
+    }
+    if let Some(protected) = filter.protected {
+        conditions.push("clips.protected = ?");
+        params.push(Box::new(protected as i32));
+    }
+    match filter.url {
+        Some(true) => conditions.push("clips.canonical_url IS NOT NULL"),
+        Some(false) => conditions.push("clips.canonical_url IS NULL"),
+        None => {}
+    }
+    if let Some(ref lang) = filter.lang {
+        conditions.push("clips.lang = ?");
+        params.push(Box::new(lang.clone()));
+    }
+    if let Some(ref origin) = filter.origin {
+        conditions.push("clips.origin = ? COLLATE NOCASE");
+        params.push(Box::new(origin.clone()));
+    }
+    if let Some(ref app) = filter.app {
+        // By name, bundle identifier, or its last part ("Safari" for
+        // "com.apple.Safari"), for clips captured before names were kept.
+        conditions.push(
+            "(clips.app_name = ? COLLATE NOCASE OR clips.source_app = ? COLLATE NOCASE
+              OR clips.source_app LIKE '%.' || ?)",
+        );
+        for _ in 0..3 {
+            params.push(Box::new(app.clone()));
+        }
+    }
+    for tag in filter.tag.iter().chain(&filter.extra_tags) {
+        conditions.push("EXISTS (SELECT 1 FROM tags WHERE tags.clip_id = clips.id AND tags.tag = ?)");
+        params.push(Box::new(tag.clone()));
+    }
+    for (key, value) in &filter.meta {
+        params.push(Box::new(key.clone()));
+        match value {
+            Some(value) => {
+                conditions.push(
+                    "EXISTS (SELECT 1 FROM clip_meta WHERE clip_meta.clip_id = clips.id
+                     AND clip_meta.key = ? AND clip_meta.value = ?)",
+                );
+                params.push(Box::new(value.clone()));
+            }
+            None => conditions.push(
+                "EXISTS (SELECT 1 FROM clip_meta WHERE clip_meta.clip_id = clips.id AND clip_meta.key = ?)",
+            ),
+        }
+    }
+    if let Some(space) = filter.space {
+        conditions.push("EXISTS (SELECT 1 FROM captures WHERE captures.clip_id = clips.id AND captures.space = ?)");
+        params.push(Box::new(space));
+    }
+    if let Some(display) = filter.display {
+        conditions.push(
+            "EXISTS (SELECT 1 FROM captures WHERE captures.clip_id = clips.id AND captures.display = ?)",
+        );
+        params.push(Box::new(display));
+    }
+    if let Some(before) = filter.before {
+        conditions.push("clips.created_at < ?");
+        params.push(Box::new(before));
+    }
+    if let Some(after) = filter.after {
+        conditions.push("clips.created_at >= ?");
+        params.push(Box::new(after));
+    }
+    if let Some(cursor) = filter.cursor {
+        conditions.push(if filter.oldest_first { "clips.id > ?" } else { "clips.id < ?" });
+        params.push(Box::new(cursor));
+    }
+    if let Some(as_of) = filter.as_of {
+        conditions.push("clips.created_at <= ?");
+        params.push(Box::new(as_of));
+    }
+    if let Some(ref query) = filter.query {
+        conditions.push(
+            "(clips.text_content LIKE ? ESCAPE '\\' OR clips.ocr_text LIKE ? ESCAPE '\\')",
+        );
+        params.push(Box::new(contains_pattern(query)));
+        params.push(Box::new(contains_pattern(query)));
+    }
+    match filter.tone {
+        Some(Tone::Dark) => {
+            conditions.push("clips.brightness <= ?");
+            params.push(Box::new(DARK_THRESHOLD));
+        }
+        Some(Tone::Light) => {
+            conditions.push("clips.brightness >= ?");
+            params.push(Box::new(LIGHT_THRESHOLD));
+        }
+        None => {}
+    }
+
+    (format!("WHERE {}", conditions.join(" AND ")), params)
+}
+
+/// A `LIKE` pattern (with `ESCAPE '\'`) for text containing `text`, whose
+/// `%` and `_` are taken literally. `LIKE` ignores ASCII case.
+fn contains_pattern(text: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// SQL condition (and its parameters) selecting clips a retention policy may
+/// remove: unpinned, unprotected, and without any exempt tag.
+fn retention_candidates(exempt_tags: &[String]) -> (String, Vec<Box<dyn rusqlite::types::ToSql>>) {
+    let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+    if exempt_tags.is_empty() {
+        return ("clips.pinned = 0 AND clips.protected = 0".to_string(), params);
+    }
+    let placeholders = vec!["?"; exempt_tags.len()].join(", ");
+    for tag in exempt_tags {
+        params.push(Box::new(tag.clone()));
+    }
+    (
+        format!(
+            "clips.pinned = 0 AND clips.protected = 0 AND NOT EXISTS (
+                SELECT 1 FROM tags WHERE tags.clip_id = clips.id AND tags.tag IN ({}))",
+            placeholders
+        ),
+        params,
+    )
+}
+
+/// Apply every migration newer than the database's `user_version`, each in
+/// its own transaction so a failure leaves the previous version intact.
+/// Returns the backup taken first, if any.
+fn migrate(conn: &Connection) -> Result<Option<PathBuf>> {
+    let current: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let backup = if current < schema::MIGRATIONS.len() as i64 {
+        backup_before_migration(conn, current)?
+    } else {
+        None
+    };
+    for (idx, sql) in schema::MIGRATIONS.iter().enumerate() {
+        let version = idx as i64 + 1;
+        if version <= current {
+            continue;
+        }
+        conn.execute_batch(&format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            sql, version
+        ))?;
+    }
+    if current < schema::CANONICAL_URL_VERSION {
+        backfill_canonical_urls(conn)?;
+    }
+    if current < schema::TITLE_VERSION {
+        backfill_titles(conn)?;
+    }
+    Ok(backup)
+}
+
+/// Fill in `title` for text clips stored before the column existed.
+fn backfill_titles(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT id, text_content FROM clips
+         WHERE content_type = 'text' AND text_content IS NOT NULL",
+    )?;
+    let texts = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    for (id, text) in texts {
+        conn.execute(
+            "UPDATE clips SET title = ? WHERE id = ?",
+            params![title::derive(&text), id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Canonical URL of a clip's text, if it is a single URL.
+fn canonical_url(text: Option<&str>) -> Option<String> {
+    text.and_then(links::extract_url).map(links::canonical_url)
+}
+
+/// Fill in `canonical_url` for URL clips stored before the column existed.
+fn backfill_canonical_urls(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT id, text_content FROM clips
+         WHERE content_type = 'text' AND LTRIM(text_content) LIKE 'http%'",
+    )?;
+    let urls = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    for (id, text) in urls {
+        if let Some(canonical) = canonical_url(Some(&text)) {
+            conn.execute(
+                "UPDATE clips SET canonical_url = ? WHERE id = ?",
+                params![canonical, id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Snapshot the database to `<db>.bak-<version>` before upgrading it, so a
+/// bad migration can't take the history with it. In-memory and brand-new
+/// databases are skipped, as is a version that already has a backup.
+fn backup_before_migration(conn: &Connection, version: i64) -> Result<Option<PathBuf>> {
+    let Some(db_path) = conn.path().filter(|p| !p.is_empty()) else {
+        return Ok(None);
+    };
+    if version == 0 {
+        let clips: i64 = conn.query_row("SELECT COUNT(*) FROM clips", [], |row| row.get(0))?;
+        if clips == 0 {
+            return Ok(None);
+        }
+    }
+    let backup = PathBuf::from(format!("{}.bak-{}", db_path, version));
+    if !backup.exists() {
+        // Unlike a file copy, VACUUM INTO includes anything still in the WAL.
+        conn.execute("VACUUM INTO ?", [backup.to_string_lossy()])?;
+    }
+    Ok(Some(backup))
+}
+
 impl ClipStorage for SqliteStorage {
     fn insert(&self, clip: NewClip) -> Result<Clip> {
         let now = Utc::now();
+        let lang = clip.text_content.as_deref().and_then(lang::detect);
+        let canonical = canonical_url(clip.text_content.as_deref());
+        let title = clip.text_content.as_deref().and_then(title::derive);
         self.conn.execute(
-            "INSERT INTO clips (content_type, text_content, image_path, image_width, image_height, hash, size_bytes, pinned, created_at, updated_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?, 0, ?, ?)",
+            "INSERT INTO clips (content_type, text_content, image_path, image_width, image_height, hash, norm_hash, size_bytes, pinned, created_at, updated_at, lang, origin, canonical_url, title)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, 0, ?, ?, ?, ?, ?, ?)",
             params![
                 clip.content_type.as_str(),
                 clip.text_content,
@@ -79,9 +461,14 @@ impl ClipStorage for SqliteStorage {
                 clip.image_width,
                 clip.image_height,
                 clip.hash,
+                clip.norm_hash,
                 clip.size_bytes,
+                clip.created_at.unwrap_or(now),
                 now,
-                now,
+                lang,
+                clip.origin,
+                canonical,
+                title,
             ],
         )?;
         let id = self.conn.last_insert_rowid();
@@ -89,7 +476,10 @@ impl ClipStorage for SqliteStorage {
     }
 
     fn get_by_id(&self, id: i64) -> Result<Clip> {
-        let sql = format!("{} WHERE clips.id = ? GROUP BY clips.id", BASE_SELECT);
+        let sql = format!(
+            "{} WHERE clips.id = ? AND clips.deleted_at IS NULL GROUP BY clips.id",
+            BASE_SELECT
+        );
         self.conn
             .query_row(&sql, params![id], row_to_clip)
             .map_err(|e| match e {
@@ -101,52 +491,23 @@ impl ClipStorage for SqliteStorage {
     }
 
     fn list(&self, filter: ClipFilter) -> Result<Vec<Clip>> {
-        // TODO: This is the query builder — see below for your contribution spot
-        let mut conditions = Vec::new();
-        let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
-        let mut use_tag_join = false;
-
-        if let Some(ref ct) = filter.content_type {
-            conditions.push("clips.content_type = ?".to_string());
-            param_values.push(Box::new(ct.as_str().to_string()));
-        }
-        if let Some(pinned) = filter.pinned {
-            conditions.push("clips.pinned = ?".to_string());
-            param_values.push(Box::new(pinned as i32));
-        }
-        if let Some(ref tag) = filter.tag {
-            use_tag_join = true;
-            param_values.push(Box::new(tag.clone()));
-        }
-
-        let from_clause = if use_tag_join {
-            "FROM clips
-             INNER JOIN tags t1 ON t1.clip_id = clips.id AND t1.tag = ?
-             LEFT JOIN tags t2 ON t2.clip_id = clips.id"
-        } else {
-            "FROM clips
-             LEFT JOIN tags t ON t.clip_id = clips.id"
-        };
-
-        let tag_col = if use_tag_join {
-            "GROUP_CONCAT(t2.tag)"
-        } else {
-            "GROUP_CONCAT(t.tag)"
-        };
-
-        let where_clause = if conditions.is_empty() {
-            String::new()
-        } else {
-            format!("WHERE {}", conditions.join(" AND "))
+        let (where_clause, mut param_values) = filter_clause(&filter);
+        // As of a past instant, order by the last capture up to then, so a
+        // clip re-copied just before it comes first as it did at the time.
+        let order = match filter.as_of {
+            Some(as_of) => {
+                param_values.push(Box::new(as_of));
+                "COALESCE((SELECT MAX(captured_at) FROM captures
+                           WHERE captures.clip_id = clips.id AND captured_at <= ?),
+                          clips.created_at) DESC, clips.id DESC"
+            }
+            None if filter.trashed => "clips.deleted_at DESC, clips.id DESC",
+            None if filter.oldest_first => "clips.id ASC",
+            None => "clips.id DESC",
         };
-
         let sql = format!(
-            "SELECT clips.id, clips.content_type, clips.text_content, clips.image_path,
-                    clips.image_width, clips.image_height, clips.hash, clips.size_bytes,
-                    clips.pinned, clips.created_at, clips.updated_at,
-                    {} as tags
-             {} {} GROUP BY clips.id ORDER BY clips.id DESC LIMIT ? OFFSET ?",
-            tag_col, from_clause, where_clause
+            "{} {} GROUP BY clips.id ORDER BY {} LIMIT ? OFFSET ?",
+            BASE_SELECT, where_clause, order
         );
 
         param_values.push(Box::new(filter.effective_limit()));
@@ -162,24 +523,145 @@ impl ClipStorage for SqliteStorage {
         Ok(clips)
     }
 
+    fn count_matching(&self, filter: &ClipFilter) -> Result<i64> {
+        let (where_clause, param_values) = filter_clause(filter);
+        let param_refs: Vec<&dyn rusqlite::types::ToSql> =
+            param_values.iter().map(|p| p.as_ref()).collect();
+        Ok(self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM clips {}", where_clause),
+            param_refs.as_slice(),
+            |row| row.get(0),
+        )?)
+    }
+
+    fn delete_matching(&self, filter: &ClipFilter) -> Result<i64> {
+        let filter = ClipFilter {
+            protected: Some(false),
+            ..filter.clone()
+        };
+        let (where_clause, filter_params) = filter_clause(&filter);
+        let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(Utc::now())];
+        param_values.extend(filter_params);
+        let param_refs: Vec<&dyn rusqlite::types::ToSql> =
+            param_values.iter().map(|p| p.as_ref()).collect();
+        let removed = self.conn.execute(
+            &format!("UPDATE clips SET deleted_at = ? {}", where_clause),
+            param_refs.as_slice(),
+        )?;
+        Ok(removed as i64)
+    }
+
+    fn purge_matching(&self, filter: &ClipFilter) -> Result<i64> {
+        let mut removed = 0;
+        for trashed in [false, true] {
+            let (where_clause, param_values) = filter_clause(&ClipFilter {
+                protected: Some(false),
+                trashed,
+                as_of: None,
+                ..filter.clone()
+            });
+            let param_refs: Vec<&dyn rusqlite::types::ToSql> =
+                param_values.iter().map(|p| p.as_ref()).collect();
+            removed += self
+                .conn
+                .execute(&format!("DELETE FROM clips {}", where_clause), param_refs.as_slice())?;
+        }
+        Ok(removed as i64)
+    }
+
     fn search(&self, query: &str, limit: i64) -> Result<Vec<Clip>> {
         let sql = format!(
-            "{} WHERE clips.text_content LIKE '%' || ? || '%' COLLATE NOCASE
-             GROUP BY clips.id ORDER BY clips.id DESC LIMIT ?",
+            "{} WHERE (clips.text_content LIKE ?1 ESCAPE '\\' OR clips.ocr_text LIKE ?1 ESCAPE '\\')
+               AND clips.deleted_at IS NULL
+             GROUP BY clips.id ORDER BY clips.id DESC LIMIT ?2",
             BASE_SELECT
         );
         let mut stmt = self.conn.prepare(&sql)?;
         let clips = stmt
-            .query_map(params![query, limit], row_to_clip)?
+            .query_map(params![contains_pattern(query), limit], row_to_clip)?
             .collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(clips)
     }
 
+    fn search_fuzzy(&self, filter: ClipFilter) -> Result<Vec<Clip>> {
+        let query = filter.query.clone().unwrap_or_default();
+        let limit = filter.effective_limit();
+        let oldest_first = filter.oldest_first;
+        let (where_clause, mut param_values) = filter_clause(&ClipFilter {
+            query: None,
+            ..filter
+        });
+        // `a%b%c` finds every text holding the query's characters in order,
+        // so only those get scored.
+        let mut pattern = String::from("%");
+        for c in query.chars().filter(|c| !c.is_whitespace()) {
+            if matches!(c, '%' | '_' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+            pattern.push('%');
+        }
+        let sql = format!(
+            "{} {} AND (clips.text_content LIKE ? ESCAPE '\\' OR clips.ocr_text LIKE ? ESCAPE '\\')
+             GROUP BY clips.id ORDER BY clips.id DESC LIMIT ?",
+            BASE_SELECT, where_clause
+        );
+        param_values.push(Box::new(pattern.clone()));
+        param_values.push(Box::new(pattern));
+        param_values.push(Box::new(FUZZY_CANDIDATES));
+        let param_refs: Vec<&dyn rusqlite::types::ToSql> =
+            param_values.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut candidates = stmt
+            .query_map(param_refs.as_slice(), row_to_clip)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if oldest_first {
+            candidates.reverse();
+        }
+        let mut scored: Vec<(i64, Clip)> = candidates
+            .into_iter()
+            .filter_map(|clip| {
+                let best = [clip.text_content.as_deref(), clip.ocr_text.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|text| fuzzy::score(&query, text))
+                    .max()?;
+                Some((best, clip))
+            })
+            .collect();
+        // Stable, so equal scores keep the filter's order.
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        Ok(scored
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .map(|(_, clip)| clip)
+            .collect())
+    }
+
     fn delete(&self, id: i64) -> Result<bool> {
-        let changes = self.conn.execute("DELETE FROM clips WHERE id = ?", params![id])?;
+        self.ensure_unprotected(id)?;
+        let changes = self.conn.execute(
+            "UPDATE clips SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL",
+            params![Utc::now(), id],
+        )?;
+        Ok(changes > 0)
+    }
+
+    fn restore(&self, id: i64) -> Result<bool> {
+        let changes = self.conn.execute(
+            "UPDATE clips SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+            params![id],
+        )?;
         Ok(changes > 0)
     }
 
+    fn empty_trash(&self) -> Result<i64> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM clips WHERE deleted_at IS NOT NULL", [])?;
+        Ok(removed as i64)
+    }
+
     fn find_by_hash(&self, hash: &str) -> Result<Option<Clip>> {
         let sql = format!("{} WHERE clips.hash = ? GROUP BY clips.id", BASE_SELECT);
         match self.conn.query_row(&sql, params![hash], row_to_clip) {
@@ -189,6 +671,45 @@ impl ClipStorage for SqliteStorage {
         }
     }
 
+    fn find_by_external_id(&self, external_id: &str) -> Result<Option<Clip>> {
+        let sql = format!("{} WHERE clips.external_id = ? GROUP BY clips.id", BASE_SELECT);
+        Ok(self
+            .conn
+            .query_row(&sql, params![external_id], row_to_clip)
+            .optional()?)
+    }
+
+    fn set_external_id(&self, id: i64, external_id: &str) -> Result<()> {
+        if let Some(other) = self.find_by_external_id(external_id)?
+            && other.id != id
+        {
+            return Err(CbError::InvalidInput(format!(
+                "external ID \"{}\" already belongs to clip #{}",
+                external_id, other.id
+            )));
+        }
+        let changes = self.conn.execute(
+            "UPDATE clips SET external_id = ? WHERE id = ?",
+            params![external_id, id],
+        )?;
+        if changes == 0 {
+            return Err(CbError::NotFound(format!("Clip with id {} not found", id)));
+        }
+        Ok(())
+    }
+
+    fn find_by_norm_hash(&self, norm_hash: &str) -> Result<Option<Clip>> {
+        let sql = format!(
+            "{} WHERE clips.norm_hash = ? AND clips.deleted_at IS NULL GROUP BY clips.id ORDER BY clips.id DESC LIMIT 1",
+            BASE_SELECT
+        );
+        match self.conn.query_row(&sql, params![norm_hash], row_to_clip) {
+            Ok(clip) => Ok(Some(clip)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(CbError::Storage(e)),
+        }
+    }
+
     fn add_tag(&self, clip_id: i64, tag: &str) -> Result<()> {
         self.conn.execute(
             "INSERT OR IGNORE INTO tags (clip_id, tag) VALUES (?, ?)",
@@ -197,6 +718,24 @@ impl ClipStorage for SqliteStorage {
         Ok(())
     }
 
+    fn set_meta(&self, clip_id: i64, key: &str, value: &str) -> Result<()> {
+        self.get_by_id(clip_id)?;
+        self.conn.execute(
+            "INSERT INTO clip_meta (clip_id, key, value) VALUES (?, ?, ?)
+             ON CONFLICT (clip_id, key) DO UPDATE SET value = excluded.value",
+            params![clip_id, key, value],
+        )?;
+        Ok(())
+    }
+
+    fn remove_meta(&self, clip_id: i64, key: &str) -> Result<bool> {
+        let changes = self.conn.execute(
+            "DELETE FROM clip_meta WHERE clip_id = ? AND key = ?",
+            params![clip_id, key],
+        )?;
+        Ok(changes > 0)
+    }
+
     fn remove_tag(&self, clip_id: i64, tag: &str) -> Result<()> {
         self.conn.execute(
             "DELETE FROM tags WHERE clip_id = ? AND tag = ?",
@@ -208,20 +747,229 @@ impl ClipStorage for SqliteStorage {
     fn set_pinned(&self, id: i64, pinned: bool) -> Result<()> {
         let now = Utc::now();
         self.conn.execute(
-            "UPDATE clips SET pinned = ?, updated_at = ? WHERE id = ?",
+            "UPDATE clips SET pinned = ?, pinned_until = NULL, updated_at = ? WHERE id = ?",
             params![pinned as i32, now, id],
         )?;
         Ok(())
     }
 
+    fn pin_until(&self, id: i64, until: DateTime<Utc>) -> Result<()> {
+        let changes = self.conn.execute(
+            "UPDATE clips SET pinned = 1, pinned_until = ?, updated_at = ? WHERE id = ?",
+            params![until, Utc::now(), id],
+        )?;
+        if changes == 0 {
+            return Err(CbError::NotFound(format!("Clip with id {} not found", id)));
+        }
+        Ok(())
+    }
+
+    fn unpin_expired(&self, now: DateTime<Utc>) -> Result<i64> {
+        let changed = self.conn.execute(
+            "UPDATE clips SET pinned = 0, pinned_until = NULL
+             WHERE pinned = 1 AND pinned_until IS NOT NULL AND pinned_until <= ?",
+            params![now],
+        )?;
+        Ok(changed as i64)
+    }
+
+    fn set_protected(&self, id: i64, protected: bool) -> Result<()> {
+        let changes = self.conn.execute(
+            "UPDATE clips SET protected = ? WHERE id = ? AND deleted_at IS NULL",
+            params![protected as i32, id],
+        )?;
+        if changes == 0 {
+            return Err(CbError::NotFound(format!("Clip with id {} not found", id)));
+        }
+        Ok(())
+    }
+
+    fn set_damaged(&self, id: i64, damaged: bool) -> Result<()> {
+        self.conn.execute(
+            "UPDATE clips SET damaged = ? WHERE id = ?",
+            params![damaged as i32, id],
+        )?;
+        Ok(())
+    }
+
+    fn relink_image(&self, id: i64, path: &str) -> Result<()> {
+        self.ensure_unprotected(id)?;
+        let changes = self.conn.execute(
+            "UPDATE clips SET image_path = ?, damaged = 0 WHERE id = ? AND content_type = 'image'",
+            params![path, id],
+        )?;
+        if changes == 0 {
+            return Err(CbError::NotFound(format!("Image clip with id {} not found", id)));
+        }
+        Ok(())
+    }
+
+    fn set_pinned_matching(&self, filter: &ClipFilter, pinned: bool) -> Result<i64> {
+        // Skip clips already in the target state so the count reflects real changes.
+        let filter = ClipFilter {
+            pinned: Some(!pinned),
+            ..filter.clone()
+        };
+        let (where_clause, filter_params) = filter_clause(&filter);
+        let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> =
+            vec![Box::new(pinned as i32), Box::new(Utc::now())];
+        param_values.extend(filter_params);
+        let param_refs: Vec<&dyn rusqlite::types::ToSql> =
+            param_values.iter().map(|p| p.as_ref()).collect();
+        let changed = self.conn.execute(
+            &format!("UPDATE clips SET pinned = ?, pinned_until = NULL, updated_at = ? {}", where_clause),
+            param_refs.as_slice(),
+        )?;
+        Ok(changed as i64)
+    }
+
+    fn tag_matching(&self, filter: &ClipFilter, tag: &str, remove: bool) -> Result<i64> {
+        let (where_clause, filter_params) = filter_clause(filter);
+        let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(tag.to_string())];
+        param_values.extend(filter_params);
+        let param_refs: Vec<&dyn rusqlite::types::ToSql> =
+            param_values.iter().map(|p| p.as_ref()).collect();
+        let sql = if remove {
+            format!(
+                "DELETE FROM tags WHERE tag = ? AND clip_id IN (SELECT clips.id FROM clips {})",
+                where_clause
+            )
+        } else {
+            format!(
+                "INSERT OR IGNORE INTO tags (clip_id, tag) SELECT clips.id, ? FROM clips {}",
+                where_clause
+            )
+        };
+        Ok(self.conn.execute(&sql, param_refs.as_slice())? as i64)
+    }
+
     fn clear_older_than(&self, before: DateTime<Utc>) -> Result<i64> {
         let changes = self.conn.execute(
-            "DELETE FROM clips WHERE updated_at < ? AND pinned = 0",
+            "DELETE FROM clips WHERE updated_at < ? AND pinned = 0 AND protected = 0",
             params![before],
         )?;
         Ok(changes as i64)
     }
 
+    fn apply_retention(&self, policy: &RetentionPolicy) -> Result<i64> {
+        let mut removed = 0;
+
+        let mut run = |sql: String, extra: Option<Box<dyn rusqlite::types::ToSql>>| -> Result<()> {
+            let (candidates, mut param_values) = retention_candidates(&policy.exempt_tags);
+            let sql = sql.replace("{candidates}", &candidates);
+            if let Some(p) = extra {
+                param_values.push(p);
+            }
+            let param_refs: Vec<&dyn rusqlite::types::ToSql> =
+                param_values.iter().map(|p| p.as_ref()).collect();
+            removed += self.conn.execute(&sql, param_refs.as_slice())? as i64;
+            Ok(())
+        };
+
+        if let Some(cutoff) = policy.cutoff(Utc::now()) {
+            run(
+                "DELETE FROM clips WHERE {candidates} AND clips.updated_at < ?".to_string(),
+                Some(Box::new(cutoff)),
+            )?;
+        }
+        // The caps count live clips only, as `stats` does; the trash ages
+        // out through the cutoff above.
+        if let Some(max_clips) = policy.max_clips {
+            run(
+                "DELETE FROM clips WHERE id IN (
+                    SELECT clips.id FROM clips WHERE {candidates} AND clips.deleted_at IS NULL
+                    ORDER BY clips.updated_at DESC, clips.id DESC LIMIT -1 OFFSET ?)"
+                    .to_string(),
+                Some(Box::new(max_clips.max(0))),
+            )?;
+        }
+        if let Some(max_bytes) = policy.max_bytes() {
+            run(
+                "DELETE FROM clips WHERE id IN (
+                    SELECT id FROM (
+                        SELECT clips.id, SUM(clips.size_bytes) OVER (
+                            ORDER BY clips.updated_at DESC, clips.id DESC) AS running
+                        FROM clips WHERE {candidates} AND clips.deleted_at IS NULL)
+                    WHERE running > ?)"
+                    .to_string(),
+                Some(Box::new(max_bytes)),
+            )?;
+        }
+        Ok(removed)
+    }
+
+    fn preview_retention(&self, policy: &RetentionPolicy) -> Result<RetentionPreview> {
+        let total_size = |conn: &Connection| -> rusqlite::Result<i64> {
+            conn.query_row("SELECT COALESCE(SUM(size_bytes), 0) FROM clips", [], |row| row.get(0))
+        };
+
+        let protected = match policy.cutoff(Utc::now()) {
+            Some(cutoff) => {
+                let (candidates, mut param_values) = retention_candidates(&policy.exempt_tags);
+                param_values.push(Box::new(cutoff));
+                let param_refs: Vec<&dyn rusqlite::types::ToSql> =
+                    param_values.iter().map(|p| p.as_ref()).collect();
+                self.conn.query_row(
+                    &format!(
+                        "SELECT COUNT(*) FROM clips WHERE NOT ({}) AND clips.updated_at < ?",
+                        candidates
+                    ),
+                    param_refs.as_slice(),
+                    |row| row.get(0),
+                )?
+            }
+            None => 0,
+        };
+
+        // Run the real deletion inside a savepoint and roll it back, so the
+        // preview can never drift from what `apply_retention` does.
+        self.conn.execute_batch("SAVEPOINT retention_preview")?;
+        let result = (|| -> Result<(i64, i64)> {
+            let before = total_size(&self.conn)?;
+            let clips = self.apply_retention(policy)?;
+            let after = total_size(&self.conn)?;
+            Ok((clips, before - after))
+        })();
+        self.conn
+            .execute_batch("ROLLBACK TO retention_preview; RELEASE retention_preview")?;
+        let (clips, bytes) = result?;
+
+        Ok(RetentionPreview {
+            clips,
+            bytes,
+            protected,
+        })
+    }
+
+    fn release_unused_images(&self, images_dir: &Path) -> Result<i64> {
+        let released: Vec<String> = self
+            .conn
+            .prepare("SELECT path FROM released_images")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        let mut removed = 0;
+        for path in released {
+            // A clip captured since may have brought the file back into use.
+            let in_use: bool = self.conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM clips WHERE image_path = ?)",
+                params![path],
+                |row| row.get(0),
+            )?;
+            let file = Path::new(&path);
+            if !in_use && file.starts_with(images_dir) {
+                match std::fs::remove_file(file) {
+                    Ok(()) => removed += 1,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(CbError::Image(format!("{}: {}", path, e))),
+                }
+                let _ = std::fs::remove_file(thumbs::path_for(file));
+            }
+            self.conn
+                .execute("DELETE FROM released_images WHERE path = ?", params![path])?;
+        }
+        Ok(removed)
+    }
+
     fn stats(&self) -> Result<StorageStats> {
         self.conn.query_row(
             "SELECT
@@ -232,7 +980,7 @@ impl ClipStorage for SqliteStorage {
                 COALESCE(SUM(size_bytes), 0),
                 MIN(created_at),
                 MAX(created_at)
-             FROM clips",
+             FROM clips WHERE deleted_at IS NULL",
             [],
             |row| {
                 Ok(StorageStats {
@@ -259,21 +1007,568 @@ impl ClipStorage for SqliteStorage {
         }
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::errors::CbError;
-    use crate::hash::hash_content;
-    use super::super::models::ContentType;
-    use chrono::{Duration, Utc};
+    fn record_copy(&self, id: i64) -> Result<()> {
+        let now = Utc::now();
+        let changes = self.conn.execute(
+            "UPDATE clips SET updated_at = ?, copy_count = copy_count + 1 WHERE id = ?",
+            params![now, id],
+        )?;
+        if changes == 0 {
+            return Err(CbError::NotFound(format!("Clip with id {} not found", id)));
+        }
+        Ok(())
+    }
 
-    fn test_storage() -> SqliteStorage {
-        SqliteStorage::in_memory().unwrap()
+    fn record_capture(
+        &self,
+        clip_id: i64,
+        source_app: Option<&str>,
+        app_name: Option<&str>,
+        machine: Option<&str>,
+        workspace: Workspace,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO captures (clip_id, captured_at, source_app, machine, space, display)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            params![clip_id, Utc::now(), source_app, machine, workspace.space, workspace.display],
+        )?;
+        if source_app.is_some() {
+            self.conn.execute(
+                "UPDATE clips SET source_app = ?, app_name = ? WHERE id = ?",
+                params![source_app, app_name, clip_id],
+            )?;
+        }
+        Ok(())
     }
 
-    fn text_clip(content: &str) -> NewClip {
+    fn captures(&self, clip_id: i64) -> Result<Vec<Capture>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT captured_at, source_app, machine, space, display FROM captures
+             WHERE clip_id = ? ORDER BY id",
+        )?;
+        let captures = stmt
+            .query_map(params![clip_id], |row| {
+                Ok(Capture {
+                    captured_at: row.get(0)?,
+                    source_app: row.get(1)?,
+                    machine: row.get(2)?,
+                    space: row.get(3)?,
+                    display: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(captures)
+    }
+
+    fn usage_stats(&self, top: i64) -> Result<UsageStats> {
+        let sql = format!(
+            "{} WHERE clips.copy_count > 0 AND clips.deleted_at IS NULL
+             GROUP BY clips.id ORDER BY clips.copy_count DESC, clips.id DESC LIMIT ?",
+            BASE_SELECT
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let most_copied = stmt
+            .query_map(params![top], row_to_clip)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut clips_by_hour = vec![0i64; 24];
+        let mut stmt = self.conn.prepare(
+            "SELECT CAST(strftime('%H', created_at, 'localtime') AS INTEGER), COUNT(*)
+             FROM clips WHERE deleted_at IS NULL GROUP BY 1",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, Option<i64>>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        for row in rows {
+            if let (Some(hour), count) = row?
+                && (0..24).contains(&hour)
+            {
+                clips_by_hour[hour as usize] = count;
+            }
+        }
+
+        // Captures of live clips per app, named after the clip most recently
+        // captured from it.
+        let mut stmt = self.conn.prepare(
+            "SELECT captures.source_app,
+                    (SELECT app_name FROM clips AS named
+                     WHERE named.source_app = captures.source_app AND named.app_name IS NOT NULL
+                     ORDER BY named.updated_at DESC LIMIT 1),
+                    COUNT(*)
+             FROM captures JOIN clips ON clips.id = captures.clip_id
+             WHERE captures.source_app IS NOT NULL AND clips.deleted_at IS NULL
+             GROUP BY captures.source_app ORDER BY 3 DESC, 1 LIMIT ?",
+        )?;
+        let top_apps = stmt
+            .query_map(params![top], |row| {
+                Ok(AppUsage {
+                    source_app: row.get(0)?,
+                    app_name: row.get(1)?,
+                    captures: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(UsageStats {
+            most_copied,
+            clips_by_hour,
+            top_apps,
+        })
+    }
+
+    fn append_text(&self, id: i64, text: &str, separator: &str) -> Result<Clip> {
+        let clip = self.get_by_id(id)?;
+        if clip.content_type != ContentType::Text {
+            return Err(CbError::InvalidInput(format!(
+                "clip #{} is {}, only text clips can be appended to",
+                id,
+                clip.content_type.as_str()
+            )));
+        }
+        let existing = clip.text_content.unwrap_or_default();
+        let combined = if existing.is_empty() {
+            text.to_string()
+        } else {
+            format!("{}{}{}", existing, separator, text)
+        };
+        self.update_content(id, &combined)
+    }
+
+    fn update_content(&self, id: i64, text: &str) -> Result<Clip> {
+        let clip = self.get_by_id(id)?;
+        if clip.content_type != ContentType::Text {
+            return Err(CbError::InvalidInput(format!(
+                "clip #{} is {}, only text clips can be edited",
+                id,
+                clip.content_type.as_str()
+            )));
+        }
+        self.ensure_unprotected(id)?;
+
+        let hash = hash_content(text.as_bytes());
+        if let Some(other) = self.find_by_hash(&hash)?
+            && other.id != id
+        {
+            return Err(CbError::InvalidInput(format!(
+                "the new text would duplicate clip #{}",
+                other.id
+            )));
+        }
+        // The normalized hash depends on the daemon's dedup mode, so drop it
+        // rather than leave a stale one behind.
+        self.conn.execute(
+            "UPDATE clips SET text_content = ?, hash = ?, norm_hash = NULL, size_bytes = ?,
+                              lang = ?, canonical_url = ?, title = ?, updated_at = ?
+             WHERE id = ?",
+            params![
+                text,
+                hash,
+                text.len() as i64,
+                lang::detect(text),
+                canonical_url(Some(text)),
+                title::derive(text),
+                Utc::now(),
+                id
+            ],
+        )?;
+        self.get_by_id(id)
+    }
+
+    fn set_ocr_text(&self, id: i64, text: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE clips SET ocr_text = ? WHERE id = ?",
+            params![text, id],
+        )?;
+        Ok(())
+    }
+
+    fn pending_enrichment(&self, kind: JobKind) -> Result<Vec<Clip>> {
+        let missing = match kind {
+            JobKind::Ocr => "clips.content_type = 'image' AND clips.ocr_text IS NULL",
+            JobKind::Colors => "clips.content_type = 'image' AND clips.colors IS NULL",
+            JobKind::Thumbnail => "clips.content_type = 'image' AND clips.image_path IS NOT NULL",
+            JobKind::Title => {
+                "clips.content_type = 'text' AND clips.link_title IS NULL
+                 AND LTRIM(clips.text_content) LIKE 'http%'"
+            }
+        };
+        let sql = format!(
+            "{} WHERE {} AND clips.deleted_at IS NULL GROUP BY clips.id ORDER BY clips.id DESC",
+            BASE_SELECT, missing
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let clips = stmt
+            .query_map([], row_to_clip)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(clips)
+    }
+
+    fn set_image_descriptor(&self, id: i64, descriptor: Option<&Descriptor>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE clips SET colors = ?, brightness = ? WHERE id = ?",
+            params![
+                descriptor.map(|d| d.colors.join(",")).unwrap_or_default(),
+                descriptor.map(|d| d.brightness),
+                id
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn unpaired_images(&self, since: DateTime<Utc>) -> Result<Vec<Clip>> {
+        let sql = format!(
+            "{} WHERE clips.content_type = 'image' AND clips.original_path IS NULL
+               AND clips.created_at > ? AND clips.deleted_at IS NULL
+             GROUP BY clips.id ORDER BY clips.id DESC",
+            BASE_SELECT
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let clips = stmt
+            .query_map(params![since], row_to_clip)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(clips)
+    }
+
+    fn set_original_path(&self, id: i64, path: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE clips SET original_path = ? WHERE id = ?",
+            params![path, id],
+        )?;
+        Ok(())
+    }
+
+    fn set_source_page(&self, id: i64, url: &str, title: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE clips SET source_url = ?, source_title = ? WHERE id = ?",
+            params![url, title, id],
+        )?;
+        Ok(())
+    }
+
+    fn set_link_status(&self, id: i64, status: i32, redirect: Option<&str>) -> Result<()> {
+        let changes = self.conn.execute(
+            "UPDATE clips SET link_status = ?, link_redirect = ?, link_checked_at = ? WHERE id = ?",
+            params![status, redirect, Utc::now(), id],
+        )?;
+        if changes == 0 {
+            return Err(CbError::NotFound(format!("Clip with id {} not found", id)));
+        }
+        Ok(())
+    }
+
+    fn set_slot(&self, name: &str, text: &str) -> Result<Slot> {
+        if name.is_empty() || name.chars().any(char::is_whitespace) {
+            return Err(CbError::InvalidInput(format!(
+                "invalid slot name \"{}\" (no spaces)",
+                name
+            )));
+        }
+        self.conn.execute(
+            "INSERT INTO slots (name, text, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(name) DO UPDATE SET text = ?2, updated_at = ?3",
+            params![name, text, Utc::now()],
+        )?;
+        self.get_slot(name)
+    }
+
+    fn get_slot(&self, name: &str) -> Result<Slot> {
+        self.conn
+            .query_row(
+                "SELECT name, text, updated_at FROM slots WHERE name = ?",
+                params![name],
+                |row| {
+                    Ok(Slot {
+                        name: row.get(0)?,
+                        text: row.get(1)?,
+                        updated_at: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?
+            .ok_or_else(|| CbError::NotFound(format!("slot \"{}\"", name)))
+    }
+
+    fn slots(&self) -> Result<Vec<Slot>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, text, updated_at FROM slots ORDER BY name")?;
+        let slots = stmt
+            .query_map([], |row| {
+                Ok(Slot {
+                    name: row.get(0)?,
+                    text: row.get(1)?,
+                    updated_at: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(slots)
+    }
+
+    fn delete_slot(&self, name: &str) -> Result<bool> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM slots WHERE name = ?", params![name])?;
+        Ok(removed > 0)
+    }
+
+    fn start_session(&self, name: &str) -> Result<Session> {
+        if name.is_empty() || name.chars().any(|c| c.is_whitespace() || c == ',') {
+            return Err(CbError::InvalidInput(format!(
+                "invalid session name \"{}\" (no spaces or commas)",
+                name
+            )));
+        }
+        if let Some(open) = self.active_session()? {
+            return Err(CbError::InvalidInput(format!(
+                "session \"{}\" is already running; stop it first",
+                open.name
+            )));
+        }
+        let started_at = Utc::now();
+        self.conn.execute(
+            "INSERT INTO sessions (name, started_at) VALUES (?, ?)",
+            params![name, started_at],
+        )?;
+        Ok(Session {
+            name: name.to_string(),
+            started_at,
+            ended_at: None,
+        })
+    }
+
+    fn stop_session(&self) -> Result<Session> {
+        let mut session = self
+            .active_session()?
+            .ok_or_else(|| CbError::NotFound("running session".into()))?;
+        let ended_at = Utc::now();
+        self.conn.execute(
+            "UPDATE sessions SET ended_at = ? WHERE ended_at IS NULL",
+            params![ended_at],
+        )?;
+        session.ended_at = Some(ended_at);
+        Ok(session)
+    }
+
+    fn active_session(&self) -> Result<Option<Session>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT name, started_at, ended_at FROM sessions
+                 WHERE ended_at IS NULL ORDER BY id DESC LIMIT 1",
+                [],
+                row_to_session,
+            )
+            .optional()?)
+    }
+
+    fn sessions(&self) -> Result<Vec<Session>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, started_at, ended_at FROM sessions ORDER BY id")?;
+        let sessions = stmt
+            .query_map([], row_to_session)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(sessions)
+    }
+
+    fn create_token(&self, name: &str, token_hash: &str, scope: TokenScope) -> Result<ApiToken> {
+        if name.is_empty() || name.chars().any(char::is_whitespace) {
+            return Err(CbError::InvalidInput(format!(
+                "invalid token name \"{}\" (no spaces)",
+                name
+            )));
+        }
+        let created_at = Utc::now();
+        let inserted = self.conn.execute(
+            "INSERT INTO api_tokens (name, token_hash, scope, created_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(name) DO NOTHING",
+            params![name, token_hash, scope.as_str(), created_at],
+        )?;
+        if inserted == 0 {
+            return Err(CbError::InvalidInput(format!(
+                "a token named \"{}\" already exists",
+                name
+            )));
+        }
+        Ok(ApiToken {
+            name: name.to_string(),
+            scope,
+            created_at,
+        })
+    }
+
+    fn find_token(&self, token_hash: &str) -> Result<Option<ApiToken>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT name, scope, created_at FROM api_tokens WHERE token_hash = ?",
+                params![token_hash],
+                row_to_token,
+            )
+            .optional()?)
+    }
+
+    fn tokens(&self) -> Result<Vec<ApiToken>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, scope, created_at FROM api_tokens ORDER BY name")?;
+        let tokens = stmt
+            .query_map([], row_to_token)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(tokens)
+    }
+
+    fn revoke_token(&self, name: &str) -> Result<bool> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM api_tokens WHERE name = ?", params![name])?;
+        Ok(removed > 0)
+    }
+
+    fn enqueue_job(&self, clip_id: i64, kind: JobKind) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO jobs (clip_id, kind) VALUES (?, ?)",
+            params![clip_id, kind.as_str()],
+        )?;
+        Ok(())
+    }
+
+    fn claim_job(&self, kinds: &[JobKind]) -> Result<Option<Job>> {
+        if kinds.is_empty() {
+            return Ok(None);
+        }
+        // One statement, so workers on other connections can't claim the
+        // same job.
+        let sql = format!(
+            "UPDATE jobs SET started_at = ? WHERE id = (
+                SELECT id FROM jobs
+                WHERE started_at IS NULL AND attempts < ? AND kind IN ({})
+                ORDER BY id LIMIT 1)
+             RETURNING id, clip_id, kind, attempts",
+            vec!["?"; kinds.len()].join(", ")
+        );
+        let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> =
+            vec![Box::new(Utc::now()), Box::new(MAX_JOB_ATTEMPTS)];
+        for kind in kinds {
+            param_values.push(Box::new(kind.as_str()));
+        }
+        let param_refs: Vec<&dyn rusqlite::types::ToSql> =
+            param_values.iter().map(|p| p.as_ref()).collect();
+        let job = self
+            .conn
+            .query_row(&sql, param_refs.as_slice(), |row| {
+                let kind: String = row.get(2)?;
+                Ok((row.get(0)?, row.get(1)?, kind, row.get(3)?))
+            })
+            .optional()?;
+        Ok(job.and_then(|(id, clip_id, kind, attempts)| {
+            Some(Job {
+                id,
+                clip_id,
+                kind: JobKind::parse(&kind)?,
+                attempts,
+            })
+        }))
+    }
+
+    fn finish_job(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM jobs WHERE id = ?", params![id])?;
+        Ok(())
+    }
+
+    fn fail_job(&self, id: i64, error: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE jobs SET started_at = NULL, attempts = attempts + 1, last_error = ?
+             WHERE id = ?",
+            params![error, id],
+        )?;
+        Ok(())
+    }
+
+    fn release_jobs(&self) -> Result<i64> {
+        let released = self
+            .conn
+            .execute("UPDATE jobs SET started_at = NULL WHERE started_at IS NOT NULL", [])?;
+        Ok(released as i64)
+    }
+
+    fn job_counts(&self) -> Result<Vec<JobCounts>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT kind,
+                    SUM(started_at IS NULL AND attempts < ?1),
+                    SUM(started_at IS NOT NULL),
+                    SUM(started_at IS NULL AND attempts >= ?1),
+                    (SELECT last_error FROM jobs AS j
+                     WHERE j.kind = jobs.kind AND j.last_error IS NOT NULL
+                     ORDER BY j.id DESC LIMIT 1)
+             FROM jobs GROUP BY kind",
+        )?;
+        let rows = stmt
+            .query_map(params![MAX_JOB_ATTEMPTS], |row| {
+                Ok(JobCounts {
+                    kind: row.get(0)?,
+                    queued: row.get(1)?,
+                    running: row.get(2)?,
+                    failed: row.get(3)?,
+                    last_error: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(JobKind::ALL
+            .iter()
+            .map(|kind| {
+                rows.iter()
+                    .find(|c| c.kind == kind.as_str())
+                    .cloned()
+                    .unwrap_or_else(|| JobCounts {
+                        kind: kind.as_str().to_string(),
+                        ..Default::default()
+                    })
+            })
+            .collect())
+    }
+
+    fn set_link_title(&self, id: i64, title: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE clips SET link_title = ? WHERE id = ?",
+            params![title, id],
+        )?;
+        Ok(())
+    }
+}
+
+fn row_to_session(row: &rusqlite::Row) -> rusqlite::Result<Session> {
+    Ok(Session {
+        name: row.get(0)?,
+        started_at: row.get(1)?,
+        ended_at: row.get(2)?,
+    })
+}
+
+fn row_to_token(row: &rusqlite::Row) -> rusqlite::Result<ApiToken> {
+    let scope: String = row.get(1)?;
+    Ok(ApiToken {
+        name: row.get(0)?,
+        scope: TokenScope::parse(&scope).unwrap_or_default(),
+        created_at: row.get(2)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::CbError;
+    use crate::hash::hash_content;
+    use super::super::models::ContentType;
+    use chrono::{Duration, Utc};
+    use proptest::prelude::*;
+
+    fn test_storage() -> SqliteStorage {
+        SqliteStorage::in_memory().unwrap()
+    }
+
+    fn text_clip(content: &str) -> NewClip {
         NewClip {
             content_type: ContentType::Text,
             text_content: Some(content.to_string()),
@@ -282,6 +1577,7 @@ mod tests {
             image_height: None,
             hash: hash_content(content.as_bytes()),
             size_bytes: content.len() as i64,
+            ..Default::default()
         }
     }
 
@@ -295,6 +1591,7 @@ mod tests {
             image_height: Some(h),
             hash: hash_content(hash_input.as_bytes()),
             size_bytes: 1024,
+            ..Default::default()
         }
     }
 
@@ -314,6 +1611,93 @@ mod tests {
         assert_eq!(count, 2);
     }
 
+    #[test]
+    fn test_new_applies_all_migrations() {
+        let storage = test_storage();
+        assert_eq!(storage.schema_version().unwrap(), schema::MIGRATIONS.len() as i64);
+    }
+
+    #[test]
+    fn test_migrates_legacy_database() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(schema::CREATE_CLIPS_TABLE, []).unwrap();
+        conn.execute(
+            "INSERT INTO clips (content_type, text_content, hash, size_bytes, created_at, updated_at)
+             VALUES ('text', 'legacy', 'h', 6, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+            [],
+        )
+        .unwrap();
+        let storage = SqliteStorage::new(conn).unwrap();
+        assert_eq!(storage.schema_version().unwrap(), schema::MIGRATIONS.len() as i64);
+        let clip = storage.get_by_id(1).unwrap();
+        assert_eq!(clip.text_content.as_deref(), Some("legacy"));
+        assert!(clip.ocr_text.is_none());
+    }
+
+    #[test]
+    fn test_migration_backfills_canonical_urls() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(schema::CREATE_CLIPS_TABLE, []).unwrap();
+        conn.execute(
+            "INSERT INTO clips (content_type, text_content, hash, size_bytes, created_at, updated_at)
+             VALUES ('text', 'https://www.example.com/doc#intro', 'h', 33, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+            [],
+        )
+        .unwrap();
+        let storage = SqliteStorage::new(conn).unwrap();
+        let clip = storage.get_by_id(1).unwrap();
+        assert_eq!(clip.canonical_url.as_deref(), Some("https://example.com/doc"));
+        assert_eq!(clip.title.as_deref(), Some("https://www.example.com/doc#intro"));
+    }
+
+    #[test]
+    fn test_url_variants_share_canonical_url() {
+        let storage = test_storage();
+        let a = storage.insert(text_clip("https://example.com/doc?utm_source=mail")).unwrap();
+        let b = storage.insert(text_clip("https://example.com/doc#section-2")).unwrap();
+        storage.insert(text_clip("not a link")).unwrap();
+        assert_eq!(a.canonical_url.as_deref(), Some("https://example.com/doc"));
+        assert_eq!(a.canonical_url, b.canonical_url);
+
+        let urls = storage
+            .list(ClipFilter { url: Some(true), ..Default::default() })
+            .unwrap();
+        assert_eq!(urls.iter().map(|c| c.id).collect::<Vec<_>>(), vec![b.id, a.id]);
+    }
+
+    #[test]
+    fn test_backs_up_before_migrating() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let fresh = SqliteStorage::new(Connection::open(dir.path().join("new.db")).unwrap()).unwrap();
+        assert!(fresh.migration_backup().is_none());
+
+        let path = dir.path().join("cb.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute(schema::CREATE_CLIPS_TABLE, []).unwrap();
+        conn.execute(
+            "INSERT INTO clips (content_type, text_content, hash, size_bytes, created_at, updated_at)
+             VALUES ('text', 'precious', 'h', 8, '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z')",
+            [],
+        )
+        .unwrap();
+        let storage = SqliteStorage::new(conn).unwrap();
+        let backup = dir.path().join("cb.db.bak-0");
+        assert_eq!(storage.migration_backup(), Some(backup.as_path()));
+
+        let saved = Connection::open(&backup).unwrap();
+        let text: String = saved
+            .query_row("SELECT text_content FROM clips", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(text, "precious");
+        let version: i64 = saved.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, 0);
+
+        // Already up to date: nothing new to back up.
+        drop(storage);
+        let storage = SqliteStorage::new(Connection::open(&path).unwrap()).unwrap();
+        assert!(storage.migration_backup().is_none());
+    }
+
     // --- Insert ---
 
     #[test]
@@ -337,6 +1721,17 @@ mod tests {
         assert_eq!(clip.image_height, Some(600));
     }
 
+    #[test]
+    fn test_insert_with_created_at_override() {
+        let storage = test_storage();
+        let when = Utc::now() - Duration::days(3);
+        let clip = storage
+            .insert(NewClip { created_at: Some(when), ..text_clip("backdated") })
+            .unwrap();
+        assert_eq!(clip.created_at.timestamp(), when.timestamp());
+        assert!(clip.updated_at > clip.created_at);
+    }
+
     #[test]
     fn test_insert_returns_incrementing_ids() {
         let storage = test_storage();
@@ -362,152 +1757,788 @@ mod tests {
     #[test]
     fn test_get_by_id_not_found() {
         let storage = test_storage();
-        let result = storage.get_by_id(999);
-        assert!(matches!(result, Err(CbError::NotFound(_))));
+        let result = storage.get_by_id(999);
+        assert!(matches!(result, Err(CbError::NotFound(_))));
+    }
+
+    // --- List ---
+
+    #[test]
+    fn test_list_empty() {
+        let storage = test_storage();
+        let clips = storage.list(ClipFilter::default()).unwrap();
+        assert!(clips.is_empty());
+    }
+
+    #[test]
+    fn test_list_returns_clips() {
+        let storage = test_storage();
+        storage.insert(text_clip("one")).unwrap();
+        storage.insert(text_clip("two")).unwrap();
+        let clips = storage.list(ClipFilter::default()).unwrap();
+        assert_eq!(clips.len(), 2);
+    }
+
+    #[test]
+    fn test_list_with_limit() {
+        let storage = test_storage();
+        for i in 0..5 {
+            storage.insert(text_clip(&format!("clip {}", i))).unwrap();
+        }
+        let clips = storage.list(ClipFilter { limit: 3, ..Default::default() }).unwrap();
+        assert_eq!(clips.len(), 3);
+    }
+
+    #[test]
+    fn test_list_with_offset() {
+        let storage = test_storage();
+        for i in 0..5 {
+            storage.insert(text_clip(&format!("clip {}", i))).unwrap();
+        }
+        let clips = storage.list(ClipFilter { limit: 2, offset: 3, ..Default::default() }).unwrap();
+        assert_eq!(clips.len(), 2);
+    }
+
+    #[test]
+    fn test_list_filter_by_type() {
+        let storage = test_storage();
+        storage.insert(text_clip("text")).unwrap();
+        storage.insert(image_clip("/img.png", 100, 100)).unwrap();
+        let clips = storage.list(ClipFilter {
+            content_type: Some(ContentType::Text),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].content_type, ContentType::Text);
+    }
+
+    #[test]
+    fn test_list_filter_by_pinned() {
+        let storage = test_storage();
+        let c1 = storage.insert(text_clip("pinned")).unwrap();
+        storage.insert(text_clip("not pinned")).unwrap();
+        storage.set_pinned(c1.id, true).unwrap();
+        let clips = storage.list(ClipFilter {
+            pinned: Some(true),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(clips.len(), 1);
+        assert!(clips[0].pinned);
+    }
+
+    #[test]
+    fn test_list_filter_by_tag() {
+        let storage = test_storage();
+        let c1 = storage.insert(text_clip("tagged")).unwrap();
+        storage.insert(text_clip("untagged")).unwrap();
+        storage.add_tag(c1.id, "important").unwrap();
+        let clips = storage.list(ClipFilter {
+            tag: Some("important".to_string()),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].text_content.as_deref(), Some("tagged"));
+    }
+
+    #[test]
+    fn test_list_filter_by_tag_and_type() {
+        let storage = test_storage();
+        let text = storage.insert(text_clip("tagged text")).unwrap();
+        let img = storage.insert(image_clip("/img.png", 1, 1)).unwrap();
+        storage.add_tag(text.id, "mixed").unwrap();
+        storage.add_tag(img.id, "mixed").unwrap();
+        let clips = storage.list(ClipFilter {
+            content_type: Some(ContentType::Text),
+            tag: Some("mixed".to_string()),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].id, text.id);
+    }
+
+    #[test]
+    fn test_list_filter_by_lang() {
+        let storage = test_storage();
+        let code = storage.insert(text_clip("pub fn main() {\n    let mut v = Vec::new();\n}")).unwrap();
+        storage.insert(text_clip("this is the note for the team")).unwrap();
+        storage.insert(image_clip("/img.png", 1, 1)).unwrap();
+        assert_eq!(code.lang.as_deref(), Some("rust"));
+        let clips = storage.list(ClipFilter {
+            lang: Some("rust".to_string()),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].id, code.id);
+    }
+
+    #[test]
+    fn test_list_filter_by_before() {
+        let storage = test_storage();
+        let old = storage
+            .insert(NewClip { created_at: Some(Utc::now() - Duration::days(10)), ..text_clip("old") })
+            .unwrap();
+        storage.insert(text_clip("new")).unwrap();
+        let clips = storage.list(ClipFilter {
+            before: Some(Utc::now() - Duration::days(1)),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].id, old.id);
+    }
+
+    #[test]
+    fn test_list_filters_by_origin() {
+        let storage = test_storage();
+        storage.insert(NewClip { origin: Some("laptop".into()), ..text_clip("a") }).unwrap();
+        storage.insert(NewClip { origin: Some("studio".into()), ..text_clip("b") }).unwrap();
+        storage.insert(text_clip("c")).unwrap();
+        let from = |origin: &str| {
+            storage
+                .list(ClipFilter { origin: Some(origin.into()), ..Default::default() })
+                .unwrap()
+                .into_iter()
+                .map(|c| c.text_content.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(from("Laptop"), vec!["a"]);
+        assert_eq!(from("studio"), vec!["b"]);
+        assert!(from("desk").is_empty());
+    }
+
+    #[test]
+    fn test_list_filters_by_app() {
+        let storage = test_storage();
+        let safari = storage.insert(text_clip("a")).unwrap();
+        let old = storage.insert(text_clip("b")).unwrap();
+        let notes = storage.insert(text_clip("c")).unwrap();
+        storage.insert(text_clip("d")).unwrap();
+        let capture = |id: i64, app: &str, name: Option<&str>| {
+            storage.record_capture(id, Some(app), name, None, Workspace::default()).unwrap()
+        };
+        capture(safari.id, "com.apple.Safari", Some("Safari"));
+        capture(old.id, "com.apple.Safari", None);
+        capture(notes.id, "com.apple.Safari", Some("Safari"));
+        capture(notes.id, "com.apple.Notes", Some("Notes"));
+        // A capture with no known app leaves the clip's app alone.
+        storage.record_capture(notes.id, None, None, None, Workspace::default()).unwrap();
+
+        let from = |app: &str| {
+            storage
+                .list(ClipFilter { app: Some(app.into()), ..Default::default() })
+                .unwrap()
+                .into_iter()
+                .map(|c| c.text_content.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(from("safari"), vec!["b", "a"]);
+        assert_eq!(from("com.apple.Safari"), vec!["b", "a"]);
+        assert_eq!(from("Notes"), vec!["c"]);
+        assert!(from("apple").is_empty());
+
+        let clip = storage.get_by_id(notes.id).unwrap();
+        assert_eq!(clip.app_name.as_deref(), Some("Notes"));
+        assert_eq!(clip.app_label().as_deref(), Some("Notes (com.apple.Notes)"));
+    }
+
+    #[test]
+    fn test_list_order_desc() {
+        let storage = test_storage();
+        storage.insert(text_clip("first")).unwrap();
+        storage.insert(text_clip("second")).unwrap();
+        let clips = storage.list(ClipFilter::default()).unwrap();
+        assert!(clips[0].id > clips[1].id);
+    }
+
+    // --- Search ---
+
+    #[test]
+    fn test_search_finds_match() {
+        let storage = test_storage();
+        storage.insert(text_clip("hello world")).unwrap();
+        storage.insert(text_clip("goodbye world")).unwrap();
+        let results = storage.search("hello", 50).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].text_content.as_deref(), Some("hello world"));
+    }
+
+    #[test]
+    fn test_search_no_results() {
+        let storage = test_storage();
+        storage.insert(text_clip("hello")).unwrap();
+        let results = storage.search("xyz", 50).unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_search_case_insensitive() {
+        let storage = test_storage();
+        storage.insert(text_clip("Hello World")).unwrap();
+        let results = storage.search("hello", 50).unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_search_respects_limit() {
+        let storage = test_storage();
+        for i in 0..5 {
+            storage.insert(text_clip(&format!("match {}", i))).unwrap();
+        }
+        let results = storage.search("match", 3).unwrap();
+        assert_eq!(results.len(), 3);
+    }
+
+    // --- OCR ---
+
+    #[test]
+    fn test_pending_ocr_lists_unprocessed_images() {
+        let storage = test_storage();
+        storage.insert(text_clip("text")).unwrap();
+        let img = storage.insert(image_clip("/a.png", 10, 10)).unwrap();
+        let pending = storage.pending_enrichment(JobKind::Ocr).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, img.id);
+
+        storage.set_ocr_text(img.id, "").unwrap();
+        assert!(storage.pending_enrichment(JobKind::Ocr).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_fuzzy_ranks_best_match_first() {
+        let storage = test_storage();
+        let spread = storage.insert(text_clip("do kernel review, then log in")).unwrap();
+        let exact = storage.insert(text_clip("docker login -u me")).unwrap();
+        storage.insert(text_clip("unrelated")).unwrap();
+        storage.insert(text_clip("100% done_")).unwrap();
+
+        let ids = |query: &str| {
+            let filter = ClipFilter {
+                query: Some(query.into()),
+                limit: 10,
+                ..Default::default()
+            };
+            storage.search_fuzzy(filter).unwrap().iter().map(|c| c.id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids("dkrlogin"), vec![exact.id, spread.id]);
+        assert_eq!(ids("DOCKER"), vec![exact.id]);
+        // LIKE wildcards in the query are taken literally.
+        assert_eq!(ids("%_").len(), 1);
+        assert!(ids("zzz").is_empty());
+    }
+
+    #[test]
+    fn test_search_fuzzy_honors_time_window() {
+        let storage = test_storage();
+        let old = storage.insert(text_clip("docker login old")).unwrap();
+        storage
+            .conn
+            .execute(
+                "UPDATE clips SET created_at = ? WHERE id = ?",
+                params![Utc::now() - Duration::days(3), old.id],
+            )
+            .unwrap();
+        let recent = storage.insert(text_clip("docker login new")).unwrap();
+
+        let found = |after, before| {
+            let filter = ClipFilter {
+                query: Some("dkrlg".into()),
+                after,
+                before,
+                ..Default::default()
+            };
+            storage.search_fuzzy(filter).unwrap().iter().map(|c| c.id).collect::<Vec<_>>()
+        };
+        let day_ago = Some(Utc::now() - Duration::days(1));
+        assert_eq!(found(day_ago, None), vec![recent.id]);
+        assert_eq!(found(None, day_ago), vec![old.id]);
+        assert_eq!(found(None, None).len(), 2);
+    }
+
+    #[test]
+    fn test_search_matches_ocr_text() {
+        let storage = test_storage();
+        let img = storage.insert(image_clip("/trace.png", 10, 10)).unwrap();
+        storage.set_ocr_text(img.id, "panicked: Error code 42").unwrap();
+        let results = storage.search("error code", 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].ocr_text.as_deref(), Some("panicked: Error code 42"));
+    }
+
+    #[test]
+    fn test_image_descriptors_and_tone_filter() {
+        let storage = test_storage();
+        let dark = storage.insert(image_clip("/dark.png", 10, 10)).unwrap();
+        let light = storage.insert(image_clip("/light.png", 10, 10)).unwrap();
+        let broken = storage.insert(image_clip("/gone.png", 10, 10)).unwrap();
+        assert_eq!(storage.pending_enrichment(JobKind::Colors).unwrap().len(), 3);
+
+        let descriptor = |colors: &[&str], brightness| Descriptor {
+            colors: colors.iter().map(|c| c.to_string()).collect(),
+            brightness,
+        };
+        storage
+            .set_image_descriptor(dark.id, Some(&descriptor(&["#1e1e1e", "#d62728"], 0.12)))
+            .unwrap();
+        storage
+            .set_image_descriptor(light.id, Some(&descriptor(&["#ffffff"], 0.95)))
+            .unwrap();
+        storage.set_image_descriptor(broken.id, None).unwrap();
+        assert!(storage.pending_enrichment(JobKind::Colors).unwrap().is_empty());
+
+        let clip = storage.get_by_id(dark.id).unwrap();
+        assert_eq!(clip.colors, vec!["#1e1e1e", "#d62728"]);
+        assert_eq!(clip.brightness, Some(0.12));
+        assert!(storage.get_by_id(broken.id).unwrap().colors.is_empty());
+
+        let toned = |tone| {
+            storage
+                .list(ClipFilter { tone: Some(tone), ..Default::default() })
+                .unwrap()
+                .iter()
+                .map(|c| c.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(toned(Tone::Dark), vec![dark.id]);
+        assert_eq!(toned(Tone::Light), vec![light.id]);
+    }
+
+    // --- Jobs ---
+
+    #[test]
+    fn test_job_queue_claims_each_job_once() {
+        let storage = test_storage();
+        let img = storage.insert(image_clip("/a.png", 10, 10)).unwrap();
+        let url = storage.insert(text_clip("https://example.com")).unwrap();
+        storage.enqueue_job(img.id, JobKind::Ocr).unwrap();
+        storage.enqueue_job(img.id, JobKind::Ocr).unwrap();
+        storage.enqueue_job(url.id, JobKind::Title).unwrap();
+
+        // Kinds a worker can't do stay queued.
+        let job = storage.claim_job(&[JobKind::Title]).unwrap().unwrap();
+        assert_eq!((job.clip_id, job.kind), (url.id, JobKind::Title));
+        assert!(storage.claim_job(&[JobKind::Title]).unwrap().is_none());
+        storage.finish_job(job.id).unwrap();
+
+        let job = storage.claim_job(&JobKind::ALL).unwrap().unwrap();
+        assert_eq!((job.clip_id, job.kind), (img.id, JobKind::Ocr));
+        assert!(storage.claim_job(&JobKind::ALL).unwrap().is_none());
+        let ocr = |s: &SqliteStorage| s.job_counts().unwrap().into_iter().find(|c| c.kind == "ocr").unwrap();
+        assert_eq!((ocr(&storage).queued, ocr(&storage).running), (0, 1));
+
+        // A restart returns the interrupted job to the queue.
+        assert_eq!(storage.release_jobs().unwrap(), 1);
+        assert_eq!(storage.claim_job(&JobKind::ALL).unwrap().unwrap().id, job.id);
+    }
+
+    #[test]
+    fn test_job_gives_up_after_repeated_failures() {
+        let storage = test_storage();
+        let img = storage.insert(image_clip("/gone.png", 10, 10)).unwrap();
+        storage.enqueue_job(img.id, JobKind::Thumbnail).unwrap();
+        for attempt in 0..MAX_JOB_ATTEMPTS {
+            let job = storage.claim_job(&JobKind::ALL).unwrap().unwrap();
+            assert_eq!(job.attempts, attempt);
+            storage.fail_job(job.id, "no such file").unwrap();
+        }
+        assert!(storage.claim_job(&JobKind::ALL).unwrap().is_none());
+
+        let counts = storage.job_counts().unwrap();
+        assert_eq!(counts.len(), JobKind::ALL.len());
+        let thumbs = counts.iter().find(|c| c.kind == "thumbnail").unwrap();
+        assert_eq!((thumbs.queued, thumbs.failed), (0, 1));
+        assert_eq!(thumbs.last_error.as_deref(), Some("no such file"));
+
+        // Deleting the clip for good drops its jobs.
+        storage.delete(img.id).unwrap();
+        storage.empty_trash().unwrap();
+        assert_eq!(storage.job_counts().unwrap().iter().map(|c| c.failed).sum::<i64>(), 0);
+    }
+
+    #[test]
+    fn test_unpaired_images() {
+        let storage = test_storage();
+        storage.insert(text_clip("text")).unwrap();
+        let img = storage.insert(image_clip("/a.png", 10, 10)).unwrap();
+        let hour_ago = Utc::now() - chrono::Duration::hours(1);
+        assert_eq!(storage.unpaired_images(hour_ago).unwrap()[0].id, img.id);
+        assert!(storage.unpaired_images(Utc::now()).unwrap().is_empty());
+
+        storage.set_original_path(img.id, "/Users/me/Desktop/Screenshot.png").unwrap();
+        assert!(storage.unpaired_images(hour_ago).unwrap().is_empty());
+        assert_eq!(
+            storage.get_by_id(img.id).unwrap().original_path.as_deref(),
+            Some("/Users/me/Desktop/Screenshot.png")
+        );
+    }
+
+    // --- Links ---
+
+    #[test]
+    fn test_set_link_status() {
+        let storage = test_storage();
+        let clip = storage.insert(text_clip("https://example.com/old")).unwrap();
+        assert!(clip.link_status.is_none());
+        storage.set_link_status(clip.id, 301, Some("https://example.com/new")).unwrap();
+        let fetched = storage.get_by_id(clip.id).unwrap();
+        assert_eq!(fetched.link_status, Some(301));
+        assert_eq!(fetched.link_redirect.as_deref(), Some("https://example.com/new"));
+        assert!(fetched.link_checked_at.is_some());
+        assert!(matches!(storage.set_link_status(999, 200, None), Err(CbError::NotFound(_))));
     }
 
-    // --- List ---
+    // --- Delete ---
 
     #[test]
-    fn test_list_empty() {
+    fn test_delete_existing() {
         let storage = test_storage();
-        let clips = storage.list(ClipFilter::default()).unwrap();
-        assert!(clips.is_empty());
+        let clip = storage.insert(text_clip("delete me")).unwrap();
+        let deleted = storage.delete(clip.id).unwrap();
+        assert!(deleted);
+        assert!(matches!(storage.get_by_id(clip.id), Err(CbError::NotFound(_))));
     }
 
     #[test]
-    fn test_list_returns_clips() {
+    fn test_delete_nonexistent() {
         let storage = test_storage();
-        storage.insert(text_clip("one")).unwrap();
-        storage.insert(text_clip("two")).unwrap();
-        let clips = storage.list(ClipFilter::default()).unwrap();
-        assert_eq!(clips.len(), 2);
+        let deleted = storage.delete(999).unwrap();
+        assert!(!deleted);
     }
 
     #[test]
-    fn test_list_with_limit() {
+    fn test_delete_moves_to_trash() {
         let storage = test_storage();
-        for i in 0..5 {
-            storage.insert(text_clip(&format!("clip {}", i))).unwrap();
-        }
-        let clips = storage.list(ClipFilter { limit: 3, ..Default::default() }).unwrap();
-        assert_eq!(clips.len(), 3);
+        let gone = storage.insert(text_clip("trash me")).unwrap();
+        let kept = storage.insert(text_clip("keep me")).unwrap();
+        assert!(storage.delete(gone.id).unwrap());
+        assert!(!storage.delete(gone.id).unwrap());
+
+        let live = storage.list(ClipFilter::default()).unwrap();
+        assert_eq!(live.iter().map(|c| c.id).collect::<Vec<_>>(), vec![kept.id]);
+        assert!(storage.search("trash", 10).unwrap().is_empty());
+        assert_eq!(storage.stats().unwrap().total_clips, 1);
+
+        let trashed = storage
+            .list(ClipFilter {
+                trashed: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].id, gone.id);
+        assert!(trashed[0].deleted_at.is_some());
+        // Still found by hash, so capturing it again can bring it back.
+        assert_eq!(storage.find_by_hash(&gone.hash).unwrap().unwrap().id, gone.id);
     }
 
     #[test]
-    fn test_list_with_offset() {
+    fn test_restore_and_empty_trash() {
         let storage = test_storage();
-        for i in 0..5 {
-            storage.insert(text_clip(&format!("clip {}", i))).unwrap();
-        }
-        let clips = storage.list(ClipFilter { limit: 2, offset: 3, ..Default::default() }).unwrap();
-        assert_eq!(clips.len(), 2);
+        let back = storage.insert(text_clip("bring back")).unwrap();
+        let purge = storage.insert(text_clip("purge")).unwrap();
+        storage.add_tag(back.id, "keep").unwrap();
+        storage.delete(back.id).unwrap();
+        storage.delete(purge.id).unwrap();
+
+        assert!(storage.restore(back.id).unwrap());
+        assert!(!storage.restore(back.id).unwrap());
+        assert_eq!(storage.get_by_id(back.id).unwrap().tags, vec!["keep"]);
+
+        assert_eq!(storage.empty_trash().unwrap(), 1);
+        assert!(storage.find_by_hash(&purge.hash).unwrap().is_none());
+        assert!(!storage.restore(purge.id).unwrap());
+        assert_eq!(storage.empty_trash().unwrap(), 0);
     }
 
     #[test]
-    fn test_list_filter_by_type() {
+    fn test_shared_image_file_goes_with_its_last_clip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let images = dir.path().join("images");
+        std::fs::create_dir_all(images.join("thumbs")).unwrap();
+        let file = images.join("abc.png");
+        std::fs::write(&file, b"png").unwrap();
+        std::fs::write(thumbs::path_for(&file), b"thumb").unwrap();
+        let outside = dir.path().join("elsewhere.png");
+        std::fs::write(&outside, b"png").unwrap();
+
         let storage = test_storage();
-        storage.insert(text_clip("text")).unwrap();
-        storage.insert(image_clip("/img.png", 100, 100)).unwrap();
-        let clips = storage.list(ClipFilter {
-            content_type: Some(ContentType::Text),
-            ..Default::default()
-        }).unwrap();
-        assert_eq!(clips.len(), 1);
-        assert_eq!(clips[0].content_type, ContentType::Text);
+        let path = file.to_string_lossy();
+        let first = storage.insert(image_clip(&path, 1, 1)).unwrap();
+        let second = storage.insert(image_clip(&path, 2, 2)).unwrap();
+        let foreign = storage.insert(image_clip(&outside.to_string_lossy(), 3, 3)).unwrap();
+
+        // A rolled-back preview releases nothing.
+        let everything = RetentionPolicy { max_clips: Some(0), ..no_limits() };
+        assert_eq!(storage.preview_retention(&everything).unwrap().clips, 3);
+        assert_eq!(storage.release_unused_images(&images).unwrap(), 0);
+
+        storage.delete(first.id).unwrap();
+        storage.empty_trash().unwrap();
+        assert_eq!(storage.release_unused_images(&images).unwrap(), 0);
+        assert!(file.exists());
+
+        storage.delete(second.id).unwrap();
+        storage.delete(foreign.id).unwrap();
+        // Trashed clips still hold their image.
+        assert_eq!(storage.release_unused_images(&images).unwrap(), 0);
+        storage.empty_trash().unwrap();
+        assert_eq!(storage.release_unused_images(&images).unwrap(), 1);
+        assert!(!file.exists());
+        assert!(!thumbs::path_for(&file).exists());
+        assert!(outside.exists());
+        assert_eq!(storage.release_unused_images(&images).unwrap(), 0);
     }
 
     #[test]
-    fn test_list_filter_by_pinned() {
+    fn test_meta_set_filter_and_remove() {
         let storage = test_storage();
-        let c1 = storage.insert(text_clip("pinned")).unwrap();
-        storage.insert(text_clip("not pinned")).unwrap();
-        storage.set_pinned(c1.id, true).unwrap();
-        let clips = storage.list(ClipFilter {
-            pinned: Some(true),
-            ..Default::default()
-        }).unwrap();
-        assert_eq!(clips.len(), 1);
-        assert!(clips[0].pinned);
+        let a = storage.insert(text_clip("a")).unwrap();
+        let b = storage.insert(text_clip("b")).unwrap();
+        storage.set_meta(a.id, "ticket", "JIRA-1").unwrap();
+        storage.set_meta(a.id, "ticket", "JIRA-123").unwrap();
+        storage.set_meta(a.id, "owner", "sam").unwrap();
+        storage.set_meta(b.id, "ticket", "JIRA-9").unwrap();
+        assert!(matches!(storage.set_meta(99, "k", "v"), Err(CbError::NotFound(_))));
+
+        let clip = storage.get_by_id(a.id).unwrap();
+        assert_eq!(clip.meta.get("ticket").map(String::as_str), Some("JIRA-123"));
+        assert_eq!(clip.meta.len(), 2);
+        assert!(storage.get_by_id(b.id).unwrap().meta.contains_key("ticket"));
+
+        let ids = |meta: Vec<(&str, Option<&str>)>| -> Vec<i64> {
+            let filter = ClipFilter {
+                meta: meta
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v.map(String::from)))
+                    .collect(),
+                ..Default::default()
+            };
+            storage.list(filter).unwrap().iter().map(|c| c.id).collect()
+        };
+        assert_eq!(ids(vec![("ticket", Some("JIRA-123"))]), vec![a.id]);
+        assert_eq!(ids(vec![("ticket", None)]), vec![b.id, a.id]);
+        assert_eq!(ids(vec![("ticket", None), ("owner", Some("sam"))]), vec![a.id]);
+
+        assert!(storage.remove_meta(a.id, "owner").unwrap());
+        assert!(!storage.remove_meta(a.id, "owner").unwrap());
+        storage.delete(a.id).unwrap();
+        storage.empty_trash().unwrap();
+        let left: i64 = storage
+            .conn()
+            .query_row("SELECT COUNT(*) FROM clip_meta", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(left, 1);
     }
 
     #[test]
-    fn test_list_filter_by_tag() {
+    fn test_delete_matching() {
         let storage = test_storage();
-        let c1 = storage.insert(text_clip("tagged")).unwrap();
-        storage.insert(text_clip("untagged")).unwrap();
-        storage.add_tag(c1.id, "important").unwrap();
-        let clips = storage.list(ClipFilter {
-            tag: Some("important".to_string()),
+        let scratch = storage.insert(text_clip("scratch text")).unwrap();
+        let scratch_img = storage.insert(image_clip("/s.png", 1, 1)).unwrap();
+        let keep = storage.insert(image_clip("/k.png", 2, 2)).unwrap();
+        storage.add_tag(scratch.id, "scratch").unwrap();
+        storage.add_tag(scratch_img.id, "scratch").unwrap();
+
+        let filter = ClipFilter {
+            content_type: Some(ContentType::Image),
+            tag: Some("scratch".into()),
             ..Default::default()
-        }).unwrap();
-        assert_eq!(clips.len(), 1);
-        assert_eq!(clips[0].text_content.as_deref(), Some("tagged"));
+        };
+        assert_eq!(storage.count_matching(&filter).unwrap(), 1);
+        assert_eq!(storage.delete_matching(&filter).unwrap(), 1);
+        assert!(storage.get_by_id(scratch_img.id).is_err());
+        for id in [scratch.id, keep.id] {
+            assert!(storage.get_by_id(id).is_ok());
+        }
+        assert_eq!(storage.count_matching(&filter).unwrap(), 0);
     }
 
     #[test]
-    fn test_list_order_desc() {
+    fn test_pin_and_tag_matching() {
         let storage = test_storage();
-        storage.insert(text_clip("first")).unwrap();
-        storage.insert(text_clip("second")).unwrap();
-        let clips = storage.list(ClipFilter::default()).unwrap();
-        assert!(clips[0].id > clips[1].id);
-    }
+        let key = storage.insert(text_clip("export API_KEY=abc")).unwrap();
+        let other_key = storage.insert(text_clip("api_key: xyz")).unwrap();
+        let plain = storage.insert(text_clip("hello")).unwrap();
+        storage.add_tag(plain.id, "release").unwrap();
+        storage.set_pinned(other_key.id, true).unwrap();
+
+        let by_search = ClipFilter {
+            query: Some("API_KEY".into()),
+            ..Default::default()
+        };
+        assert_eq!(storage.tag_matching(&by_search, "secret", false).unwrap(), 2);
+        assert_eq!(storage.tag_matching(&by_search, "secret", false).unwrap(), 0);
+        assert_eq!(storage.get_by_id(key.id).unwrap().tags, vec!["secret"]);
+        assert_eq!(storage.get_by_id(plain.id).unwrap().tags, vec!["release"]);
 
-    // --- Search ---
+        assert_eq!(storage.set_pinned_matching(&by_search, true).unwrap(), 1);
+        assert!(storage.get_by_id(key.id).unwrap().pinned);
 
-    #[test]
-    fn test_search_finds_match() {
-        let storage = test_storage();
-        storage.insert(text_clip("hello world")).unwrap();
-        storage.insert(text_clip("goodbye world")).unwrap();
-        let results = storage.search("hello", 50).unwrap();
-        assert_eq!(results.len(), 1);
-        assert_eq!(results[0].text_content.as_deref(), Some("hello world"));
+        let by_tag = ClipFilter {
+            tag: Some("release".into()),
+            ..Default::default()
+        };
+        assert_eq!(storage.set_pinned_matching(&by_tag, true).unwrap(), 1);
+        assert!(storage.get_by_id(plain.id).unwrap().pinned);
+        assert_eq!(storage.set_pinned_matching(&by_search, false).unwrap(), 2);
+        assert_eq!(storage.tag_matching(&by_search, "secret", true).unwrap(), 2);
+        assert!(storage.get_by_id(other_key.id).unwrap().tags.is_empty());
     }
 
     #[test]
-    fn test_search_no_results() {
+    fn test_protected_clips_refuse_removal() {
         let storage = test_storage();
-        storage.insert(text_clip("hello")).unwrap();
-        let results = storage.search("xyz", 50).unwrap();
-        assert!(results.is_empty());
+        let locked = storage.insert(text_clip("locked")).unwrap();
+        let loose = storage.insert(text_clip("loose")).unwrap();
+        storage.set_protected(locked.id, true).unwrap();
+        assert!(storage.get_by_id(locked.id).unwrap().protected);
+        let past = Utc::now() - Duration::days(30);
+        storage
+            .conn()
+            .execute("UPDATE clips SET updated_at = ?", params![past])
+            .unwrap();
+
+        assert!(matches!(storage.delete(locked.id), Err(CbError::Protected(id)) if id == locked.id));
+        assert_eq!(storage.delete_matching(&ClipFilter::default()).unwrap(), 1);
+        assert_eq!(storage.empty_trash().unwrap(), 1);
+        assert_eq!(storage.clear_older_than(Utc::now()).unwrap(), 0);
+        let policy = RetentionPolicy {
+            max_clips: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(storage.apply_retention(&policy).unwrap(), 0);
+        assert!(storage.get_by_id(loose.id).is_err());
+
+        // Even raw SQL can't remove or rewrite it.
+        assert!(storage.conn().execute("DELETE FROM clips", []).is_err());
+        assert!(storage
+            .conn()
+            .execute("UPDATE clips SET text_content = 'x'", [])
+            .is_err());
+
+        storage.set_protected(locked.id, false).unwrap();
+        assert!(storage.delete(locked.id).unwrap());
+        assert!(matches!(storage.set_protected(locked.id, true), Err(CbError::NotFound(_))));
     }
 
     #[test]
-    fn test_search_case_insensitive() {
-        let storage = test_storage();
-        storage.insert(text_clip("Hello World")).unwrap();
-        let results = storage.search("hello", 50).unwrap();
-        assert_eq!(results.len(), 1);
+    fn test_open_read_only() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("cb.db");
+        let storage = SqliteStorage::new(Connection::open(&path).unwrap()).unwrap();
+        let clip = storage.insert(text_clip("synced")).unwrap();
+        drop(storage);
+
+        let storage = SqliteStorage::open_read_only(&path).unwrap();
+        assert!(storage.is_read_only());
+        assert_eq!(storage.get_by_id(clip.id).unwrap().text_content.as_deref(), Some("synced"));
+        assert!(storage.insert(text_clip("new")).is_err());
+        assert!(storage.set_pinned(clip.id, true).is_err());
+
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch("PRAGMA user_version = 1").unwrap();
+        assert!(matches!(
+            SqliteStorage::open_read_only(&path),
+            Err(CbError::InvalidInput(_))
+        ));
+        assert!(SqliteStorage::open_read_only(&dir.path().join("missing.db")).is_err());
     }
 
     #[test]
-    fn test_search_respects_limit() {
+    fn test_list_as_of() {
         let storage = test_storage();
-        for i in 0..5 {
-            storage.insert(text_clip(&format!("match {}", i))).unwrap();
+        let t = |hours: i64| Utc::now() - Duration::hours(hours);
+        let old = storage.insert(text_clip("old")).unwrap();
+        let mid = storage.insert(text_clip("mid")).unwrap();
+        let new = storage.insert(text_clip("new")).unwrap();
+        for (clip, created) in [(&old, t(10)), (&mid, t(5)), (&new, t(1))] {
+            storage
+                .conn()
+                .execute(
+                    "UPDATE clips SET created_at = ?1, updated_at = ?1 WHERE id = ?2",
+                    params![created, clip.id],
+                )
+                .unwrap();
+            storage
+                .conn()
+                .execute(
+                    "INSERT INTO captures (clip_id, captured_at) VALUES (?, ?)",
+                    params![clip.id, created],
+                )
+                .unwrap();
         }
-        let results = storage.search("match", 3).unwrap();
-        assert_eq!(results.len(), 3);
-    }
+        // "old" was copied again shortly before the 3-hours-ago mark.
+        storage
+            .conn()
+            .execute(
+                "INSERT INTO captures (clip_id, captured_at) VALUES (?, ?)",
+                params![old.id, t(4)],
+            )
+            .unwrap();
+        // "gone" existed 3 hours ago but was trashed 2 hours ago.
+        let gone = storage.insert(text_clip("gone")).unwrap();
+        storage.delete(gone.id).unwrap();
+        storage
+            .conn()
+            .execute(
+                "UPDATE clips SET created_at = ?1, updated_at = ?1, deleted_at = ?2 WHERE id = ?3",
+                params![t(8), t(2), gone.id],
+            )
+            .unwrap();
 
-    // --- Delete ---
+        let ids = |as_of| -> Vec<i64> {
+            storage
+                .list(ClipFilter {
+                    as_of: Some(as_of),
+                    ..Default::default()
+                })
+                .unwrap()
+                .iter()
+                .map(|c| c.id)
+                .collect()
+        };
+        assert_eq!(ids(t(3)), vec![old.id, mid.id, gone.id]);
+        assert_eq!(ids(t(6)), vec![gone.id, old.id]);
+        assert_eq!(ids(t(0)), vec![new.id, old.id, mid.id]);
+    }
 
     #[test]
-    fn test_delete_existing() {
+    fn test_append_text() {
         let storage = test_storage();
-        let clip = storage.insert(text_clip("delete me")).unwrap();
-        let deleted = storage.delete(clip.id).unwrap();
-        assert!(deleted);
-        assert!(matches!(storage.get_by_id(clip.id), Err(CbError::NotFound(_))));
+        let links = storage.insert(text_clip("https://a.example")).unwrap();
+        let clip = storage.append_text(links.id, "https://b.example", "\n").unwrap();
+        assert_eq!(clip.text_content.as_deref(), Some("https://a.example\nhttps://b.example"));
+        assert_eq!(clip.size_bytes, 35);
+        assert_eq!(clip.hash, hash_content(b"https://a.example\nhttps://b.example"));
+        assert_eq!(storage.find_by_hash(&clip.hash).unwrap().unwrap().id, links.id);
+
+        let taken = storage.insert(text_clip("x, y")).unwrap();
+        let x = storage.insert(text_clip("x")).unwrap();
+        assert!(matches!(storage.append_text(x.id, "y", ", "), Err(CbError::InvalidInput(_))));
+        assert!(storage.get_by_id(taken.id).is_ok());
+
+        let image = storage.insert(image_clip("/i.png", 1, 1)).unwrap();
+        assert!(matches!(storage.append_text(image.id, "y", ""), Err(CbError::InvalidInput(_))));
+        storage.set_protected(x.id, true).unwrap();
+        assert!(matches!(storage.append_text(x.id, "z", ""), Err(CbError::Protected(_))));
+        assert!(matches!(storage.append_text(999, "z", ""), Err(CbError::NotFound(_))));
     }
 
     #[test]
-    fn test_delete_nonexistent() {
+    fn test_update_content() {
         let storage = test_storage();
-        let deleted = storage.delete(999).unwrap();
-        assert!(!deleted);
+        let clip = storage.insert(text_clip("draft")).unwrap();
+        storage.add_tag(clip.id, "notes").unwrap();
+        let edited = storage.update_content(clip.id, "# Final\nhttps://x.example").unwrap();
+        assert_eq!(edited.text_content.as_deref(), Some("# Final\nhttps://x.example"));
+        assert_eq!(edited.hash, hash_content(b"# Final\nhttps://x.example"));
+        assert_eq!(edited.size_bytes, 25);
+        assert_eq!(edited.title.as_deref(), Some("Final"));
+        assert_eq!(edited.tags, vec!["notes"]);
+        assert!(storage.find_by_hash(&clip.hash).unwrap().is_none());
+
+        let other = storage.insert(text_clip("other")).unwrap();
+        assert!(matches!(storage.update_content(other.id, "# Final\nhttps://x.example"), Err(CbError::InvalidInput(_))));
+        storage.set_protected(other.id, true).unwrap();
+        assert!(matches!(storage.update_content(other.id, "new"), Err(CbError::Protected(_))));
     }
 
     #[test]
@@ -516,6 +2547,7 @@ mod tests {
         let clip = storage.insert(text_clip("tagged")).unwrap();
         storage.add_tag(clip.id, "tag1").unwrap();
         storage.delete(clip.id).unwrap();
+        storage.empty_trash().unwrap();
         let tag_count: i64 = storage
             .conn()
             .query_row("SELECT COUNT(*) FROM tags WHERE clip_id = ?", [clip.id], |row| row.get(0))
@@ -613,6 +2645,32 @@ mod tests {
         assert!(!fetched.pinned);
     }
 
+    #[test]
+    fn test_pin_until_lapses() {
+        let storage = test_storage();
+        let timed = storage.insert(text_clip("for the sprint")).unwrap();
+        let lasting = storage.insert(text_clip("forever")).unwrap();
+        let until = Utc::now() + Duration::days(3);
+        storage.pin_until(timed.id, until).unwrap();
+        storage.set_pinned(lasting.id, true).unwrap();
+        let fetched = storage.get_by_id(timed.id).unwrap();
+        assert!(fetched.pinned);
+        assert_eq!(fetched.pinned_until, Some(until));
+
+        assert_eq!(storage.unpin_expired(Utc::now()).unwrap(), 0);
+        assert_eq!(storage.unpin_expired(until).unwrap(), 1);
+        let fetched = storage.get_by_id(timed.id).unwrap();
+        assert!(!fetched.pinned);
+        assert_eq!(fetched.pinned_until, None);
+        assert!(storage.get_by_id(lasting.id).unwrap().pinned);
+
+        // Pinning again without a date makes the pin last.
+        storage.pin_until(timed.id, until).unwrap();
+        storage.set_pinned(timed.id, true).unwrap();
+        assert_eq!(storage.unpin_expired(until).unwrap(), 0);
+        assert!(storage.pin_until(999, until).is_err());
+    }
+
     // --- Clear ---
 
     #[test]
@@ -641,6 +2699,133 @@ mod tests {
         assert!(clips[0].pinned);
     }
 
+    // --- Retention ---
+
+    fn no_limits() -> RetentionPolicy {
+        RetentionPolicy {
+            max_age_days: None,
+            max_clips: None,
+            max_size_mb: None,
+            exempt_tags: vec!["snippets".into()],
+        }
+    }
+
+    #[test]
+    fn test_retention_by_age_exempts_pinned_and_tags() {
+        let storage = test_storage();
+        let old = storage.insert(text_clip("old")).unwrap();
+        let pinned = storage.insert(text_clip("pinned")).unwrap();
+        let snippet = storage.insert(text_clip("snippet")).unwrap();
+        storage.set_pinned(pinned.id, true).unwrap();
+        storage.add_tag(snippet.id, "snippets").unwrap();
+        storage
+            .conn()
+            .execute("UPDATE clips SET updated_at = ?", params![Utc::now() - Duration::days(60)])
+            .unwrap();
+        let fresh = storage.insert(text_clip("fresh")).unwrap();
+
+        let removed = storage
+            .apply_retention(&RetentionPolicy { max_age_days: Some(30), ..no_limits() })
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert!(storage.get_by_id(old.id).is_err());
+        for id in [pinned.id, snippet.id, fresh.id] {
+            assert!(storage.get_by_id(id).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_retention_by_count_keeps_newest() {
+        let storage = test_storage();
+        let ids: Vec<i64> = (0..5)
+            .map(|i| storage.insert(text_clip(&format!("clip {}", i))).unwrap().id)
+            .collect();
+        storage.set_pinned(ids[0], true).unwrap();
+
+        let removed = storage
+            .apply_retention(&RetentionPolicy { max_clips: Some(2), ..no_limits() })
+            .unwrap();
+        assert_eq!(removed, 2);
+        let remaining: Vec<i64> = storage
+            .list(ClipFilter::default())
+            .unwrap()
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(remaining, vec![ids[4], ids[3], ids[0]]);
+    }
+
+    #[test]
+    fn test_retention_caps_ignore_trashed_clips() {
+        let storage = test_storage();
+        let ids: Vec<i64> = (0..5)
+            .map(|i| storage.insert(text_clip(&format!("clip {}", i))).unwrap().id)
+            .collect();
+        storage.delete(ids[3]).unwrap();
+        storage.delete(ids[4]).unwrap();
+
+        let removed = storage
+            .apply_retention(&RetentionPolicy { max_clips: Some(2), ..no_limits() })
+            .unwrap();
+        assert_eq!(removed, 1);
+        let live: Vec<i64> = storage
+            .list(ClipFilter::default())
+            .unwrap()
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(live, vec![ids[2], ids[1]]);
+        let trashed = storage
+            .list(ClipFilter {
+                trashed: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(trashed.len(), 2);
+    }
+
+    #[test]
+    fn test_retention_by_size() {
+        let storage = test_storage();
+        let big = "x".repeat(700 * 1024);
+        let first = storage.insert(text_clip(&big)).unwrap();
+        let second = storage.insert(text_clip(&format!("{}y", big))).unwrap();
+        storage.touch(second.id).unwrap();
+
+        let removed = storage
+            .apply_retention(&RetentionPolicy { max_size_mb: Some(1), ..no_limits() })
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert!(storage.get_by_id(first.id).is_err());
+        assert!(storage.get_by_id(second.id).is_ok());
+    }
+
+    #[test]
+    fn test_preview_retention_matches_without_deleting() {
+        let storage = test_storage();
+        let old = storage.insert(text_clip("old clip")).unwrap();
+        let pinned = storage.insert(text_clip("pinned")).unwrap();
+        storage.set_pinned(pinned.id, true).unwrap();
+        storage
+            .conn()
+            .execute("UPDATE clips SET updated_at = ?", params![Utc::now() - Duration::days(60)])
+            .unwrap();
+        storage.insert(text_clip("fresh")).unwrap();
+
+        let policy = RetentionPolicy { max_age_days: Some(30), ..no_limits() };
+        let preview = storage.preview_retention(&policy).unwrap();
+        assert_eq!(preview, RetentionPreview { clips: 1, bytes: old.size_bytes, protected: 1 });
+        assert_eq!(storage.stats().unwrap().total_clips, 3);
+        assert_eq!(storage.apply_retention(&policy).unwrap(), preview.clips);
+    }
+
+    #[test]
+    fn test_retention_without_limits_is_noop() {
+        let storage = test_storage();
+        storage.insert(text_clip("keep")).unwrap();
+        assert_eq!(storage.apply_retention(&no_limits()).unwrap(), 0);
+    }
+
     // --- Stats ---
 
     #[test]
@@ -682,10 +2867,385 @@ mod tests {
         assert!(fetched.updated_at >= original_updated);
     }
 
+    #[test]
+    fn test_record_copy_increments_count() {
+        let storage = test_storage();
+        let clip = storage.insert(text_clip("copied")).unwrap();
+        assert_eq!(clip.copy_count, 0);
+        storage.record_copy(clip.id).unwrap();
+        storage.record_copy(clip.id).unwrap();
+        assert_eq!(storage.get_by_id(clip.id).unwrap().copy_count, 2);
+        assert!(matches!(storage.record_copy(999), Err(CbError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_captures_logged_in_order_and_cascade() {
+        let storage = test_storage();
+        let clip = storage.insert(text_clip("token")).unwrap();
+        assert!(storage.captures(clip.id).unwrap().is_empty());
+        storage
+            .record_capture(clip.id, Some("com.apple.Terminal"), None, Some("studio"), Workspace::default())
+            .unwrap();
+        storage.record_capture(clip.id, None, None, None, Workspace::default()).unwrap();
+
+        let captures = storage.captures(clip.id).unwrap();
+        assert_eq!(captures.len(), 2);
+        assert_eq!(captures[0].source_app.as_deref(), Some("com.apple.Terminal"));
+        assert_eq!(captures[0].machine.as_deref(), Some("studio"));
+        assert!(captures[1].source_app.is_none());
+        assert!(captures[0].captured_at <= captures[1].captured_at);
+
+        assert_eq!(
+            storage.get_by_id(clip.id).unwrap().source_app.as_deref(),
+            Some("com.apple.Terminal")
+        );
+
+        storage.delete(clip.id).unwrap();
+        storage.empty_trash().unwrap();
+        let remaining: i64 = storage
+            .conn()
+            .query_row("SELECT COUNT(*) FROM captures", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn test_filter_by_space_and_display() {
+        let storage = test_storage();
+        let here = storage.insert(text_clip("in space 3")).unwrap();
+        let there = storage.insert(text_clip("in space 7")).unwrap();
+        storage.insert(text_clip("not recorded")).unwrap();
+        let space = |space, display| Workspace {
+            space: Some(space),
+            display: Some(display),
+        };
+        storage.record_capture(here.id, None, None, None, space(3, 1)).unwrap();
+        storage.record_capture(there.id, None, None, None, space(7, 1)).unwrap();
+        // Copied again elsewhere: it belongs to both Spaces.
+        storage.record_capture(there.id, None, None, None, space(3, 2)).unwrap();
+
+        let ids = |filter: ClipFilter| -> Vec<i64> {
+            storage.list(filter).unwrap().iter().map(|c| c.id).collect()
+        };
+        let in_space = |id| ClipFilter {
+            space: Some(id),
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(ids(in_space(3)), vec![there.id, here.id]);
+        assert_eq!(ids(in_space(7)), vec![there.id]);
+        assert_eq!(
+            ids(ClipFilter {
+                display: Some(2),
+                limit: 10,
+                ..Default::default()
+            }),
+            vec![there.id]
+        );
+        assert_eq!(storage.captures(there.id).unwrap()[1].space, Some(3));
+    }
+
+    #[test]
+    fn test_usage_stats() {
+        let storage = test_storage();
+        let a = storage.insert(text_clip("a")).unwrap();
+        let b = storage.insert(text_clip("b")).unwrap();
+        storage.insert(text_clip("never copied")).unwrap();
+        storage.record_copy(a.id).unwrap();
+        storage.record_copy(b.id).unwrap();
+        storage.record_copy(b.id).unwrap();
+
+        let usage = storage.usage_stats(10).unwrap();
+        let ids: Vec<i64> = usage.most_copied.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![b.id, a.id]);
+        assert_eq!(usage.clips_by_hour.len(), 24);
+        assert_eq!(usage.clips_by_hour.iter().sum::<i64>(), 3);
+        assert!(usage.top_apps.is_empty());
+    }
+
+    #[test]
+    fn test_usage_stats_top_apps() {
+        let storage = test_storage();
+        let a = storage.insert(text_clip("a")).unwrap();
+        let b = storage.insert(text_clip("b")).unwrap();
+        let trashed = storage.insert(text_clip("trashed")).unwrap();
+        let safari = (Some("com.apple.Safari"), Some("Safari"));
+        let capture = |id, (app, name): (Option<&str>, Option<&str>)| {
+            storage.record_capture(id, app, name, None, Workspace::default()).unwrap();
+        };
+        capture(a.id, safari);
+        capture(b.id, safari);
+        capture(a.id, (Some("com.apple.Terminal"), None));
+        capture(b.id, (None, None));
+        for _ in 0..3 {
+            capture(trashed.id, (Some("com.apple.Notes"), Some("Notes")));
+        }
+        storage.delete(trashed.id).unwrap();
+
+        let usage = storage.usage_stats(10).unwrap();
+        let apps: Vec<(&str, i64)> = usage.top_apps.iter().map(|a| (a.label(), a.captures)).collect();
+        assert_eq!(apps, vec![("Safari", 2), ("com.apple.Terminal", 1)]);
+        assert_eq!(storage.usage_stats(1).unwrap().top_apps.len(), 1);
+    }
+
     #[test]
     fn test_touch_nonexistent() {
         let storage = test_storage();
         let result = storage.touch(999);
         assert!(matches!(result, Err(CbError::NotFound(_))));
     }
+
+    #[test]
+    fn test_sessions() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        assert!(storage.active_session().unwrap().is_none());
+        assert!(matches!(storage.stop_session(), Err(CbError::NotFound(_))));
+        assert!(storage.start_session("paper review").is_err());
+
+        storage.start_session("paper-review").unwrap();
+        assert!(storage.start_session("other").is_err());
+        assert_eq!(storage.active_session().unwrap().unwrap().name, "paper-review");
+        let stopped = storage.stop_session().unwrap();
+        assert!(stopped.ended_at.is_some());
+        assert!(storage.active_session().unwrap().is_none());
+
+        storage.start_session("paper-review").unwrap();
+        let spans = storage.sessions().unwrap();
+        assert_eq!(spans.len(), 2);
+        assert!(spans[0].ended_at.is_some() && spans[1].ended_at.is_none());
+    }
+
+    #[test]
+    fn test_api_tokens() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        storage.create_token("phone", "hash-1", TokenScope::Read).unwrap();
+        storage.create_token("script", "hash-2", TokenScope::ReadWrite).unwrap();
+        assert!(storage.create_token("phone", "hash-3", TokenScope::Read).is_err());
+        assert!(storage.create_token("my phone", "hash-4", TokenScope::Read).is_err());
+
+        let found = storage.find_token("hash-2").unwrap().unwrap();
+        assert_eq!((found.name.as_str(), found.scope), ("script", TokenScope::ReadWrite));
+        assert!(storage.find_token("phone").unwrap().is_none());
+        let names: Vec<_> = storage.tokens().unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["phone", "script"]);
+
+        assert!(storage.revoke_token("phone").unwrap());
+        assert!(!storage.revoke_token("phone").unwrap());
+        assert!(storage.find_token("hash-1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_slots_live_outside_history() {
+        let storage = test_storage();
+        storage.set_slot("build-cmd", "cargo build --release").unwrap();
+        storage.set_slot("ticket", "CB-12").unwrap();
+        let updated = storage.set_slot("build-cmd", "cargo test").unwrap();
+        assert_eq!(updated.text, "cargo test");
+        assert!(storage.set_slot("two words", "x").is_err());
+
+        let names: Vec<_> = storage.slots().unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["build-cmd", "ticket"]);
+        assert_eq!(storage.stats().unwrap().total_clips, 0);
+        assert_eq!(storage.apply_retention(&RetentionPolicy::default()).unwrap(), 0);
+
+        assert!(storage.delete_slot("ticket").unwrap());
+        assert!(!storage.delete_slot("ticket").unwrap());
+        assert!(matches!(storage.get_slot("ticket"), Err(CbError::NotFound(_))));
+    }
+
+    // --- Generated filters ---
+
+    /// A small history covering what filters look at: tags, metadata,
+    /// pins, protection, captures, URLs, images, ages, and the trash.
+    fn varied_storage() -> SqliteStorage {
+        let storage = test_storage();
+        let now = Utc::now();
+        let clips: Vec<Clip> = [
+            "abc",
+            "a%c",
+            "A_C",
+            "https://example.com/?q=a",
+            "fn main() { let a = 1; }",
+            "back\\slash 'quoted'",
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, text)| {
+            let clip = NewClip {
+                created_at: Some(now - Duration::days(i as i64)),
+                origin: (i % 2 == 0).then(|| "a".to_string()),
+                ..text_clip(text)
+            };
+            storage.insert(clip).unwrap()
+        })
+        .collect();
+        storage.insert(image_clip("/tmp/a.png", 4, 4)).unwrap();
+
+        storage.add_tag(clips[0].id, "a").unwrap();
+        storage.add_tag(clips[1].id, "b'").unwrap();
+        storage.set_pinned(clips[0].id, true).unwrap();
+        storage.set_protected(clips[2].id, true).unwrap();
+        storage.set_meta(clips[1].id, "a", "b").unwrap();
+        let space = Workspace { space: Some(1), display: Some(2) };
+        storage.record_capture(clips[2].id, Some("com.apple.Safari"), Some("Safari"), None, space).unwrap();
+        storage.delete(clips[5].id).unwrap();
+        storage
+    }
+
+    /// Short strings heavy in characters SQL and `LIKE` treat specially.
+    fn tricky_text() -> impl Strategy<Value = String> {
+        "[aAbc%_'\"\\\\ ]{0,3}"
+    }
+
+    fn filters() -> impl Strategy<Value = ClipFilter> {
+        let text = || proptest::option::of(tricky_text());
+        let time = || proptest::option::of((-3i64..3).prop_map(|days| Utc::now() + Duration::days(days)));
+        let flag = || proptest::option::of(any::<bool>());
+        (
+            (
+                proptest::option::of(prop_oneof![
+                    Just(ContentType::Text),
+                    Just(ContentType::Image),
+                    Just(ContentType::FileRef),
+                ]),
+                flag(),
+                text(),
+                proptest::option::of(prop_oneof![Just("en".to_string()), Just("rust".to_string())]),
+                text(),
+                text(),
+                time(),
+                time(),
+                text(),
+                flag(),
+                flag(),
+                proptest::collection::vec(tricky_text(), 0..2),
+            ),
+            (
+                time(),
+                proptest::option::of(prop_oneof![Just(Tone::Dark), Just(Tone::Light)]),
+                any::<bool>(),
+                proptest::option::of(0i64..10),
+                any::<bool>(),
+                proptest::collection::vec((tricky_text(), proptest::option::of(tricky_text())), 0..3),
+                proptest::option::of(0i64..3),
+                proptest::option::of(0i64..3),
+                -1i64..5,
+                0i64..3,
+            ),
+        )
+            .prop_map(
+                |(
+                    (content_type, pinned, tag, lang, origin, app, before, after, query, protected, url, extra_tags),
+                    (as_of, tone, oldest_first, cursor, trashed, meta, space, display, limit, offset),
+                )| ClipFilter {
+                    content_type,
+                    pinned,
+                    tag,
+                    extra_tags,
+                    lang,
+                    origin,
+                    app,
+                    before,
+                    after,
+                    query,
+                    protected,
+                    url,
+                    as_of,
+                    tone,
+                    oldest_first,
+                    cursor,
+                    trashed,
+                    meta,
+                    space,
+                    display,
+                    limit,
+                    offset,
+                },
+            )
+    }
+
+    fn contains_ignoring_case(text: Option<&str>, needle: &str) -> bool {
+        text.is_some_and(|t| t.to_ascii_lowercase().contains(&needle.to_ascii_lowercase()))
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::prelude::ProptestConfig::with_cases(128))]
+
+        #[test]
+        fn prop_list_returns_only_matching_clips(filter in filters()) {
+            let storage = varied_storage();
+            let listed = storage.list(filter.clone()).unwrap();
+            let count = storage.count_matching(&filter).unwrap();
+            prop_assert!(listed.len() as i64 <= filter.effective_limit());
+            if filter.offset == 0 {
+                prop_assert_eq!(listed.len() as i64, count.min(filter.effective_limit()));
+            }
+            if filter.as_of.is_none() && !filter.trashed {
+                let ids: Vec<i64> = listed.iter().map(|c| c.id).collect();
+                let ordered = if filter.oldest_first {
+                    ids.windows(2).all(|w| w[0] < w[1])
+                } else {
+                    ids.windows(2).all(|w| w[0] > w[1])
+                };
+                prop_assert!(ordered, "{:?}", ids);
+            }
+            for clip in &listed {
+                prop_assert_eq!(clip.deleted_at.is_some(), filter.trashed);
+                if let Some(ref ct) = filter.content_type {
+                    prop_assert_eq!(&clip.content_type, ct);
+                }
+                if let Some(pinned) = filter.pinned {
+                    prop_assert_eq!(clip.pinned, pinned);
+                }
+                if let Some(protected) = filter.protected {
+                    prop_assert_eq!(clip.protected, protected);
+                }
+                if let Some(url) = filter.url {
+                    prop_assert_eq!(clip.canonical_url.is_some(), url);
+                }
+                for tag in filter.tag.iter().chain(&filter.extra_tags) {
+                    prop_assert!(clip.tags.contains(tag));
+                }
+                if let Some(ref lang) = filter.lang {
+                    prop_assert_eq!(clip.lang.as_ref(), Some(lang));
+                }
+                if let Some(ref origin) = filter.origin {
+                    prop_assert!(clip.origin.as_ref().is_some_and(|o| o.eq_ignore_ascii_case(origin)));
+                }
+                if let Some(ref query) = filter.query {
+                    prop_assert!(
+                        contains_ignoring_case(clip.text_content.as_deref(), query)
+                            || contains_ignoring_case(clip.ocr_text.as_deref(), query),
+                        "{:?} doesn't contain {:?}", clip.text_content, query
+                    );
+                }
+                if let Some(before) = filter.before {
+                    prop_assert!(clip.created_at < before);
+                }
+                if let Some(after) = filter.after {
+                    prop_assert!(clip.created_at >= after);
+                }
+                for (key, value) in &filter.meta {
+                    let found = clip.meta.get(key);
+                    prop_assert!(found.is_some());
+                    if let Some(value) = value {
+                        prop_assert_eq!(found, Some(value));
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn prop_searches_accept_any_query(query in "\\PC{0,12}", filter in filters()) {
+            let storage = varied_storage();
+            for clip in storage.search(&query, 10).unwrap() {
+                prop_assert!(
+                    contains_ignoring_case(clip.text_content.as_deref(), &query)
+                        || contains_ignoring_case(clip.ocr_text.as_deref(), &query)
+                );
+            }
+            let fuzzy = ClipFilter { query: Some(query), ..filter };
+            storage.search_fuzzy(fuzzy).unwrap();
+        }
+    }
 }
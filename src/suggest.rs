@@ -0,0 +1,248 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::errors::{CbError, Result};
+use crate::storage::ClipStorage;
+use crate::storage::models::{Clip, ClipFilter};
+
+/// Recent clips considered for suggestions.
+pub const DEFAULT_POOL: i64 = 500;
+
+/// Suggestions returned when no limit is given.
+pub const DEFAULT_LIMIT: i64 = 5;
+
+/// Hours after which a clip's recency counts half as much.
+const HALF_LIFE_HOURS: f64 = 72.0;
+
+/// Words too common to say anything about what the user is doing.
+const STOP_WORDS: &[&str] = &[
+    "a", "about", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "has", "have",
+    "in", "into", "is", "it", "its", "my", "of", "on", "or", "our", "so", "that", "the", "their",
+    "this", "to", "was", "we", "what", "with", "you", "your",
+];
+
+/// What the user is working on, reduced to the terms clips are matched on.
+#[derive(Debug, Clone)]
+pub struct Context {
+    pub terms: BTreeSet<String>,
+    pub now: DateTime<Utc>,
+}
+
+impl Context {
+    pub fn new(text: &str) -> Self {
+        Self {
+            terms: terms(text),
+            now: Utc::now(),
+        }
+    }
+
+    /// Context terms that also appear in `clip`, in order.
+    pub fn matched(&self, clip: &Clip) -> Vec<String> {
+        let words = clip_terms(clip);
+        self.terms.intersection(&words).cloned().collect()
+    }
+}
+
+/// Scores how likely a clip is what the user needs in a context. Higher is
+/// better; 0 means not at all.
+pub trait Scorer {
+    fn score(&self, clip: &Clip, context: &Context) -> f64;
+}
+
+/// Share of the context's terms that the clip contains, 0 to 1.
+pub struct TermOverlap;
+
+impl Scorer for TermOverlap {
+    fn score(&self, clip: &Clip, context: &Context) -> f64 {
+        if context.terms.is_empty() {
+            return 0.0;
+        }
+        context.matched(clip).len() as f64 / context.terms.len() as f64
+    }
+}
+
+/// How recently and how often a clip was used, 0 to 1: recency halves every
+/// `HALF_LIFE_HOURS`, and each copy back to the clipboard weighs it up.
+pub struct Frecency;
+
+impl Scorer for Frecency {
+    fn score(&self, clip: &Clip, context: &Context) -> f64 {
+        let hours = (context.now - clip.updated_at).num_minutes().max(0) as f64 / 60.0;
+        let recency = 0.5f64.powf(hours / HALF_LIFE_HOURS);
+        let uses = 1.0 - 1.0 / (1.0 + clip.copy_count.max(0) as f64);
+        recency * (1.0 + uses) / 2.0
+    }
+}
+
+/// Term overlap weighed together with frecency. With the default weights
+/// any overlap outranks frecency alone, and frecency orders clips that
+/// match equally well.
+pub struct Blend {
+    pub overlap: f64,
+    pub frecency: f64,
+}
+
+impl Default for Blend {
+    fn default() -> Self {
+        Self {
+            overlap: 0.8,
+            frecency: 0.2,
+        }
+    }
+}
+
+impl Scorer for Blend {
+    fn score(&self, clip: &Clip, context: &Context) -> f64 {
+        self.overlap * TermOverlap.score(clip, context) + self.frecency * Frecency.score(clip, context)
+    }
+}
+
+/// The scorer called `name` on the command line and in the API.
+pub fn scorer(name: &str) -> Result<Box<dyn Scorer>> {
+    match name {
+        "blend" => Ok(Box::new(Blend::default())),
+        "overlap" => Ok(Box::new(TermOverlap)),
+        "frecency" => Ok(Box::new(Frecency)),
+        _ => Err(CbError::InvalidInput(format!(
+            "unknown scorer \"{}\" (expected blend, overlap, or frecency)",
+            name
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Suggestion {
+    pub score: f64,
+    /// Context terms found in the clip.
+    pub matched: Vec<String>,
+    #[serde(flatten)]
+    pub clip: Clip,
+}
+
+/// The `limit` best-scoring of the `pool` most recent live clips for
+/// `context`, best first, leaving out clips that score 0. Ties keep the
+/// newer clip first.
+pub fn suggest<S: ClipStorage>(
+    storage: &S,
+    context: &str,
+    scorer: &dyn Scorer,
+    pool: i64,
+    limit: usize,
+) -> Result<Vec<Suggestion>> {
+    let context = Context::new(context);
+    let clips = storage.list(ClipFilter {
+        limit: pool.max(1),
+        ..Default::default()
+    })?;
+    let mut suggestions: Vec<Suggestion> = clips
+        .into_iter()
+        .filter_map(|clip| {
+            let score = scorer.score(&clip, &context);
+            (score > 0.0).then(|| Suggestion {
+                score,
+                matched: context.matched(&clip),
+                clip,
+            })
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suggestions.truncate(limit);
+    Ok(suggestions)
+}
+
+/// Everything in `clip` a context can match: its text, recognized text,
+/// titles, and tags.
+fn clip_terms(clip: &Clip) -> BTreeSet<String> {
+    let fields = [
+        clip.text_content.as_deref(),
+        clip.ocr_text.as_deref(),
+        clip.title.as_deref(),
+        clip.source_title.as_deref(),
+        clip.link_title.as_deref(),
+    ];
+    let mut words: BTreeSet<String> = fields.into_iter().flatten().flat_map(terms).collect();
+    words.extend(clip.tags.iter().flat_map(|tag| terms(tag)));
+    words
+}
+
+/// Lowercased words of `text` without stop words or single letters, with a
+/// plural `s` dropped so "invoices" matches "invoice".
+fn terms(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|w| w.chars().count() > 1 && !STOP_WORDS.contains(&w.as_str()))
+        .map(|w| match w.strip_suffix('s') {
+            Some(stem) if stem.chars().count() > 2 && !stem.ends_with('s') => stem.to_string(),
+            _ => w,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::hash_content;
+    use crate::storage::models::{ContentType, NewClip};
+    use crate::storage::sqlite::SqliteStorage;
+    use chrono::Duration;
+
+    fn add(storage: &SqliteStorage, text: &str) -> Clip {
+        storage
+            .insert(NewClip {
+                content_type: ContentType::Text,
+                text_content: Some(text.to_string()),
+                hash: hash_content(text.as_bytes()),
+                size_bytes: text.len() as i64,
+                ..Default::default()
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_terms_drop_stop_words_and_plurals() {
+        let words: Vec<String> = terms("Writing an email to Bob about invoices, class A").into_iter().collect();
+        assert_eq!(words, vec!["bob", "class", "email", "invoice", "writing"]);
+    }
+
+    #[test]
+    fn test_suggest_ranks_overlap_then_frecency() {
+        let storage = SqliteStorage::in_memory().unwrap();
+        let invoice = add(&storage, "Invoice #1042 for March");
+        let bob = add(&storage, "bob@example.com");
+        storage.add_tag(bob.id, "invoices").unwrap();
+        add(&storage, "cargo build --release");
+
+        let context = "writing an email to Bob about invoices";
+        let found = suggest(&storage, context, &Blend::default(), DEFAULT_POOL, 5).unwrap();
+        let ids: Vec<i64> = found.iter().map(|s| s.clip.id).collect();
+        // Bob's address matches two terms, the invoice one; the build command
+        // only has frecency to go on.
+        assert_eq!(ids, vec![bob.id, invoice.id, 3]);
+        assert_eq!(found[0].matched, vec!["bob", "invoice"]);
+
+        let found = suggest(&storage, context, &TermOverlap, DEFAULT_POOL, 5).unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(suggest(&storage, context, &TermOverlap, DEFAULT_POOL, 1).unwrap().len() == 1);
+    }
+
+    #[test]
+    fn test_frecency_favors_recent_and_reused() {
+        let now = Utc::now();
+        let context = Context {
+            terms: BTreeSet::new(),
+            now,
+        };
+        let clip = |hours: i64, copies: i64| Clip {
+            updated_at: now - Duration::hours(hours),
+            copy_count: copies,
+            ..Default::default()
+        };
+        let score = |c: &Clip| Frecency.score(c, &context);
+        assert!(score(&clip(1, 0)) > score(&clip(100, 0)));
+        assert!(score(&clip(1, 5)) > score(&clip(1, 0)));
+        assert!((score(&clip(72, 0)) - 0.25).abs() < 1e-9);
+        assert!(scorer("nope").is_err());
+    }
+}
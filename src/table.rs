@@ -0,0 +1,258 @@
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::width;
+
+/// Tab- or comma-separated text, as copied from Numbers, Excel, or a CSV
+/// file. The first row is treated as the header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Parse `text` as a table if every line splits into the same number (at
+/// least two) of tab- or comma-separated fields, over at least two lines.
+///
+/// Commas are only trusted when no unquoted field starts with a space, so
+/// ordinary prose ("Hi, there") isn't mistaken for CSV.
+pub fn parse(text: &str) -> Option<Table> {
+    let lines: Vec<&str> = text.trim_end_matches(['\n', '\r']).lines().collect();
+    if lines.len() < 2 || lines.iter().any(|l| l.trim().is_empty()) {
+        return None;
+    }
+
+    // Lines starting with a tab are indented code, not rows.
+    let rows: Vec<Vec<String>> = if lines
+        .iter()
+        .all(|l| l.contains('\t') && !l.starts_with('\t'))
+    {
+        lines
+            .iter()
+            .map(|l| l.split('\t').map(|f| f.trim().to_string()).collect())
+            .collect()
+    } else {
+        lines
+            .iter()
+            .map(|l| split_csv_line(l))
+            .collect::<Option<_>>()?
+    };
+    let width = rows[0].len();
+    if width < 2 || rows.iter().any(|r| r.len() != width) {
+        return None;
+    }
+    Some(Table { rows })
+}
+
+/// Split one CSV line, honoring double-quoted fields with `""` escapes.
+/// `None` if a quote is left open or a bare field starts with whitespace.
+fn split_csv_line(line: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next()? {
+                    '"' if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    '"' => break,
+                    c => field.push(c),
+                }
+            }
+            fields.push(field);
+            // Only a separator (or the end) may follow a closing quote.
+            match chars.next() {
+                Some(',') => continue,
+                None => return Some(fields),
+                Some(_) => return None,
+            }
+        }
+        if chars.peek().is_some_and(|c| c.is_whitespace()) {
+            return None;
+        }
+        let mut more = false;
+        for c in chars.by_ref() {
+            if c == ',' {
+                more = true;
+                break;
+            }
+            field.push(c);
+        }
+        fields.push(field.trim_end().to_string());
+        if !more {
+            return Some(fields);
+        }
+    }
+}
+
+impl Table {
+    pub fn header(&self) -> &[String] {
+        &self.rows[0]
+    }
+
+    pub fn body(&self) -> &[Vec<String>] {
+        &self.rows[1..]
+    }
+
+    pub fn to_csv(&self) -> String {
+        let field = |f: &String| {
+            if f.contains([',', '"', '\n']) || f.starts_with(' ') {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.clone()
+            }
+        };
+        self.rows
+            .iter()
+            .map(|r| r.iter().map(field).collect::<Vec<_>>().join(",") + "\n")
+            .collect()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let escaped = Table {
+            rows: self
+                .rows
+                .iter()
+                .map(|r| r.iter().map(|f| f.replace('|', "\\|")).collect())
+                .collect(),
+        };
+        let widths: Vec<usize> = escaped.widths().into_iter().map(|w| w.max(3)).collect();
+        let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+        let pad = |r: &Vec<String>| {
+            r.iter()
+                .zip(&widths)
+                .map(|(f, w)| width::pad_right(f, *w))
+                .collect()
+        };
+        let mut out = line(pad(&escaped.rows[0]));
+        out += &line(widths.iter().map(|w| "-".repeat(*w)).collect());
+        for row in escaped.body() {
+            out += &line(pad(row));
+        }
+        out
+    }
+
+    /// A JSON array with one object per body row, keyed by the header in
+    /// column order. Fields that look like numbers become numbers.
+    pub fn to_json(&self) -> String {
+        let rows: Vec<JsonRow> = self
+            .body()
+            .iter()
+            .map(|r| JsonRow {
+                header: self.header(),
+                row: r,
+            })
+            .collect();
+        serde_json::to_string_pretty(&rows).unwrap()
+    }
+
+    /// Rows with each column padded to its widest cell, for display.
+    pub fn aligned(&self) -> Vec<String> {
+        let widths = self.widths();
+        self.rows
+            .iter()
+            .map(|r| {
+                r.iter()
+                    .zip(&widths)
+                    .map(|(f, w)| width::pad_right(f, *w))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        (0..self.rows[0].len())
+            .map(|c| {
+                self.rows
+                    .iter()
+                    .map(|r| width::of(&r[c]))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+}
+
+struct JsonRow<'a> {
+    header: &'a [String],
+    row: &'a [String],
+}
+
+impl Serialize for JsonRow<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.header.len()))?;
+        for (key, value) in self.header.iter().zip(self.row) {
+            match value.parse::<f64>() {
+                Ok(n) if n.is_finite() && !value.contains(char::is_alphabetic) => {
+                    match value.parse::<i64>() {
+                        Ok(i) => map.serialize_entry(key, &i)?,
+                        Err(_) => map.serialize_entry(key, &n)?,
+                    }
+                }
+                _ => map.serialize_entry(key, value)?,
+            }
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tsv_and_csv() {
+        let tsv = parse("Name\tQty\tPrice\nApples\t3\t1.50\nPears\t10\t0.75\n").unwrap();
+        assert_eq!(tsv.rows.len(), 3);
+        assert_eq!(tsv.rows[2], vec!["Pears", "10", "0.75"]);
+
+        let csv = parse("name,note\n\"Smith, J\",\"said \"\"hi\"\"\"\nLee,\n").unwrap();
+        assert_eq!(csv.rows[1], vec!["Smith, J", "said \"hi\""]);
+        assert_eq!(csv.rows[2], vec!["Lee", ""]);
+
+        assert!(parse("Hello, world\nSee you, later").is_none());
+        assert!(parse("a,b\nc,d,e").is_none());
+        assert!(parse("just one line,here").is_none());
+        assert!(parse("\"open,quote\nx,y").is_none());
+        assert!(parse("x := 1\tfoo\n\tbar\tbaz").is_none());
+    }
+
+    #[test]
+    fn test_conversions() {
+        let t = parse("Name\tQty\tPrice\nApples\t3\t1.50\nPears | Co\t10\t0.75").unwrap();
+        assert_eq!(
+            t.aligned(),
+            vec![
+                "Name        Qty  Price",
+                "Apples      3    1.50",
+                "Pears | Co  10   0.75"
+            ]
+        );
+        assert_eq!(
+            t.to_markdown(),
+            "| Name        | Qty | Price |\n\
+             | ----------- | --- | ----- |\n\
+             | Apples      | 3   | 1.50  |\n\
+             | Pears \\| Co | 10  | 0.75  |\n"
+        );
+        assert_eq!(
+            t.to_csv(),
+            "Name,Qty,Price\nApples,3,1.50\nPears | Co,10,0.75\n"
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&t.to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"Name": "Apples", "Qty": 3, "Price": 1.5},
+                {"Name": "Pears | Co", "Qty": 10, "Price": 0.75}
+            ])
+        );
+        assert!(t.to_json().find("Name").unwrap() < t.to_json().find("Qty").unwrap());
+    }
+}
@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::{ImageEncoder, RgbaImage};
+use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+use serde_json::Value;
+
+use crate::errors::{CbError, Result};
+
+/// Longest edge of a generated thumbnail, in pixels.
+pub const THUMB_SIZE: u32 = 256;
+
+/// Where the thumbnail for `image_path` lives: a `thumbs/` directory next to
+/// the original, under the same file name.
+pub fn path_for(image_path: &Path) -> PathBuf {
+    let dir = image_path.parent().unwrap_or(Path::new("."));
+    match image_path.file_name() {
+        Some(name) => dir.join("thumbs").join(name),
+        None => dir.join("thumbs"),
+    }
+}
+
+/// Scale raw RGBA pixels down to fit `THUMB_SIZE` and write them as PNG.
+/// Images already that small are written at their original size.
+pub fn write(data: &[u8], width: u32, height: u32, path: &Path) -> Result<()> {
+    let img = RgbaImage::from_raw(width, height, data.to_vec())
+        .ok_or_else(|| CbError::Image("pixel data does not match dimensions".into()))?;
+    save(&shrink(img), path)
+}
+
+/// The thumbnail for the image at `image_path`, generating it first if it
+/// doesn't exist yet (e.g. for images captured before thumbnails).
+pub fn ensure(image_path: &Path) -> Result<PathBuf> {
+    let thumb = path_for(image_path);
+    if !thumb.exists() {
+        let img = image::open(image_path).map_err(|e| CbError::Image(e.to_string()))?;
+        save(&shrink(img.to_rgba8()), &thumb)?;
+    }
+    Ok(thumb)
+}
+
+/// Add a preview to `clip`, a clip serialized as JSON, for clients such as
+/// Alfred or Raycast: `thumb_url`, a `file://` URL of the thumbnail, or with
+/// `embed` `thumb_data`, the thumbnail inlined as a base64 `data:` URL. Clips
+/// without an image, or whose image file is gone, are left as they are.
+pub fn add_preview(clip: &mut Value, embed: bool) {
+    let Some(path) = clip.get("image_path").and_then(Value::as_str) else {
+        return;
+    };
+    let Ok(thumb) = ensure(Path::new(path)) else {
+        return;
+    };
+    if !embed {
+        clip["thumb_url"] = file_url(&thumb).into();
+    } else if let Ok(png) = fs::read(&thumb) {
+        clip["thumb_data"] = format!("data:image/png;base64,{}", STANDARD.encode(png)).into();
+    }
+}
+
+/// Characters escaped in `file://` URLs, beyond control characters.
+const URL_PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+pub fn file_url(path: &Path) -> String {
+    format!("file://{}", utf8_percent_encode(&path.to_string_lossy(), URL_PATH))
+}
+
+fn shrink(img: RgbaImage) -> RgbaImage {
+    let (w, h) = img.dimensions();
+    if w <= THUMB_SIZE && h <= THUMB_SIZE {
+        return img;
+    }
+    let scale = THUMB_SIZE as f64 / w.max(h) as f64;
+    let tw = ((w as f64 * scale).round() as u32).max(1);
+    let th = ((h as f64 * scale).round() as u32).max(1);
+    image::imageops::thumbnail(&img, tw, th)
+}
+
+fn save(img: &RgbaImage, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| CbError::Image(e.to_string()))?;
+    }
+    let file = fs::File::create(path).map_err(|e| CbError::Image(e.to_string()))?;
+    image::codecs::png::PngEncoder::new(file)
+        .write_image(img.as_raw(), img.width(), img.height(), image::ColorType::Rgba8.into())
+        .map_err(|e| CbError::Image(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_path_for() {
+        assert_eq!(
+            path_for(Path::new("/home/u/.cb/images/abc.png")),
+            PathBuf::from("/home/u/.cb/images/thumbs/abc.png")
+        );
+    }
+
+    #[test]
+    fn test_write_scales_longest_edge() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("thumbs/wide.png");
+        write(&vec![255; 1024 * 512 * 4], 1024, 512, &path).unwrap();
+        let img = image::open(&path).unwrap();
+        assert_eq!((img.width(), img.height()), (256, 128));
+    }
+
+    #[test]
+    fn test_ensure_generates_once_and_keeps_small_images() {
+        let dir = TempDir::new().unwrap();
+        let original = dir.path().join("small.png");
+        save(&RgbaImage::new(40, 30), &original).unwrap();
+
+        let thumb = ensure(&original).unwrap();
+        assert_eq!(thumb, dir.path().join("thumbs/small.png"));
+        let img = image::open(&thumb).unwrap();
+        assert_eq!((img.width(), img.height()), (40, 30));
+        assert_eq!(ensure(&original).unwrap(), thumb);
+    }
+
+    #[test]
+    fn test_add_preview() {
+        let dir = TempDir::new().unwrap();
+        let original = dir.path().join("my shot.png");
+        save(&RgbaImage::new(4, 4), &original).unwrap();
+        let clip = serde_json::json!({ "image_path": original.to_string_lossy() });
+
+        let mut linked = clip.clone();
+        add_preview(&mut linked, false);
+        let url = linked["thumb_url"].as_str().unwrap();
+        assert!(url.starts_with("file:///") && url.ends_with("/thumbs/my%20shot.png"));
+        assert!(linked.get("thumb_data").is_none());
+
+        let mut embedded = clip.clone();
+        add_preview(&mut embedded, true);
+        let data = embedded["thumb_data"].as_str().unwrap();
+        let png = STANDARD.decode(data.strip_prefix("data:image/png;base64,").unwrap()).unwrap();
+        assert_eq!(image::load_from_memory(&png).unwrap().width(), 4);
+
+        let mut text = serde_json::json!({ "image_path": null });
+        add_preview(&mut text, true);
+        assert_eq!(text, serde_json::json!({ "image_path": null }));
+    }
+
+    #[test]
+    fn test_write_rejects_short_data() {
+        let dir = TempDir::new().unwrap();
+        assert!(write(&[0; 8], 10, 10, &dir.path().join("bad.png")).is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use crate::width;
+
+/// Longest title stored for a clip, in columns. Narrower displays
+/// shorten it further with `shorten`.
+pub const MAX_TITLE_WIDTH: usize = 80;
+
+/// Characters that open a comment, heading, quote, or list item and say
+/// nothing about the content, e.g. `// `, `# `, `-- `, `* `, `> `.
+const LEAD_MARKERS: &[char] = &['/', '#', '*', '-', ';', '>'];
+
+/// Display title for a text clip: its first line with letters or digits in
+/// it, without comment or list markers and with whitespace collapsed,
+/// shortened at a word boundary. Lines like `{`, `"""`, or a shebang are
+/// skipped, so code gets its first meaningful line.
+pub fn derive(text: &str) -> Option<String> {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("#!"))
+        .map(strip_markers)
+        .find(|line| line.chars().any(char::is_alphanumeric))?;
+    let collapsed = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(shorten(&collapsed, MAX_TITLE_WIDTH))
+}
+
+/// `line` without a leading run of marker characters followed by a space.
+fn strip_markers(line: &str) -> &str {
+    let rest = line.trim_start_matches(LEAD_MARKERS);
+    if rest.len() < line.len() && rest.starts_with(char::is_whitespace) {
+        rest.trim_start()
+    } else {
+        line
+    }
+}
+
+/// `s` cut to at most `max` columns, ending in `…` when shortened. Cuts at
+/// the last space that keeps at least half the width, so words stay whole;
+/// a single long word is cut mid-word (between graphemes, see `width`).
+pub fn shorten(s: &str, max: usize) -> String {
+    if width::of(s) <= max {
+        return s.to_string();
+    }
+    let keep = max.saturating_sub(1);
+    let head = width::take(s, keep);
+    let next_is_space = s[head.len()..].starts_with(char::is_whitespace);
+    let cut = if next_is_space {
+        head.trim_end()
+    } else {
+        match head.rfind(char::is_whitespace) {
+            Some(idx) if width::of(&head[..idx]) >= keep / 2 => head[..idx].trim_end(),
+            _ => head,
+        }
+    };
+    let cut = cut.trim_end_matches([',', ';', ':', '-']);
+    format!("{}…", cut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_uses_first_meaningful_line() {
+        assert_eq!(derive("\n\n  Meeting notes  \nsecond line").as_deref(), Some("Meeting notes"));
+        assert_eq!(derive("{\n  \"name\": \"cb\"\n}").as_deref(), Some("\"name\": \"cb\""));
+        assert_eq!(derive("#!/bin/sh\n# Deploy the site\nrsync -a").as_deref(), Some("Deploy the site"));
+        assert_eq!(derive("/// Parses a config file.\nfn parse()").as_deref(), Some("Parses a config file."));
+        assert_eq!(derive("- [ ] buy milk").as_deref(), Some("[ ] buy milk"));
+        assert_eq!(derive("-5 degrees").as_deref(), Some("-5 degrees"));
+        assert_eq!(derive("a\tb    c").as_deref(), Some("a b c"));
+        assert_eq!(derive("  \n}\n"), None);
+    }
+
+    #[test]
+    fn test_shorten_keeps_words_whole() {
+        assert_eq!(shorten("short", 10), "short");
+        assert_eq!(shorten("the quick brown fox jumps", 12), "the quick…");
+        assert_eq!(shorten("the quick brown", 10), "the quick…");
+        assert_eq!(shorten("one, two, three", 10), "one, two…");
+        assert_eq!(shorten("supercalifragilistic", 8), "superca…");
+    }
+
+    #[test]
+    fn test_shorten_is_utf8_safe() {
+        assert_eq!(shorten("héllo wörld ünïcode", 13), "héllo wörld…");
+        // Wide characters take two columns each.
+        assert_eq!(shorten("日本語のテキストです", 9), "日本語の…");
+    }
+}
@@ -0,0 +1,280 @@
+use crate::errors::{CbError, Result};
+use crate::lang;
+use crate::links;
+use crate::storage::models::{Clip, ContentType};
+use crate::table;
+
+/// Ways `cb copy --as` can reshape a clip's text before copying it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    /// Tabular data as comma-separated values.
+    Csv,
+    /// Tabular data as a Markdown table.
+    Markdown,
+    /// Tabular data as a JSON array of row objects.
+    Json,
+    /// A URL as `[Page title](url)`.
+    MdLink,
+    /// A URL as `<a href="url">Page title</a>`.
+    HtmlLink,
+    /// Text in a Markdown code fence, tagged with its language when it was
+    /// detected as code.
+    Fence,
+    /// Lines trimmed and joined with spaces; trailing `\` continuations are
+    /// dropped.
+    OneLine,
+    /// The whole text as one shell-quoted word on a single line.
+    ShellEscape,
+}
+
+impl Transform {
+    /// Names accepted by `--as`.
+    pub const NAMES: &[&str] = &[
+        "csv",
+        "markdown",
+        "json",
+        "md-link",
+        "html-link",
+        "fence",
+        "oneline",
+        "shell-escape",
+    ];
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(Transform::Csv),
+            "markdown" | "md" => Ok(Transform::Markdown),
+            "json" => Ok(Transform::Json),
+            "md-link" => Ok(Transform::MdLink),
+            "html-link" => Ok(Transform::HtmlLink),
+            "fence" => Ok(Transform::Fence),
+            "oneline" => Ok(Transform::OneLine),
+            "shell-escape" => Ok(Transform::ShellEscape),
+            _ => Err(CbError::InvalidInput(format!(
+                "unknown transform \"{}\" (expected one of: {})",
+                s,
+                Self::NAMES.join(", ")
+            ))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transform::Csv => "csv",
+            Transform::Markdown => "markdown",
+            Transform::Json => "json",
+            Transform::MdLink => "md-link",
+            Transform::HtmlLink => "html-link",
+            Transform::Fence => "fence",
+            Transform::OneLine => "oneline",
+            Transform::ShellEscape => "shell-escape",
+        }
+    }
+
+    /// The text to copy for `clip`, or an error if the transform doesn't
+    /// apply to it. Link transforms fetch the page for its title, falling
+    /// back to the URL itself when that fails.
+    pub fn apply(&self, clip: &Clip) -> Result<String> {
+        let text = match (&clip.content_type, clip.text_content.as_deref()) {
+            (ContentType::Text, Some(text)) => text,
+            _ => {
+                return Err(CbError::InvalidInput(format!(
+                    "clip #{} is not text, so it can't be copied as {}",
+                    clip.id,
+                    self.name()
+                )));
+            }
+        };
+        match self {
+            Transform::OneLine => return Ok(one_line(text)),
+            Transform::ShellEscape => return Ok(shell_escape(text)),
+            _ => {}
+        }
+        if *self == Transform::Fence {
+            let lang = clip.lang.as_deref().filter(|l| lang::is_code(l));
+            return Ok(fence(text, lang));
+        }
+        if let Transform::MdLink | Transform::HtmlLink = self {
+            let url = links::extract_url(text)
+                .ok_or_else(|| CbError::InvalidInput(format!("clip #{} is not a URL", clip.id)))?;
+            let title = links::fetch_title(url).unwrap_or_else(|| url.to_string());
+            return Ok(match self {
+                Transform::MdLink => markdown_link(&title, url),
+                _ => html_link(&title, url),
+            });
+        }
+
+        let table = table::parse(text).ok_or_else(|| {
+            CbError::InvalidInput(format!(
+                "clip #{} doesn't look like a table (tab- or comma-separated rows)",
+                clip.id
+            ))
+        })?;
+        Ok(match self {
+            Transform::Csv => table.to_csv(),
+            Transform::Markdown => table.to_markdown(),
+            _ => table.to_json(),
+        })
+    }
+}
+
+pub fn one_line(text: &str) -> String {
+    text.lines()
+        .map(|l| l.trim().trim_end_matches('\\').trim_end())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quote `text` as a single shell word: plain `'...'` when possible, or
+/// bash/zsh `$'...'` when it has newlines or other control characters, so
+/// the result always stays on one line.
+pub fn shell_escape(text: &str) -> String {
+    if !text.contains(char::is_control) {
+        return format!("'{}'", text.replace('\'', "'\\''"));
+    }
+    let mut out = String::from("$'");
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\x{:02x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// Wrap `text` in a code fence longer than any backtick run inside it.
+pub fn fence(text: &str, lang: Option<&str>) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let ticks = "`".repeat(longest.max(2) + 1);
+    format!(
+        "{}{}\n{}\n{}\n",
+        ticks,
+        lang.unwrap_or(""),
+        text.trim_end_matches('\n'),
+        ticks
+    )
+}
+
+pub fn markdown_link(title: &str, url: &str) -> String {
+    let title = title.replace('[', "\\[").replace(']', "\\]");
+    let url = url.replace('(', "%28").replace(')', "%29");
+    format!("[{}]({})", title, url)
+}
+
+pub fn html_link(title: &str, url: &str) -> String {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    };
+    format!("<a href=\"{}\">{}</a>", escape(url), escape(title))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(id: i64, text: &str) -> Clip {
+        Clip {
+            id,
+            text_content: Some(text.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_table_transforms() {
+        let sheet = text(1, "City\tPop\nOslo\t709000\n");
+        assert_eq!(
+            Transform::parse("csv").unwrap().apply(&sheet).unwrap(),
+            "City,Pop\nOslo,709000\n"
+        );
+        assert!(
+            Transform::parse("md")
+                .unwrap()
+                .apply(&sheet)
+                .unwrap()
+                .starts_with("| City |")
+        );
+        assert!(
+            Transform::parse("json")
+                .unwrap()
+                .apply(&sheet)
+                .unwrap()
+                .contains("\"Pop\": 709000")
+        );
+
+        let prose = text(2, "not a table");
+        assert!(matches!(
+            Transform::Csv.apply(&prose),
+            Err(CbError::InvalidInput(_))
+        ));
+        assert!(Transform::parse("xml").is_err());
+    }
+
+    #[test]
+    fn test_link_transforms() {
+        assert_eq!(
+            markdown_link("Docs [beta]", "https://x.dev/a_(b)"),
+            "[Docs \\[beta\\]](https://x.dev/a_%28b%29)"
+        );
+        assert_eq!(
+            html_link("Q&A <new>", "https://x.dev/?a=1&b=2"),
+            "<a href=\"https://x.dev/?a=1&amp;b=2\">Q&amp;A &lt;new&gt;</a>"
+        );
+
+        // Nothing listens on port 9, so the title falls back to the URL.
+        let url = text(3, "http://127.0.0.1:9/page");
+        assert_eq!(
+            Transform::MdLink.apply(&url).unwrap(),
+            "[http://127.0.0.1:9/page](http://127.0.0.1:9/page)"
+        );
+        assert!(Transform::HtmlLink.apply(&text(4, "not a url")).is_err());
+    }
+
+    #[test]
+    fn test_oneline_and_shell_escape() {
+        let cmd = "docker run \\\n  -p 80:80 \\\n\n  nginx\n";
+        assert_eq!(one_line(cmd), "docker run -p 80:80 nginx");
+
+        assert_eq!(shell_escape("it's here"), "'it'\\''s here'");
+        assert_eq!(shell_escape("a\\b\n'c'\t"), "$'a\\\\b\\n\\'c\\'\\t'");
+        assert!(!shell_escape("x\ny\u{7}").contains('\n'));
+        assert_eq!(
+            Transform::parse("oneline")
+                .unwrap()
+                .apply(&text(7, "a\nb"))
+                .unwrap(),
+            "a b"
+        );
+    }
+
+    #[test]
+    fn test_fence() {
+        let code = Clip {
+            lang: Some("rust".into()),
+            ..text(5, "fn main() {}\n")
+        };
+        assert_eq!(
+            Transform::Fence.apply(&code).unwrap(),
+            "```rust\nfn main() {}\n```\n"
+        );
+
+        let prose = Clip {
+            lang: Some("en".into()),
+            ..text(6, "see `x` and ```y```")
+        };
+        assert_eq!(
+            Transform::Fence.apply(&prose).unwrap(),
+            "````\nsee `x` and ```y```\n````\n"
+        );
+    }
+}
@@ -1,20 +1,76 @@
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 
-use chrono::{Duration as ChronoDuration, Utc};
 use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 use ratatui::layout::{Constraint, Layout};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
 use ratatui::Frame;
-use rusqlite::Connection;
+use rusqlite::OpenFlags;
+use serde::{Deserialize, Serialize};
 
-use crate::clipboard::{write_image_to_clipboard, write_text_to_clipboard};
-use crate::config::AppPaths;
+use crate::clipboard::{self, ClipboardProvider, SystemClipboard, copy_clip};
+use crate::config::{AppPaths, Config};
 use crate::daemon;
-use crate::storage::models::{ClipFilter, ContentType};
+use crate::i18n::{t, tf};
+use crate::metrics::TextMetrics;
+use crate::render::{format_age, format_bytes};
+use crate::storage::models::{Capture, Clip, ClipFilter, ContentType, StorageStats, UsageStats};
 use crate::storage::sqlite::SqliteStorage;
 use crate::storage::ClipStorage;
+use crate::table;
+use crate::thumbs;
+use crate::title;
+use crate::transform::Transform;
+use crate::verify;
+use crate::width;
+
+/// The list pane's share of the width, in percent, and how far `<` and `>`
+/// move it.
+const DEFAULT_SPLIT: u16 = 40;
+const MIN_SPLIT: u16 = 20;
+const MAX_SPLIT: u16 = 80;
+const SPLIT_STEP: u16 = 5;
+
+/// Where the TUI left off, kept in the profile's `tui.json` so the next
+/// launch resumes there.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+struct SavedState {
+    search_query: String,
+    fuzzy: bool,
+    oldest_first: bool,
+    /// ID of the selected clip.
+    selected: Option<i64>,
+    split: u16,
+}
+
+impl Default for SavedState {
+    fn default() -> Self {
+        Self {
+            search_query: String::new(),
+            fuzzy: false,
+            oldest_first: false,
+            selected: None,
+            split: DEFAULT_SPLIT,
+        }
+    }
+}
+
+impl SavedState {
+    fn load(path: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    fn save(&self, path: &Path) -> crate::errors::Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self).unwrap())
+            .map_err(|e| crate::errors::CbError::Daemon(format!("{}: {}", path.display(), e)))
+    }
+}
 
 #[derive(PartialEq)]
 enum Mode {
@@ -23,6 +79,7 @@ enum Mode {
     Tag,
     RemoveTag,
     ConfirmDelete(i64),
+    Stats,
 }
 
 struct App {
@@ -30,27 +87,51 @@ struct App {
     list_state: ListState,
     mode: Mode,
     search_query: String,
+    /// Search matches characters in order (Tab toggles) instead of substrings.
+    fuzzy: bool,
+    /// List oldest clips first (`o` toggles).
+    oldest_first: bool,
+    /// The list pane's width, in percent (`<` and `>` change it).
+    split: u16,
     tag_input: String,
     status: String,
     status_time: Option<Instant>,
     preview_scroll: u16,
+    dashboard: Option<(StorageStats, UsageStats)>,
+    /// Rendered thumbnail for the selected image clip, keyed by clip ID and width.
+    thumb: Option<(i64, u16, Vec<Line<'static>>)>,
+    /// Launcher mode (`cb tui --quick`): search is focused, Enter copies,
+    /// pastes, and quits, and Esc quits.
+    quick: bool,
+    /// Paste into the frontmost app once the TUI has exited.
+    paste_on_exit: bool,
     should_quit: bool,
+    /// Where copies go: the system clipboard, or a fake one in tests.
+    clipboard: Rc<dyn ClipboardProvider>,
 }
 
 impl App {
-    fn new() -> Self {
+    fn new(quick: bool) -> Self {
         let mut list_state = ListState::default();
         list_state.select(Some(0));
         Self {
             clips: Vec::new(),
             list_state,
-            mode: Mode::Normal,
+            mode: if quick { Mode::Search } else { Mode::Normal },
             search_query: String::new(),
+            fuzzy: false,
+            oldest_first: false,
+            split: DEFAULT_SPLIT,
             tag_input: String::new(),
             status: String::new(),
             status_time: None,
             preview_scroll: 0,
+            dashboard: None,
+            thumb: None,
+            quick,
+            paste_on_exit: false,
             should_quit: false,
+            clipboard: Rc::new(SystemClipboard),
         }
     }
 
@@ -114,19 +195,48 @@ impl App {
         }
     }
 
+    /// Resume where a previous session left off: its search, order, pane
+    /// split, and selected clip, if that is still listed.
+    fn restore(&mut self, state: SavedState, storage: &SqliteStorage) {
+        self.search_query = state.search_query;
+        self.fuzzy = state.fuzzy;
+        self.oldest_first = state.oldest_first;
+        self.split = state.split.clamp(MIN_SPLIT, MAX_SPLIT);
+        self.refresh(storage);
+        if let Some(idx) = state
+            .selected
+            .and_then(|id| self.clips.iter().position(|c| c.id == id))
+        {
+            self.list_state.select(Some(idx));
+        }
+    }
+
+    fn saved_state(&self) -> SavedState {
+        SavedState {
+            search_query: self.search_query.clone(),
+            fuzzy: self.fuzzy,
+            oldest_first: self.oldest_first,
+            selected: self.selected_clip_id(),
+            split: self.split,
+        }
+    }
+
     fn refresh(&mut self, storage: &SqliteStorage) {
-        let result = if self.search_query.is_empty() {
-            storage.list(ClipFilter {
-                limit: 100,
-                ..Default::default()
-            })
+        let filter = ClipFilter {
+            query: (!self.search_query.is_empty()).then(|| self.search_query.clone()),
+            oldest_first: self.oldest_first,
+            limit: 100,
+            ..Default::default()
+        };
+        let result = if self.fuzzy && filter.query.is_some() {
+            storage.search_fuzzy(filter)
         } else {
-            storage.search(&self.search_query, 100)
+            storage.list(filter)
         };
 
         match result {
             Ok(clips) => self.clips = clips,
-            Err(e) => self.set_status(format!("Error: {e}")),
+            Err(e) => self.set_status(tf("Error: {0}", &[&e])),
         }
 
         // Clamp selection
@@ -141,44 +251,76 @@ impl App {
         }
     }
 
-    fn copy_selected(&mut self, storage: &SqliteStorage) {
+    /// Copy the selected clip, reshaped by `transform` if given. Returns
+    /// whether anything was copied.
+    fn copy_selected(
+        &mut self,
+        storage: &SqliteStorage,
+        paths: &AppPaths,
+        transform: Option<Transform>,
+    ) -> bool {
         let Some(idx) = self.list_state.selected() else {
-            return;
+            return false;
         };
         let Some(clip) = self.clips.get(idx) else {
-            return;
+            return false;
+        };
+        let transformed;
+        let clip = match transform {
+            Some(t) => match t.apply(clip) {
+                Ok(text) => {
+                    transformed = Clip {
+                        text_content: Some(text),
+                        ..clip.clone()
+                    };
+                    &transformed
+                }
+                Err(e) => {
+                    self.set_status(tf("Copy failed: {0}", &[&e]));
+                    return false;
+                }
+            },
+            None => clip,
+        };
+        let on_copy = match Config::load(&paths.config_file) {
+            Ok(config) => config.on_copy,
+            Err(e) => {
+                self.set_status(tf("Config error: {0}", &[&e]));
+                return false;
+            }
         };
 
-        match clip.content_type {
-            ContentType::Text => {
-                if let Some(ref text) = clip.text_content {
-                    match write_text_to_clipboard(text) {
-                        Ok(()) => {
-                            let _ = storage.touch(clip.id);
-                            self.set_status(format!("Copied #{}", clip.id));
-                        }
-                        Err(e) => self.set_status(format!("Copy failed: {e}")),
-                    }
+        if let Err(e) = verify::check_file(storage, clip) {
+            self.set_status(tf("Copy failed: {0}", &[&e]));
+            return false;
+        }
+        let (id, content_type) = (clip.id, clip.content_type.clone());
+        match copy_clip(self.clipboard.as_ref(), clip, on_copy.as_ref()) {
+            Ok(true) => {
+                let _ = storage.record_copy(id);
+                match content_type {
+                    ContentType::Image => self.set_status(tf("Copied image #{0}", &[&id])),
+                    _ => match transform {
+                        Some(tr) => self.set_status(tf("Copied #{0} as {1}", &[&id, &tr.name()])),
+                        None => self.set_status(tf("Copied #{0}", &[&id])),
+                    },
                 }
+                true
             }
-            ContentType::Image => {
-                if let Some(ref path) = clip.image_path {
-                    match write_image_to_clipboard(std::path::Path::new(path)) {
-                        Ok(()) => {
-                            let _ = storage.touch(clip.id);
-                            self.set_status(format!("Copied image #{}", clip.id));
-                        }
-                        Err(e) => self.set_status(format!("Copy image failed: {e}")),
+            Ok(false) => {
+                match content_type {
+                    ContentType::FileRef => {
+                        let target = clip.text_content.as_deref().unwrap_or("?");
+                        self.set_status(tf("File ref: {0}", &[&target]))
                     }
-                } else {
-                    self.set_status("Image clip has no path".to_string());
+                    ContentType::Image => self.set_status(t("Image clip has no path").to_string()),
+                    ContentType::Text => {}
                 }
+                false
             }
-            ContentType::FileRef => {
-                self.set_status(format!(
-                    "File ref: {}",
-                    clip.text_content.as_deref().unwrap_or("?")
-                ));
+            Err(e) => {
+                self.set_status(tf("Copy failed: {0}", &[&e]));
+                false
             }
         }
     }
@@ -188,17 +330,17 @@ impl App {
             return;
         };
         self.mode = Mode::ConfirmDelete(id);
-        self.set_status(format!("Delete #{id}? [y/n]"));
+        self.set_status(tf("Delete #{0}? [y/n]", &[&id]));
     }
 
     fn confirm_delete(&mut self, storage: &SqliteStorage, id: i64) {
         match storage.delete(id) {
             Ok(true) => {
-                self.set_status(format!("Deleted #{id}"));
+                self.set_status(tf("Moved #{0} to the trash", &[&id]));
                 self.refresh(storage);
             }
-            Ok(false) => self.set_status(format!("#{id} not found")),
-            Err(e) => self.set_status(format!("Delete error: {e}")),
+            Ok(false) => self.set_status(tf("#{0} not found", &[&id])),
+            Err(e) => self.set_status(tf("Delete error: {0}", &[&e])),
         }
     }
 
@@ -212,11 +354,11 @@ impl App {
         let new_pinned = !clip.pinned;
         match storage.set_pinned(clip.id, new_pinned) {
             Ok(()) => {
-                let verb = if new_pinned { "Pinned" } else { "Unpinned" };
-                self.set_status(format!("{verb} #{}", clip.id));
+                let msg = if new_pinned { "Pinned #{0}" } else { "Unpinned #{0}" };
+                self.set_status(tf(msg, &[&clip.id]));
                 self.refresh(storage);
             }
-            Err(e) => self.set_status(format!("Pin error: {e}")),
+            Err(e) => self.set_status(tf("Pin error: {0}", &[&e])),
         }
     }
 
@@ -231,10 +373,10 @@ impl App {
         };
         match storage.add_tag(id, &tag) {
             Ok(()) => {
-                self.set_status(format!("Tagged #{id} \"{tag}\""));
+                self.set_status(tf("Tagged #{0} \"{1}\"", &[&id, &tag]));
                 self.refresh(storage);
             }
-            Err(e) => self.set_status(format!("Tag error: {e}")),
+            Err(e) => self.set_status(tf("Tag error: {0}", &[&e])),
         }
         self.tag_input.clear();
     }
@@ -250,10 +392,10 @@ impl App {
         };
         match storage.remove_tag(id, &tag) {
             Ok(()) => {
-                self.set_status(format!("Removed tag \"{tag}\" from #{id}"));
+                self.set_status(tf("Removed tag \"{0}\" from #{1}", &[&tag, &id]));
                 self.refresh(storage);
             }
-            Err(e) => self.set_status(format!("Remove tag error: {e}")),
+            Err(e) => self.set_status(tf("Remove tag error: {0}", &[&e])),
         }
         self.tag_input.clear();
     }
@@ -262,29 +404,48 @@ impl App {
         match daemon::daemon_status(paths) {
             Ok(Some(pid)) => {
                 match daemon::stop_daemon(paths) {
-                    Ok(true) => self.set_status(format!("Stopped daemon (was pid {pid})")),
-                    Ok(false) => self.set_status("Daemon already stopped".to_string()),
-                    Err(e) => self.set_status(format!("Stop error: {e}")),
+                    Ok(true) => self.set_status(tf("Stopped daemon (was pid {0})", &[&pid])),
+                    Ok(false) => self.set_status(t("Daemon already stopped").to_string()),
+                    Err(e) => self.set_status(tf("Stop error: {0}", &[&e])),
                 }
             }
             Ok(None) => {
                 match start_daemon(paths) {
-                    Ok(pid) => self.set_status(format!("Started daemon (pid {pid})")),
-                    Err(e) => self.set_status(format!("Start error: {e}")),
+                    Ok(pid) => self.set_status(tf("Started daemon (pid {0})", &[&pid])),
+                    Err(e) => self.set_status(tf("Start error: {0}", &[&e])),
                 }
             }
-            Err(e) => self.set_status(format!("Status error: {e}")),
+            Err(e) => self.set_status(tf("Status error: {0}", &[&e])),
+        }
+    }
+
+    fn open_dashboard(&mut self, storage: &SqliteStorage) {
+        match storage.stats().and_then(|s| Ok((s, storage.usage_stats(5)?))) {
+            Ok(data) => {
+                self.dashboard = Some(data);
+                self.mode = Mode::Stats;
+            }
+            Err(e) => self.set_status(tf("Stats error: {0}", &[&e])),
         }
     }
 
-    fn clear_old(&mut self, storage: &SqliteStorage) {
-        let cutoff = Utc::now() - ChronoDuration::days(30);
-        match storage.clear_older_than(cutoff) {
+    fn clear_old(&mut self, storage: &SqliteStorage, paths: &AppPaths) {
+        let policy = match Config::load(&paths.config_file) {
+            Ok(config) => config.retention_policy(),
+            Err(e) => {
+                self.set_status(tf("Config error: {0}", &[&e]));
+                return;
+            }
+        };
+        match storage
+            .apply_retention(&policy)
+            .and_then(|n| storage.release_unused_images(&paths.images_dir).map(|_| n))
+        {
             Ok(n) => {
-                self.set_status(format!("Cleared {n} clip(s) older than 30 days"));
+                self.set_status(tf("Cleared {0} clip(s) ({1})", &[&n, &policy.describe()]));
                 self.refresh(storage);
             }
-            Err(e) => self.set_status(format!("Clear error: {e}")),
+            Err(e) => self.set_status(tf("Clear error: {0}", &[&e])),
         }
     }
 }
@@ -298,7 +459,7 @@ fn start_daemon(paths: &AppPaths) -> crate::errors::Result<u32> {
         .map_err(|e| crate::errors::CbError::Daemon(e.to_string()))?;
 
     let child = std::process::Command::new(exe)
-        .args(["daemon", "run"])
+        .args(["--profile", &paths.profile, "daemon", "run"])
         .stdin(std::process::Stdio::null())
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::from(log_file))
@@ -308,42 +469,162 @@ fn start_daemon(paths: &AppPaths) -> crate::errors::Result<u32> {
     Ok(child.id())
 }
 
-fn format_age(dt: chrono::DateTime<Utc>) -> String {
-    let dur = Utc::now() - dt;
-    if dur.num_seconds() < 60 {
-        "now".to_string()
-    } else if dur.num_minutes() < 60 {
-        format!("{}m", dur.num_minutes())
-    } else if dur.num_hours() < 24 {
-        format!("{}h", dur.num_hours())
-    } else {
-        format!("{}d", dur.num_days())
+/// A text clip's title, or for clips stored without one, its text on one line.
+fn text_title(clip: &Clip) -> String {
+    match clip.title {
+        Some(ref title) => title.clone(),
+        None => clip.text_content.as_deref().unwrap_or("").replace('\n', "↵"),
     }
 }
 
-fn format_bytes(bytes: i64) -> String {
-    if bytes < 1024 {
-        format!("{bytes} B")
-    } else if bytes < 1024 * 1024 {
-        format!("{:.1} KB", bytes as f64 / 1024.0)
+// ── UI rendering ───────────────────────────────────────────────────
+
+/// A dimmed field label in the current locale, padded to `width` columns
+/// with at least one space after it, or unpadded when `width` is 0.
+fn label(name: &str, width: usize) -> Span<'static> {
+    let name = t(name);
+    let text = if width == 0 {
+        name.to_string()
     } else {
-        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+        width::pad_right(&format!("{name} "), width)
+    };
+    Span::styled(text, Style::new().fg(Color::DarkGray))
+}
+
+/// Columns taken by `{id:>4} {type}{pin} {age:>4}  ` before a list row's preview.
+const LIST_PREFIX_WIDTH: usize = 14;
+
+fn dashboard_lines(stats: &StorageStats, usage: &UsageStats) -> Vec<Line<'static>> {
+    let mut lines = vec![
+        Line::from(vec![label("Total clips:", 13), Span::raw(stats.total_clips.to_string())]),
+        Line::from(vec![
+            Span::raw("  "),
+            label("Text / Image / File:", 0),
+            Span::raw(" "),
+            Span::raw(format!(
+                "{} / {} / {}",
+                stats.text_clips, stats.image_clips, stats.fileref_clips
+            )),
+        ]),
+        Line::from(vec![label("Total size:", 13), Span::raw(format_bytes(stats.total_size))]),
+        Line::raw(""),
+        Line::styled(t("Most copied").to_string(), Style::new().add_modifier(Modifier::BOLD)),
+    ];
+    if usage.most_copied.is_empty() {
+        lines.push(Line::raw("  —"));
+    }
+    for clip in &usage.most_copied {
+        let preview = match clip.content_type {
+            ContentType::Text => title::shorten(&text_title(clip), 40),
+            _ => clip.content_type.as_str().to_string(),
+        };
+        lines.push(Line::raw(format!("  {:>3}×  #{:<5} {preview}", clip.copy_count, clip.id)));
+    }
+
+    lines.push(Line::raw(""));
+    lines.push(Line::styled(t("Top apps").to_string(), Style::new().add_modifier(Modifier::BOLD)));
+    if usage.top_apps.is_empty() {
+        lines.push(Line::raw("  —"));
+    }
+    for app in &usage.top_apps {
+        lines.push(Line::raw(format!("  {:>4}  {}", app.captures, app.label())));
     }
+
+    lines.push(Line::raw(""));
+    lines.push(Line::styled(t("Clips by hour").to_string(), Style::new().add_modifier(Modifier::BOLD)));
+    let max = usage.clips_by_hour.iter().copied().max().unwrap_or(0).max(1);
+    for (hour, count) in usage.clips_by_hour.iter().enumerate() {
+        if *count == 0 {
+            continue;
+        }
+        let bar = "█".repeat(((*count * 30) / max).max(1) as usize);
+        lines.push(Line::from(vec![
+            Span::raw(format!("  {hour:02}:00 ")),
+            Span::styled(bar, Style::new().fg(Color::Cyan)),
+            Span::raw(format!(" {count}")),
+        ]));
+    }
+    lines
 }
 
-fn truncate_chars(s: &str, max: usize) -> String {
-    let mut chars = s.chars();
-    let truncated: String = chars.by_ref().take(max).collect();
-    if chars.next().is_some() {
-        format!("{truncated}…")
-    } else {
-        truncated
+/// Number of most recent captures listed in the preview.
+const PREVIEW_CAPTURES: usize = 5;
+
+fn capture_lines(captures: &[Capture]) -> Vec<Line<'static>> {
+    let mut lines = vec![Line::from(vec![
+        label("Captured:", 0),
+        Span::raw(format!(" {}×", captures.len())),
+    ])];
+    for capture in captures.iter().rev().take(PREVIEW_CAPTURES) {
+        let machine = capture.machine.as_deref().map(|m| tf(" on {0}", &[&m])).unwrap_or_default();
+        let space = capture.space.map(|s| tf(" in Space {0}", &[&s])).unwrap_or_default();
+        lines.push(Line::raw(format!(
+            "  {}  {}{}{}",
+            capture.captured_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            capture.source_app.as_deref().unwrap_or("?"),
+            machine,
+            space
+        )));
     }
+    lines
 }
 
-// ── UI rendering ───────────────────────────────────────────────────
+/// Widest and tallest a thumbnail may be drawn, in terminal cells.
+const THUMB_PREVIEW_COLS: u16 = 48;
+const THUMB_PREVIEW_ROWS: u32 = 16;
+
+/// Render the selected image's thumbnail if it isn't cached for this width yet.
+fn refresh_thumb(app: &mut App, cols: u16) {
+    let Some(clip) = app.list_state.selected().and_then(|i| app.clips.get(i)) else {
+        return;
+    };
+    if app.thumb.as_ref().is_some_and(|(id, w, _)| *id == clip.id && *w == cols) {
+        return;
+    }
+    let lines = clip
+        .image_path
+        .as_deref()
+        .and_then(|path| thumbs::ensure(std::path::Path::new(path)).ok())
+        .and_then(|thumb| image::open(thumb).ok())
+        .map(|img| thumbnail_lines(&img.to_rgba8(), cols))
+        .unwrap_or_default();
+    app.thumb = Some((clip.id, cols, lines));
+}
 
-fn draw(frame: &mut Frame, app: &mut App, paths: &AppPaths) {
+/// Draw an image with half-block characters: each cell shows two pixels, the
+/// upper as foreground and the lower as background.
+fn thumbnail_lines(img: &image::RgbaImage, max_cols: u16) -> Vec<Line<'static>> {
+    let (w, h) = img.dimensions();
+    if w == 0 || h == 0 || max_cols == 0 {
+        return Vec::new();
+    }
+    let mut cols = (max_cols as u32).min(w);
+    let mut rows = (cols * h).div_ceil(w * 2).max(1);
+    if rows > THUMB_PREVIEW_ROWS {
+        rows = THUMB_PREVIEW_ROWS;
+        cols = (rows * 2 * w / h).max(1);
+    }
+    let small = image::imageops::resize(img, cols, rows * 2, image::imageops::FilterType::Triangle);
+    (0..rows)
+        .map(|row| {
+            let spans: Vec<Span<'static>> = (0..cols)
+                .map(|x| {
+                    let top = small.get_pixel(x, row * 2);
+                    let bottom = small.get_pixel(x, row * 2 + 1);
+                    Span::styled(
+                        "▀",
+                        Style::new()
+                            .fg(Color::Rgb(top[0], top[1], top[2]))
+                            .bg(Color::Rgb(bottom[0], bottom[1], bottom[2])),
+                    )
+                })
+                .collect();
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn draw(frame: &mut Frame, app: &mut App, storage: &SqliteStorage, paths: &AppPaths) {
     let [title_area, body_area, help_area] = Layout::vertical([
         Constraint::Length(1),
         Constraint::Fill(1),
@@ -353,14 +634,14 @@ fn draw(frame: &mut Frame, app: &mut App, paths: &AppPaths) {
 
     // Title bar
     let daemon_info = match daemon::daemon_status(paths) {
-        Ok(Some(pid)) => format!("daemon: running (pid {pid})"),
-        _ => "daemon: not running".to_string(),
+        Ok(Some(pid)) => tf("daemon: running (pid {0})", &[&pid]),
+        _ => t("daemon: not running").to_string(),
     };
     let clip_count = app.clips.len();
     let total_size: i64 = app.clips.iter().map(|c| c.size_bytes).sum();
-    let title = format!(
-        " CB — {clip_count} clips — {} — {daemon_info} ",
-        format_bytes(total_size)
+    let title = tf(
+        " CB — {0} clips — {1} — {2} ",
+        &[&clip_count, &format_bytes(total_size), &daemon_info],
     );
     frame.render_widget(
         Paragraph::new(title).style(Style::new().fg(Color::Black).bg(Color::Cyan)),
@@ -369,10 +650,12 @@ fn draw(frame: &mut Frame, app: &mut App, paths: &AppPaths) {
 
     // Body: two-pane split
     let [list_area, preview_area] =
-        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+        Layout::horizontal([Constraint::Percentage(app.split), Constraint::Percentage(100 - app.split)])
             .areas(body_area);
 
-    // Left pane: clip list
+    // Left pane: clip list. Rows are cut to the pane, less its borders, the
+    // highlight symbol, and the id/type/age prefix.
+    let preview_width = (list_area.width as usize).saturating_sub(2 + 2 + LIST_PREFIX_WIDTH).max(10);
     let items: Vec<ListItem> = app
         .clips
         .iter()
@@ -385,26 +668,33 @@ fn draw(frame: &mut Frame, app: &mut App, paths: &AppPaths) {
             let pin = if clip.pinned { "*" } else { " " };
             let age = format_age(clip.updated_at);
             let preview = match clip.content_type {
-                ContentType::Text => {
-                    let text = clip.text_content.as_deref().unwrap_or("");
-                    let oneline = text.replace('\n', "↵");
-                    truncate_chars(&oneline, 30)
-                }
-                ContentType::Image => format!(
-                    "{}x{} img",
-                    clip.image_width.unwrap_or(0),
-                    clip.image_height.unwrap_or(0)
+                ContentType::Text => title::shorten(&text_title(clip), preview_width),
+                ContentType::Image if clip.damaged => t("image file missing").to_string(),
+                ContentType::Image => tf(
+                    "{0}x{1} img",
+                    &[&clip.image_width.unwrap_or(0), &clip.image_height.unwrap_or(0)],
                 ),
-                ContentType::FileRef => "file ref".to_string(),
+                ContentType::FileRef => t("file ref").to_string(),
             };
             ListItem::new(format!("{:>4} {}{} {:>4}  {}", clip.id, type_ch, pin, age, preview))
         })
         .collect();
 
+    let oldest = if app.oldest_first {
+        format!(" ({})", t("oldest first"))
+    } else {
+        String::new()
+    };
     let list_title = if app.mode == Mode::Search {
-        format!("Search: {}_", app.search_query)
+        let mode = if app.fuzzy { t("Fuzzy") } else { t("Search") };
+        format!("{}: {}_{}", mode, app.search_query, oldest)
+    } else if !app.search_query.is_empty() {
+        let mode = if app.fuzzy { t("Fuzzy") } else { t("Search") };
+        format!("{}: {}{}", mode, app.search_query, oldest)
+    } else if app.oldest_first {
+        t("Clips (oldest first)").to_string()
     } else {
-        "Clips".to_string()
+        t("Clips").to_string()
     };
 
     let list = List::new(items)
@@ -420,6 +710,7 @@ fn draw(frame: &mut Frame, app: &mut App, paths: &AppPaths) {
     frame.render_stateful_widget(list, list_area, &mut app.list_state);
 
     // Right pane: preview
+    refresh_thumb(app, preview_area.width.saturating_sub(2).min(THUMB_PREVIEW_COLS));
     let preview_content = if let Some(idx) = app.list_state.selected() {
         if let Some(clip) = app.clips.get(idx) {
             let tags = if clip.tags.is_empty() {
@@ -430,57 +721,135 @@ fn draw(frame: &mut Frame, app: &mut App, paths: &AppPaths) {
 
             let mut lines = vec![
                 Line::from(vec![
-                    Span::styled("ID:      ", Style::new().fg(Color::DarkGray)),
+                    label("ID:", 9),
                     Span::raw(clip.id.to_string()),
                 ]),
                 Line::from(vec![
-                    Span::styled("Type:    ", Style::new().fg(Color::DarkGray)),
-                    Span::raw(clip.content_type.as_str()),
+                    label("Type:", 9),
+                    Span::raw(match clip.lang {
+                        Some(ref lang) => format!("{} ({lang})", clip.content_type.as_str()),
+                        None => clip.content_type.as_str().to_string(),
+                    }),
                 ]),
                 Line::from(vec![
-                    Span::styled("Pinned:  ", Style::new().fg(Color::DarkGray)),
-                    Span::raw(clip.pinned.to_string()),
+                    label("Pinned:", 9),
+                    Span::raw(match clip.pinned_until {
+                        Some(until) => format!(
+                            "{}{}",
+                            clip.pinned,
+                            tf(" (until {0})", &[&until.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")])
+                        ),
+                        None => clip.pinned.to_string(),
+                    }),
                 ]),
                 Line::from(vec![
-                    Span::styled("Tags:    ", Style::new().fg(Color::DarkGray)),
+                    label("Tags:", 9),
                     Span::raw(tags),
                 ]),
                 Line::from(vec![
-                    Span::styled("Size:    ", Style::new().fg(Color::DarkGray)),
+                    label("Size:", 9),
                     Span::raw(format_bytes(clip.size_bytes)),
                 ]),
                 Line::from(vec![
-                    Span::styled("Created: ", Style::new().fg(Color::DarkGray)),
+                    label("Created:", 9),
                     Span::raw(clip.created_at.format("%Y-%m-%d %H:%M").to_string()),
                 ]),
                 Line::raw("─────────────────────────"),
             ];
 
+            if let Some(ref origin) = clip.origin {
+                let sep = lines.len() - 1;
+                lines.insert(
+                    sep,
+                    Line::from(vec![
+                        label("Origin:", 9),
+                        Span::raw(origin.clone()),
+                    ]),
+                );
+            }
+            if let Some(app) = clip.app_label() {
+                let sep = lines.len() - 1;
+                lines.insert(
+                    sep,
+                    Line::from(vec![
+                        label("App:", 9),
+                        Span::raw(app),
+                    ]),
+                );
+            }
+            if let Some(ref url) = clip.source_url {
+                let sep = lines.len() - 1;
+                lines.insert(
+                    sep,
+                    Line::from(vec![
+                        label("Source:", 9),
+                        Span::raw(clip.source_title.clone().unwrap_or_else(|| url.clone())),
+                    ]),
+                );
+            }
+
+            if let Ok(captures) = storage.captures(clip.id)
+                && captures.len() > 1
+            {
+                let sep = lines.len() - 1;
+                lines.splice(sep..sep, capture_lines(&captures));
+            }
+
             match clip.content_type {
                 ContentType::Text => {
                     if let Some(ref text) = clip.text_content {
-                        for line in text.lines() {
-                            lines.push(Line::raw(line.to_string()));
+                        let m = TextMetrics::of(text);
+                        lines.insert(
+                            lines.len() - 1,
+                            Line::from(vec![
+                                label("Counts:", 9),
+                                Span::raw(format!("{}w {}c {}l", m.words, m.chars, m.lines)),
+                            ]),
+                        );
+                        match table::parse(text).filter(|_| clip.lang.as_deref() == Some("table")) {
+                            Some(table) => lines.extend(table.aligned().into_iter().map(Line::raw)),
+                            None => {
+                                for line in text.lines() {
+                                    lines.push(Line::raw(line.to_string()));
+                                }
+                            }
                         }
                     }
                 }
                 ContentType::Image => {
                     lines.push(Line::from(vec![
-                        Span::styled("Path:    ", Style::new().fg(Color::DarkGray)),
+                        label("Path:", 9),
                         Span::raw(clip.image_path.as_deref().unwrap_or("?")),
                     ]));
+                    if verify::file_missing(clip) {
+                        lines.push(Line::from(vec![
+                            label("File:", 9),
+                            Span::styled(t("missing"), Style::new().fg(Color::Red)),
+                        ]));
+                    }
                     lines.push(Line::from(vec![
-                        Span::styled("Dims:    ", Style::new().fg(Color::DarkGray)),
+                        label("Dims:", 9),
                         Span::raw(format!(
                             "{}x{}",
                             clip.image_width.unwrap_or(0),
                             clip.image_height.unwrap_or(0)
                         )),
                     ]));
+                    if let Some((id, _, ref thumb)) = app.thumb
+                        && id == clip.id
+                    {
+                        lines.extend(thumb.iter().cloned());
+                    }
+                    if let Some(text) = clip.ocr_text.as_deref().filter(|t| !t.is_empty()) {
+                        lines.push(Line::from(label("OCR:", 0)));
+                        for line in text.lines() {
+                            lines.push(Line::raw(line.to_string()));
+                        }
+                    }
                 }
                 ContentType::FileRef => {
                     lines.push(Line::from(vec![
-                        Span::styled("File:    ", Style::new().fg(Color::DarkGray)),
+                        label("File:", 9),
                         Span::raw(clip.text_content.as_deref().unwrap_or("?")),
                     ]));
                 }
@@ -488,20 +857,26 @@ fn draw(frame: &mut Frame, app: &mut App, paths: &AppPaths) {
 
             lines
         } else {
-            vec![Line::raw("No clip selected")]
+            vec![Line::raw(t("No clip selected"))]
         }
     } else {
-        vec![Line::raw("No clips")]
+        vec![Line::raw(t("No clips"))]
+    };
+
+    let preview_content = match (&app.mode, &app.dashboard) {
+        (Mode::Stats, Some((stats, usage))) => dashboard_lines(stats, usage),
+        _ => preview_content,
     };
 
     let preview_title = match app.mode {
-        Mode::Tag => format!("Tag: {}_", app.tag_input),
-        Mode::RemoveTag => format!("Remove tag: {}_", app.tag_input),
+        Mode::Stats => t("Stats").to_string(),
+        Mode::Tag => tf("Tag: {0}_", &[&app.tag_input]),
+        Mode::RemoveTag => tf("Remove tag: {0}_", &[&app.tag_input]),
         _ => {
             if app.preview_scroll > 0 {
-                format!("Preview [scroll: {}]", app.preview_scroll)
+                tf("Preview [scroll: {0}]", &[&app.preview_scroll])
             } else {
-                "Preview".to_string()
+                t("Preview").to_string()
             }
         }
     };
@@ -518,8 +893,8 @@ fn draw(frame: &mut Frame, app: &mut App, paths: &AppPaths) {
     frame.render_widget(preview, preview_area);
 
     // Auto-clear status after 3 seconds
-    if let Some(t) = app.status_time
-        && t.elapsed() > Duration::from_secs(3)
+    if let Some(shown) = app.status_time
+        && shown.elapsed() > Duration::from_secs(3)
     {
         app.status.clear();
         app.status_time = None;
@@ -529,15 +904,25 @@ fn draw(frame: &mut Frame, app: &mut App, paths: &AppPaths) {
     let help_text = match app.mode {
         Mode::Normal | Mode::ConfirmDelete(_) => {
             if app.status.is_empty() {
-                " [q]uit [/]search [Enter]copy [d]el [p]in [t]ag [T]untag [r]efresh [D]aemon [c]lear [J/K]scroll"
+                t(" [q]uit [/]search [Enter]copy [f]enced [d]el [p]in [t]ag [T]untag [r]efresh [D]aemon [c]lear [s]tats [o]rder [</>]resize [J/K]scroll")
                     .to_string()
             } else {
                 format!(" {} ", app.status)
             }
         }
-        Mode::Search => " Type to search (live) · [Enter] done · [Esc] cancel".to_string(),
-        Mode::Tag => " Type tag name · [Enter] add · [Esc] cancel".to_string(),
-        Mode::RemoveTag => " Type tag name · [Enter] remove · [Esc] cancel".to_string(),
+        Mode::Search if app.quick => {
+            if app.status.is_empty() {
+                t(" Type to search · [↑/↓] select · [Tab] fuzzy · [Enter] paste · [Esc] quit").to_string()
+            } else {
+                format!(" {} ", app.status)
+            }
+        }
+        Mode::Search => {
+            t(" Type to search (live) · [↑/↓] select · [Tab] fuzzy · [Enter] done · [Esc] cancel").to_string()
+        }
+        Mode::Stats => t(" [s/Esc] back · [q]uit").to_string(),
+        Mode::Tag => t(" Type tag name · [Enter] add · [Esc] cancel").to_string(),
+        Mode::RemoveTag => t(" Type tag name · [Enter] remove · [Esc] cancel").to_string(),
     };
 
     frame.render_widget(
@@ -582,7 +967,12 @@ fn handle_event(app: &mut App, storage: &SqliteStorage, paths: &AppPaths) -> std
                 KeyCode::PageUp => app.select_by(-10),
                 KeyCode::Char('g') | KeyCode::Home => app.select_first(),
                 KeyCode::Char('G') | KeyCode::End => app.select_last(),
-                KeyCode::Enter => app.copy_selected(storage),
+                KeyCode::Enter => {
+                    app.copy_selected(storage, paths, None);
+                }
+                KeyCode::Char('f') => {
+                    app.copy_selected(storage, paths, Some(Transform::Fence));
+                }
                 KeyCode::Char('d') => app.request_delete(),
                 KeyCode::Char('p') => app.toggle_pin(storage),
                 KeyCode::Char('t') => {
@@ -605,13 +995,29 @@ fn handle_event(app: &mut App, storage: &SqliteStorage, paths: &AppPaths) -> std
                 }
                 KeyCode::Char('r') => {
                     app.refresh(storage);
-                    app.set_status("Refreshed".to_string());
+                    app.set_status(t("Refreshed").to_string());
                 }
                 KeyCode::Char('D') => app.toggle_daemon(paths),
-                KeyCode::Char('c') => app.clear_old(storage),
+                KeyCode::Char('c') => app.clear_old(storage, paths),
+                KeyCode::Char('s') => app.open_dashboard(storage),
+                KeyCode::Char('o') => {
+                    app.oldest_first = !app.oldest_first;
+                    app.refresh(storage);
+                    app.select_first();
+                }
+                KeyCode::Char('<') => app.split = app.split.saturating_sub(SPLIT_STEP).max(MIN_SPLIT),
+                KeyCode::Char('>') => app.split = (app.split + SPLIT_STEP).min(MAX_SPLIT),
                 _ => {}
             }
         }
+        Mode::Stats => match key.code {
+            KeyCode::Char('q') => app.should_quit = true,
+            KeyCode::Char('s') | KeyCode::Esc => {
+                app.mode = Mode::Normal;
+                app.dashboard = None;
+            }
+            _ => {}
+        },
         Mode::ConfirmDelete(id) => match key.code {
             KeyCode::Char('y') => {
                 app.mode = Mode::Normal;
@@ -619,18 +1025,30 @@ fn handle_event(app: &mut App, storage: &SqliteStorage, paths: &AppPaths) -> std
             }
             _ => {
                 app.mode = Mode::Normal;
-                app.set_status("Delete cancelled".to_string());
+                app.set_status(t("Delete cancelled").to_string());
             }
         },
         Mode::Search => match key.code {
+            KeyCode::Esc if app.quick => app.should_quit = true,
             KeyCode::Esc => {
                 app.mode = Mode::Normal;
                 app.search_query.clear();
                 app.refresh(storage);
             }
+            KeyCode::Enter if app.quick => {
+                // Stay open on failure so the status explains why.
+                app.paste_on_exit = app.copy_selected(storage, paths, None);
+                app.should_quit = app.paste_on_exit;
+            }
             KeyCode::Enter => {
                 app.mode = Mode::Normal;
             }
+            KeyCode::Down => app.select_next(),
+            KeyCode::Up => app.select_prev(),
+            KeyCode::Tab => {
+                app.fuzzy = !app.fuzzy;
+                app.refresh(storage);
+            }
             KeyCode::Backspace => {
                 app.search_query.pop();
                 app.refresh(storage);
@@ -682,20 +1100,35 @@ fn handle_event(app: &mut App, storage: &SqliteStorage, paths: &AppPaths) -> std
 
 // ── Entry point ────────────────────────────────────────────────────
 
-pub fn run(paths: &AppPaths) -> crate::errors::Result<()> {
-    std::fs::create_dir_all(&paths.base_dir)
-        .map_err(|e| crate::errors::CbError::Daemon(e.to_string()))?;
-    let conn = Connection::open(&paths.db_path)?;
-    let storage = SqliteStorage::new(conn)?;
+/// Run the TUI. With `quick`, it behaves as a launcher for a global hotkey:
+/// search is focused, Enter pastes the selected clip into the app that was
+/// in front, and Esc quits.
+pub fn run(paths: &AppPaths, quick: bool) -> crate::errors::Result<()> {
+    let storage = if paths.read_only {
+        SqliteStorage::open_read_only(&paths.db_path)?
+    } else {
+        std::fs::create_dir_all(&paths.data_dir)
+            .map_err(|e| crate::errors::CbError::Daemon(e.to_string()))?;
+        SqliteStorage::new(crate::encryption::connect(&paths.db_path, OpenFlags::default())?)?
+    };
 
-    let mut app = App::new();
-    app.refresh(&storage);
+    let mut app = App::new(quick);
+    // The launcher always starts fresh, at the newest clip.
+    match SavedState::load(&paths.tui_state_file) {
+        Some(state) if !quick => app.restore(state, &storage),
+        _ => app.refresh(&storage),
+    }
+    if let Some(backup) = storage.migration_backup() {
+        app.set_status(tf("Upgraded database; backup at {0}", &[&backup.display()]));
+    } else if quick && clipboard::accessibility_trusted() == Some(false) {
+        app.set_status(t("No Accessibility permission: Enter will copy but not paste (see cb doctor)").to_string());
+    }
 
     let mut terminal = ratatui::init();
 
     let result = (|| {
         loop {
-            terminal.draw(|frame| draw(frame, &mut app, paths))?;
+            terminal.draw(|frame| draw(frame, &mut app, &storage, paths))?;
             handle_event(&mut app, &storage, paths)?;
             if app.should_quit {
                 break;
@@ -706,5 +1139,83 @@ pub fn run(paths: &AppPaths) -> crate::errors::Result<()> {
 
     ratatui::restore();
 
-    result.map_err(|e| crate::errors::CbError::Daemon(e.to_string()))
+    if !quick
+        && let Err(e) = app.saved_state().save(&paths.tui_state_file)
+    {
+        eprintln!("cb: {}", e);
+    }
+    result.map_err(|e| crate::errors::CbError::Daemon(e.to_string()))?;
+    if app.paste_on_exit {
+        // Long enough for a hotkey terminal window to close and hand focus
+        // back before Cmd+V lands.
+        clipboard::paste_after(Duration::from_millis(250))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::{FakeClipboard, clipboard_content_to_new_clip};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_copy_selected_writes_to_clipboard() {
+        let dir = TempDir::new().unwrap();
+        let paths = AppPaths::from_base(dir.path().to_path_buf());
+        let storage = SqliteStorage::in_memory().unwrap();
+        for text in ["older", "newest"] {
+            let content = clipboard::ClipboardContent::from_text(text.into());
+            storage.insert(clipboard_content_to_new_clip(content, None)).unwrap();
+        }
+        let fake = Rc::new(FakeClipboard::default());
+        let mut app = App::new(false);
+        app.clipboard = fake.clone();
+        app.refresh(&storage);
+
+        assert!(app.copy_selected(&storage, &paths, None));
+        assert_eq!(fake.text().as_deref(), Some("newest"));
+        assert!(app.copy_selected(&storage, &paths, Some(Transform::Fence)));
+        assert!(fake.text().unwrap().starts_with("```"));
+    }
+
+    #[test]
+    fn test_saved_state_resumes_session() {
+        let dir = TempDir::new().unwrap();
+        let paths = AppPaths::from_base(dir.path().to_path_buf());
+        let storage = SqliteStorage::in_memory().unwrap();
+        for text in ["cargo build", "cargo test", "notes", "cargo run"] {
+            let content = clipboard::ClipboardContent::from_text(text.into());
+            storage.insert(clipboard_content_to_new_clip(content, None)).unwrap();
+        }
+        assert!(SavedState::load(&paths.tui_state_file).is_none());
+
+        let mut app = App::new(false);
+        app.search_query = "cargo".into();
+        app.oldest_first = true;
+        app.split = 55;
+        app.refresh(&storage);
+        app.select_next();
+        let selected = app.selected_clip_id();
+        app.saved_state().save(&paths.tui_state_file).unwrap();
+
+        let mut resumed = App::new(false);
+        resumed.restore(SavedState::load(&paths.tui_state_file).unwrap(), &storage);
+        assert_eq!(resumed.search_query, "cargo");
+        let texts: Vec<_> = resumed.clips.iter().map(|c| c.text_content.as_deref().unwrap()).collect();
+        assert_eq!(texts, vec!["cargo build", "cargo test", "cargo run"]);
+        assert_eq!((resumed.oldest_first, resumed.split), (true, 55));
+        assert_eq!(resumed.selected_clip_id(), selected);
+
+        // A clip that's gone leaves the selection at the top.
+        storage.delete(selected.unwrap()).unwrap();
+        let state = SavedState {
+            split: 5,
+            ..SavedState::load(&paths.tui_state_file).unwrap()
+        };
+        let mut resumed = App::new(false);
+        resumed.restore(state, &storage);
+        assert_eq!(resumed.list_state.selected(), Some(0));
+        assert_eq!(resumed.split, MIN_SPLIT);
+    }
 }
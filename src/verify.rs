@@ -0,0 +1,205 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::errors::{CbError, Result};
+use crate::i18n::tf;
+use crate::storage::ClipStorage;
+use crate::storage::models::{Clip, ClipFilter, ContentType};
+
+/// Whether `clip` is an image whose file is no longer there.
+pub fn file_missing(clip: &Clip) -> bool {
+    clip.content_type == ContentType::Image
+        && clip.image_path.as_deref().is_some_and(|p| !Path::new(p).is_file())
+}
+
+/// Before using `clip`'s image file: flag the clip as damaged and fail with
+/// a clear error when the file is gone, or clear a stale flag when it's
+/// back. Flag updates are best-effort, so read-only storage still gets the
+/// error.
+pub fn check_file<S: ClipStorage>(storage: &S, clip: &Clip) -> Result<()> {
+    if !file_missing(clip) {
+        if clip.damaged {
+            let _ = storage.set_damaged(clip.id, false);
+        }
+        return Ok(());
+    }
+    if !clip.damaged {
+        let _ = storage.set_damaged(clip.id, true);
+    }
+    Err(CbError::NotFound(tf(
+        "the image file of clip #{0} is missing ({1}); `cb verify --fix` re-links or removes it",
+        &[&clip.id, &clip.image_path.as_deref().unwrap_or("")],
+    )))
+}
+
+/// What happened to one image clip whose file is missing.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// Flagged as damaged; nothing else changed.
+    Flagged,
+    /// Pointed at a file of the same name in the images directory.
+    Relinked,
+    /// Moved to the trash.
+    Pruned,
+    /// Left alone because it's protected.
+    Protected,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Damaged {
+    pub id: i64,
+    /// Where the file was expected.
+    pub path: String,
+    pub action: Action,
+    /// The file the clip now points at, when relinked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relinked_to: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VerifyReport {
+    /// Image clips looked at.
+    pub checked: usize,
+    pub damaged: Vec<Damaged>,
+    /// Clips flagged earlier whose file has since come back.
+    pub healed: usize,
+}
+
+/// Check every live image clip's file, flagging clips whose file is missing
+/// and clearing the flag on those whose file is back. With `fix`, each
+/// damaged clip is re-linked to a file of the same name in `images_dir`
+/// (e.g. after the data directory moved) or else moved to the trash.
+pub fn verify<S: ClipStorage>(storage: &S, images_dir: &Path, fix: bool) -> Result<VerifyReport> {
+    let filter = ClipFilter {
+        content_type: Some(ContentType::Image),
+        ..Default::default()
+    };
+    let count = storage.count_matching(&filter)?;
+    let clips = storage.list(ClipFilter {
+        limit: count.max(1),
+        ..filter
+    })?;
+
+    let mut report = VerifyReport {
+        checked: clips.len(),
+        ..Default::default()
+    };
+    for clip in clips {
+        if !file_missing(&clip) {
+            if clip.damaged {
+                storage.set_damaged(clip.id, false)?;
+                report.healed += 1;
+            }
+            continue;
+        }
+        let path = clip.image_path.clone().unwrap_or_default();
+        storage.set_damaged(clip.id, true)?;
+        let mut damaged = Damaged {
+            id: clip.id,
+            path,
+            action: Action::Flagged,
+            relinked_to: None,
+        };
+        if fix {
+            let candidate = Path::new(&damaged.path)
+                .file_name()
+                .map(|name| images_dir.join(name))
+                .filter(|p| p.is_file());
+            let outcome = match candidate {
+                Some(found) => {
+                    let found = found.to_string_lossy().into_owned();
+                    storage.relink_image(clip.id, &found).map(|()| {
+                        damaged.relinked_to = Some(found);
+                        Action::Relinked
+                    })
+                }
+                None => storage.delete(clip.id).map(|_| Action::Pruned),
+            };
+            damaged.action = match outcome {
+                Ok(action) => action,
+                Err(CbError::Protected(_)) => Action::Protected,
+                Err(e) => return Err(e),
+            };
+        }
+        report.damaged.push(damaged);
+    }
+    report.damaged.reverse();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::NewClip;
+    use crate::storage::sqlite::SqliteStorage;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn image(storage: &SqliteStorage, path: &Path) -> Clip {
+        storage
+            .insert(NewClip {
+                content_type: ContentType::Image,
+                image_path: Some(path.to_string_lossy().into_owned()),
+                hash: path.to_string_lossy().into_owned(),
+                size_bytes: 3,
+                ..Default::default()
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_check_file_flags_and_clears() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("a.png");
+        let storage = SqliteStorage::in_memory().unwrap();
+        let clip = image(&storage, &path);
+
+        let err = check_file(&storage, &clip).unwrap_err();
+        assert!(err.to_string().contains("cb verify --fix"));
+        let clip = storage.get_by_id(clip.id).unwrap();
+        assert!(clip.damaged);
+
+        fs::write(&path, b"png").unwrap();
+        check_file(&storage, &clip).unwrap();
+        assert!(!storage.get_by_id(clip.id).unwrap().damaged);
+    }
+
+    #[test]
+    fn test_verify_fix_relinks_or_prunes() {
+        let tmp = TempDir::new().unwrap();
+        let images = tmp.path().join("images");
+        fs::create_dir_all(&images).unwrap();
+        fs::write(images.join("moved.png"), b"png").unwrap();
+        fs::write(images.join("fine.png"), b"png").unwrap();
+        let storage = SqliteStorage::in_memory().unwrap();
+        let moved = image(&storage, &tmp.path().join("old").join("moved.png"));
+        let gone = image(&storage, &tmp.path().join("gone.png"));
+        let kept = image(&storage, &tmp.path().join("kept.png"));
+        storage.set_protected(kept.id, true).unwrap();
+        image(&storage, &images.join("fine.png"));
+
+        let report = verify(&storage, &images, false).unwrap();
+        assert_eq!(report.checked, 4);
+        assert_eq!(report.damaged.len(), 3);
+        assert!(report.damaged.iter().all(|d| d.action == Action::Flagged));
+        assert!(storage.get_by_id(gone.id).unwrap().damaged);
+
+        let report = verify(&storage, &images, true).unwrap();
+        let actions: Vec<_> = report.damaged.iter().map(|d| (d.id, d.action.clone())).collect();
+        assert_eq!(
+            actions,
+            vec![
+                (moved.id, Action::Relinked),
+                (gone.id, Action::Pruned),
+                (kept.id, Action::Protected),
+            ]
+        );
+        let relinked = storage.get_by_id(moved.id).unwrap();
+        assert!(!relinked.damaged);
+        assert_eq!(relinked.image_path.unwrap(), images.join("moved.png").to_string_lossy());
+        assert!(storage.get_by_id(gone.id).is_err());
+        assert_eq!(verify(&storage, &images, true).unwrap().damaged.len(), 1);
+    }
+}
@@ -0,0 +1,818 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use percent_encoding::percent_decode_str;
+use rusqlite::OpenFlags;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::config::{AppPaths, Config};
+use crate::daemon;
+use crate::encryption;
+use crate::errors::{CbError, Result};
+use crate::hash::hash_content;
+use crate::rules::RuleSet;
+use crate::serve;
+use crate::storage::ClipStorage;
+use crate::storage::models::{ClipFilter, ContentType, SourcePage, TokenScope, Tone};
+use crate::storage::sqlite::SqliteStorage;
+use crate::thumbs;
+use crate::verify;
+use crate::when;
+
+const INDEX_HTML: &str = include_str!("web.html");
+
+/// Page size for `/api/clips` when `limit` isn't given, and the most it may ask for.
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 500;
+
+/// How often `/api/events` checks for new clips, and how long it stays
+/// silent before sending a keepalive.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+/// Largest `POST /clips` body accepted, in bytes.
+const MAX_BODY: u64 = 1 << 20;
+
+/// Query parameters `/api/clips` understands; anything else is rejected so
+/// typos don't silently return unfiltered results.
+const CLIP_PARAMS: &[&str] = &[
+    "token", "type", "tag", "lang", "origin", "pinned", "q", "before", "after", "tone", "sort",
+    "cursor", "limit",
+];
+
+/// An HTTP response, kept independent of the server so routing can be tested
+/// without opening a socket.
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: &Value) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::problem(status, message, &[])
+    }
+
+    /// An RFC 9457 `application/problem+json` body. `invalid` lists each
+    /// rejected query parameter as `(name, reason)`.
+    fn problem(status: u16, detail: &str, invalid: &[(String, String)]) -> Self {
+        let title = match status {
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Content Too Large",
+            _ => "Internal Server Error",
+        };
+        let mut body = json!({
+            "type": "about:blank",
+            "title": title,
+            "status": status,
+            "detail": detail,
+        });
+        if !invalid.is_empty() {
+            body["invalid_params"] = invalid
+                .iter()
+                .map(|(name, reason)| json!({ "name": name, "reason": reason }))
+                .collect();
+        }
+        Response {
+            status,
+            content_type: "application/problem+json",
+            body: body.to_string().into_bytes(),
+        }
+    }
+}
+
+/// Serve the web viewer and its API on `addr` until the process is killed.
+///
+/// Every request must carry a token, either as a `?token=` query parameter
+/// or an `Authorization: Bearer` header: one made with `cb token create`, or
+/// `shared` when given. Apart from `POST /clips`, which needs a read-write
+/// token, requests only read, through a read-only connection.
+///
+/// `/api/events` is a server-sent event stream, served on its own thread and
+/// connection; see [`stream_events`].
+pub fn serve_http(paths: &AppPaths, addr: &str, shared: Option<&str>) -> Result<()> {
+    let db_path = &paths.db_path;
+    let storage = SqliteStorage::open_read_only(db_path)?;
+    let config = Config::load(&paths.config_file)?;
+    let rules = RuleSet::compile(&config.rules)?;
+    // Opened on the first POST, so a server that only reads never writes.
+    let mut writer: Option<SqliteStorage> = None;
+    let server = tiny_http::Server::http(addr)
+        .map_err(|e| CbError::InvalidInput(format!("can't listen on {}: {}", addr, e)))?;
+    for mut request in server.incoming_requests() {
+        let header = |name: &'static str| {
+            request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv(name))
+                .map(|h| h.value.as_str().to_string())
+        };
+        let bearer =
+            header("Authorization").and_then(|v| v.strip_prefix("Bearer ").map(str::to_string));
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        if *request.method() == tiny_http::Method::Get && path == "/api/events" {
+            let params = parse_query(query);
+            if token_scope(&storage, given_token(bearer.as_deref(), &params), shared).is_some() {
+                // EventSource resends the last id it saw when reconnecting.
+                let since = header("Last-Event-ID")
+                    .or_else(|| param(&params, "since").map(str::to_string))
+                    .and_then(|v| v.parse().ok());
+                match since.map_or_else(|| newest_id(&storage), Ok) {
+                    Ok(since) => {
+                        let db_path = db_path.to_path_buf();
+                        thread::spawn(move || {
+                            let _ = stream_events(&db_path, request.into_writer(), since);
+                        });
+                    }
+                    Err(e) => respond(request, error_response(&e)),
+                }
+                continue;
+            }
+        }
+        let response = match (request.method(), path) {
+            (tiny_http::Method::Get, _) => handle(&storage, &url, bearer.as_deref(), shared),
+            (tiny_http::Method::Post, "/clips" | "/api/clips") => {
+                let params = parse_query(query);
+                match token_scope(&storage, given_token(bearer.as_deref(), &params), shared) {
+                    None => Response::error(401, "missing or wrong token"),
+                    Some(TokenScope::Read) => Response::error(403, "this token is read-only"),
+                    Some(TokenScope::ReadWrite) if paths.read_only => {
+                        Response::error(403, "this history is read-only")
+                    }
+                    Some(TokenScope::ReadWrite) => {
+                        let mut body = Vec::new();
+                        let read = request
+                            .as_reader()
+                            .take(MAX_BODY + 1)
+                            .read_to_end(&mut body);
+                        if read.is_err() {
+                            Response::error(400, "couldn't read the request body")
+                        } else if body.len() as u64 > MAX_BODY {
+                            Response::error(413, "request body is too large")
+                        } else {
+                            let opened = match writer.take() {
+                                Some(storage) => Ok(storage),
+                                None => encryption::connect(db_path, OpenFlags::default())
+                                    .and_then(SqliteStorage::new),
+                            };
+                            match opened {
+                                Ok(storage) => {
+                                    let response = add_clip(&storage, paths, &rules, &config, &body);
+                                    writer = Some(storage);
+                                    response
+                                }
+                                // Answer and keep serving; the next POST tries again.
+                                Err(e) => Response::error(500, &format!("can't open the database for writing: {}", e)),
+                            }
+                        }
+                    }
+                }
+            }
+            _ => Response::error(405, "only GET and POST /clips are supported"),
+        };
+        respond(request, response);
+    }
+    Ok(())
+}
+
+fn respond(request: tiny_http::Request, response: Response) {
+    let header = tiny_http::Header::from_bytes("Content-Type", response.content_type).unwrap();
+    let reply = tiny_http::Response::from_data(response.body)
+        .with_status_code(response.status)
+        .with_header(header);
+    // A client that hangs up mid-response shouldn't stop the server.
+    let _ = request.respond(reply);
+}
+
+/// Route one GET request for `url` (path plus query string).
+///
+/// - `/` — the viewer page
+/// - `/api/clips?...` — a filtered page of clips plus `next_cursor`; see [`clip_filter`]
+/// - `/api/recent?limit=&offset=`, `/api/search?q=`, `/api/clip/<id>` — clips as JSON
+/// - `/api/suggest?context=&limit=&scorer=` — clips ranked for a context, with scores
+/// - `/thumb/<id>`, `/image/<id>` — PNG data for image clips
+pub fn handle<S: ClipStorage>(
+    storage: &S,
+    url: &str,
+    bearer: Option<&str>,
+    shared: Option<&str>,
+) -> Response {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = parse_query(query);
+    if token_scope(storage, given_token(bearer, &params), shared).is_none() {
+        return Response::error(401, "missing or wrong token");
+    }
+    let param = |key: &str| param(&params, key);
+    let number = |key: &str| param(key).and_then(|v| v.parse::<i64>().ok());
+
+    let result = match path.trim_end_matches('/').split('/').collect::<Vec<_>>()[..] {
+        [""] => {
+            return Response {
+                status: 200,
+                content_type: "text/html; charset=utf-8",
+                body: INDEX_HTML.as_bytes().to_vec(),
+            };
+        }
+        ["", "api", "clips"] => {
+            return match clip_filter(&params) {
+                Ok(filter) => match storage.list(filter.clone()) {
+                    Ok(clips) => {
+                        let full = clips.len() as i64 == filter.limit;
+                        let next = clips.last().filter(|_| full).map(|c| c.id);
+                        let clips: Vec<Value> = clips
+                            .iter()
+                            .map(|c| strip_paths(serve::clip_json(c)))
+                            .collect();
+                        Response::json(200, &json!({ "clips": clips, "next_cursor": next }))
+                    }
+                    Err(e) => error_response(&e),
+                },
+                Err(invalid) => Response::problem(400, "invalid query parameters", &invalid),
+            };
+        }
+        ["", "api", "recent"] => serve::dispatch(
+            storage,
+            "recent",
+            &json!({ "limit": number("limit"), "offset": number("offset") }),
+        ),
+        ["", "api", "search"] => serve::dispatch(
+            storage,
+            "search",
+            &json!({ "query": param("q"), "limit": number("limit") }),
+        ),
+        ["", "api", "suggest"] => serve::dispatch(
+            storage,
+            "suggest",
+            &json!({ "context": param("context"), "limit": number("limit"), "scorer": param("scorer") }),
+        ),
+        ["", "api", "clip", id] => match id.parse::<i64>() {
+            Ok(id) => serve::dispatch(storage, "get", &json!({ "id": id })),
+            Err(_) => Err(CbError::InvalidInput(format!("invalid clip id \"{}\"", id))),
+        },
+        ["", kind @ ("thumb" | "image"), id] => {
+            return match image_bytes(storage, id, kind == "thumb") {
+                Ok(body) => Response {
+                    status: 200,
+                    content_type: "image/png",
+                    body,
+                },
+                Err(e) => error_response(&e),
+            };
+        }
+        _ => return Response::error(404, "no such page"),
+    };
+    match result {
+        Ok(value) => Response::json(200, &strip_paths(value)),
+        Err(e) => error_response(&e),
+    }
+}
+
+/// Stream clips captured after `since` to `writer` as server-sent events,
+/// until the client goes away:
+///
+/// ```text
+/// id: 1234
+/// event: clip
+/// data: {"id":1234,"content_type":"text",...}
+/// ```
+///
+/// The daemon writes from another process, so new clips are found by
+/// polling the database every `POLL_INTERVAL`. A comment line is sent when
+/// nothing has happened for `KEEPALIVE`, which is also how a closed
+/// connection gets noticed.
+pub fn stream_events(
+    db_path: &Path,
+    mut writer: Box<dyn Write + Send>,
+    since: i64,
+) -> io::Result<()> {
+    let storage = SqliteStorage::open_read_only(db_path).map_err(io::Error::other)?;
+    // Written by hand rather than through tiny_http's chunked encoder, which
+    // holds data back until a whole chunk fills up.
+    writer.write_all(
+        b"HTTP/1.1 200 OK\r\n\
+          Content-Type: text/event-stream\r\n\
+          Cache-Control: no-cache\r\n\
+          Access-Control-Allow-Origin: *\r\n\
+          Connection: close\r\n\r\n",
+    )?;
+    writer.flush()?;
+    let mut last = since;
+    let mut idle = Duration::ZERO;
+    loop {
+        let events = new_clip_events(&storage, &mut last).map_err(io::Error::other)?;
+        if !events.is_empty() {
+            writer.write_all(events.as_bytes())?;
+            writer.flush()?;
+            idle = Duration::ZERO;
+        } else if idle >= KEEPALIVE {
+            writer.write_all(b": keepalive\n\n")?;
+            writer.flush()?;
+            idle = Duration::ZERO;
+        }
+        thread::sleep(POLL_INTERVAL);
+        idle += POLL_INTERVAL;
+    }
+}
+
+/// Events for clips newer than `last`, oldest first, advancing `last` past them.
+fn new_clip_events<S: ClipStorage>(storage: &S, last: &mut i64) -> Result<String> {
+    let clips = storage.list(ClipFilter {
+        cursor: Some(*last),
+        oldest_first: true,
+        limit: MAX_LIMIT,
+        ..Default::default()
+    })?;
+    let mut out = String::new();
+    for clip in clips {
+        let data = strip_paths(serve::clip_json(&clip));
+        out += &format!("id: {}\nevent: clip\ndata: {}\n\n", clip.id, data);
+        *last = clip.id;
+    }
+    Ok(out)
+}
+
+fn newest_id<S: ClipStorage>(storage: &S) -> Result<i64> {
+    let newest = storage.list(ClipFilter {
+        limit: 1,
+        ..Default::default()
+    })?;
+    Ok(newest.first().map_or(0, |c| c.id))
+}
+
+/// The bearer token, or else the `token` query parameter (which is all
+/// `EventSource` and `<img>` requests can send).
+fn given_token<'a>(bearer: Option<&'a str>, params: &'a [(String, String)]) -> Option<&'a str> {
+    bearer.or_else(|| param(params, "token"))
+}
+
+fn param<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+/// What the given token allows, or `None` if it isn't valid. The shared
+/// token from config is the owner's, so it may write.
+pub(crate) fn token_scope<S: ClipStorage>(
+    storage: &S,
+    given: Option<&str>,
+    shared: Option<&str>,
+) -> Option<TokenScope> {
+    let given = given?;
+    if shared == Some(given) {
+        return Some(TokenScope::ReadWrite);
+    }
+    storage
+        .find_token(&hash_content(given.as_bytes()))
+        .ok()
+        .flatten()
+        .map(|token| token.scope)
+}
+
+/// Body of `POST /clips`, as sent by a browser extension.
+#[derive(Debug, Deserialize)]
+struct AddRequest {
+    text: String,
+    source_url: Option<String>,
+    source_title: Option<String>,
+}
+
+/// Store the text in a `POST /clips` body like a capture, with the page it
+/// came from. Answers `201` with the new clip, or `200` with the existing one.
+pub fn add_clip(
+    storage: &SqliteStorage,
+    paths: &AppPaths,
+    rules: &RuleSet,
+    config: &Config,
+    body: &[u8],
+) -> Response {
+    let request: AddRequest = match serde_json::from_slice(body) {
+        Ok(r) => r,
+        Err(e) => return Response::error(400, &format!("malformed request body: {}", e)),
+    };
+    let source = request.source_url.map(|url| SourcePage {
+        url,
+        title: request.source_title,
+    });
+    match daemon::add_text(storage, paths, rules, config, request.text, source.as_ref()) {
+        Ok((clip, created)) => Response::json(
+            if created { 201 } else { 200 },
+            &strip_paths(serve::clip_json(&clip)),
+        ),
+        Err(e) => error_response(&e),
+    }
+}
+
+fn error_response(e: &CbError) -> Response {
+    let status = match e {
+        CbError::NotFound(_) => 404,
+        CbError::InvalidInput(_) => 400,
+        _ => 500,
+    };
+    Response::error(status, &e.to_string())
+}
+
+fn image_bytes<S: ClipStorage>(storage: &S, id: &str, thumb: bool) -> Result<Vec<u8>> {
+    let id: i64 = id
+        .parse()
+        .map_err(|_| CbError::InvalidInput(format!("invalid clip id \"{}\"", id)))?;
+    let clip = storage.get_by_id(id)?;
+    verify::check_file(storage, &clip)?;
+    let path = clip
+        .image_path
+        .ok_or_else(|| CbError::NotFound(format!("clip #{} has no image", id)))?;
+    let path = if thumb {
+        thumbs::ensure(Path::new(&path))?
+    } else {
+        path.into()
+    };
+    fs::read(&path).map_err(|e| CbError::Image(format!("{}: {}", path.display(), e)))
+}
+
+/// Parse `/api/clips` query parameters:
+///
+/// - `type` (text, image, fileref), `lang`, `origin`, `pinned` (true/false)
+/// - `tag` — repeat it to require several tags
+/// - `q` — text or OCR text contains, ignoring case
+/// - `after`, `before` — RFC 3339 or `YYYY-MM-DD` (local midnight)
+/// - `tone` (dark, light) — image brightness
+/// - `sort` (newest, oldest), `cursor` — the previous page's `next_cursor`
+/// - `limit` — 1 to `MAX_LIMIT`, default `DEFAULT_LIMIT`
+///
+/// Every invalid parameter is reported, not just the first.
+fn clip_filter(
+    params: &[(String, String)],
+) -> std::result::Result<ClipFilter, Vec<(String, String)>> {
+    let mut filter = ClipFilter {
+        limit: DEFAULT_LIMIT,
+        ..Default::default()
+    };
+    let mut invalid = Vec::new();
+    let mut seen: Vec<&str> = Vec::new();
+    for (name, value) in params {
+        let mut reject = |reason: &str| invalid.push((name.clone(), reason.to_string()));
+        if !CLIP_PARAMS.contains(&name.as_str()) {
+            reject("unknown parameter");
+            continue;
+        }
+        if name != "tag" && seen.contains(&name.as_str()) {
+            reject("given more than once");
+            continue;
+        }
+        seen.push(name);
+        match name.as_str() {
+            "type" => match ContentType::parse(value) {
+                Some(ct) => filter.content_type = Some(ct),
+                None => reject("expected text, image, or fileref"),
+            },
+            "tag" if filter.tag.is_none() => filter.tag = Some(value.clone()),
+            "tag" => filter.extra_tags.push(value.clone()),
+            "lang" => filter.lang = Some(value.clone()),
+            "origin" => filter.origin = Some(value.clone()),
+            "q" => filter.query = Some(value.clone()),
+            "pinned" => match value.as_str() {
+                "true" => filter.pinned = Some(true),
+                "false" => filter.pinned = Some(false),
+                _ => reject("expected true or false"),
+            },
+            "before" | "after" => match when::parse_when(value) {
+                Ok(date) if name == "before" => filter.before = Some(date),
+                Ok(date) => filter.after = Some(date),
+                Err(_) => reject("expected an age like 2d, YYYY-MM-DD [HH:MM[:SS]], or RFC 3339"),
+            },
+            "tone" => match value.as_str() {
+                "dark" => filter.tone = Some(Tone::Dark),
+                "light" => filter.tone = Some(Tone::Light),
+                _ => reject("expected dark or light"),
+            },
+            "sort" => match value.as_str() {
+                "newest" => filter.oldest_first = false,
+                "oldest" => filter.oldest_first = true,
+                _ => reject("expected newest or oldest"),
+            },
+            "cursor" => match value.parse::<i64>() {
+                Ok(id) => filter.cursor = Some(id),
+                Err(_) => reject("expected a clip id"),
+            },
+            "limit" => match value.parse::<i64>() {
+                Ok(n) if (1..=MAX_LIMIT).contains(&n) => filter.limit = n,
+                _ => reject(&format!("expected a number from 1 to {}", MAX_LIMIT)),
+            },
+            _ => {}
+        }
+    }
+    if invalid.is_empty() {
+        Ok(filter)
+    } else {
+        Err(invalid)
+    }
+}
+
+/// Local file paths mean nothing to a phone, and needn't leave the machine.
+fn strip_paths(mut value: Value) -> Value {
+    let strip = |clip: &mut Value| {
+        if let Some(obj) = clip.as_object_mut() {
+            for key in ["image_path", "thumb_path", "original_path"] {
+                obj.remove(key);
+            }
+        }
+    };
+    match value.as_array_mut() {
+        Some(clips) => clips.iter_mut().for_each(strip),
+        None => strip(&mut value),
+    }
+    value
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    let decode = |s: &str| {
+        percent_decode_str(&s.replace('+', " "))
+            .decode_utf8_lossy()
+            .into_owned()
+    };
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(k), decode(v))
+        })
+        .collect()
+}
+
+/// A random 32-character hex token, for when none is configured.
+pub fn random_token() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    fs::File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut bytes))
+        .map_err(|e| CbError::Config(format!("can't generate an API token: {}", e)))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::{NewClip, TokenScope};
+
+    fn seeded_storage() -> SqliteStorage {
+        let storage = SqliteStorage::in_memory().unwrap();
+        for text in ["alpha one", "beta & two"] {
+            storage
+                .insert(NewClip {
+                    content_type: ContentType::Text,
+                    text_content: Some(text.to_string()),
+                    hash: hash_content(text.as_bytes()),
+                    size_bytes: text.len() as i64,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        storage
+    }
+
+    fn body(response: &Response) -> Value {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_requires_token() {
+        let storage = seeded_storage();
+        assert_eq!(handle(&storage, "/", None, Some("s3cret")).status, 401);
+        assert_eq!(
+            handle(&storage, "/?token=nope", None, Some("s3cret")).status,
+            401
+        );
+
+        let page = handle(&storage, "/?token=s3cret", None, Some("s3cret"));
+        assert_eq!(page.status, 200);
+        assert!(page.content_type.starts_with("text/html"));
+        let api = handle(&storage, "/api/recent", Some("s3cret"), Some("s3cret"));
+        assert_eq!(body(&api).as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_accepts_stored_tokens() {
+        let storage = seeded_storage();
+        storage
+            .create_token("phone", &hash_content(b"cb_abc"), TokenScope::Read)
+            .unwrap();
+        assert_eq!(handle(&storage, "/?token=cb_abc", None, None).status, 200);
+        assert_eq!(
+            handle(&storage, "/", Some("cb_abc"), Some("s3cret")).status,
+            200
+        );
+        assert_eq!(handle(&storage, "/", None, None).status, 401);
+
+        storage.revoke_token("phone").unwrap();
+        assert_eq!(handle(&storage, "/?token=cb_abc", None, None).status, 401);
+    }
+
+    #[test]
+    fn test_api_routes() {
+        let storage = seeded_storage();
+        let get = |url: &str| handle(&storage, url, Some("t"), Some("t"));
+
+        let found = body(&get("/api/search?q=beta+%26"));
+        assert_eq!(found[0]["text_content"], "beta & two");
+        assert_eq!(body(&get("/api/clip/1"))["text_content"], "alpha one");
+        assert_eq!(
+            body(&get("/api/recent?limit=1")).as_array().unwrap().len(),
+            1
+        );
+
+        let suggested = body(&get("/api/suggest?context=beta+notes&scorer=overlap"));
+        assert_eq!(suggested[0]["text_content"], "beta & two");
+        assert_eq!(suggested[0]["matched"], json!(["beta"]));
+
+        assert_eq!(get("/api/clip/99").status, 404);
+        assert_eq!(get("/api/clip/x").status, 400);
+        assert_eq!(get("/thumb/1").status, 404);
+        assert_eq!(get("/elsewhere").status, 404);
+    }
+
+    #[test]
+    fn test_clips_filters_and_pages() {
+        let storage = seeded_storage();
+        storage
+            .insert(NewClip {
+                content_type: ContentType::Text,
+                text_content: Some("gamma".into()),
+                hash: "g".into(),
+                ..Default::default()
+            })
+            .unwrap();
+        storage.set_pinned(2, true).unwrap();
+        let get = |url: &str| handle(&storage, url, Some("t"), Some("t"));
+
+        let first = body(&get("/api/clips?limit=2"));
+        let ids: Vec<i64> = first["clips"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["id"].as_i64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![3, 2]);
+        assert_eq!(first["next_cursor"], 2);
+        let rest = body(&get("/api/clips?limit=2&cursor=2"));
+        assert_eq!(rest["clips"][0]["id"], 1);
+        assert!(rest["next_cursor"].is_null());
+
+        let oldest = body(&get("/api/clips?sort=oldest&limit=1"));
+        assert_eq!(oldest["clips"][0]["id"], 1);
+        let pinned = body(&get("/api/clips?pinned=true&type=text&after=2000-01-01"));
+        assert_eq!(pinned["clips"].as_array().unwrap().len(), 1);
+        assert_eq!(pinned["clips"][0]["text_content"], "beta & two");
+        // Ages and date-times parse as they do on the command line.
+        let recent = body(&get("/api/clips?after=2d"));
+        assert_eq!(recent["clips"].as_array().unwrap().len(), 3);
+        let older = body(&get("/api/clips?before=1h"));
+        assert!(older["clips"].as_array().unwrap().is_empty());
+        assert_eq!(get("/api/clips?after=2000-01-01%2009:30").status, 200);
+
+        // Repeated tags must all match.
+        storage.add_tag(1, "work").unwrap();
+        storage.add_tag(2, "work").unwrap();
+        storage.add_tag(2, "urgent").unwrap();
+        let tagged = body(&get("/api/clips?tag=work&tag=urgent"));
+        assert_eq!(tagged["clips"].as_array().unwrap().len(), 1);
+        assert_eq!(tagged["clips"][0]["id"], 2);
+        assert_eq!(body(&get("/api/clips?tag=work"))["clips"].as_array().unwrap().len(), 2);
+        assert_eq!(get("/api/clips?limit=1&limit=2").status, 400);
+    }
+
+    #[test]
+    fn test_invalid_params_are_problem_json() {
+        let storage = seeded_storage();
+        let response = handle(
+            &storage,
+            "/api/clips?type=video&limit=0&pinned=yes&colour=red&before=soon",
+            Some("t"),
+            Some("t"),
+        );
+        assert_eq!(response.status, 400);
+        assert_eq!(response.content_type, "application/problem+json");
+        let problem = body(&response);
+        assert_eq!(problem["status"], 400);
+        let names: Vec<&str> = problem["invalid_params"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["type", "limit", "pinned", "colour", "before"]);
+    }
+
+    #[test]
+    fn test_new_clip_events() {
+        let storage = seeded_storage();
+        let mut last = newest_id(&storage).unwrap();
+        assert_eq!(last, 2);
+        assert_eq!(new_clip_events(&storage, &mut last).unwrap(), "");
+
+        for text in ["gamma", "delta"] {
+            storage
+                .insert(NewClip {
+                    text_content: Some(text.into()),
+                    hash: text.into(),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        let events = new_clip_events(&storage, &mut last).unwrap();
+        let frames: Vec<&str> = events.trim_end().split("\n\n").collect();
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0].starts_with("id: 3\nevent: clip\ndata: {"));
+        assert!(frames[1].contains("\"text_content\":\"delta\""));
+        assert_eq!(last, 4);
+        assert_eq!(new_clip_events(&storage, &mut last).unwrap(), "");
+    }
+
+    #[test]
+    fn test_add_clip_from_extension() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths = AppPaths::from_base(dir.path().to_path_buf());
+        let storage = SqliteStorage::in_memory().unwrap();
+        let (rules, config) = (RuleSet::default(), Config::default());
+        let post = |body: &str| add_clip(&storage, &paths, &rules, &config, body.as_bytes());
+
+        let body = r#"{"text": "quoted line", "source_url": "https://example.com/post", "source_title": "A post"}"#;
+        let created = post(body);
+        assert_eq!(created.status, 201);
+        let clip = serde_json::from_slice::<Value>(&created.body).unwrap();
+        assert_eq!(clip["source_url"], "https://example.com/post");
+        assert_eq!(clip["source_title"], "A post");
+        assert_eq!(post(body).status, 200);
+
+        assert_eq!(post("{\"url\": 1}").status, 400);
+        assert_eq!(post("{\"text\": \"\"}").status, 400);
+    }
+
+    #[test]
+    fn test_token_scopes() {
+        let storage = seeded_storage();
+        storage
+            .create_token("ext", &hash_content(b"cb_w"), TokenScope::ReadWrite)
+            .unwrap();
+        storage
+            .create_token("phone", &hash_content(b"cb_r"), TokenScope::Read)
+            .unwrap();
+        assert_eq!(
+            token_scope(&storage, Some("cb_w"), None),
+            Some(TokenScope::ReadWrite)
+        );
+        assert_eq!(
+            token_scope(&storage, Some("cb_r"), None),
+            Some(TokenScope::Read)
+        );
+        assert_eq!(
+            token_scope(&storage, Some("s"), Some("s")),
+            Some(TokenScope::ReadWrite)
+        );
+        assert_eq!(token_scope(&storage, Some("x"), Some("s")), None);
+    }
+
+    #[test]
+    fn test_images_are_served_without_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let original = dir.path().join("shot.png");
+        image::RgbaImage::new(600, 300).save(&original).unwrap();
+        let storage = SqliteStorage::in_memory().unwrap();
+        storage
+            .insert(NewClip {
+                content_type: ContentType::Image,
+                image_path: Some(original.to_string_lossy().into()),
+                hash: "img".into(),
+                ..Default::default()
+            })
+            .unwrap();
+        let get = |url: &str| handle(&storage, url, Some("t"), Some("t"));
+
+        let clip = body(&get("/api/clip/1"));
+        assert!(clip.get("image_path").is_none() && clip.get("thumb_path").is_none());
+
+        let thumb = get("/thumb/1");
+        assert_eq!(thumb.content_type, "image/png");
+        let img = image::load_from_memory(&thumb.body).unwrap();
+        assert_eq!(img.width(), thumbs::THUMB_SIZE);
+        assert_eq!(get("/image/1").body, fs::read(&original).unwrap());
+    }
+}
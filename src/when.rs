@@ -0,0 +1,115 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, Utc};
+
+use crate::errors::{CbError, Result};
+
+/// Parse `YYYY-MM-DD` (local midnight), `YYYY-MM-DD HH:MM[:SS]` (local
+/// time), or an RFC 3339 timestamp.
+pub fn parse_date(s: &str) -> Result<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+        .and_then(|dt| dt.and_local_timezone(Local).earliest())
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok_or_else(|| {
+            CbError::InvalidInput(format!(
+                "invalid date \"{}\" (expected YYYY-MM-DD [HH:MM[:SS]] or RFC 3339)",
+                s
+            ))
+        })
+}
+
+/// Parse a span like `30m`, `12h`, `2d`, or `1w`.
+fn parse_span(s: &str) -> Option<Duration> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+    let (number, unit) = s.split_at(split);
+    let n = number.parse::<i64>().ok()?;
+    match unit {
+        "m" => Some(Duration::minutes(n)),
+        "h" => Some(Duration::hours(n)),
+        "d" => Some(Duration::days(n)),
+        "w" => Some(Duration::weeks(n)),
+        _ => None,
+    }
+}
+
+/// Parse an age (`30m`, `12h`, `2d`, `1w`, meaning that long ago) or a date
+/// as for `parse_date`.
+pub fn parse_when(s: &str) -> Result<DateTime<Utc>> {
+    let trimmed = s.trim();
+    if let Some(age) = parse_span(trimmed) {
+        return Ok(Utc::now() - age);
+    }
+    parse_date(trimmed).map_err(|_| {
+        CbError::InvalidInput(format!(
+            "invalid time \"{}\" (expected an age like 30m, 2d, or 1w, or a date)",
+            s
+        ))
+    })
+}
+
+/// Parse a time to come: a span from now (`3d`, `2w`), a bare date meaning
+/// the end of that day, or a date and time as for `parse_date`.
+pub fn parse_deadline(s: &str) -> Result<DateTime<Utc>> {
+    let trimmed = s.trim();
+    let deadline = match parse_span(trimmed) {
+        Some(span) => Utc::now() + span,
+        None if NaiveDate::parse_from_str(trimmed, "%Y-%m-%d").is_ok() => {
+            parse_date(trimmed)? + Duration::days(1) - Duration::seconds(1)
+        }
+        None => parse_date(trimmed)?,
+    };
+    if deadline <= Utc::now() {
+        return Err(CbError::InvalidInput(format!("\"{}\" is in the past", s)));
+    }
+    Ok(deadline)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date_forms() {
+        assert_eq!(
+            parse_date("2024-05-01T12:30:00Z").unwrap(),
+            DateTime::parse_from_rfc3339("2024-05-01T12:30:00Z").unwrap()
+        );
+        let local = |s: &str| {
+            NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+                .unwrap()
+                .and_local_timezone(Local)
+                .earliest()
+                .unwrap()
+        };
+        assert_eq!(parse_date("2024-05-01").unwrap(), local("2024-05-01 00:00:00"));
+        assert_eq!(parse_date("2024-05-01 09:15").unwrap(), local("2024-05-01 09:15:00"));
+        assert!(matches!(parse_date("2d"), Err(CbError::InvalidInput(_))));
+        assert!(parse_date("May 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_when_accepts_ages_and_dates() {
+        let ago = Utc::now() - parse_when(" 2d ").unwrap();
+        assert!((ago - Duration::days(2)).num_seconds().abs() < 5);
+        assert!(parse_when("2024-05-01").is_ok());
+        assert!(parse_when("2x").is_err());
+        assert!(parse_when("").is_err());
+    }
+
+    #[test]
+    fn test_parse_deadline() {
+        assert!(parse_deadline("3d").unwrap() > Utc::now());
+        assert!(parse_deadline("2000-01-01").is_err());
+        let end_of_day = parse_deadline("2999-12-31").unwrap();
+        assert_eq!(end_of_day, parse_date("2999-12-31 23:59:59").unwrap());
+    }
+}
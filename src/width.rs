@@ -0,0 +1,78 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Columns `s` takes up in a terminal: wide characters such as CJK and most
+/// emoji count two, combining marks none.
+pub fn of(s: &str) -> usize {
+    s.width()
+}
+
+/// `s` cut to at most `max` columns, ending in `…` when shortened. Cuts
+/// between grapheme clusters, so accented letters, flags, and emoji with
+/// modifiers are never split.
+pub fn truncate(s: &str, max: usize) -> String {
+    if of(s) <= max {
+        return s.to_string();
+    }
+    let mut out = take(s, max.saturating_sub(1)).to_string();
+    out.push('…');
+    out
+}
+
+/// The longest prefix of `s`, in whole grapheme clusters, at most `max`
+/// columns wide.
+pub fn take(s: &str, max: usize) -> &str {
+    let mut used = 0;
+    for (idx, grapheme) in s.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > max {
+            return &s[..idx];
+        }
+    }
+    s
+}
+
+/// `s` followed by spaces to fill `width` columns.
+pub fn pad_right(s: &str, width: usize) -> String {
+    format!("{}{}", s, " ".repeat(width.saturating_sub(of(s))))
+}
+
+/// `s` preceded by spaces to fill `width` columns.
+pub fn pad_left(s: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(of(s))), s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_width_of_wide_and_combining() {
+        assert_eq!(of("abc"), 3);
+        assert_eq!(of("日本"), 4);
+        assert_eq!(of("e\u{301}"), 1);
+    }
+
+    #[test]
+    fn test_truncate_is_utf8_safe() {
+        assert_eq!(truncate("héllo wörld", 6), "héllo…");
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("日本語のテキスト", 7), "日本語…");
+        // A wide character that would overflow is dropped whole.
+        assert_eq!(truncate("ab日本", 4), "ab…");
+    }
+
+    #[test]
+    fn test_truncate_keeps_graphemes_whole() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(truncate(&format!("{}{}x", family, family), 4), format!("{}…", family));
+        assert_eq!(truncate("cafe\u{301} au lait", 5), "cafe\u{301}…");
+    }
+
+    #[test]
+    fn test_pad_by_columns() {
+        assert_eq!(pad_right("日本", 6), "日本  ");
+        assert_eq!(pad_left("ab", 4), "  ab");
+        assert_eq!(pad_right("toolong", 3), "toolong");
+    }
+}